@@ -0,0 +1,211 @@
+use crate::ocr::Word;
+use crate::output::ImagePlacement;
+use crate::renderer::TextLine;
+
+/// Margin around text-layer lines for OCR words to count as on them, in points.
+const LINE_MARGIN: f32 = 2.0;
+/// Below this share of agreeing words, a page gets a `text-layer-mismatch` warning.
+pub const MIN_AGREEMENT: f32 = 0.8;
+/// Fewer text-layer words than this are not enough to judge agreement.
+pub const MIN_WORDS_TO_JUDGE: usize = 10;
+
+/// A word of the text layer, with what OCR read at its place.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlignedWord {
+    pub text: String,
+    /// Bounds on the page in points, estimated from the line's bounds and the
+    /// word's position in it.
+    pub rect: [f32; 4],
+    /// The differing OCR reading, `None` when OCR agrees or read nothing there.
+    pub ocr: Option<String>,
+    /// Pseudo-confidence (0-100): 100 when OCR reads the same word, 100 minus
+    /// OCR's confidence when it reads another, 0 when it read nothing.
+    pub conf: f32,
+}
+
+impl AlignedWord {
+    /// Whether OCR read the same word.
+    pub fn agrees(&self) -> bool {
+        self.ocr.is_none() && self.conf == 100.0
+    }
+}
+
+/// The words of the text layer aligned with OCR words (`--align`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Alignment {
+    pub words: Vec<AlignedWord>,
+}
+
+impl Alignment {
+    /// Share of text-layer words OCR read the same, 1 for a page without words.
+    pub fn agreement(&self) -> f32 {
+        if self.words.is_empty() {
+            return 1.0;
+        }
+        self.words.iter().filter(|w| w.agrees()).count() as f32 / self.words.len() as f32
+    }
+}
+
+/// Align the OCR words of a page with the words of its text-layer lines.
+/// OCR words are assigned to the line they lie on, then both word sequences
+/// of each line are aligned by edit distance, so a misread or missing word
+/// does not shift the words after it. OCR words off the text layer are ignored.
+pub fn align(lines: &[TextLine], words: &[Word], placement: ImagePlacement) -> Alignment {
+    let mut on_line: Vec<Vec<(f32, &Word)>> = vec![Vec::new(); lines.len()];
+    for word in words {
+        let rect = placement.to_points(word.bbox);
+        let (cx, cy) = ((rect[0] + rect[2]) / 2.0, (rect[1] + rect[3]) / 2.0);
+        let line = lines.iter().position(|line| {
+            let [x0, y0, x1, y1] = line.rect;
+            x0 - LINE_MARGIN <= cx && cx <= x1 + LINE_MARGIN && y0 - LINE_MARGIN <= cy && cy <= y1 + LINE_MARGIN
+        });
+        if let Some(line) = line {
+            on_line[line].push((rect[0], word));
+        }
+    }
+
+    let mut aligned = Vec::new();
+    for (line, ocr_words) in lines.iter().zip(on_line.iter_mut()) {
+        ocr_words.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let ocr_words: Vec<&Word> = ocr_words.iter().map(|(_, w)| *w).collect();
+        let text_words = split_line(line);
+        for (text_word, ocr_word) in pair(&text_words, &ocr_words) {
+            let (text, rect) = text_words[text_word].clone();
+            aligned.push(match ocr_word.map(|i| ocr_words[i]) {
+                Some(ocr) if fold(&ocr.text) == fold(&text) => AlignedWord { text, rect, ocr: None, conf: 100.0 },
+                Some(ocr) => AlignedWord { text, rect, ocr: Some(ocr.text.clone()), conf: 100.0 - ocr.conf.clamp(0.0, 100.0) },
+                None => AlignedWord { text, rect, ocr: None, conf: 0.0 },
+            });
+        }
+    }
+    Alignment { words: aligned }
+}
+
+/// Words of a line with their bounds, spread over the line's width in
+/// proportion to their characters.
+fn split_line(line: &TextLine) -> Vec<(String, [f32; 4])> {
+    let chars = line.text.chars().count().max(1) as f32;
+    let width = line.rect[2] - line.rect[0];
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in line.text.chars().chain(std::iter::once(' ')).enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                let text: String = line.text.chars().skip(s).take(i - s).collect();
+                let x0 = line.rect[0] + width * s as f32 / chars;
+                let x1 = line.rect[0] + width * i as f32 / chars;
+                words.push((text, [x0, line.rect[1], x1, line.rect[3]]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// For each text word (by index), the OCR word (by index) it is aligned with,
+/// by minimal edit distance over words: equal words pair at no cost, other
+/// pairs, and words left unpaired on either side, cost 1.
+fn pair(text: &[(String, [f32; 4])], ocr: &[&Word]) -> Vec<(usize, Option<usize>)> {
+    let (n, m) = (text.len(), ocr.len());
+    let same = |i: usize, j: usize| fold(&text[i].0) == fold(&ocr[j].text);
+    // cost[i][j]: distance between the first i text words and the first j OCR words
+    let mut cost = vec![vec![0usize; m + 1]; n + 1];
+    for (i, row) in cost.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in cost[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let substitute = cost[i - 1][j - 1] + usize::from(!same(i - 1, j - 1));
+            cost[i][j] = substitute.min(cost[i - 1][j] + 1).min(cost[i][j - 1] + 1);
+        }
+    }
+    let mut pairs = Vec::with_capacity(n);
+    let (mut i, mut j) = (n, m);
+    while i > 0 {
+        if j > 0 && cost[i][j] == cost[i - 1][j - 1] + usize::from(!same(i - 1, j - 1)) {
+            pairs.push((i - 1, Some(j - 1)));
+            j -= 1;
+        } else if j > 0 && cost[i][j] == cost[i][j - 1] + 1 {
+            j -= 1;
+            continue;
+        } else {
+            pairs.push((i - 1, None));
+        }
+        i -= 1;
+    }
+    pairs.reverse();
+    pairs
+}
+
+/// A word as compared: typographic quotes, dashes and ligatures, which text
+/// layers and OCR write differently, are folded to their plain forms.
+fn fold(word: &str) -> String {
+    let mut folded = String::with_capacity(word.len());
+    for c in word.chars() {
+        match c {
+            '‘' | '’' | '‚' | '′' => folded.push('\''),
+            '“' | '”' | '„' | '″' => folded.push('"'),
+            '‐' | '‑' | '‒' | '–' | '—' | '−' => folded.push('-'),
+            'ﬀ' => folded.push_str("ff"),
+            'ﬁ' => folded.push_str("fi"),
+            'ﬂ' => folded.push_str("fl"),
+            'ﬃ' => folded.push_str("ffi"),
+            'ﬄ' => folded.push_str("ffl"),
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align() {
+        // 10 points per character; OCR at 144 DPI (2 pixels per point)
+        let lines = [
+            TextLine { rect: [100.0, 100.0, 300.0, 112.0], text: "Total “due”: 120 EUR".into(), ..Default::default() },
+            TextLine { rect: [100.0, 200.0, 200.0, 212.0], text: "Hidden text".into(), ..Default::default() },
+        ];
+        let word = |text: &str, x0: i32, x1: i32, conf: f32| Word { text: text.into(), conf, bbox: [x0, 200, x1, 224], block: 0, para: 0, line: 0, lang: None };
+        let words = [
+            word("Total", 200, 300, 96.0),
+            word("\"due\":", 320, 440, 91.0),
+            word("l20", 460, 520, 70.0),
+            word("EUR", 540, 600, 95.0),
+            // Off the text layer
+            word("Stamp", 800, 900, 90.0),
+        ];
+        let alignment = align(&lines, &words, ImagePlacement { scale: 0.5, offset: [0.0, 0.0] });
+        let read: Vec<(&str, Option<&str>, f32)> = alignment.words.iter().map(|w| (w.text.as_str(), w.ocr.as_deref(), w.conf)).collect();
+        assert_eq!(
+            read,
+            [
+                ("Total", None, 100.0),
+                ("“due”:", None, 100.0),
+                ("120", Some("l20"), 30.0),
+                ("EUR", None, 100.0),
+                ("Hidden", None, 0.0),
+                ("text", None, 0.0)
+            ]
+        );
+        assert_eq!(alignment.words[2].rect, [230.0, 100.0, 260.0, 112.0]);
+        assert_eq!(alignment.agreement(), 0.5);
+    }
+
+    #[test]
+    fn test_pair_skips_extra_words() {
+        let text: Vec<(String, [f32; 4])> = ["a", "b", "c"].iter().map(|t| (t.to_string(), [0.0; 4])).collect();
+        let word = |text: &str| Word { text: text.into(), conf: 90.0, bbox: [0; 4], block: 0, para: 0, line: 0, lang: None };
+        let ocr = [word("a"), word("x"), word("b"), word("c")];
+        let ocr: Vec<&Word> = ocr.iter().collect();
+        assert_eq!(pair(&text, &ocr), [(0, Some(0)), (1, Some(2)), (2, Some(3))]);
+        assert_eq!(pair(&text, &[]), [(0, None), (1, None), (2, None)]);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::output::{OutputWriter, PageResult, XfaData};
+use crate::ocr::Word;
+use crate::renderer::{ActiveContent, FontInfo, SignatureField};
+use crate::warnings::Warning;
+use std::io::{self, Write};
+
+/// An ALTO 4 document: one `Page` per processed page, with Tesseract's blocks,
+/// lines and words. Positions are in pixels of the OCRed image and word
+/// confidences (`WC`) run from 0 to 1. Form data and warnings are not included.
+pub struct AltoWriter<W: Write> {
+    out: W,
+    started: bool,
+    /// Pages written so far, numbering element ids in output order.
+    pages: usize,
+}
+
+impl<W: Write> AltoWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, started: false, pages: 0 }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            write!(self.out, "{}", ALTO_HEADER.replace("{version}", env!("CARGO_PKG_VERSION")))?;
+        }
+        Ok(())
+    }
+
+    fn text_block(&mut self, id: &str, words: &[Word]) -> io::Result<()> {
+        writeln!(self.out, "        <TextBlock ID=\"{}\"{}>", id, position(words))?;
+        for (i, line) in words.chunk_by(|a, b| a.line == b.line).enumerate() {
+            writeln!(self.out, "          <TextLine ID=\"{}_l{}\"{}>", id, i + 1, position(line))?;
+            for (j, word) in line.iter().enumerate() {
+                if j > 0 {
+                    writeln!(self.out, "            <SP/>")?;
+                }
+                writeln!(
+                    self.out,
+                    "            <String ID=\"{}_l{}_w{}\"{} WC=\"{:.2}\" CONTENT=\"{}\"/>",
+                    id,
+                    i + 1,
+                    j + 1,
+                    position(std::slice::from_ref(word)),
+                    (word.conf / 100.0).clamp(0.0, 1.0),
+                    escape(&word.text)
+                )?;
+            }
+            writeln!(self.out, "          </TextLine>")?;
+        }
+        writeln!(self.out, "        </TextBlock>")
+    }
+}
+
+const ALTO_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<alto xmlns="http://www.loc.gov/standards/alto/ns-v4#" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://www.loc.gov/standards/alto/ns-v4# http://www.loc.gov/alto/v4/alto-4-2.xsd">
+  <Description>
+    <MeasurementUnit>pixel</MeasurementUnit>
+    <OCRProcessing ID="OCR_0">
+      <ocrProcessingStep>
+        <processingSoftware>
+          <softwareName>crabocr (tesseract)</softwareName>
+          <softwareVersion>{version}</softwareVersion>
+        </processingSoftware>
+      </ocrProcessingStep>
+    </OCRProcessing>
+  </Description>
+  <Layout>
+"#;
+
+impl<W: Write> OutputWriter for AltoWriter<W> {
+    fn xfa(&mut self, _xfa: &XfaData) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn signatures(&mut self, _fields: &[SignatureField]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn active_content(&mut self, _content: &ActiveContent) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn fonts(&mut self, _fonts: &[FontInfo]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        self.start()?;
+        self.pages += 1;
+        let [width, height] = page.ocr_size.unwrap_or_default();
+        writeln!(
+            self.out,
+            "    <Page ID=\"page_{}\" PHYSICAL_IMG_NR=\"{}\" PRINTED_IMG_NR=\"{}\" WIDTH=\"{}\" HEIGHT=\"{}\">",
+            self.pages,
+            page.number,
+            page.label(),
+            width,
+            height
+        )?;
+        writeln!(self.out, "      <PrintSpace HPOS=\"0\" VPOS=\"0\" WIDTH=\"{}\" HEIGHT=\"{}\">", width, height)?;
+        // Pages rejected for low confidence have no words, and no content
+        for (i, block) in page.words.chunk_by(|a, b| a.block == b.block).enumerate() {
+            self.text_block(&format!("p{}_b{}", self.pages, i + 1), block)?;
+        }
+        writeln!(self.out, "      </PrintSpace>\n    </Page>")
+    }
+
+    fn no_pages(&mut self, _reason: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn warnings(&mut self, _warnings: &[Warning]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.start()?;
+        writeln!(self.out, "  </Layout>\n</alto>")?;
+        self.out.flush()
+    }
+}
+
+/// `HPOS`, `VPOS`, `WIDTH` and `HEIGHT` attributes of the box around `words`.
+fn position(words: &[Word]) -> String {
+    let [l, t, r, b] = words.iter().fold([i32::MAX, i32::MAX, i32::MIN, i32::MIN], |acc, w| {
+        [acc[0].min(w.bbox[0]), acc[1].min(w.bbox[1]), acc[2].max(w.bbox[2]), acc[3].max(w.bbox[3])]
+    });
+    format!(" HPOS=\"{}\" VPOS=\"{}\" WIDTH=\"{}\" HEIGHT=\"{}\"", l, t, r - l, b - t)
+}
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, bbox: [i32; 4], block: usize, line: usize) -> Word {
+        Word { text: text.to_string(), conf: 91.0, bbox, block, para: block, line, lang: None }
+    }
+
+    #[test]
+    fn test_alto_writer() {
+        let first = PageResult {
+            number: 3,
+            ocr_size: Some([1000, 1400]),
+            words: vec![
+                word("Fish", [100, 100, 200, 140], 0, 0),
+                word("&", [210, 100, 230, 140], 0, 0),
+                word("Chips", [240, 100, 350, 140], 0, 0),
+                word("Menu", [100, 160, 200, 200], 0, 1),
+                word("12", [800, 1300, 840, 1330], 1, 2),
+            ],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        {
+            let mut w = AltoWriter::new(&mut buf);
+            w.page(&first).unwrap();
+            w.page(&PageResult { number: 4, ..Default::default() }).unwrap();
+            w.finish().unwrap();
+        }
+        let xml = String::from_utf8(buf).unwrap();
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let pages: Vec<_> = doc.descendants().filter(|n| n.has_tag_name("Page")).collect();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].attribute("PHYSICAL_IMG_NR"), Some("3"));
+        assert_eq!(pages[0].attribute("WIDTH"), Some("1000"));
+        assert_eq!(pages[1].attribute("ID"), Some("page_2"));
+
+        let blocks: Vec<_> = pages[0].descendants().filter(|n| n.has_tag_name("TextBlock")).collect();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].children().filter(|n| n.has_tag_name("TextLine")).count(), 2);
+        let line = blocks[0].children().find(|n| n.has_tag_name("TextLine")).unwrap();
+        assert_eq!(
+            [line.attribute("HPOS"), line.attribute("WIDTH"), line.attribute("HEIGHT")],
+            [Some("100"), Some("250"), Some("40")]
+        );
+        let strings: Vec<_> = line.children().filter(|n| n.has_tag_name("String")).collect();
+        assert_eq!(strings[1].attribute("CONTENT"), Some("&"));
+        assert_eq!(strings[1].attribute("WC"), Some("0.91"));
+        assert_eq!(pages[1].descendants().filter(|n| n.has_tag_name("String")).count(), 0);
+    }
+}
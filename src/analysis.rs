@@ -0,0 +1,270 @@
+use crate::preprocess::GrayImage;
+use crate::renderer::{Pixmap, Renderer, VectorStats};
+
+/// Resolution used to sample pages for analysis. Color and tone survive
+/// downsampling, so a small render is enough.
+pub const ANALYSIS_DPI: i32 = 72;
+
+/// Difference between the strongest and weakest channel for a pixel to count as colored.
+const MIN_CHROMA: u8 = 24;
+/// Share of colored pixels above which a page is color (ignores stray tinted specks).
+const MIN_COLOR_SHARE: f32 = 0.005;
+/// Share of pixels close to black or white above which a page is bilevel.
+/// Not 100%, as edges pick up intermediate grays when downsampled.
+const MIN_BILEVEL_SHARE: f32 = 0.95;
+
+/// Luminance below which a pixel counts as ink.
+const INK_LUMA: u32 = 128;
+/// Number of buckets in the density histogram.
+pub const DENSITY_BUCKETS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorClass {
+    Color,
+    Grayscale,
+    Bilevel,
+}
+
+impl ColorClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColorClass::Color => "color",
+            ColorClass::Grayscale => "grayscale",
+            ColorClass::Bilevel => "bilevel",
+        }
+    }
+}
+
+/// How much of a page is covered by ink, to spot nearly blank or over-dark pages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InkMetrics {
+    /// Percentage of pixels dark enough to be ink.
+    pub coverage: f32,
+    /// Share of pixels (0-1) per luminance band, darkest first.
+    pub histogram: [f32; DENSITY_BUCKETS],
+}
+
+/// Color class and ink metrics of a page, from an RGB render.
+pub struct PageAnalysis {
+    pub color: ColorClass,
+    pub ink: InkMetrics,
+}
+
+/// Analyze a rendered page. Gray renders are read as RGB with equal channels.
+pub fn analyze(pix: &Pixmap, renderer: &Renderer) -> PageAnalysis {
+    let width = pix.width(renderer).max(0) as usize;
+    let height = pix.height(renderer).max(0) as usize;
+    let stride = pix.stride(renderer).max(0) as usize;
+    let n = pix.n(renderer).max(1) as usize;
+    let samples = pix.samples(renderer);
+    let rows = samples.chunks(stride.max(1)).take(height);
+    let pixels = rows.flat_map(|row| row.chunks(n).take(width)).map(|px| match px {
+        [r, g, b, ..] => [*r, *g, *b],
+        [v, ..] => [*v; 3],
+        [] => [255; 3],
+    });
+    analyze_pixels(pixels)
+}
+
+fn analyze_pixels(pixels: impl Iterator<Item = [u8; 3]>) -> PageAnalysis {
+    let (mut total, mut colored, mut extreme, mut ink) = (0usize, 0usize, 0usize, 0usize);
+    let mut buckets = [0usize; DENSITY_BUCKETS];
+    for [r, g, b] in pixels {
+        total += 1;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        if max - min > MIN_CHROMA {
+            colored += 1;
+        }
+        let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+        if !(32..224).contains(&luma) {
+            extreme += 1;
+        }
+        if luma < INK_LUMA {
+            ink += 1;
+        }
+        buckets[luma as usize * DENSITY_BUCKETS / 256] += 1;
+    }
+
+    let share = |count: usize| if total == 0 { 0.0 } else { count as f32 / total as f32 };
+    let color = if total == 0 {
+        ColorClass::Bilevel
+    } else if share(colored) > MIN_COLOR_SHARE {
+        ColorClass::Color
+    } else if share(extreme) >= MIN_BILEVEL_SHARE {
+        ColorClass::Bilevel
+    } else {
+        ColorClass::Grayscale
+    };
+
+    PageAnalysis {
+        color,
+        ink: InkMetrics {
+            coverage: share(ink) * 100.0,
+            histogram: buckets.map(share),
+        },
+    }
+}
+
+/// Share of each side ignored when checking for a blank page (scanner edges, shadows).
+const BLANK_MARGIN: f32 = 0.05;
+/// Share of ink pixels up to which a page is blank (dust, specks, bleed-through).
+const BLANK_MAX_INK: f32 = 0.002;
+
+/// Whether a page is blank: almost no ink away from its edges.
+pub fn is_blank(image: &GrayImage) -> bool {
+    let (width, height) = (image.width as usize, image.height as usize);
+    let (mx, my) = ((width as f32 * BLANK_MARGIN) as usize, (height as f32 * BLANK_MARGIN) as usize);
+    let (mut total, mut ink) = (0usize, 0usize);
+    for y in my..height.saturating_sub(my) {
+        for &v in &image.data[y * width + mx..y * width + width.saturating_sub(mx).max(mx)] {
+            total += 1;
+            if (v as u32) < INK_LUMA {
+                ink += 1;
+            }
+        }
+    }
+    total == 0 || (ink as f32 / total as f32) <= BLANK_MAX_INK
+}
+
+/// Share of the width, around the center, searched for a gutter.
+const GUTTER_BAND: (f32, f32) = (0.35, 0.65);
+/// A gutter column varies at most this much relative to the typical column in the band.
+const MAX_GUTTER_VARIANCE_RATIO: f32 = 0.25;
+
+/// Find the gutter of a two-page spread: a column near the center whose
+/// pixels barely change from top to bottom (blank paper or the binding
+/// shadow), unlike the text on either side. Only landscape images are
+/// considered. Returns the gutter column.
+pub fn find_gutter(image: &GrayImage) -> Option<u32> {
+    let (width, height) = (image.width as usize, image.height as usize);
+    if width <= height || height == 0 {
+        return None;
+    }
+
+    let start = (width as f32 * GUTTER_BAND.0) as usize;
+    let end = (width as f32 * GUTTER_BAND.1) as usize;
+    let variance: Vec<f32> = (start..end)
+        .map(|x| {
+            let column = (0..height).map(|y| image.data[y * width + x] as f32);
+            let mean = column.clone().sum::<f32>() / height as f32;
+            column.map(|v| (v - mean).powi(2)).sum::<f32>() / height as f32
+        })
+        .collect();
+
+    // Smooth over ~1% of the width so a single clean column between letters does not count
+    let window = (width / 100).max(1);
+    let smoothed: Vec<f32> = variance
+        .windows(window.min(variance.len()).max(1))
+        .map(|w| w.iter().sum::<f32>() / w.len() as f32)
+        .collect();
+    let (best, &min) = smoothed.iter().enumerate().min_by(|a, b| a.1.total_cmp(b.1))?;
+
+    let mut sorted = smoothed.clone();
+    sorted.sort_by(f32::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    if median == 0.0 || min > median * MAX_GUTTER_VARIANCE_RATIO {
+        return None;
+    }
+    // A blank gutter is several columns wide; take the middle of it
+    let run = smoothed[best..].iter().take_while(|&&v| v <= min).count();
+    Some((start + best + run / 2 + window / 2) as u32)
+}
+
+/// Curve segments from which a page without text layer is taken to draw its
+/// text as outlines: a glyph has several, so a few dozen words are enough.
+const OUTLINE_MIN_CURVES: i32 = 200;
+/// Filled paths needed as well, as one large illustration can have many curves.
+const OUTLINE_MIN_PATHS: i32 = 10;
+
+/// Whether a page without text layer looks like text converted to vector
+/// outlines, rather than an empty page or a scan, and should be OCRed.
+pub fn looks_outlined(stats: &VectorStats) -> bool {
+    stats.curves >= OUTLINE_MIN_CURVES && stats.paths >= OUTLINE_MIN_PATHS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(page: &[[u8; 3]]) -> ColorClass {
+        analyze_pixels(page.iter().copied()).color
+    }
+
+    #[test]
+    fn test_classify() {
+        let white = [255, 255, 255];
+        let mut page = vec![white; 1000];
+        page[..50].fill([0, 0, 0]);
+        assert_eq!(color(&page), ColorClass::Bilevel);
+
+        page[50..200].fill([128, 128, 128]);
+        assert_eq!(color(&page), ColorClass::Grayscale);
+
+        page[200..210].fill([200, 30, 30]);
+        assert_eq!(color(&page), ColorClass::Color);
+    }
+
+    #[test]
+    fn test_is_blank() {
+        let (width, height) = (100usize, 100usize);
+        let mut data = vec![255u8; width * height];
+        // Black scanner edge and a speck of dust
+        for y in 0..height {
+            data[y * width] = 0;
+        }
+        data[50 * width + 50] = 0;
+        let mut page = GrayImage { width: width as u32, height: height as u32, data };
+        assert!(is_blank(&page));
+
+        for x in 20..80 {
+            page.data[30 * width + x] = 0;
+        }
+        assert!(!is_blank(&page));
+    }
+
+    /// Landscape page of striped "text" with a blank column at `gutter`.
+    fn spread(gutter: Option<usize>) -> GrayImage {
+        let (width, height) = (200usize, 100usize);
+        let mut data = vec![255u8; width * height];
+        for y in (0..height).filter(|y| y % 4 == 0) {
+            for x in 10usize..190 {
+                if gutter.is_none_or(|g| x.abs_diff(g) > 4) {
+                    data[y * width + x] = 0;
+                }
+            }
+        }
+        GrayImage {
+            width: width as u32,
+            height: height as u32,
+            data,
+        }
+    }
+
+    #[test]
+    fn test_find_gutter() {
+        let gutter = find_gutter(&spread(Some(104))).unwrap();
+        assert!(gutter.abs_diff(104) <= 2, "{}", gutter);
+        assert_eq!(find_gutter(&spread(None)), None);
+    }
+
+    #[test]
+    fn test_ink_metrics() {
+        let mut page = vec![[255, 255, 255]; 100];
+        page[..5].fill([0, 0, 0]);
+        let ink = analyze_pixels(page.into_iter()).ink;
+        assert!((ink.coverage - 5.0).abs() < 1e-4);
+        assert!((ink.histogram[0] - 0.05).abs() < 1e-6);
+        assert!((ink.histogram[DENSITY_BUCKETS - 1] - 0.95).abs() < 1e-6);
+        assert_eq!(analyze_pixels(std::iter::empty()).ink.coverage, 0.0);
+    }
+
+    #[test]
+    fn test_looks_outlined() {
+        assert!(looks_outlined(&VectorStats { paths: 120, curves: 1500 }));
+        // A chart or a logo
+        assert!(!looks_outlined(&VectorStats { paths: 40, curves: 12 }));
+        assert!(!looks_outlined(&VectorStats { paths: 1, curves: 900 }));
+        assert!(!looks_outlined(&VectorStats::default()));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::app::Target;
+use crate::cli::{Cli, Dpi, Mode, Models, XfaMode};
+use crate::errors::CrabError;
+use crate::schema;
+use crate::warnings::Warnings;
+use clap::Parser;
+use std::path::Path;
+
+/// Result of `ocr_document`: the document as `--format json` writes it.
+pub type DocumentResult = schema::Document;
+
+/// What `ocr_document` extracts, with the command line defaults.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// Tesseract language code(s), e.g. `eng+fra`.
+    pub lang: String,
+    pub mode: Mode,
+    /// Rasterization DPI for OCR.
+    pub dpi: Dpi,
+    /// Pages to extract, e.g. `1-3,5`, or `all`.
+    pub range: String,
+    pub xfa: XfaMode,
+    pub models: Models,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            lang: "eng".into(),
+            mode: Mode::Hybrid,
+            dpi: Dpi::Fixed(300),
+            range: "all".into(),
+            xfa: XfaMode::Clean,
+            models: Models::Installed,
+        }
+    }
+}
+
+/// Extract the text layer, OCR and XFA data of the PDF or image at `path` in
+/// one call, as `crabocr FILE -f json` would, without handling renderers,
+/// OCR engines and page images. Warnings are returned in the result rather
+/// than printed; errors are those of the command line, with its exit codes.
+///
+/// # Side effects
+///
+/// OCR redirects the process's file descriptor 2 to `/dev/null` while
+/// Tesseract runs, to silence Leptonica, so anything another thread writes
+/// to stderr meanwhile is lost. Callers that log to stderr from other threads
+/// should run extractions in a separate process.
+///
+/// ```no_run
+/// let options = crabocr::Options { lang: "deu".into(), ..Default::default() };
+/// let doc = crabocr::ocr_document("scan.pdf".as_ref(), &options).unwrap();
+/// for page in doc.pages.unwrap_or_default() {
+///     let text: Vec<_> = page.ocr_layer.unwrap_or_default().into_iter().map(|unit| unit.text).collect();
+///     println!("page {}: {}", page.page, text.join("\n"));
+/// }
+/// ```
+pub fn ocr_document(path: &Path, options: &Options) -> Result<DocumentResult, CrabError> {
+    let mut warnings = Warnings::new(true);
+    crate::app::extract(cli(path, options), Target::Document, &mut warnings, None, &mut 0)?
+        .ok_or_else(|| CrabError::Internal("The extraction produced no document".into()))
+}
+
+/// Arguments of an extraction of `path` with `options`: the command line
+/// defaults for everything else.
+fn cli(path: &Path, options: &Options) -> Cli {
+    let mut args = Cli::parse_from(["crabocr"]);
+    args.input = Some(path.to_path_buf());
+    args.lang = options.lang.clone();
+    args.mode = options.mode.clone();
+    args.dpi = options.dpi;
+    args.range = options.range.clone();
+    args.xfa = options.xfa.clone();
+    args.models = options.models;
+    args.quiet = true;
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        // Paths and values starting with '-' are not read as flags
+        let options = Options { mode: Mode::XfaOnly, dpi: Dpi::Auto, range: "-3".into(), lang: "-".into(), ..Default::default() };
+        let args = cli(Path::new("-scan.pdf"), &options);
+        assert_eq!(args.input.as_deref(), Some(Path::new("-scan.pdf")));
+        assert_eq!((args.mode, args.dpi, args.range.as_str(), args.lang.as_str()), (Mode::XfaOnly, Dpi::Auto, "-3", "-"));
+        assert_eq!(args.xfa, XfaMode::Clean);
+        assert!(args.output.is_empty());
+        assert!(args.quiet);
+
+        // The defaults are those of the command line
+        let defaults = Cli::try_parse_from(["crabocr", "in.pdf"]).unwrap();
+        let args = cli(Path::new("in.pdf"), &Options::default());
+        assert_eq!((args.mode, args.dpi, args.xfa, args.models), (defaults.mode, defaults.dpi, defaults.xfa, defaults.models));
+    }
+}
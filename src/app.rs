@@ -0,0 +1,1437 @@
+use crate::{
+    align, analysis, audit, budget, cli, cluster, completions, correct, denoise, digest, dryrun,
+    ensemble, langid, layout, limits, logging, merge, ocr, output, pipeline, postprocess,
+    preflight, preprocess, redact, renderer, schema, searchable, selftest, sidecar, stats,
+    synthtest, tables, textdiff, xfa, xfadiff, xfamap, zones,
+};
+use crate::cli::{Cli, Command, Dpi, Format, Granularity, ImageFormat, Models, XfaMode, XfaOnError, Mode, SpaceCheck, SplitOn, StampMode, TextLayerCheck};
+use crate::errors::CrabError;
+use crate::input::InputSource;
+use crate::preprocess::GrayImage;
+use crate::output::{AnnotationResult, EncodingFallback, ImagePlacement, Layer, PageInfo, PageResult, Rotation, Timings, XfaData};
+use crate::renderer::{DocumentGuard, Renderer, TextLine};
+use std::process;
+use std::time::Instant;
+use std::io::Write; // For writing stdin bytes to a temp file
+use crate::warnings::{WarningKind, Warnings};
+
+/// Run the command line program with the process arguments.
+pub fn run() -> Result<(), CrabError> {
+    let args = Cli::parse_args();
+    if let Some(command) = &args.command {
+        return match command {
+            Command::Completions { shell } => completions::print_completions(*shell),
+            Command::Man => completions::print_man(),
+            Command::Selftest => selftest::run(),
+            Command::XfaDiff { old, new, full } => xfadiff::run(old, new, *full),
+            Command::Diff { a, b, mode, lang, dpi, boxes } => textdiff::run(a, b, mode, lang, *dpi, *boxes),
+            Command::Correct { input, corrections, output } => correct::run(input, corrections, output),
+            Command::Cluster { inputs, max_distance } => cluster::run(inputs, *max_distance),
+            Command::SynthTest { fonts, sizes, rotations, lang, dpi, min_recovery, keep } => {
+                synthtest::run(fonts, sizes, rotations, lang, *dpi, *min_recovery, keep.as_deref())
+            }
+        };
+    }
+    
+    if args.print_schema {
+        print!("{}", crate::schema::JSON_SCHEMA);
+        return Ok(());
+    }
+
+    // Initialize logging
+    logging::init(args.verbose);
+    let started = Instant::now();
+    let quiet = args.quiet;
+    if let Some(kind) = args.warn_as_error.iter().find(|k| args.ignore_warning.contains(k)) {
+        return Err(CrabError::Cli(format!(
+            "{} is given to both --warn-as-error and --ignore-warning",
+            kind.as_str().replace('_', "-")
+        )));
+    }
+    let mut warnings = Warnings::new(quiet).with_policy(&args.ignore_warning, &args.warn_as_error);
+    let mut pages_written = 0;
+    let mut audit = args.audit_log.as_deref().map(|path| audit::AuditLog::open(path, &args.meta)).transpose()?;
+    let mut result = extract(args, Target::Outputs, &mut warnings, audit.as_mut(), &mut pages_written).map(|_| ());
+    // Encryption processes are waited for as their writers are dropped
+    #[cfg(feature = "encrypt")]
+    if let Some(reason) = crate::encrypt::failed().filter(|_| result.is_ok()) {
+        result = Err(CrabError::Output(std::io::Error::other(reason)));
+    }
+    if result.is_ok() {
+        if let Some(e) = warnings.promoted_error() {
+            result = Err(e);
+        }
+    }
+    if let Some(audit) = audit.as_mut() {
+        // The run's own error takes precedence over failing to log it
+        let logged = audit.document(&result, warnings.list().len());
+        result = result.and(logged);
+    }
+
+    if quiet {
+        let status = match &result {
+            Ok(()) => "status=ok".to_string(),
+            Err(e) => format!("status=error exit_code={}", e.exit_code()),
+        };
+        eprintln!(
+            "{} pages={} warnings={} duration_ms={}",
+            status,
+            pages_written,
+            warnings.list().len(),
+            started.elapsed().as_millis()
+        );
+        // The summary line replaces the error message
+        if let Err(e) = &result {
+            process::exit(e.exit_code());
+        }
+    }
+    result
+}
+
+/// Where `extract` sends the document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Target {
+    /// The `--output` sinks, or per-page files with `--split-pages`.
+    Outputs,
+    /// The JSON document, kept in memory and returned.
+    Document,
+}
+
+/// Extract the input document once arguments are parsed, returning the JSON
+/// document with `Target::Document`. `pages_written` counts the pages passed
+/// to the output.
+pub(crate) fn extract(
+    mut args: Cli,
+    target: Target,
+    warnings: &mut Warnings,
+    mut audit: Option<&mut audit::AuditLog>,
+    pages_written: &mut usize,
+) -> Result<Option<schema::Document>, CrabError> {
+    let started = Instant::now();
+    let budget = budget::Budget::new(args.cpu_time_limit, args.wall_limit);
+    budget.enforce().map_err(|e| CrabError::Internal(format!("Cannot set the CPU time limit: {}", e)))?;
+
+    // Validate DPI
+    let max_dpi = if args.i_know_what_im_doing { cli::MAX_DPI_EXTENDED } else { cli::MAX_DPI };
+    if let Dpi::Fixed(dpi) = args.dpi {
+        if (args.mode == Mode::Ocr || args.mode == Mode::Hybrid) && !(cli::MIN_DPI..=max_dpi).contains(&dpi) {
+            if !args.dpi_clamp {
+                return Err(CrabError::Cli(format!(
+                    "DPI must be between {} and {}. Got: {}",
+                    cli::MIN_DPI, max_dpi, dpi
+                )));
+            }
+            let clamped = dpi.clamp(cli::MIN_DPI, max_dpi);
+            warnings.push(
+                WarningKind::DpiClamped,
+                format!("DPI {} is outside {}-{}; clamped to {}.", dpi, cli::MIN_DPI, max_dpi, clamped),
+            );
+            args.dpi = Dpi::Fixed(clamped);
+        }
+    }
+
+    let redactions = match &args.redact {
+        Some(path) => redact::load(path)?,
+        None => Vec::new(),
+    };
+
+    if let Some(ext) = args.sidecar.clone() {
+        match sidecar::resolve(&args, ext.as_deref())? {
+            sidecar::Sidecars::Write(paths) => args.output = paths,
+            sidecar::Sidecars::Skip(path) => {
+                if !args.quiet {
+                    eprintln!("Skipping the input: {:?} exists.", path);
+                }
+                return Ok(None);
+            }
+        }
+    }
+
+    let xfa_map = args.xfa_map.as_deref().map(xfamap::load).transpose()?;
+    if args.merge && args.mode != Mode::Hybrid {
+        return Err(CrabError::Cli("--merge needs hybrid mode (-m hybrid)".into()));
+    }
+    if args.align && args.mode != Mode::Hybrid {
+        return Err(CrabError::Cli("--align needs hybrid mode (-m hybrid)".into()));
+    }
+    if args.layout && args.merge {
+        return Err(CrabError::Cli("--layout cannot be combined with --merge".into()));
+    }
+    if args.text_structure && !matches!(args.mode, Mode::Hybrid | Mode::Text) {
+        return Err(CrabError::Cli("--text-structure needs the text layer (-m hybrid or -m text)".into()));
+    }
+    let sinks = args.sinks().map_err(CrabError::Cli)?;
+    let hocr = sinks.iter().any(|(format, _)| *format == Format::Hocr);
+    if hocr && args.mode == Mode::Text {
+        return Err(CrabError::Cli("hOCR output needs OCR (-m ocr or -m hybrid)".into()));
+    }
+    if args.mode == Mode::Text && sinks.iter().any(|(format, _)| *format == Format::Alto) {
+        return Err(CrabError::Cli("ALTO output needs OCR (-m ocr or -m hybrid)".into()));
+    }
+    if args.stream_text {
+        if !matches!(args.mode, Mode::Hybrid | Mode::Text) {
+            return Err(CrabError::Cli("--stream-text needs the text layer (-m hybrid or -m text)".into()));
+        }
+        if sinks.len() != 1 || sinks[0].0 != Format::Text || args.split_pages {
+            return Err(CrabError::Cli("--stream-text writes to a single text output (-f text), without --split-pages".into()));
+        }
+        let conflicts = [
+            ("--layout", args.layout),
+            ("--tables", args.tables),
+            ("--merge", args.merge),
+            ("--redact", args.redact.is_some()),
+            ("--split-spread", args.split_spread),
+            ("--ocr-suspicious", args.ocr_suspicious),
+            ("--ocr-outlines", args.ocr_outlines),
+            ("--stats", args.stats),
+            ("--detect-language", args.detect_language),
+            ("--zones", args.zones),
+        ];
+        if let Some((flag, _)) = conflicts.iter().find(|(_, set)| *set) {
+            return Err(CrabError::Cli(format!("--stream-text does not keep the text layer that {} needs", flag)));
+        }
+    }
+    let page_xml = sinks.iter().any(|(format, _)| *format == Format::PageXml);
+    if page_xml && args.mode == Mode::Text {
+        return Err(CrabError::Cli("PAGE XML output needs OCR (-m ocr or -m hybrid)".into()));
+    }
+    let markdown = sinks.iter().any(|(format, _)| *format == Format::Markdown);
+    let tsv = sinks.iter().any(|(format, _)| *format == Format::Tsv);
+    if tsv && args.mode == Mode::Text {
+        return Err(CrabError::Cli("TSV output needs OCR (-m ocr or -m hybrid)".into()));
+    }
+    if args.output_pdf.is_some() && args.mode == Mode::Text {
+        return Err(CrabError::Cli("--output-pdf needs OCR (-m ocr or -m hybrid)".into()));
+    }
+    if args.encrypt_output.is_some() {
+        let plain = [
+            ("--output-pdf", args.output_pdf.is_some()),
+            ("--dump-ocr-input", args.dump_ocr_input.is_some()),
+            ("--export-images", args.export_images.is_some()),
+            ("--redacted-images", args.redacted_images.is_some()),
+            ("--extract-fonts", args.extract_fonts.is_some()),
+        ];
+        if let Some((flag, _)) = plain.iter().find(|(_, set)| *set) {
+            return Err(CrabError::Cli(format!("--encrypt-output cannot encrypt the files of {}, which would be written in clear", flag)));
+        }
+        if !cfg!(feature = "encrypt") {
+            return Err(CrabError::Cli("--encrypt-output needs crabocr built with the `encrypt` feature".into()));
+        }
+    }
+    // A missing encryption tool fails the run before any page is processed
+    #[cfg(feature = "encrypt")]
+    if let Some(id) = &args.encrypt_output {
+        crate::encrypt::Recipient::new(id).check().map_err(CrabError::Output)?;
+    }
+    if args.summary && (args.split_pages || !sinks.iter().any(|(format, _)| *format == Format::Text)) {
+        return Err(CrabError::Cli("--summary ends text output (-f text), without --split-pages".into()));
+    }
+    if args.xfa_out.is_some() && args.xfa == XfaMode::Off {
+        return Err(CrabError::Cli("--xfa-out needs an XFA mode other than off".into()));
+    }
+    if args.mode == Mode::XfaOnly {
+        if args.xfa == XfaMode::Off {
+            return Err(CrabError::Cli("--mode xfa-only needs an XFA mode other than off".into()));
+        }
+        if let Some((format, _)) = sinks.iter().find(|(format, _)| !matches!(format, Format::Json | Format::Text | Format::Xlsx)) {
+            return Err(CrabError::Cli(format!("--mode xfa-only writes JSON, text or XLSX, not {:?}", format).to_lowercase()));
+        }
+        if args.split_pages || args.output_pdf.is_some() {
+            return Err(CrabError::Cli("--mode xfa-only has no pages for --split-pages or --output-pdf".into()));
+        }
+    }
+
+    // Handle Input
+    let input = InputSource::new(args.input.clone())?;
+    
+    if args.verbose {
+        match &input {
+            InputSource::File(p) => eprintln!("Mode: File({:?})", p),
+            InputSource::StdinBytes(b) => eprintln!("Mode: StdinBytes({} bytes)", b.len()),
+            InputSource::TempFile(f) => eprintln!("Mode: TempFile({:?})", f.path()),
+        }
+        eprintln!("Config: lang='{}', dpi={:?}, xfa={:?}, mode={:?}, range='{}', timeout={}, format={:?}, granularity={:?}", 
+            args.lang, args.dpi, args.xfa, args.mode, args.range, args.timeout, args.format, args.granularity);
+    }
+    
+    // Initialize Renderer
+    let mut renderer = Renderer::new()?;
+    renderer.set_render_retries(args.render_retries);
+    if args.verbose {
+        eprintln!("Renderer initialized.");
+    }
+
+    let mut _tmp_file_handle: Option<tempfile::NamedTempFile> = None;
+    let final_path = match &input {
+        InputSource::File(p) => p.clone(),
+        InputSource::TempFile(f) => f.path().to_path_buf(),
+        InputSource::StdinBytes(b) => {
+             let mut t = tempfile::NamedTempFile::new()?;
+             t.write_all(b)?;
+             let p = t.path().to_path_buf();
+             _tmp_file_handle = Some(t);
+             p
+        }
+    };
+
+    if let Some(audit) = audit.as_deref_mut() {
+        let name = match &input {
+            InputSource::File(p) => p.display().to_string(),
+            _ => "-".to_string(),
+        };
+        audit.set_input(name, &std::fs::read(&final_path)?);
+    }
+
+    // Form intake only needs the XFA streams: skip document handlers, page counting and OCR
+    if args.mode == Mode::XfaOnly {
+        if args.dry_run {
+            return dryrun::report_forms(&args).map(|()| None);
+        }
+        let mut writer = create_writer(&args, target, &sinks)?;
+        let doc = DocumentGuard::new(&renderer, renderer.open_pdf(&final_path)?);
+        write_forms(&renderer, &doc, &args, xfa_map.as_ref(), writer.as_mut(), warnings)?;
+        drop(doc);
+        writer.warnings(warnings.list()).map_err(CrabError::Output)?;
+        if args.summary {
+            let summary = output::RunSummary { warnings: warnings.list().len(), duration: started.elapsed(), ..Default::default() };
+            writer.summary(&summary).map_err(CrabError::Output)?;
+        }
+        writer.finish().map_err(CrabError::Output)?;
+        return Ok(writer.document());
+    }
+
+    let doc = DocumentGuard::new(&renderer, renderer.open(&final_path)?);
+    let page_count = renderer.page_count(&doc)?;
+    
+    if args.verbose {
+        eprintln!("Opened document: {:?} ({} pages)", final_path, page_count);
+    }
+    
+    // Parse Range
+    let pages_to_process = cli::parse_range(&args.range, page_count as usize)
+        .map_err(|e| CrabError::Cli(format!("Invalid range: {}", e)))?;
+    
+    if args.verbose {
+        eprintln!("Processing {} pages: {:?}", pages_to_process.len(), pages_to_process);
+    }
+    if page_xml && !args.split_pages && (pages_to_process.len() > 1 || args.split_spread) {
+        return Err(CrabError::Cli("PAGE XML holds one page per document; use --output-dir with --split-pages".into()));
+    }
+
+    // Dumped images are large; make sure they fit before spending time on OCR
+    let dump_bytes = match &args.dump_ocr_input {
+        Some(_) if args.mode != Mode::Text && args.space_check != SpaceCheck::Off => {
+            Some(preflight::estimate_dump_bytes(&args, &renderer, &doc, &pages_to_process)?)
+        }
+        _ => None,
+    };
+
+    if args.dry_run {
+        return dryrun::report(&args, page_count as usize, &pages_to_process, dump_bytes).map(|()| None);
+    }
+
+    if let (Some(dir), Some(bytes)) = (&args.dump_ocr_input, dump_bytes) {
+        preflight::check_free_space(dir, bytes, args.space_check, warnings)?;
+    }
+
+    let mut writer = create_writer(&args, target, &sinks)?;
+
+    // Form data: XFA and signature fields
+    if args.xfa != XfaMode::Off {
+        write_forms(&renderer, &doc, &args, xfa_map.as_ref(), writer.as_mut(), warnings)?;
+    }
+
+    // Security triage: whatever runs or reaches outside the document
+    if args.page_info {
+        match renderer.active_content(&doc) {
+            Ok(content) => writer.active_content(&content).map_err(CrabError::Output)?,
+            Err(e) => warnings.push(WarningKind::SecurityScanFailed, e.to_string()),
+        }
+    }
+    if args.page_info || args.extract_fonts.is_some() {
+        match renderer.fonts(&doc) {
+            Ok(fonts) => {
+                if args.page_info {
+                    writer.fonts(&fonts).map_err(CrabError::Output)?;
+                }
+                if let Some(dir) = &args.extract_fonts {
+                    extract_fonts(&renderer, &doc, &fonts, dir, args.verbose, warnings)?;
+                }
+            }
+            Err(e) => warnings.push(WarningKind::FontReadFailed, e.to_string()),
+        }
+    }
+
+    // An empty document or a range matching no pages is reported, not silently accepted
+    if pages_to_process.is_empty() {
+        let reason = if page_count == 0 {
+            "document has no pages".to_string()
+        } else {
+            format!("range '{}' matches none of the {} pages", args.range, page_count)
+        };
+        writer.no_pages(&reason).map_err(CrabError::Output)?;
+        writer.warnings(warnings.list()).map_err(CrabError::Output)?;
+        if args.summary {
+            let summary = output::RunSummary { warnings: warnings.list().len(), duration: started.elapsed(), ..Default::default() };
+            writer.summary(&summary).map_err(CrabError::Output)?;
+        }
+        writer.finish().map_err(CrabError::Output)?;
+        return Err(CrabError::NoPages(reason));
+    }
+
+    // Initialize OCR if needed
+    let check_text_layer = args.check_text_layer != TextLayerCheck::Off && args.mode != Mode::Ocr;
+    let ocr_needed = args.mode != Mode::Text || args.annotations || check_text_layer || args.ocr_suspicious || args.ocr_outlines;
+    let min_conf = args.drop_confidence();
+    let ocr = if ocr_needed {
+        let tessdata = ocr::model_dir(args.models);
+        if tessdata.is_none() && args.models != Models::Installed {
+            warnings.push(
+                WarningKind::ModelsUnavailable,
+                format!("No tessdata_{:?} folder found; using the installed models.", args.models).to_lowercase(),
+            );
+        }
+        let mut ocr_instance = ocr::Ocr::with_tessdata(&args.lang, tessdata.as_deref(), !args.no_dict)?;
+        if !ocr_instance.has_osd() && args.psm.is_none() {
+            warnings.push(WarningKind::OsdUnavailable, "osd.traineddata not found; page orientation is not detected.");
+        }
+        ocr_instance.set_preserve_spaces(args.preserve_spaces || args.layout);
+        ocr_instance.set_min_confidence(min_conf);
+        if let Some(psm) = args.psm {
+            ocr_instance.set_page_seg_mode(psm)?;
+        }
+        if args.verbose {
+            eprintln!("OCR initialized with lang '{}'.", args.lang);
+        }
+        Some(ocr_instance)
+    } else {
+        None
+    };
+
+    let mut searchable = match &args.output_pdf {
+        Some(_) => {
+            let filter = searchable::WordFilter {
+                min_conf: args.pdf_min_word_conf,
+                mark_below: args.mark_words_below.map(|below| (below, args.markers.clone())),
+            };
+            Some(searchable::SearchablePdf::new(&renderer, filter)?)
+        }
+        None => None,
+    };
+    let mut searchable_document = 1;
+
+    for dir in [&args.dump_ocr_input, &args.redacted_images, &args.export_images].into_iter().flatten() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| CrabError::Cli(format!("Cannot create directory {:?}: {}", dir, e)))?;
+    }
+
+    // A text layer with a broken encoding extracts fine but is gibberish
+    if let Some(ocr_engine) = ocr.as_ref().filter(|_| check_text_layer) {
+        let sample = pages_to_process[0];
+        if let Some(similarity) = text_layer_similarity(&renderer, &doc, ocr_engine, sample, &args)? {
+            if args.verbose {
+                eprintln!("Page {}: text layer similarity to OCR {:.2}", sample + 1, similarity);
+            }
+            if similarity < postprocess::MIN_TEXT_LAYER_SIMILARITY {
+                warnings.push_page(
+                    WarningKind::TextLayerMismatch,
+                    sample + 1,
+                    None,
+                    format!("The text layer does not match its OCR (similarity {:.2}); it is likely garbled.", similarity),
+                );
+                if args.check_text_layer == TextLayerCheck::Switch {
+                    if !args.quiet {
+                        eprintln!("Switching to OCR mode.");
+                    }
+                    args.mode = Mode::Ocr;
+                }
+            }
+        }
+    }
+
+    let lang_rules = if args.no_lang_rules { Vec::new() } else { postprocess::rules_for(&args.lang) };
+
+    // Every page is OCRed outside text mode, so pages are rendered ahead on a
+    // second thread while Tesseract works on the current one
+    let limits = resource_limits(&args);
+    let mut prerender = (ocr.is_some() && args.mode != Mode::Text).then(|| {
+        pipeline::Prerender::spawn(final_path.clone(), pages_to_process.clone(), args.dpi, args.dpi_target, limits, args.render_retries)
+    });
+
+    // Execution Loop
+    let start_time = Instant::now();
+    // Retries are counted per page from here
+    renderer.take_render_retries();
+    let mut timed_out = false;
+    // Degradation of the remaining pages near --cpu-time-limit or --wall-limit
+    let mut degradation = None;
+
+    // With --split-on, the document of a batch scan the pages belong to, and
+    // the last separator check (both halves of a spread share it)
+    let mut document = 1;
+    let mut document_pages = 0;
+    let mut document_stats = stats::DocumentStats::default();
+    // For --summary: pages of the range output (halves of a spread once), and their characters
+    let mut source_pages_written = 0;
+    let mut characters = 0;
+    let mut separator: Option<(usize, bool)> = None;
+
+    // With --split-spread, two-page scans are processed as two logical pages
+    // (a failed detection is carried through so the loop can record a warning).
+    // Pages past a resource limit are caught here, before anything reads them.
+    let logical_pages = pages_to_process.iter().flat_map(|&page_idx| {
+        let halves = if let Err(e) = limits.check(&renderer, &doc, page_idx as i32) {
+            Err(e)
+        } else if args.split_spread {
+            spread_halves(&renderer, &doc, page_idx as i32)
+        } else {
+            Ok(None)
+        };
+        match halves {
+            Ok(Some([left, right])) => vec![(page_idx, Ok(Some(('a', left)))), (page_idx, Ok(Some(('b', right))))],
+            Ok(None) => vec![(page_idx, Ok(None))],
+            Err(e) => vec![(page_idx, Err(e))],
+        }
+    });
+
+    for (page_idx, half) in logical_pages {
+        // Timeout handling
+        if args.timeout > 0 && start_time.elapsed().as_secs() > args.timeout {
+             timed_out = true;
+             break;
+        }
+
+        if let Some((level, limit, share)) = budget.check().filter(|(level, ..)| Some(*level) > degradation) {
+            let message = format!("{:.0}% of the {} limit used: pages from {} on {}.", share * 100.0, limit, page_idx + 1, level.describe());
+            warnings.push(WarningKind::ResourceLimit, message);
+            degradation = Some(level);
+            // Pages are rendered here from now on, at the degraded DPI
+            prerender = None;
+        }
+        if degradation == Some(budget::Degradation::Stop) {
+            break;
+        }
+
+        let half = match half {
+            Err(e @ CrabError::ResourceLimit(_)) => return Err(e),
+            half => half,
+        };
+        let half = half.unwrap_or_else(|e| {
+            warnings.push_page(WarningKind::PageReadFailed, page_idx + 1, None, format!("Spread detection failed: {}", e));
+            None
+        });
+
+        // Separator sheets end a document and are not output
+        if let Some(SplitOn::Blank) = args.split_on {
+            let blank = match separator {
+                Some((idx, blank)) if idx == page_idx => blank,
+                _ => {
+                    let blank = render_gray(&renderer, &doc, page_idx as i32, analysis::ANALYSIS_DPI)
+                        .map(|image| analysis::is_blank(&image))
+                        .unwrap_or_else(|e| {
+                            warnings.push_page(WarningKind::PageReadFailed, page_idx + 1, None, e.to_string());
+                            false
+                        });
+                    separator = Some((page_idx, blank));
+                    blank
+                }
+            };
+            if blank {
+                if document_pages > 0 {
+                    document += 1;
+                    document_pages = 0;
+                }
+                if args.verbose {
+                    eprintln!("Page {}: separator", page_idx + 1);
+                }
+                renderer.take_render_retries();
+                continue;
+            }
+            document_pages += 1;
+        }
+
+        let declared_rotation = renderer.page_rotation(&doc, page_idx as i32).unwrap_or_else(|e| {
+            warnings.push_page(WarningKind::PageReadFailed, page_idx + 1, None, e.to_string());
+            0
+        });
+
+        let mut page = PageResult {
+            number: page_idx + 1,
+            part: half.map(|(part, _)| part),
+            document: args.split_on.map(|_| document),
+            raster_hash: None,
+            hocr: None,
+            tsv: None,
+            words: Vec::new(),
+            ocr_size: None,
+            ocr_placement: None,
+            lines: Vec::new(),
+            text_blocks: Vec::new(),
+            tables: Vec::new(),
+            zones: Vec::new(),
+            degraded: None,
+            render_retries: 0,
+            rotation: Rotation { declared: declared_rotation, ..Default::default() },
+            info: None,
+            text_layer: None,
+            ocr_layer: None,
+            annotations: Vec::new(),
+            encoding_fallback: Vec::new(),
+            stamps: Vec::new(),
+            suspicious_text_layer: Vec::new(),
+            outlined_text: false,
+            languages: Vec::new(),
+            language: None,
+            merged: None,
+            alignment: None,
+            ocr_conf: None,
+            stats: None,
+            image_file: None,
+            timings: Timings::default(),
+        };
+
+        let redact_rects = redact::for_page(&redactions, page.number);
+        // Region of the page this logical page covers, in points
+        let clip = half.map(|(_, rect)| rect);
+        let image_name = format!("page_{:04}{}", page.number, page.part.map(String::from).unwrap_or_default());
+
+        if args.hash_pages {
+            match render_gray(&renderer, &doc, page_idx as i32, digest::RASTER_HASH_DPI) {
+                Ok(image) => page.raster_hash = Some(digest::raster_hash(&image)),
+                Err(e) => warnings.push_page(WarningKind::PageReadFailed, page.number, page.part, e.to_string()),
+            }
+        }
+
+        if args.page_info {
+            page.info = Some(page_info(&renderer, &doc, page_idx as i32, warnings)?);
+        }
+
+        let full_dpi = match args.dpi {
+            Dpi::Fixed(dpi) => dpi as i32,
+            Dpi::Auto if ocr.is_some() || args.redacted_images.is_some() => {
+                let dpi = pipeline::page_dpi(&renderer, &doc, page_idx as i32, args.dpi, args.dpi_target)?;
+                if args.verbose {
+                    eprintln!("Page {}: auto DPI {}", page_idx + 1, dpi);
+                }
+                dpi
+            }
+            Dpi::Auto => 0, // Nothing is rendered
+        };
+        let dpi = match degradation {
+            Some(_) => full_dpi.min(budget::DEGRADED_DPI),
+            None => full_dpi,
+        };
+
+        // Text Layer (Hybrid or Text modes)
+        // With --stream-text, the text layer is extracted as the page is written
+        if (args.mode == Mode::Hybrid || args.mode == Mode::Text) && !args.stream_text {
+            let started = Instant::now();
+            page.text_layer = Some(match renderer.extract_text(&doc, page_idx as i32) {
+                Ok(text) => {
+                    // Redacted or split text is rebuilt from lines, so hidden text never reaches the output
+                    let text = if redact_rects.is_empty() && clip.is_none() {
+                        text
+                    } else {
+                        let mut lines = renderer.extract_lines(&doc, page_idx as i32)?;
+                        lines.retain(|line| clip.is_none_or(|c| contains_center(c, line.rect)));
+                        redact::redact_lines(&lines, &redact_rects)
+                    };
+                    let units = output::split_text(&text, args.granularity);
+                    Layer { text, units }
+                }
+                Err(e) => {
+                    warnings.push_page(
+                        WarningKind::TextExtractionFailed,
+                        page.number,
+                        page.part,
+                        format!("Failed to extract text: {}", e),
+                    );
+                    Layer::empty()
+                }
+            });
+            if args.text_structure {
+                match renderer.extract_text_structured(&doc, page_idx as i32) {
+                    Ok(mut blocks) => {
+                        // Lines off the spread half or under a redaction are left out, as in the text layer
+                        for block in &mut blocks {
+                            block.lines.retain(|line| {
+                                clip.is_none_or(|c| contains_center(c, line.rect))
+                                    && !redact_rects.iter().any(|r| redact::intersects(*r, line.rect))
+                            });
+                            if let Some(first) = block.lines.first() {
+                                block.rect = block.lines.iter().fold(first.rect, |a, l| {
+                                    [a[0].min(l.rect[0]), a[1].min(l.rect[1]), a[2].max(l.rect[2]), a[3].max(l.rect[3])]
+                                });
+                            }
+                        }
+                        blocks.retain(|block| !block.lines.is_empty());
+                        page.text_blocks = blocks;
+                    }
+                    Err(e) => warnings.push_page(WarningKind::TextExtractionFailed, page.number, page.part, e.to_string()),
+                }
+            }
+            page.timings.text = started.elapsed();
+        }
+
+        if let Some(layer) = &page.text_layer {
+            page.suspicious_text_layer = postprocess::suspicious_text_reasons(&layer.text);
+            if !page.suspicious_text_layer.is_empty() {
+                warnings.push_page(
+                    WarningKind::SuspiciousTextLayer,
+                    page.number,
+                    page.part,
+                    format!("The text layer looks broken ({}).", page.suspicious_text_layer.join(", ")),
+                );
+            }
+        }
+        // Text converted to vector outlines leaves the text layer empty although the page is not a scan
+        if page.text_layer.as_ref().is_some_and(|l| l.text.trim().is_empty()) {
+            match renderer.vector_stats(&doc, page_idx as i32) {
+                Ok(stats) if analysis::looks_outlined(&stats) => {
+                    page.outlined_text = true;
+                    let action = if args.mode == Mode::Hybrid || args.ocr_outlines {
+                        "reading it with OCR"
+                    } else {
+                        "use --ocr-outlines to read it with OCR"
+                    };
+                    warnings.push_page(
+                        WarningKind::OutlinedText,
+                        page.number,
+                        page.part,
+                        format!("No text layer, but the page draws {} curves in {} filled paths: text converted to outlines; {}.", stats.curves, stats.paths, action),
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => warnings.push_page(WarningKind::PageReadFailed, page.number, page.part, e.to_string()),
+            }
+        }
+        let mut ocr_page = args.mode != Mode::Text
+            || (args.ocr_suspicious && !page.suspicious_text_layer.is_empty())
+            || (args.ocr_outlines && page.outlined_text);
+        if ocr_page && ocr.is_some() {
+            if degradation == Some(budget::Degradation::SkipOcr) {
+                ocr_page = false;
+                page.degraded = degradation;
+            } else if dpi < full_dpi {
+                page.degraded = Some(budget::Degradation::LowerDpi);
+            }
+        }
+
+        // OCR Layer (Hybrid or Ocr modes, suspicious pages with --ocr-suspicious, outlined text with --ocr-outlines)
+        let mut page_image = None;
+        if let Some(ocr_engine) = ocr.as_ref().filter(|_| ocr_page) {
+             // Render
+             let started = Instant::now();
+             let mut full = match prerender.as_mut() {
+                 Some(prerender) => {
+                     let (image, elapsed, retries) = prerender.take(page_idx)?;
+                     page.timings.render = elapsed;
+                     page.render_retries = retries;
+                     image
+                 }
+                 None => {
+                     let image = render_gray(&renderer, &doc, page_idx as i32, dpi)?;
+                     page.timings.render = started.elapsed();
+                     image
+                 }
+             };
+             let started = Instant::now();
+             if let Some(source) = args.denoise {
+                 full = denoise::denoise(&full, source)?;
+             }
+             if args.clean_borders {
+                 full = preprocess::clean_borders(&full);
+             }
+             redact::apply_to_image(&mut full, &redact_rects, dpi);
+             if args.stamps != StampMode::Off {
+                 for stamp in preprocess::stamp_regions(&full, dpi) {
+                     let [x0, y0, x1, y1] = stamp.bbox;
+                     let scale = 72.0 / dpi as f32;
+                     let rect = [x0, y0, x1 + 1, y1 + 1].map(|v| v as f32 * scale);
+                     if clip.is_some_and(|c| !contains_center(c, rect)) {
+                         continue;
+                     }
+                     if args.stamps == StampMode::Mask {
+                         full.erase(&stamp);
+                     }
+                     page.stamps.push(rect);
+                 }
+             }
+             let half_image = clip.map(|c| full.crop(to_pixels(c, dpi)));
+             let image = half_image.as_ref().unwrap_or(&full);
+             page.timings.preprocessing = started.elapsed();
+             dump_ocr_input(&args, image, &image_name)?;
+             if let Some(dir) = &args.export_images {
+                 page.image_file = Some(export_image(&renderer, image, dpi, dir, &image_name, args.image_format, args.verbose)?);
+             }
+             // Recognize; hOCR and TSV pages are numbered in output order, as spread halves share a page number
+             let renderings = ocr::Renderings { page: *pages_written as i32, hocr, tsv };
+             let ensemble = |page: &mut PageResult| -> Result<ocr::OcrResult, CrabError> {
+                 let started = Instant::now();
+                 let variants = preprocess::ensemble_variants(image);
+                 page.timings.preprocessing += started.elapsed();
+                 for (i, variant) in variants.iter().enumerate() {
+                     dump_ocr_input(&args, variant, &format!("{}_variant{}", image_name, i + 1))?;
+                 }
+                 let started = Instant::now();
+                 let result = ensemble::recognize(ocr_engine, image, &variants, dpi, args.granularity, renderings)?;
+                 page.timings.ocr += started.elapsed();
+                 Ok(result)
+             };
+             let mut result = if args.ensemble {
+                 ensemble(&mut page)?
+             } else {
+                 let started = Instant::now();
+                 let result = ocr_engine.recognize_page(image, dpi, args.granularity, renderings)?;
+                 page.timings.ocr = started.elapsed();
+                 result
+             };
+             if args.retry_low_confidence && !args.ensemble && result.mean_conf > 0 && result.mean_conf < args.retry_confidence() {
+                 if args.verbose {
+                     eprintln!("Page {}: OCR mean confidence {}, retrying with the ensemble", page_idx + 1, result.mean_conf);
+                 }
+                 result = ensemble(&mut page)?;
+             }
+             if args.verbose {
+                 eprintln!("Page {}: OCR mean confidence {}", page_idx + 1, result.mean_conf);
+             }
+             // A confidence of 0 means nothing was recognized (blank page), not a rejection
+             if result.mean_conf > 0 && result.mean_conf < min_conf && result.layer.text.is_empty() {
+                 warnings.push_page(
+                     WarningKind::LowConfidence,
+                     page.number,
+                     page.part,
+                     format!("OCR output dropped for low confidence ({} < {}).", result.mean_conf, min_conf),
+                 );
+             }
+             // Which of the loaded languages the words were read with
+             if args.lang.contains('+') {
+                 page.languages = ocr::language_stats(&result.words);
+             }
+             // Pages rejected for low confidence keep their image, without text
+             if let Some(pdf) = searchable.as_mut() {
+                 // With --split-on, each document gets its own PDF
+                 if let (Some(document), Some(path)) = (page.document, &args.output_pdf) {
+                     if document != searchable_document {
+                         pdf.save_document(&renderer, &searchable::document_path(path, searchable_document))?;
+                         searchable_document = document;
+                     }
+                 }
+                 pdf.add_page(&renderer, image, dpi, &result.words)?;
+             }
+             page.words = result.words;
+             page.ocr_size = Some([image.width, image.height]);
+             // Words of a split half are positioned within its crop
+             let scale = 72.0 / dpi as f32;
+             let offset = clip.map_or([0.0, 0.0], |c| {
+                 let [x, y, _, _] = to_pixels(c, dpi);
+                 [x as f32 * scale, y as f32 * scale]
+             });
+             page.ocr_placement = Some(ImagePlacement { scale, offset });
+             page.hocr = result.hocr;
+             page.tsv = result.tsv;
+             page.ocr_conf = Some(result.mean_conf as f32);
+             page.rotation.detected = result.orientation;
+             page.rotation.deskew = result.deskew_angle;
+             let mut layer = result.layer;
+             if args.layout {
+                 layer.text = layout::from_words(&page.words);
+             }
+             if let Some(ratio) = args.garbage_ratio {
+                 let dropped = postprocess::drop_garbage_lines(&mut layer, ratio);
+                 if args.verbose {
+                     for line in &dropped {
+                         eprintln!("Page {}: dropped garbage line {:?}", page_idx + 1, line);
+                     }
+                 }
+                 if !dropped.is_empty() {
+                     warnings.push_page(
+                         WarningKind::GarbageDropped,
+                         page.number,
+                         page.part,
+                         format!("Dropped {} OCR line(s) below the garbage ratio.", dropped.len()),
+                     );
+                 }
+             }
+             if let Some(below) = args.mark_words_below {
+                 let (open, close) = &args.markers;
+                 ocr::mark_layer(&mut layer, &page.words, below as f32, (open, close));
+             }
+             postprocess::normalize_layer(&mut layer, &lang_rules);
+             page.ocr_layer = Some(layer);
+             // The whole page, as text-layer line positions refer to it
+             page_image = Some(full);
+        }
+
+        // In hybrid mode, lines whose font has no Unicode mapping are read from the render instead
+        if let (Some(layer), Some(image), Some(ocr_engine)) = (page.text_layer.as_mut(), &page_image, ocr.as_ref()) {
+            if layer.text.contains(char::REPLACEMENT_CHARACTER) {
+                let started = Instant::now();
+                page.encoding_fallback =
+                    encoding_fallback(&renderer, &doc, ocr_engine, page_idx as i32, image, dpi, layer, args.granularity)?;
+                page.timings.ocr += started.elapsed();
+                if args.verbose {
+                    eprintln!("Page {}: {} line(s) read with OCR for missing Unicode mappings", page_idx + 1, page.encoding_fallback.len());
+                }
+            }
+        }
+
+        // With --merge, OCR of regions without text layer joins the text-layer blocks;
+        // Markdown output, --tables and --layout rebuild the structure of the page from the lines
+        let merge_page = args.merge && page.text_layer.is_some() && page.ocr_layer.is_some();
+        let has_text = page.text_layer.as_ref().is_some_and(|l| !l.text.is_empty());
+        let markdown_page = markdown && has_text;
+        let tables_page = args.tables && has_text;
+        let layout_page = args.layout && has_text;
+        let align_page = args.align && has_text && page.ocr_placement.is_some();
+        let zones_page = args.zones && has_text && page.suspicious_text_layer.is_empty();
+        // Region of the page zones are placed in
+        let region = match (args.zones, clip) {
+            (false, _) => None,
+            (true, Some(c)) => Some(c),
+            (true, None) => Some(renderer.page_size(&doc, page_idx as i32).map(|(w, h)| [0.0, 0.0, w, h])?),
+        };
+        if merge_page || markdown_page || tables_page || layout_page || align_page || zones_page {
+            let mut lines = renderer.extract_lines(&doc, page_idx as i32)?;
+            lines.retain(|line| clip.is_none_or(|c| contains_center(c, line.rect)));
+            for line in &mut lines {
+                if redact_rects.iter().any(|r| redact::intersects(*r, line.rect)) {
+                    line.text = redact::REDACTED.to_string();
+                } else if let Some(fallback) = page.encoding_fallback.iter().find(|f| f.rect == line.rect) {
+                    line.text = fallback.text.clone();
+                }
+            }
+            if let Some(placement) = page.ocr_placement.filter(|_| merge_page) {
+                page.merged = Some(merge::merge(&lines, &page.words, placement));
+            }
+            if tables_page {
+                page.tables = tables::from_lines(&lines);
+            }
+            if let Some(layer) = page.text_layer.as_mut().filter(|_| layout_page) {
+                layer.text = layout::from_lines(&lines);
+            }
+            if let Some(placement) = page.ocr_placement.filter(|_| align_page) {
+                // Redacted lines and lines read with OCR for missing mappings have nothing to check
+                let checked: Vec<TextLine> = lines
+                    .iter()
+                    .filter(|l| l.text != redact::REDACTED && !page.encoding_fallback.iter().any(|f| f.rect == l.rect))
+                    .cloned()
+                    .collect();
+                let alignment = align::align(&checked, &page.words, placement);
+                if alignment.words.len() >= align::MIN_WORDS_TO_JUDGE && alignment.agreement() < align::MIN_AGREEMENT {
+                    let differing = alignment.words.iter().filter(|w| !w.agrees()).count();
+                    warnings.push_page(
+                        WarningKind::TextLayerMismatch,
+                        page.number,
+                        page.part,
+                        format!("OCR reads {} of {} text-layer words differently or not at all.", differing, alignment.words.len()),
+                    );
+                }
+                page.alignment = Some(alignment);
+            }
+            if let Some(region) = region.filter(|_| zones_page) {
+                page.zones = zones::from_lines(&lines, region);
+            }
+            if markdown_page {
+                page.lines = lines;
+            }
+        } else if let Some(placement) = page.ocr_placement.filter(|_| args.tables) {
+            page.tables = tables::from_words(&page.words, placement);
+        }
+        if let (Some(region), Some(placement)) = (region.filter(|_| !zones_page), page.ocr_placement) {
+            page.zones = zones::from_words(&page.words, placement, region);
+        }
+
+        // Annotation appearances (stamps, signatures) are OCRed on their own
+        if let Some(ocr_engine) = ocr.as_ref().filter(|_| args.annotations && degradation < Some(budget::Degradation::SkipOcr)) {
+            let started = Instant::now();
+            page.annotations = ocr_annotations(&renderer, &doc, ocr_engine, page_idx as i32, dpi, &redact_rects, &args, warnings)?;
+            page.timings.ocr += started.elapsed();
+            page.annotations.retain(|a| clip.is_none_or(|c| contains_center(c, a.rect)));
+            for annot in &mut page.annotations {
+                postprocess::normalize_layer(&mut annot.layer, &lang_rules);
+            }
+        }
+
+        if let Some(dir) = &args.redacted_images {
+            let mut image = match page_image {
+                Some(image) => image,
+                None => {
+                    let mut image = render_gray(&renderer, &doc, page_idx as i32, dpi)?;
+                    redact::apply_to_image(&mut image, &redact_rects, dpi);
+                    image
+                }
+            };
+            if let Some(c) = clip {
+                image = image.crop(to_pixels(c, dpi));
+            }
+            let path = dir.join(format!("{}.pgm", image_name));
+            image.write_pgm(&path).map_err(CrabError::Output)?;
+        }
+
+        if args.detect_language {
+            page.language = langid::detect(page.read_text());
+            for layer in [page.text_layer.as_mut(), page.ocr_layer.as_mut()].into_iter().flatten() {
+                for unit in &mut layer.units {
+                    unit.lang = langid::detect(&unit.text).map(|l| l.lang);
+                }
+            }
+        }
+        // Renders on this thread, on top of those of the render thread
+        page.render_retries += renderer.take_render_retries();
+        if page.render_retries > 0 {
+            let message = format!("Rendering needed {} retries.", page.render_retries);
+            warnings.push_page(WarningKind::RenderRetried, page.number, page.part, message);
+        }
+
+        if args.stats {
+            let page_stats = stats::PageStats::of(&page);
+            document_stats.add(&page, &page_stats);
+            page.stats = Some(page_stats);
+        }
+        let written = if args.stream_text {
+            let started = Instant::now();
+            let mut failure = None;
+            let written = writer.page_streamed(&page, &mut |out| {
+                let mut out = CharCount { out, chars: 0 };
+                let extracted = renderer.extract_text_to(&doc, page_idx as i32, &mut out);
+                characters += out.chars;
+                match extracted {
+                    Err(CrabError::Output(e)) => Err(e),
+                    Err(e) => {
+                        failure = Some(e);
+                        Ok(())
+                    }
+                    Ok(()) => Ok(()),
+                }
+            });
+            page.timings.text = started.elapsed();
+            if let Some(e) = failure {
+                warnings.push_page(WarningKind::TextExtractionFailed, page.number, page.part, format!("Failed to extract text: {}", e));
+            }
+            written
+        } else {
+            characters += page.read_text().chars().count();
+            writer.page(&page)
+        };
+        if let Err(e) = written {
+            if !args.quiet {
+                eprintln!("Error: Output is incomplete; writing stopped at page {}.", page.label());
+            }
+            return Err(CrabError::Output(e));
+        }
+        *pages_written += 1;
+        if page.part != Some('b') {
+            source_pages_written += 1;
+        }
+        if let Some(audit) = audit.as_deref_mut() {
+            let page_warnings = warnings.list().iter().filter(|w| w.page == Some(page.number) && w.part == page.part).count();
+            audit.page(&page, page_warnings)?;
+        }
+    }
+    
+    // Clean up document
+    drop(doc);
+    
+    if args.stats {
+        writer.document_stats(&document_stats).map_err(CrabError::Output)?;
+    }
+    writer.warnings(warnings.list()).map_err(CrabError::Output)?;
+    if args.summary {
+        let summary = output::RunSummary {
+            pages_processed: *pages_written,
+            pages_skipped: pages_to_process.len().saturating_sub(source_pages_written),
+            warnings: warnings.list().len(),
+            characters,
+            duration: started.elapsed(),
+        };
+        writer.summary(&summary).map_err(CrabError::Output)?;
+    }
+    writer.finish().map_err(CrabError::Output)?;
+    if let (Some(pdf), Some(path)) = (searchable, &args.output_pdf) {
+        match args.split_on {
+            Some(_) => pdf.save_last_document(&renderer, &searchable::document_path(path, searchable_document))?,
+            None => pdf.save(&renderer, path)?,
+        }
+    }
+    if let Some(summary) = warnings.summary().filter(|_| !args.quiet) {
+        eprintln!("Completed with {}.", summary);
+    }
+
+    if timed_out {
+        return Err(CrabError::Timeout);
+    }
+    
+    Ok(writer.document())
+}
+
+/// The output writer: the `--output` sinks, or per-page files with `--split-pages`.
+fn create_writer(args: &Cli, target: Target, sinks: &[(Format, Option<std::path::PathBuf>)]) -> Result<Box<dyn output::OutputWriter>, CrabError> {
+    if target == Target::Document {
+        return Ok(Box::new(output::DocumentWriter::new(args.granularity).with_pages(args.mode != Mode::XfaOnly)));
+    }
+    let options = output_options(args);
+    let writer = match args.output_dir.as_ref().filter(|_| args.split_pages) {
+        Some(dir) => output::page_files_writer(dir, &args.format, args.granularity, &args.meta, args.words, &options),
+        None => output::writer_for(sinks, args.granularity, &args.meta, args.words, args.mode != Mode::XfaOnly, &options),
+    };
+    writer.map_err(CrabError::Output)
+}
+
+/// How every output file is written: `--encrypt-output`, `--newline` and `--bom`.
+fn output_options(args: &Cli) -> output::OutputOptions {
+    output::OutputOptions {
+        #[cfg(feature = "encrypt")]
+        encrypt: args.encrypt_output.as_deref().map(crate::encrypt::Recipient::new),
+        newline: args.newline,
+        bom: args.bom,
+    }
+}
+
+/// Limits on the pages of untrusted documents: `--max-object-depth`,
+/// `--max-page-images` and `--max-content-mb`.
+fn resource_limits(args: &Cli) -> limits::ResourceLimits {
+    limits::ResourceLimits {
+        max_depth: args.max_object_depth,
+        max_images: args.max_page_images,
+        max_content: args.max_content_mb.map(|mb| mb.saturating_mul(limits::MB)),
+    }
+}
+
+/// Write the form sections of a document: its XFA data and its signature fields.
+fn write_forms(
+    renderer: &Renderer,
+    doc: &renderer::Document,
+    args: &Cli,
+    xfa_map: Option<&xfamap::KeyMap>,
+    writer: &mut dyn output::OutputWriter,
+    warnings: &mut Warnings,
+) -> Result<(), CrabError> {
+    if let Some(xml) = renderer.extract_xfa(doc) {
+        match (xfa_data(xml, args, xfa_map, warnings)?, &args.xfa_out) {
+            (Some(data), Some(target)) => write_xfa_out(&data, target, args).map_err(CrabError::Output)?,
+            (Some(data), None) => writer.xfa(&data).map_err(CrabError::Output)?,
+            (None, _) => {}
+        }
+    } else if args.verbose {
+        eprintln!("No XFA data found.");
+    }
+
+    match renderer.signature_fields(doc) {
+        Ok(fields) if !fields.is_empty() => writer.signatures(&fields).map_err(CrabError::Output)?,
+        Ok(_) => {}
+        Err(e) => warnings.push(WarningKind::FormReadFailed, e.to_string()),
+    }
+    Ok(())
+}
+
+/// Write the XFA section to `--xfa-out` rather than the primary output:
+/// pretty-printed JSON, or the XML as extracted.
+fn write_xfa_out(data: &XfaData, target: &cli::XfaOut, args: &Cli) -> std::io::Result<()> {
+    let options = output_options(args);
+    let mut out = match target {
+        cli::XfaOut::Path(path) => output::create_output(Some(path), &options, true)?,
+        cli::XfaOut::Fd(fd) => output::create_fd_output(*fd, &options, true)?,
+    };
+    match data {
+        XfaData::Parsed(value) => {
+            serde_json::to_writer_pretty(&mut out, value).map_err(std::io::Error::other)?;
+            writeln!(out)?;
+        }
+        XfaData::Raw(xml) => out.write_all(xml.as_bytes())?,
+    }
+    out.flush()
+}
+
+/// Convert extracted XFA XML as configured by `--xfa` and the other XFA options.
+/// `None` when the XFA section is to be left out.
+fn xfa_data(xml: String, args: &Cli, xfa_map: Option<&xfamap::KeyMap>, warnings: &mut Warnings) -> Result<Option<XfaData>, CrabError> {
+    if let Some(path) = &args.xfa_raw {
+        let mut out = output::create_output(Some(path), &output_options(args), true).map_err(CrabError::Output)?;
+        out.write_all(xml.as_bytes()).and_then(|_| out.flush()).map_err(CrabError::Output)?;
+    }
+    if !matches!(args.xfa, XfaMode::Full | XfaMode::Clean) {
+        return Ok(Some(XfaData::Raw(xml)));
+    }
+
+    let data_only = args.xfa == XfaMode::Clean;
+    match xfa::xfa_xml_to_value(&xml, data_only, &args.xfa_always_array) {
+        Ok(mut value) => {
+            if let Some(map) = xfa_map {
+                value = map.apply(&value, args.xfa_strict_map);
+            }
+            if args.xfa_sort {
+                value.sort_all_objects();
+            }
+            Ok(Some(XfaData::Parsed(value)))
+        }
+        Err(e) => match args.xfa_on_error {
+            XfaOnError::Raw => {
+                warnings.push(
+                    WarningKind::XfaFallback,
+                    format!("Failed to parse XFA content to structured JSON ({}); outputting raw XFA XML.", e),
+                );
+                Ok(Some(XfaData::Raw(xml)))
+            }
+            XfaOnError::Skip => {
+                warnings.push(
+                    WarningKind::XfaFallback,
+                    format!("Failed to parse XFA content to structured JSON ({}); XFA section omitted.", e),
+                );
+                Ok(None)
+            }
+            XfaOnError::Fail => Err(CrabError::Pdf(format!("Failed to parse XFA content: {}", e))),
+        },
+    }
+}
+
+/// Render and OCR every annotation appearance on a page, keeping those that yield text.
+#[allow(clippy::too_many_arguments)]
+fn ocr_annotations(
+    renderer: &Renderer,
+    doc: &renderer::Document,
+    ocr_engine: &ocr::Ocr,
+    page_idx: i32,
+    dpi: i32,
+    redact_rects: &[[f32; 4]],
+    args: &Cli,
+    warnings: &mut Warnings,
+) -> Result<Vec<AnnotationResult>, CrabError> {
+    let mut results = Vec::new();
+    let count = renderer.annotation_count(doc, page_idx)?;
+
+    for index in 0..count {
+        let mut annot = match renderer.render_annotation(doc, page_idx, index, dpi) {
+            Ok(a) => a,
+            Err(e) => {
+                warnings.push_page(WarningKind::PageReadFailed, page_idx as usize + 1, None, e.to_string());
+                continue;
+            }
+        };
+
+        // Links and popups have no meaningful appearance; redacted zones are never OCRed
+        let redacted = redact_rects.iter().any(|r| redact::intersects(*r, annot.rect));
+        if annot.kind != "Link" && annot.kind != "Popup" && !redacted {
+            let image = GrayImage::from_pixmap(&annot.pixmap, renderer);
+            dump_ocr_input(args, &image, &format!("page_{:04}_annot{}", page_idx + 1, index + 1))?;
+            let result = ocr_engine.recognize(&image, dpi, args.granularity)?;
+            if !result.layer.text.trim().is_empty() {
+                results.push(AnnotationResult {
+                    kind: annot.kind.clone(),
+                    rect: annot.rect,
+                    layer: result.layer,
+                });
+            }
+        }
+        annot.pixmap.drop_with(renderer);
+    }
+
+    Ok(results)
+}
+
+/// OCR the regions of text-layer lines containing unmapped glyphs (U+FFFD) and
+/// substitute the result into the layer.
+#[allow(clippy::too_many_arguments)]
+fn encoding_fallback(
+    renderer: &Renderer,
+    doc: &renderer::Document,
+    ocr_engine: &ocr::Ocr,
+    page_idx: i32,
+    image: &GrayImage,
+    dpi: i32,
+    layer: &mut Layer,
+    granularity: Granularity,
+) -> Result<Vec<EncodingFallback>, CrabError> {
+    let scale = dpi as f32 / 72.0;
+    let mut regions = Vec::new();
+
+    for line in renderer.extract_lines(doc, page_idx)? {
+        // Lines no longer in the layer were redacted
+        if !line.text.contains(char::REPLACEMENT_CHARACTER) || !layer.text.contains(&line.text) {
+            continue;
+        }
+        // A little margin so glyph edges are not cut off
+        let [x0, y0, x1, y1] = line.rect;
+        let crop = image.crop([
+            (x0 * scale).floor() as i32 - 2,
+            (y0 * scale).floor() as i32 - 2,
+            (x1 * scale).ceil() as i32 + 2,
+            (y1 * scale).ceil() as i32 + 2,
+        ]);
+        if crop.width == 0 || crop.height == 0 {
+            continue;
+        }
+
+        let result = ocr_engine.recognize(&crop, dpi, Granularity::Page)?;
+        let text = result.layer.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            continue;
+        }
+        layer.text = layer.text.replacen(&line.text, &text, 1);
+        regions.push(EncodingFallback {
+            rect: line.rect,
+            original: line.text,
+            text,
+        });
+    }
+
+    if !regions.is_empty() {
+        layer.units = output::split_text(&layer.text, granularity);
+    }
+    Ok(regions)
+}
+
+/// Compare the text layer of a page against OCR of the rendered page.
+/// Returns `None` when either side has nothing to compare.
+fn text_layer_similarity(
+    renderer: &Renderer,
+    doc: &renderer::Document,
+    ocr_engine: &ocr::Ocr,
+    page_idx: usize,
+    args: &Cli,
+) -> Result<Option<f32>, CrabError> {
+    let text = renderer.extract_text(doc, page_idx as i32).unwrap_or_default();
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let dpi = match args.dpi {
+        Dpi::Fixed(dpi) => dpi,
+        Dpi::Auto => cli::auto_dpi(renderer.page_size(doc, page_idx as i32)?, args.dpi_target),
+    } as i32;
+    let image = render_gray(renderer, doc, page_idx as i32, dpi)?;
+    let result = ocr_engine.recognize(&image, dpi, Granularity::Page)?;
+
+    Ok(postprocess::text_layer_similarity(&text, &result.layer.text))
+}
+
+/// Boxes, color class and ink metrics of a page, for `--page-info`.
+fn page_info(renderer: &Renderer, doc: &renderer::Document, page_idx: i32, warnings: &mut Warnings) -> Result<PageInfo, CrabError> {
+    let boxes = renderer.page_boxes(doc, page_idx)?;
+    match renderer.render_page_rgb(doc, page_idx, analysis::ANALYSIS_DPI) {
+        Ok(mut pix) => {
+            let analysis = analysis::analyze(&pix, renderer);
+            pix.drop_with(renderer);
+            Ok(PageInfo { boxes, color: Some(analysis.color), ink: Some(analysis.ink) })
+        }
+        Err(e) => {
+            warnings.push_page(WarningKind::PageReadFailed, page_idx as usize + 1, None, e.to_string());
+            Ok(PageInfo { boxes, color: None, ink: None })
+        }
+    }
+}
+
+/// Left and right halves of a two-page spread, split at the detected gutter,
+/// in points. `None` if the page is not a spread.
+fn spread_halves(renderer: &Renderer, doc: &renderer::Document, page_idx: i32) -> Result<Option<[[f32; 4]; 2]>, CrabError> {
+    let image = render_gray(renderer, doc, page_idx, analysis::ANALYSIS_DPI)?;
+    let Some(gutter) = analysis::find_gutter(&image) else {
+        return Ok(None);
+    };
+    let (width, height) = renderer.page_size(doc, page_idx)?;
+    let x = gutter as f32 * 72.0 / analysis::ANALYSIS_DPI as f32;
+    Ok(Some([[0.0, 0.0, x, height], [x, 0.0, width, height]]))
+}
+
+/// Whether the center of `rect` lies inside `region`.
+fn contains_center(region: [f32; 4], rect: [f32; 4]) -> bool {
+    let (cx, cy) = ((rect[0] + rect[2]) / 2.0, (rect[1] + rect[3]) / 2.0);
+    region[0] <= cx && cx < region[2] && region[1] <= cy && cy < region[3]
+}
+
+/// Convert a rectangle in points to pixels at `dpi`, rounding outwards.
+fn to_pixels(rect: [f32; 4], dpi: i32) -> [i32; 4] {
+    let scale = dpi as f32 / 72.0;
+    [
+        (rect[0] * scale).floor() as i32,
+        (rect[1] * scale).floor() as i32,
+        (rect[2] * scale).ceil() as i32,
+        (rect[3] * scale).ceil() as i32,
+    ]
+}
+
+/// Render a page to a grayscale image.
+pub(crate) fn render_gray(renderer: &Renderer, doc: &renderer::Document, page_idx: i32, dpi: i32) -> Result<GrayImage, CrabError> {
+    let mut pix = renderer.render_page(doc, page_idx, dpi)?;
+    let image = GrayImage::from_pixmap(&pix, renderer);
+    pix.drop_with(renderer);
+    Ok(image)
+}
+
+/// Write the embedded font programs to `dir`, for glyph-level analysis.
+fn extract_fonts(
+    renderer: &Renderer,
+    doc: &renderer::Document,
+    fonts: &[renderer::FontInfo],
+    dir: &std::path::Path,
+    verbose: bool,
+    warnings: &mut Warnings,
+) -> Result<(), CrabError> {
+    std::fs::create_dir_all(dir).map_err(|e| CrabError::Cli(format!("Cannot create directory {:?}: {}", dir, e)))?;
+    for font in fonts.iter().filter(|f| f.format.is_some()) {
+        let program = match renderer.font_program(doc, font) {
+            Ok(program) => program,
+            Err(e) => {
+                warnings.push(WarningKind::FontReadFailed, e.to_string());
+                continue;
+            }
+        };
+        // Font names are untrusted: keep them to safe file name characters
+        let name: String = font
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || "+-_".contains(c) { c } else { '_' })
+            .collect();
+        let path = dir.join(format!("{}-{}.{}", font.object, name, font.extension()));
+        std::fs::write(&path, &program).map_err(CrabError::Output)?;
+        if verbose {
+            eprintln!("Wrote font {:?}", path);
+        }
+    }
+    Ok(())
+}
+
+/// Write an OCR input image to `dir` as PNG or JPEG (`--export-images`), named
+/// `.partial` until complete as with `--dump-ocr-input`.
+fn export_image(renderer: &Renderer, image: &GrayImage, dpi: i32, dir: &std::path::Path, name: &str, format: ImageFormat, verbose: bool) -> Result<std::path::PathBuf, CrabError> {
+    let path = dir.join(format!("{}.{}", name, format.extension()));
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    renderer.save_gray(image, dpi, std::path::Path::new(&partial), format.jpeg_quality())?;
+    std::fs::rename(&partial, &path).map_err(CrabError::Output)?;
+    if verbose {
+        eprintln!("Wrote page image {:?}", path);
+    }
+    Ok(path)
+}
+
+/// Save an image exactly as handed to Tesseract when `--dump-ocr-input` is set.
+fn dump_ocr_input(args: &Cli, image: &GrayImage, name: &str) -> Result<(), CrabError> {
+    if let Some(dir) = &args.dump_ocr_input {
+        let path = dir.join(format!("{}.pgm", name));
+        image.write_pgm(&path).map_err(CrabError::Output)?;
+        if args.verbose {
+            eprintln!("Wrote OCR input {:?}", path);
+        }
+    }
+    Ok(())
+}
+
+/// Passes text through, counting its characters (`--summary` with `--stream-text`).
+struct CharCount<'a> {
+    out: &'a mut dyn Write,
+    chars: usize,
+}
+
+impl Write for CharCount<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.out.write(buf)?;
+        // Every UTF-8 character has one byte that is not a continuation byte
+        self.chars += buf[..n].iter().filter(|&&b| b & 0xC0 != 0x80).count();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
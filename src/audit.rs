@@ -0,0 +1,146 @@
+use crate::digest::sha256_hex;
+use crate::errors::CrabError;
+use crate::output::PageResult;
+use serde_json::{json, Map, Value};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Append-only NDJSON trail of a run (`--audit-log`): one record per processed
+/// page and one for the document, written as they happen so a crash leaves
+/// the records up to that point.
+pub struct AuditLog {
+    file: File,
+    started: Instant,
+    /// Command line, as the record of options used.
+    options: Vec<String>,
+    /// `--meta` tags, repeated in every record.
+    meta: Map<String, Value>,
+    input: Option<String>,
+    sha256: Option<String>,
+    pages: usize,
+}
+
+impl AuditLog {
+    pub fn open(path: &Path, meta: &[(String, String)]) -> Result<Self, CrabError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| CrabError::Cli(format!("Cannot open audit log {:?}: {}", path, e)))?;
+        Ok(Self {
+            file,
+            started: Instant::now(),
+            options: std::env::args().skip(1).collect(),
+            meta: crate::output::meta_to_json(meta),
+            input: None,
+            sha256: None,
+            pages: 0,
+        })
+    }
+
+    /// Identify the input in later records. `name` is the path, or "-" for STDIN.
+    pub fn set_input(&mut self, name: String, content: &[u8]) {
+        self.input = Some(name);
+        self.sha256 = Some(sha256_hex(content));
+    }
+
+    /// Record a processed page. `warnings` counts the warnings raised for it.
+    pub fn page(&mut self, page: &PageResult, warnings: usize) -> Result<(), CrabError> {
+        self.pages += 1;
+        let chars = |layer: &Option<crate::output::Layer>| layer.as_ref().map(|l| l.text.trim().chars().count());
+        let mut record = self.record("page");
+        record["page"] = json!(page.number);
+        if let Some(part) = page.part {
+            record["part"] = json!(part.to_string());
+        }
+        if let Some(hash) = &page.raster_hash {
+            record["raster_hash"] = json!(hash);
+        }
+        record["text_layer_chars"] = json!(chars(&page.text_layer));
+        record["ocr_layer_chars"] = json!(chars(&page.ocr_layer));
+        record["annotations"] = json!(page.annotations.len());
+        record["warnings"] = json!(warnings);
+        self.write(&record)
+    }
+
+    /// Record the outcome of the run: "ok", or the error that ended it.
+    pub fn document(&mut self, result: &Result<(), CrabError>, warnings: usize) -> Result<(), CrabError> {
+        let mut record = self.record("document");
+        record["options"] = json!(self.options);
+        record["pages_processed"] = json!(self.pages);
+        record["warnings"] = json!(warnings);
+        record["duration_ms"] = json!(self.started.elapsed().as_millis() as u64);
+        match result {
+            Ok(()) => record["status"] = json!("ok"),
+            Err(e) => {
+                record["status"] = json!("error");
+                record["exit_code"] = json!(e.exit_code());
+                record["error"] = json!(e.to_string());
+            }
+        }
+        self.write(&record)
+    }
+
+    fn record(&self, event: &str) -> Value {
+        let mut record = json!({
+            "event": event,
+            "time": utc_timestamp(SystemTime::now()),
+            "input": self.input,
+            "sha256": self.sha256,
+        });
+        if !self.meta.is_empty() {
+            record["meta"] = Value::Object(self.meta.clone());
+        }
+        record
+    }
+
+    fn write(&mut self, record: &Value) -> Result<(), CrabError> {
+        // One write per line keeps concurrent appenders from interleaving records
+        let line = format!("{}\n", record);
+        self.file.write_all(line.as_bytes()).map_err(CrabError::Output)
+    }
+}
+
+/// ISO 8601 UTC time with milliseconds, e.g. "2024-01-31T12:00:00.000Z".
+pub fn utc_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_utc_timestamp() {
+        let t = UNIX_EPOCH + Duration::from_millis(1_706_702_400_123);
+        assert_eq!(utc_timestamp(t), "2024-01-31T12:00:00.123Z");
+        assert_eq!(utc_timestamp(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000-02-29T00:00:00.000Z");
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+
+/// Share of a limit after which remaining pages are OCRed at `DEGRADED_DPI`.
+const LOWER_DPI_AT: f64 = 0.5;
+/// Share of a limit after which remaining pages are not OCRed.
+const SKIP_OCR_AT: f64 = 0.8;
+/// OCR DPI of pages processed past `LOWER_DPI_AT`.
+pub const DEGRADED_DPI: i32 = 150;
+/// CPU seconds allowed past `--cpu-time-limit` before the kernel stops the
+/// process (RLIMIT_CPU), for a page stuck in OCR.
+const CPU_GRACE_SECS: u64 = 30;
+
+/// How pages are processed as the run nears its limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Degradation {
+    /// OCR at `DEGRADED_DPI`.
+    LowerDpi,
+    /// No OCR.
+    SkipOcr,
+    /// A limit is reached: remaining pages are not processed.
+    Stop,
+}
+
+impl Degradation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Degradation::LowerDpi => "lower_dpi",
+            Degradation::SkipOcr => "ocr_skipped",
+            Degradation::Stop => "stopped",
+        }
+    }
+
+    /// What happens to the remaining pages, for warnings.
+    pub fn describe(&self) -> String {
+        match self {
+            Degradation::LowerDpi => format!("are OCRed at {} DPI", DEGRADED_DPI),
+            Degradation::SkipOcr => "are not OCRed".to_string(),
+            Degradation::Stop => "are not processed".to_string(),
+        }
+    }
+}
+
+/// The CPU and wall-clock time a run may use (`--cpu-time-limit`, `--wall-limit`).
+pub struct Budget {
+    cpu: Option<Duration>,
+    wall: Option<Duration>,
+    started: Instant,
+}
+
+impl Budget {
+    /// Limits in seconds; the wall clock starts now.
+    pub fn new(cpu_secs: Option<u64>, wall_secs: Option<u64>) -> Self {
+        Self { cpu: cpu_secs.map(Duration::from_secs), wall: wall_secs.map(Duration::from_secs), started: Instant::now() }
+    }
+
+    /// Have the kernel stop the process shortly past the CPU limit, should a
+    /// page run on after the limit is reached. No-op without a CPU limit.
+    pub fn enforce(&self) -> std::io::Result<()> {
+        let Some(cpu) = self.cpu else {
+            return Ok(());
+        };
+        unsafe {
+            let mut limit: libc::rlimit = std::mem::zeroed();
+            if libc::getrlimit(libc::RLIMIT_CPU, &mut limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let soft = (cpu.as_secs() + CPU_GRACE_SECS) as libc::rlim_t;
+            if limit.rlim_max == libc::RLIM_INFINITY || soft < limit.rlim_max {
+                limit.rlim_cur = soft;
+            }
+            if libc::setrlimit(libc::RLIMIT_CPU, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// How the next page must be processed, with the limit closest to being
+    /// reached ("CPU time" or "wall time") and the share of it used.
+    pub fn check(&self) -> Option<(Degradation, &'static str, f64)> {
+        let wall = self.wall.map(|l| (self.started.elapsed().as_secs_f64() / l.as_secs_f64(), "wall time"));
+        let cpu = self.cpu.map(|l| (cpu_time().as_secs_f64() / l.as_secs_f64(), "CPU time"));
+        let (share, limit) = wall.into_iter().chain(cpu).max_by(|a, b| a.0.total_cmp(&b.0))?;
+        degradation(share).map(|d| (d, limit, share))
+    }
+}
+
+/// Degradation once `share` of a limit is used.
+fn degradation(share: f64) -> Option<Degradation> {
+    if share >= 1.0 {
+        Some(Degradation::Stop)
+    } else if share >= SKIP_OCR_AT {
+        Some(Degradation::SkipOcr)
+    } else if share >= LOWER_DPI_AT {
+        Some(Degradation::LowerDpi)
+    } else {
+        None
+    }
+}
+
+/// User and system CPU time of the process so far, all threads included.
+fn cpu_time() -> Duration {
+    let usage = unsafe {
+        let mut usage: libc::rusage = std::mem::zeroed();
+        if libc::getrusage(libc::RUSAGE_SELF, &mut usage) != 0 {
+            return Duration::ZERO;
+        }
+        usage
+    };
+    let micros = |t: libc::timeval| t.tv_sec as u64 * 1_000_000 + t.tv_usec as u64;
+    Duration::from_micros(micros(usage.ru_utime) + micros(usage.ru_stime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degradation() {
+        assert_eq!(degradation(0.2), None);
+        assert_eq!(degradation(0.5), Some(Degradation::LowerDpi));
+        assert_eq!(degradation(0.9), Some(Degradation::SkipOcr));
+        assert_eq!(degradation(1.3), Some(Degradation::Stop));
+        assert!(Budget::new(None, None).check().is_none());
+        assert!(Budget::new(Some(3600), Some(3600)).check().is_none());
+    }
+}
@@ -1,9 +1,14 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use crate::warnings::WarningKind;
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Input PDF file. If not provided, reads from STDIN.
     #[arg(value_name = "FILE")]
     pub input: Option<PathBuf>,
@@ -12,18 +17,84 @@ pub struct Cli {
     #[arg(short, long, default_value = "eng")]
     pub lang: String,
 
-    /// DPI for rasterization.
-    #[arg(short, long, default_value_t = 300)]
-    pub dpi: u32,
+    /// Speed/accuracy trade-off setting the DPI, models, preprocessing and
+    /// low-confidence retries at once; options given explicitly take precedence.
+    #[arg(long, value_enum)]
+    pub preset: Option<Preset>,
+
+    /// DPI for rasterization, or "auto" to derive it from each page's size.
+    #[arg(short, long, default_value = "300")]
+    pub dpi: Dpi,
+
+    /// Clamp out-of-range DPI values with a warning instead of failing.
+    #[arg(long)]
+    pub dpi_clamp: bool,
+
+    /// Raise the DPI ceiling to 1200 (e.g. microfiche). Renders need 4x the memory of 600 DPI.
+    #[arg(long)]
+    pub i_know_what_im_doing: bool,
+
+    /// Longest rendered edge in pixels with `--dpi auto` (3508 = A4 at 300 DPI).
+    #[arg(long, value_name = "PX", default_value_t = 3508)]
+    pub dpi_target: u32,
 
     /// Enable verbose logging to STDERR.
     #[arg(short, long)]
     pub verbose: bool,
 
+    /// Print nothing to STDERR but one summary line at the end, e.g.
+    /// "status=ok pages=12 warnings=0 duration_ms=5120".
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Fail the run (exit code 8) when a warning of these kinds occurs, once
+    /// output is complete. Comma-separated or repeated.
+    #[arg(long, value_enum, value_name = "KIND", value_delimiter = ',')]
+    pub warn_as_error: Vec<WarningKind>,
+
+    /// Drop warnings of these kinds: not printed, listed or counted.
+    #[arg(long, value_enum, value_name = "KIND", value_delimiter = ',')]
+    pub ignore_warning: Vec<WarningKind>,
+
     /// XFA extraction mode.
     #[arg(short = 'x', long, value_enum, default_value_t = XfaMode::Clean)]
     pub xfa: XfaMode,
 
+    /// JSON object renaming XFA field paths to output keys, e.g.
+    /// `{"Form.Applicant.name": "applicant_name"}`. Output becomes one key per field.
+    #[arg(long, value_name = "FILE")]
+    pub xfa_map: Option<PathBuf>,
+
+    /// With --xfa-map, drop fields the map does not cover.
+    #[arg(long, requires = "xfa_map")]
+    pub xfa_strict_map: bool,
+
+    /// XFA elements to output as arrays even with a single instance, by name or
+    /// path (e.g. "Form.Items"). Subforms the template marks repeatable always are.
+    #[arg(long, value_name = "PATHS", value_delimiter = ',')]
+    pub xfa_always_array: Vec<String>,
+
+    /// Sort XFA JSON fields alphabetically instead of keeping the form's order.
+    #[arg(long)]
+    pub xfa_sort: bool,
+
+    /// Same as `--mode xfa-only`.
+    #[arg(long, hide = true)]
+    pub xfa_only: bool,
+
+    /// What to do when XFA cannot be parsed in full or clean mode.
+    #[arg(long, value_enum, default_value_t = XfaOnError::Raw)]
+    pub xfa_on_error: XfaOnError,
+
+    /// Also write the raw XFA XML to FILE, whatever the XFA mode and outcome.
+    #[arg(long, value_name = "FILE")]
+    pub xfa_raw: Option<PathBuf>,
+
+    /// Write the XFA section to a file or an open file descriptor (a number,
+    /// e.g. 3) instead of the primary output: JSON, or XML with `--xfa raw`.
+    #[arg(long, value_name = "PATH|FD")]
+    pub xfa_out: Option<XfaOut>,
+
     /// Extraction mode.
     #[arg(short = 'm', long, value_enum, default_value_t = Mode::Hybrid)]
     pub mode: Mode,
@@ -35,6 +106,401 @@ pub struct Cli {
     /// Timeout in seconds (default: 0, no timeout).
     #[arg(short, long, default_value_t = 0)]
     pub timeout: u64,
+
+    /// CPU seconds the run may use (all threads). Past half of it, remaining
+    /// pages are OCRed at 150 DPI; past 80%, without OCR; at the limit, they are
+    /// not processed. The kernel stops the process 30 seconds past it.
+    #[arg(long, value_name = "SEC")]
+    pub cpu_time_limit: Option<u64>,
+
+    /// Wall-clock seconds the run may take, degrading remaining pages as
+    /// `--cpu-time-limit` does.
+    #[arg(long, value_name = "SEC")]
+    pub wall_limit: Option<u64>,
+
+    /// Fail on a page nesting form XObjects or tiling patterns more than N
+    /// levels deep (exit code 10), checked before the page is read.
+    #[arg(long, value_name = "N")]
+    pub max_object_depth: Option<u32>,
+
+    /// Fail on a page whose resources hold more than N images (exit code 10).
+    #[arg(long, value_name = "N")]
+    pub max_page_images: Option<u32>,
+
+    /// Fail on a page whose content streams, with those of its forms and
+    /// patterns, decode to more than MB megabytes (exit code 10).
+    #[arg(long, value_name = "MB")]
+    pub max_content_mb: Option<u64>,
+
+    /// Try a page that fails to render up to N more times, waiting 100 ms,
+    /// then 200 ms and so on, and freeing MuPDF's cache before each, for
+    /// failures under memory pressure. The page still fails after the last.
+    #[arg(long, value_name = "N", default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=10))]
+    pub render_retries: u32,
+
+    /// Also OCR annotation appearances (stamps, signatures) separately.
+    #[arg(long)]
+    pub annotations: bool,
+
+    /// OCR each page with several preprocessing variants and keep the most
+    /// confident reading of each word (about 3x slower).
+    #[arg(long)]
+    pub ensemble: bool,
+
+    /// Drop the OCR text of pages whose mean word confidence (0-100) is below
+    /// CONF, as noise from blank or garbled scans; 0 keeps every page.
+    #[arg(long, value_name = "CONF", default_value_t = crate::ocr::MIN_MEAN_CONF, value_parser = clap::value_parser!(i32).range(0..=100))]
+    pub min_confidence: i32,
+
+    /// Keep the OCR text of every page, and wrap the words recognized with a
+    /// confidence below CONF (0-100) in --markers, for review.
+    #[arg(long, value_name = "CONF", value_parser = clap::value_parser!(i32).range(0..=100), conflicts_with = "min_confidence")]
+    pub mark_words_below: Option<i32>,
+
+    /// Opening and closing markers of --mark-words-below, separated by a comma.
+    #[arg(long, value_name = "OPEN,CLOSE", default_value = "⟦,⟧", value_parser = parse_markers, requires = "mark_words_below")]
+    pub markers: (String, String),
+
+    /// Re-run OCR with --ensemble on pages whose mean confidence is below --min-confidence.
+    #[arg(long)]
+    pub retry_low_confidence: bool,
+
+    /// Tesseract models to use: the installed ones, or a `tessdata_fast` or
+    /// `tessdata_best` folder next to the executable or the installed tessdata.
+    #[arg(long, value_enum, default_value_t = Models::Installed)]
+    pub models: Models,
+
+    /// Read OCR words as the characters look, without Tesseract's word lists,
+    /// so serial numbers and codes are not "corrected" into dictionary words.
+    #[arg(long)]
+    pub no_dict: bool,
+
+    /// Keep runs of spaces between OCR words as measured, so fixed-width
+    /// reports keep their columns. Implied by --layout.
+    #[arg(long)]
+    pub preserve_spaces: bool,
+
+    /// Tesseract page segmentation mode (1-13) instead of the automatic one,
+    /// e.g. 4 for single-column receipts, 6 for a single block of text or 11
+    /// for sparse text. Only 1 and 12 also detect the page rotation. Mode 0
+    /// (orientation detection only) reads no text and is rejected.
+    #[arg(long, value_name = "PSM", value_parser = clap::value_parser!(u32).range(1..=13))]
+    pub psm: Option<u32>,
+
+    /// JSON list of regions to redact before extraction and OCR:
+    /// `[{"page": 1, "rect": [x0, y0, x1, y1]}]` in points; omit "page" for every page.
+    #[arg(long, value_name = "FILE")]
+    pub redact: Option<PathBuf>,
+
+    /// Write each page, rendered with redactions applied, to DIR as PGM.
+    #[arg(long, value_name = "DIR")]
+    pub redacted_images: Option<PathBuf>,
+
+    /// Also write a searchable PDF: each OCRed page image with its text as an
+    /// invisible layer (OCR or hybrid mode).
+    #[arg(long, value_name = "FILE")]
+    pub output_pdf: Option<PathBuf>,
+
+    /// Leave the words recognized with a confidence below CONF (0-100) out of
+    /// the --output-pdf text layer; only the page image shows them.
+    #[arg(long, value_name = "CONF", default_value_t = 0, value_parser = clap::value_parser!(i32).range(0..=100), requires = "output_pdf")]
+    pub pdf_min_word_conf: i32,
+
+    /// Write the embedded font programs of the document to DIR, as
+    /// "<object>-<name>.<ext>" (pfa, ttf, cff or otf).
+    #[arg(long, value_name = "DIR")]
+    pub extract_fonts: Option<PathBuf>,
+
+    /// Add each page's OCR words to JSON output, with their confidence, block,
+    /// paragraph and line, and their box in pixels and on the page in points.
+    #[arg(long)]
+    pub words: bool,
+
+    /// Detect tables on each page, from the text layer or OCR, and output
+    /// each as CSV (text) or as rows and CSV (JSON), numbered per page.
+    #[arg(long)]
+    pub tables: bool,
+
+    /// Split each page into header, body, sidebar and footer zones, from the
+    /// text layer or OCR blocks, and output each with its text and bounds.
+    #[arg(long)]
+    pub zones: bool,
+
+    /// Keep the page layout in text output: text is placed at the column of
+    /// its position on the page, so columns and tables stay aligned.
+    #[arg(long)]
+    pub layout: bool,
+
+    /// Write each page's text layer to text output as it is extracted, without
+    /// holding it in memory, for pages with megabytes of text. Checks that
+    /// need the whole text (suspicious or outlined text) are skipped.
+    #[arg(long)]
+    pub stream_text: bool,
+
+    /// Add the text layer of each page to JSON output as blocks, lines and
+    /// spans, with their bounds in points and each span's font and size.
+    #[arg(long)]
+    pub text_structure: bool,
+
+    /// Add a content hash of each page's render to JSON output and the audit
+    /// log, to spot duplicate or changed pages across documents.
+    #[arg(long)]
+    pub hash_pages: bool,
+
+    /// Detect two-page spreads (book scans) and process each half as its own page (12a, 12b).
+    #[arg(long)]
+    pub split_spread: bool,
+
+    /// In hybrid mode, output one reading order per page: OCR of regions without
+    /// text layer (scanned figures, images) is placed among the text-layer blocks
+    /// by position, instead of text and OCR layers.
+    #[arg(long, visible_alias = "merge-layers")]
+    pub merge: bool,
+
+    /// In hybrid mode, align OCR words with the text layer's words, report where they
+    /// disagree and give each text-layer word a confidence comparable to OCR's (JSON).
+    #[arg(long)]
+    pub align: bool,
+
+    /// Split the output into documents at separator sheets (batch scans):
+    /// `blank` pages. Separator pages are left out; other pages keep their
+    /// PDF numbers. With --output-pdf, each document gets its own PDF.
+    #[arg(long, value_name = "SEPARATOR", value_parser = parse_split_on)]
+    pub split_on: Option<SplitOn>,
+
+    /// Remove black scanner borders, edge noise and punch holes before OCR.
+    #[arg(long)]
+    pub clean_borders: bool,
+
+    /// Filter page images before OCR for a kind of degraded source. Each
+    /// source needs its own filters: those removing fax specks erode the
+    /// fine detail of photos.
+    #[arg(long, value_enum, value_name = "SOURCE")]
+    pub denoise: Option<Denoise>,
+
+    /// Detect stamps and signatures on OCRed pages: report their bounds, or
+    /// also mask them so Tesseract does not read characters into them.
+    #[arg(long, value_enum, default_value_t = StampMode::Off)]
+    pub stamps: StampMode,
+
+    /// Write each image handed to Tesseract to DIR as PGM, for reproducing OCR issues.
+    #[arg(long, value_name = "DIR")]
+    pub dump_ocr_input: Option<PathBuf>,
+
+    /// Write each page image handed to Tesseract to DIR as PNG or JPEG, to
+    /// archive the raster OCR ran on.
+    #[arg(long, value_name = "DIR")]
+    pub export_images: Option<PathBuf>,
+
+    /// Format of --export-images.
+    #[arg(long, value_enum, default_value_t = ImageFormat::Png)]
+    pub image_format: ImageFormat,
+
+    /// What to do when the --dump-ocr-input filesystem looks too small for the run.
+    #[arg(long, value_enum, default_value_t = SpaceCheck::Fail)]
+    pub space_check: SpaceCheck,
+
+    /// Drop OCR lines whose share of alphanumeric characters is below RATIO (0-1).
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+    pub garbage_ratio: Option<f32>,
+
+    /// Compare the text layer against OCR of the first page to catch broken encodings.
+    #[arg(long, value_enum, default_value_t = TextLayerCheck::Off)]
+    pub check_text_layer: TextLayerCheck,
+
+    /// In text mode, also OCR pages whose text layer looks broken.
+    #[arg(long)]
+    pub ocr_suspicious: bool,
+
+    /// In text mode, also OCR pages without text layer whose text is drawn as vector outlines.
+    #[arg(long)]
+    pub ocr_outlines: bool,
+
+    /// Skip language-specific normalization of OCR output (selected from --lang).
+    #[arg(long)]
+    pub no_lang_rules: bool,
+
+    /// Report page boxes (MediaBox, CropBox, TrimBox) in points, millimeters and inches;
+    /// classify the page as color, grayscale or bilevel, and measure ink coverage.
+    /// Also lists the document's fonts, JavaScript, launch actions, embedded files and external references.
+    #[arg(long)]
+    pub page_info: bool,
+
+    /// Add per-page statistics: character counts, mean OCR confidence, render
+    /// and OCR time, script and orientation, and whether OCR ran, to audit
+    /// quality across batches.
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Tag each page, and each text unit long enough (see --granularity), with
+    /// the language detected in its text as an ISO 639-3 code, whatever the
+    /// --lang it was read with.
+    #[arg(long)]
+    pub detect_language: bool,
+
+    /// End text output with a machine-parseable summary of the run: pages
+    /// processed and skipped, warnings, characters and duration.
+    #[arg(long)]
+    pub summary: bool,
+
+    /// Validate the document, range, languages and output paths, print the
+    /// effective configuration, and exit without processing.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print the JSON Schema of JSON output (and of NDJSON lines) and exit.
+    #[arg(long)]
+    pub print_schema: bool,
+
+    /// Output format. Repeat it with `--output` to write several formats in one
+    /// run: each `--format` goes to the `--output` at the same position.
+    #[arg(short = 'f', long, value_enum, default_values_t = [Format::Text])]
+    pub format: Vec<Format>,
+
+    /// Write output to FILE instead of STDOUT ("-" for STDOUT). Repeatable;
+    /// pages are processed once and written to every output.
+    #[arg(short = 'o', long, value_name = "FILE")]
+    pub output: Vec<PathBuf>,
+
+    /// Write output next to the input instead of STDOUT, named after it with the
+    /// extension of each --format (report.pdf to report.txt), or EXT with one format.
+    #[arg(long, value_name = "EXT", num_args = 0..=1, require_equals = true, conflicts_with_all = ["output", "split_pages"])]
+    pub sidecar: Option<Option<String>>,
+
+    /// What to do when a --sidecar file already exists: fail, skip the input,
+    /// overwrite it, or write report-1.txt (the first free number) instead.
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = SidecarExists::Fail, requires = "sidecar")]
+    pub sidecar_exists: SidecarExists,
+
+    /// Line endings of text output (all formats but DOCX and XLSX): LF, or
+    /// CRLF for Windows tools. Line endings are kept as extracted without it.
+    #[arg(long, value_enum, value_name = "EOL")]
+    pub newline: Option<Newline>,
+
+    /// Start text output with a UTF-8 byte order mark, for Windows tools that need one.
+    #[arg(long)]
+    pub bom: bool,
+
+    /// Directory for per-page output files, with --split-pages.
+    #[arg(long, value_name = "DIR", requires = "split_pages")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Write each page to its own file in --output-dir (page_0001.txt, .json, ... per
+    /// --format), with form data, warnings and a manifest of the pages in index.json.
+    #[arg(long, requires = "output_dir", conflicts_with = "output")]
+    pub split_pages: bool,
+
+    /// Encrypt every output for RECIPIENT as it is written, so results never
+    /// reach the disk in clear: an age or SSH public key (with `age`), else a
+    /// GnuPG key ID or email (with `gpg`). The --audit-log stays in clear.
+    #[arg(long, value_name = "RECIPIENT")]
+    pub encrypt_output: Option<String>,
+
+    /// Unit of records in structured output (ignored for text output).
+    #[arg(short = 'g', long, value_enum, default_value_t = Granularity::Page)]
+    pub granularity: Granularity,
+
+    /// Append one JSON line per processed page and per run to FILE (time, input
+    /// SHA-256, options, result summary, errors), as an audit trail. It holds
+    /// no extracted text and is written in clear, also with --encrypt-output.
+    #[arg(long, value_name = "FILE")]
+    pub audit_log: Option<PathBuf>,
+
+    /// Tag the run with KEY=VALUE (repeatable), copied verbatim into the JSON
+    /// output and the audit log (e.g. a case or batch ID).
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_meta)]
+    pub meta: Vec<(String, String)>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Print a shell completion script (completes --lang from the installed tessdata).
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page (roff).
+    Man,
+    /// Extract, render and OCR an embedded sample PDF to validate the installation.
+    Selftest,
+    /// Compare the XFA form data of two PDFs and print changed, added and removed fields as JSON.
+    XfaDiff {
+        /// Earlier version of the form.
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+        /// Later version of the form.
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+        /// Also compare metadata fields and lookup lists (as `--xfa full`).
+        #[arg(long)]
+        full: bool,
+    },
+    /// Compare the text of two PDFs page by page and print the added and removed lines as JSON.
+    Diff {
+        /// First document, e.g. the digital original.
+        #[arg(value_name = "A")]
+        a: PathBuf,
+        /// Second document, e.g. the signed scan.
+        #[arg(value_name = "B")]
+        b: PathBuf,
+        /// Where page text comes from. Hybrid reads the text layer, or OCR for pages without one.
+        #[arg(short, long, value_enum, default_value_t = Mode::Hybrid)]
+        mode: Mode,
+        /// Tesseract language code(s).
+        #[arg(short, long, default_value = "eng")]
+        lang: String,
+        /// DPI for rasterization of OCRed pages.
+        #[arg(short, long, default_value_t = 300, value_parser = clap::value_parser!(i32).range(MIN_DPI as i64..=MAX_DPI as i64))]
+        dpi: i32,
+        /// Include the bounds of each line on its page, in points, for visual diffing.
+        #[arg(long)]
+        boxes: bool,
+    },
+    /// Group the pages of several documents by visual similarity and print the clusters as JSON.
+    Cluster {
+        /// Documents to compare, e.g. a day's intake.
+        #[arg(value_name = "FILE", required = true)]
+        inputs: Vec<PathBuf>,
+        /// Most bits (of 256) in which the hashes of two neighboring pages of a cluster may differ.
+        #[arg(long, default_value_t = 32, value_parser = clap::value_parser!(u32).range(0..=256))]
+        max_distance: u32,
+    },
+    /// Write a copy of a PDF whose hidden text is replaced by reviewed corrections at given boxes.
+    Correct {
+        /// PDF whose text layer is corrected, e.g. a scan written with --output-pdf.
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+        /// JSON array of {"page", "rect", "text"} corrections, with rect in points (or pixels at "dpi").
+        #[arg(value_name = "CORRECTIONS")]
+        corrections: PathBuf,
+        /// Where to write the corrected PDF.
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Generate a PDF of known text in given fonts, sizes and rotations, extract and OCR it, and print the share of words recovered as JSON.
+    SynthTest {
+        /// Standard 14 fonts to set the text in.
+        #[arg(long, value_delimiter = ',', default_value = "Helvetica,Times-Roman,Courier")]
+        fonts: Vec<String>,
+        /// Font sizes in points (4-32).
+        #[arg(long, value_delimiter = ',', default_value = "9,12,24")]
+        sizes: Vec<f32>,
+        /// Text rotations in degrees counterclockwise (0, 90, 180 or 270).
+        #[arg(long, value_delimiter = ',', default_value = "0,90,180,270", value_parser = parse_rotation)]
+        rotations: Vec<u32>,
+        /// Tesseract language code(s).
+        #[arg(short, long, default_value = "eng")]
+        lang: String,
+        /// DPI for rasterization of OCRed pages.
+        #[arg(short, long, default_value_t = 300, value_parser = clap::value_parser!(i32).range(MIN_DPI as i64..=MAX_DPI as i64))]
+        dpi: i32,
+        /// Least mean share of words (0-1) each of the text layer and OCR must recover.
+        #[arg(long, default_value_t = 0.9, value_parser = parse_ratio)]
+        min_recovery: f32,
+        /// Also keep the generated PDF at this path.
+        #[arg(long, value_name = "FILE")]
+        keep: Option<PathBuf>,
+    },
 }
 
 #[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
@@ -49,6 +515,16 @@ pub enum XfaMode {
     Clean,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum XfaOnError {
+    /// Warn and output the raw XML instead of JSON.
+    Raw,
+    /// Warn and leave the XFA section out.
+    Skip,
+    /// Stop with an error.
+    Fail,
+}
+
 #[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
 pub enum Mode {
     /// Extract text (MuPDF) then OCR (Tesseract).
@@ -57,6 +533,382 @@ pub enum Mode {
     Text,
     /// Render and OCR only (Tesseract).
     Ocr,
+    /// Only extract form data (XFA, signature fields), opening the file as a
+    /// bare PDF: no page loading, text extraction or OCR.
+    XfaOnly,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum TextLayerCheck {
+    /// Trust the text layer.
+    Off,
+    /// Warn when the text layer does not match what OCR reads.
+    Warn,
+    /// Warn and switch to OCR mode.
+    Switch,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum StampMode {
+    /// No detection.
+    Off,
+    /// Report stamp and signature bounds.
+    Report,
+    /// Report them and blank them out before OCR.
+    Mask,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Denoise {
+    /// Salt-and-pepper specks and scan lines dropped from strokes.
+    Fax,
+    /// Faded, low-contrast print on a gray or uneven background.
+    Thermal,
+    /// Sensor noise and uneven lighting of camera shots, keeping edges sharp.
+    Photo,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    /// 200 DPI, fast models, no preprocessing.
+    Fast,
+    /// 300 DPI, installed models, border cleaning, ensemble retry of low-confidence pages.
+    Balanced,
+    /// 400 DPI, best models, border cleaning, ensemble OCR of every page.
+    Best,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Models {
+    /// Models in the tessdata folder Tesseract uses.
+    Installed,
+    /// tessdata_fast: integer models, quicker and slightly less accurate.
+    Fast,
+    /// tessdata_best: float models, slower and most accurate.
+    Best,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitOn {
+    /// Blank pages: no ink away from the edges.
+    Blank,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Newline {
+    /// Unix line endings (`\n`).
+    Lf,
+    /// Windows line endings (`\r\n`).
+    Crlf,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    /// Lossless, the exact pixels.
+    Png,
+    /// Lossy at quality 90, about a fifth of the size.
+    Jpeg,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+
+    /// JPEG quality (1-100), for lossy formats.
+    pub fn jpeg_quality(&self) -> Option<i32> {
+        match self {
+            ImageFormat::Png => None,
+            ImageFormat::Jpeg => Some(90),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SpaceCheck {
+    /// Skip the free space check.
+    Off,
+    /// Warn and continue.
+    Warn,
+    /// Refuse to start.
+    Fail,
+}
+
+pub const MIN_DPI: u32 = 72;
+pub const MAX_DPI: u32 = 600;
+/// Ceiling with `--i-know-what-im-doing`.
+pub const MAX_DPI_EXTENDED: u32 = 1200;
+
+/// Rasterization DPI: fixed, or chosen per page from its physical size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dpi {
+    Fixed(u32),
+    Auto,
+}
+
+impl std::str::FromStr for Dpi {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Dpi::Auto);
+        }
+        s.parse()
+            .map(Dpi::Fixed)
+            .map_err(|_| format!("expected a number or 'auto', got '{}'", s))
+    }
+}
+
+/// Where `--xfa-out` writes the XFA section: a file, or a descriptor the
+/// caller opened (`3>form.json`). A file named like a number is given as `./3`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum XfaOut {
+    Path(PathBuf),
+    Fd(i32),
+}
+
+impl std::str::FromStr for XfaOut {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("expected a path or a file descriptor".into());
+        }
+        if !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(XfaOut::Path(PathBuf::from(s)));
+        }
+        match s.parse() {
+            Ok(fd) if fd > 2 => Ok(XfaOut::Fd(fd)),
+            _ => Err(format!("file descriptor {} is not usable: give one above 2 (0-2 are STDIN, STDOUT and STDERR)", s)),
+        }
+    }
+}
+
+/// Pick the DPI at which the longest edge of a page (in points) renders at
+/// `target_px` pixels, clamped to the default 72-600 range.
+pub fn auto_dpi(page_size: (f32, f32), target_px: u32) -> u32 {
+    let longest_inches = page_size.0.max(page_size.1) / 72.0;
+    if longest_inches <= 0.0 {
+        return 300;
+    }
+    ((target_px as f32 / longest_inches).round() as u32).clamp(MIN_DPI, MAX_DPI)
+}
+
+fn parse_ratio(s: &str) -> Result<f32, String> {
+    let ratio: f32 = s.parse().map_err(|_| format!("'{}' is not a number", s))?;
+    if !(0.0..=1.0).contains(&ratio) {
+        return Err(format!("ratio must be between 0 and 1, got {}", ratio));
+    }
+    Ok(ratio)
+}
+
+fn parse_rotation(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(degrees @ (0 | 90 | 180 | 270)) => Ok(degrees),
+        _ => Err(format!("rotation must be 0, 90, 180 or 270, got '{}'", s)),
+    }
+}
+
+impl Cli {
+    /// Parse the command line, then apply the `--preset` settings to the
+    /// options that were not given explicitly.
+    pub fn parse_args() -> Self {
+        let matches = Self::command().get_matches();
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        cli.apply_preset(&matches);
+        if cli.xfa_only {
+            cli.mode = Mode::XfaOnly;
+        }
+        cli
+    }
+
+    fn apply_preset(&mut self, matches: &ArgMatches) {
+        let Some(preset) = self.preset else {
+            return;
+        };
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let (dpi, models, clean_borders, ensemble, retry) = match preset {
+            Preset::Fast => (200, Models::Fast, false, false, false),
+            Preset::Balanced => (300, Models::Installed, true, false, true),
+            Preset::Best => (400, Models::Best, true, true, false),
+        };
+        if !given("dpi") {
+            self.dpi = Dpi::Fixed(dpi);
+        }
+        if !given("models") {
+            self.models = models;
+        }
+        if !given("clean_borders") {
+            self.clean_borders = clean_borders;
+        }
+        if !given("ensemble") {
+            self.ensemble = ensemble;
+        }
+        if !given("retry_low_confidence") {
+            self.retry_low_confidence = retry;
+        }
+    }
+
+    /// Mean confidence below which a page's OCR text is dropped. Pages marked
+    /// with `--mark-words-below` are kept whatever their confidence.
+    pub fn drop_confidence(&self) -> i32 {
+        if self.mark_words_below.is_some() { 0 } else { self.min_confidence }
+    }
+
+    /// Mean confidence below which `--retry-low-confidence` reads a page again
+    /// with the ensemble, whether or not low confidence pages are dropped.
+    pub fn retry_confidence(&self) -> i32 {
+        self.min_confidence
+    }
+
+    /// Outputs of the run as (format, file) pairs, `None` for STDOUT.
+    /// A single `--format` applies to every `--output`; otherwise they pair by position.
+    /// With `--split-pages`, formats have no file: pages go to `--output-dir`.
+    pub fn sinks(&self) -> Result<Vec<(Format, Option<PathBuf>)>, String> {
+        if self.split_pages {
+            return Ok(self.format.iter().map(|format| (format.clone(), None)).collect());
+        }
+        if self.output.is_empty() {
+            return match self.format.as_slice() {
+                [format] => Ok(vec![(format.clone(), None)]),
+                _ => Err("several --format values need an --output each".to_string()),
+            };
+        }
+        if self.format.len() != 1 && self.format.len() != self.output.len() {
+            return Err(format!(
+                "{} --format values for {} --output files; give one format, or one per output",
+                self.format.len(),
+                self.output.len()
+            ));
+        }
+        let sinks: Vec<(Format, Option<PathBuf>)> = self
+            .output
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let format = self.format.get(i).unwrap_or(&self.format[0]).clone();
+                (format, Some(path.clone()).filter(|p| p.as_os_str() != "-"))
+            })
+            .collect();
+        if sinks.iter().filter(|(_, path)| path.is_none()).count() > 1 {
+            return Err("only one --output can be STDOUT".to_string());
+        }
+        Ok(sinks)
+    }
+}
+
+fn parse_markers(s: &str) -> Result<(String, String), String> {
+    match s.split_once(',') {
+        Some((open, close)) if !open.is_empty() || !close.is_empty() => Ok((open.to_string(), close.to_string())),
+        _ => Err(format!("expected OPEN,CLOSE, got '{}'", s)),
+    }
+}
+
+fn parse_split_on(s: &str) -> Result<SplitOn, String> {
+    match s {
+        "blank" => Ok(SplitOn::Blank),
+        _ if s.starts_with("barcode:") => Err("barcode separator sheets are not supported, only blank ones ('blank')".to_string()),
+        _ => Err(format!("expected 'blank', got '{}'", s)),
+    }
+}
+
+fn parse_meta(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", s)),
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum Format {
+    /// Delimited plain text.
+    Text,
+    /// A single JSON document.
+    Json,
+    /// One JSON object per line: each page as soon as it is done, then the document.
+    Ndjson,
+    /// hOCR (HTML) from Tesseract, with line and word boxes and confidences.
+    Hocr,
+    /// ALTO 4 XML, with block, line and word positions and confidences.
+    Alto,
+    /// Tesseract's TSV: one row per page, block, paragraph, line and word, with boxes and confidences.
+    Tsv,
+    /// Markdown, with headings, paragraphs and lists rebuilt from font sizes and layout.
+    Markdown,
+    /// PRImA PAGE XML, with region, line and word polygons and confidences (one page per document).
+    #[value(name = "pagexml")]
+    PageXml,
+    /// Word document, one section per page with the paragraphs of its text or OCR layer.
+    Docx,
+    /// Excel workbook: form fields on one sheet and each detected table (`--tables`) on its own.
+    Xlsx,
+    /// HTML page for browsers and e-readers: one section per page with its paragraphs, and its image with `--export-images`.
+    Html,
+}
+
+impl Format {
+    /// Written as a ZIP archive (DOCX, XLSX) rather than text.
+    pub fn is_zip(&self) -> bool {
+        matches!(self, Format::Docx | Format::Xlsx)
+    }
+
+    /// Extension of files written in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Text => "txt",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Hocr => "hocr",
+            Format::Alto => "xml",
+            Format::Tsv => "tsv",
+            Format::Markdown => "md",
+            Format::PageXml => "page.xml",
+            Format::Docx => "docx",
+            Format::Xlsx => "xlsx",
+            Format::Html => "html",
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SidecarExists {
+    /// Fail before processing.
+    Fail,
+    /// Leave the file and skip the input.
+    Skip,
+    /// Replace the file.
+    Overwrite,
+    /// Write to the first free numbered name.
+    Rename,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Granularity {
+    /// One record per page.
+    Page,
+    /// One record per layout block.
+    Block,
+    /// One record per paragraph.
+    Paragraph,
+    /// One record per text line.
+    Line,
+    /// One record per word.
+    Word,
+}
+
+impl Granularity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Granularity::Page => "page",
+            Granularity::Block => "block",
+            Granularity::Paragraph => "paragraph",
+            Granularity::Line => "line",
+            Granularity::Word => "word",
+        }
+    }
 }
 
 pub fn parse_range(range_str: &str, max_pages: usize) -> anyhow::Result<Vec<usize>> {
@@ -93,3 +945,137 @@ pub fn parse_range(range_str: &str, max_pages: usize) -> anyhow::Result<Vec<usiz
     sorted_pages.sort();
     Ok(sorted_pages)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subcommands_and_file() {
+        let cli = Cli::try_parse_from(["crabocr", "completions", "zsh"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Completions { shell: clap_complete::Shell::Zsh })));
+
+        let cli = Cli::try_parse_from(["crabocr", "xfa-diff", "a.pdf", "b.pdf"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::XfaDiff { full: false, .. })));
+
+        let cli = Cli::try_parse_from(["crabocr", "doc.pdf", "-m", "text"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.input, Some(PathBuf::from("doc.pdf")));
+    }
+
+    #[test]
+    fn test_sinks() {
+        let cli = Cli::try_parse_from(["crabocr", "doc.pdf", "-f", "json"]).unwrap();
+        assert_eq!(cli.sinks().unwrap(), vec![(Format::Json, None)]);
+
+        let cli = Cli::try_parse_from(["crabocr", "doc.pdf", "-f", "json", "-o", "r.json", "-f", "text", "-o", "-"]).unwrap();
+        assert_eq!(
+            cli.sinks().unwrap(),
+            vec![(Format::Json, Some(PathBuf::from("r.json"))), (Format::Text, None)]
+        );
+
+        let cli = Cli::try_parse_from(["crabocr", "doc.pdf", "-o", "a.txt", "-o", "b.txt"]).unwrap();
+        assert_eq!(cli.sinks().unwrap().len(), 2);
+
+        for bad in [
+            vec!["crabocr", "doc.pdf", "-f", "json", "-f", "text"],
+            vec!["crabocr", "doc.pdf", "-f", "json", "-f", "text", "-o", "a", "-o", "b", "-o", "c"],
+            vec!["crabocr", "doc.pdf", "-o", "-", "-o", "-"],
+        ] {
+            assert!(Cli::try_parse_from(bad).unwrap().sinks().is_err());
+        }
+
+        let cli = Cli::try_parse_from(["crabocr", "doc.pdf", "-f", "json", "-f", "text", "--output-dir", "out", "--split-pages"]).unwrap();
+        assert_eq!(cli.sinks().unwrap(), vec![(Format::Json, None), (Format::Text, None)]);
+        assert!(Cli::try_parse_from(["crabocr", "doc.pdf", "--split-pages"]).is_err());
+        assert!(Cli::try_parse_from(["crabocr", "doc.pdf", "--output-dir", "out", "--split-pages", "-o", "a.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_meta() {
+        let cli = Cli::try_parse_from(["crabocr", "doc.pdf", "--meta", "case=C-12", "--meta", "note=a=b"]).unwrap();
+        assert_eq!(cli.meta, vec![("case".into(), "C-12".into()), ("note".into(), "a=b".into())]);
+        assert!(Cli::try_parse_from(["crabocr", "doc.pdf", "--meta", "case"]).is_err());
+        assert!(Cli::try_parse_from(["crabocr", "doc.pdf", "--meta", "=x"]).is_err());
+        assert!(Cli::try_parse_from(["crabocr", "doc.pdf", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn test_split_on() {
+        let cli = Cli::try_parse_from(["crabocr", "scan.pdf", "--split-on", "blank"]).unwrap();
+        assert_eq!(cli.split_on, Some(SplitOn::Blank));
+        let err = Cli::try_parse_from(["crabocr", "scan.pdf", "--split-on", "barcode:SEP-*"]).unwrap_err();
+        assert!(err.to_string().contains("barcode separator sheets are not supported"));
+        assert!(Cli::try_parse_from(["crabocr", "scan.pdf", "--split-on", "qr"]).is_err());
+    }
+
+    #[test]
+    fn test_psm() {
+        let cli = Cli::try_parse_from(["crabocr", "scan.pdf", "--psm", "6"]).unwrap();
+        assert_eq!(cli.psm, Some(6));
+        // 0 only detects orientation and would read no text
+        assert!(Cli::try_parse_from(["crabocr", "scan.pdf", "--psm", "0"]).is_err());
+        assert!(Cli::try_parse_from(["crabocr", "scan.pdf", "--psm", "14"]).is_err());
+    }
+
+    #[test]
+    fn test_preset() {
+        let parse = |argv: &[&str]| {
+            let matches = Cli::command().try_get_matches_from(argv).unwrap();
+            let mut cli = Cli::from_arg_matches(&matches).unwrap();
+            cli.apply_preset(&matches);
+            cli
+        };
+        let cli = parse(&["crabocr", "doc.pdf", "--preset", "best"]);
+        assert_eq!((cli.dpi, cli.models, cli.clean_borders, cli.ensemble), (Dpi::Fixed(400), Models::Best, true, true));
+
+        let cli = parse(&["crabocr", "doc.pdf", "--preset", "fast", "--dpi", "auto", "--models", "installed", "--clean-borders"]);
+        assert_eq!((cli.dpi, cli.models, cli.clean_borders), (Dpi::Auto, Models::Installed, true));
+
+        let cli = parse(&["crabocr", "doc.pdf", "--preset", "balanced"]);
+        assert!(cli.retry_low_confidence && !cli.ensemble);
+
+        // Flags given on the command line are kept, the others are the preset's
+        let cli = parse(&["crabocr", "doc.pdf", "--preset", "fast", "--ensemble"]);
+        assert_eq!((cli.clean_borders, cli.ensemble, cli.retry_low_confidence), (false, true, false));
+
+        let cli = parse(&["crabocr", "doc.pdf", "--dpi", "150"]);
+        assert_eq!((cli.dpi, cli.models, cli.clean_borders), (Dpi::Fixed(150), Models::Installed, false));
+    }
+
+    #[test]
+    fn test_confidence_thresholds() {
+        let cli = Cli::try_parse_from(["crabocr", "doc.pdf", "--retry-low-confidence", "--min-confidence", "70"]).unwrap();
+        assert_eq!((cli.drop_confidence(), cli.retry_confidence()), (70, 70));
+
+        // Marked pages are kept, and still retried below the default threshold
+        let cli = Cli::try_parse_from(["crabocr", "doc.pdf", "--retry-low-confidence", "--mark-words-below", "80"]).unwrap();
+        assert_eq!((cli.drop_confidence(), cli.retry_confidence()), (0, crate::ocr::MIN_MEAN_CONF));
+    }
+
+    #[test]
+    fn test_parse_dpi() {
+        assert_eq!("auto".parse::<Dpi>().unwrap(), Dpi::Auto);
+        assert_eq!("150".parse::<Dpi>().unwrap(), Dpi::Fixed(150));
+        assert!("high".parse::<Dpi>().is_err());
+    }
+
+    #[test]
+    fn test_parse_xfa_out() {
+        assert_eq!("3".parse::<XfaOut>().unwrap(), XfaOut::Fd(3));
+        assert_eq!("./3".parse::<XfaOut>().unwrap(), XfaOut::Path(PathBuf::from("./3")));
+        assert_eq!("form.json".parse::<XfaOut>().unwrap(), XfaOut::Path(PathBuf::from("form.json")));
+        assert!("1".parse::<XfaOut>().is_err());
+        assert!("99999999999".parse::<XfaOut>().is_err());
+        assert!("".parse::<XfaOut>().is_err());
+    }
+
+    #[test]
+    fn test_auto_dpi() {
+        // A4 portrait (595 x 842 pt) at the default target is ~300 DPI
+        assert_eq!(auto_dpi((595.0, 842.0), 3508), 300);
+        // A1 drawings get a lower DPI, small pages are capped
+        assert_eq!(auto_dpi((2384.0, 1684.0), 3508), 106);
+        assert_eq!(auto_dpi((100.0, 100.0), 3508), 600);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::errors::CrabError;
+use crate::preprocess::GrayImage;
+use crate::renderer::Renderer;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Resolution of the renders pages are compared at. Layout shows at this
+/// size, while text and scan noise average out.
+const HASH_DPI: i32 = 36;
+/// Rows and columns of the grid the hash samples.
+const HASH_SIZE: usize = 16;
+
+/// Perceptual hash of a page: one bit per cell of a 16x16 grid, set when the
+/// cell is darker than the page on average (an average hash), so the bits map
+/// where the ink is. Pages of the same form or letterhead differ in a few
+/// bits, where they were filled in differently.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageHash([u64; HASH_SIZE * HASH_SIZE / 64]);
+
+impl PageHash {
+    pub fn of(image: &GrayImage) -> Self {
+        let cells = mean_grid(image, HASH_SIZE, HASH_SIZE);
+        let mean = cells.iter().sum::<f32>() / cells.len() as f32;
+        let mut bits = [0u64; HASH_SIZE * HASH_SIZE / 64];
+        for (i, &cell) in cells.iter().enumerate() {
+            if cell < mean {
+                bits[i / 64] |= 1 << (i % 64);
+            }
+        }
+        Self(bits)
+    }
+
+    /// Number of differing bits, from 0 to 256.
+    pub fn distance(&self, other: &Self) -> u32 {
+        self.0.iter().zip(other.0.iter()).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
+}
+
+/// Mean brightness of each cell of a `cols` x `rows` grid laid over the image, row by row.
+fn mean_grid(image: &GrayImage, cols: usize, rows: usize) -> Vec<f32> {
+    let (width, height) = (image.width as usize, image.height as usize);
+    let mut cells = Vec::with_capacity(cols * rows);
+    for row in 0..rows {
+        let (y0, y1) = (row * height / rows, ((row + 1) * height / rows).max(row * height / rows + 1).min(height));
+        for col in 0..cols {
+            let (x0, x1) = (col * width / cols, ((col + 1) * width / cols).max(col * width / cols + 1).min(width));
+            let (mut sum, mut count) = (0u64, 0u64);
+            for y in y0..y1 {
+                for &pixel in &image.data[y * width + x0..y * width + x1] {
+                    sum += pixel as u64;
+                    count += 1;
+                }
+            }
+            cells.push(if count == 0 { 255.0 } else { sum as f32 / count as f32 });
+        }
+    }
+    cells
+}
+
+/// Group pages whose hashes are within `max_distance` bits of another page
+/// of the group (single linkage). Groups are returned largest first, each in
+/// input order; pages resembling no other page form groups of one.
+pub fn clusters(hashes: &[PageHash], max_distance: u32) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..hashes.len() {
+        for j in i + 1..hashes.len() {
+            if hashes[i].distance(&hashes[j]) <= max_distance {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of = vec![usize::MAX; hashes.len()];
+    for i in 0..hashes.len() {
+        let r = root(&mut parent, i);
+        if group_of[r] == usize::MAX {
+            group_of[r] = groups.len();
+            groups.push(Vec::new());
+        }
+        groups[group_of[r]].push(i);
+    }
+    // Stable, so equal sizes keep the order of their first page
+    groups.sort_by_key(|g| std::cmp::Reverse(g.len()));
+    groups
+}
+
+/// Hash every page of `inputs` and print the clusters of similar pages as
+/// JSON, to find the templates and boilerplate shared across a batch.
+/// Documents that cannot be read are listed under `failed`.
+pub fn run(inputs: &[PathBuf], max_distance: u32) -> Result<(), CrabError> {
+    let renderer = Renderer::new()?;
+    let mut pages: Vec<(usize, i32)> = Vec::new();
+    let mut hashes = Vec::new();
+    let mut failed = Vec::new();
+    for (index, path) in inputs.iter().enumerate() {
+        match hash_pages(&renderer, path) {
+            Ok(document) => {
+                pages.extend((1..=document.len() as i32).map(|page| (index, page)));
+                hashes.extend(document);
+            }
+            Err(e) => failed.push(json!({ "document": path.display().to_string(), "error": e.to_string() })),
+        }
+    }
+
+    let page = |i: usize| json!({ "document": inputs[pages[i].0].display().to_string(), "page": pages[i].1 });
+    let groups = clusters(&hashes, max_distance);
+    let clustered: Vec<Value> = groups
+        .iter()
+        .filter(|g| g.len() > 1)
+        .map(|g| json!({ "size": g.len(), "pages": g.iter().map(|&i| page(i)).collect::<Vec<_>>() }))
+        .collect();
+    let mut out = json!({
+        "documents": inputs.len() - failed.len(),
+        "pages": hashes.len(),
+        "max_distance": max_distance,
+        "clusters": clustered,
+        "unique_pages": groups.iter().filter(|g| g.len() == 1).map(|g| page(g[0])).collect::<Vec<_>>(),
+    });
+    if !failed.is_empty() {
+        out["failed"] = json!(failed);
+    }
+    let out = serde_json::to_string_pretty(&out).map_err(|e| CrabError::Internal(e.to_string()))?;
+    println!("{}", out);
+    Ok(())
+}
+
+fn hash_pages(renderer: &Renderer, path: &Path) -> Result<Vec<PageHash>, CrabError> {
+    let mut doc = renderer.open(path)?;
+    let hashes = (0..renderer.page_count(&doc)?)
+        .map(|page| {
+            let mut pix = renderer.render_page(&doc, page, HASH_DPI)?;
+            let image = GrayImage::from_pixmap(&pix, renderer);
+            pix.drop_with(renderer);
+            Ok(PageHash::of(&image))
+        })
+        .collect();
+    doc.drop_with(renderer);
+    hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A white page with dark boxes `[x0, y0, x1, y1]` in pixels.
+    fn page(boxes: &[[u32; 4]]) -> GrayImage {
+        let (width, height) = (306, 396);
+        let mut data = vec![255u8; (width * height) as usize];
+        for b in boxes {
+            for y in b[1]..b[3] {
+                for x in b[0]..b[2] {
+                    data[(y * width + x) as usize] = 0;
+                }
+            }
+        }
+        GrayImage { width, height, data }
+    }
+
+    #[test]
+    fn test_similar_pages_cluster() {
+        // Two filled-in copies of a form (header, logo, table), a letter and a blank page
+        let form = [[20, 20, 286, 60], [230, 70, 286, 110], [20, 150, 286, 155], [20, 300, 286, 305]];
+        let filled = [form.as_slice(), &[[30, 170, 120, 180], [30, 190, 100, 200]]].concat();
+        let other_fill = [form.as_slice(), &[[30, 170, 150, 180]]].concat();
+        let letter = [[20, 20, 80, 80], [20, 120, 286, 130], [20, 140, 286, 150], [20, 160, 200, 170]];
+        let hashes: Vec<PageHash> = [page(&filled), page(&letter), page(&other_fill), page(&[])].iter().map(PageHash::of).collect();
+
+        assert_eq!(hashes[0].distance(&hashes[0]), 0);
+        assert!(hashes[0].distance(&hashes[2]) <= 32, "{}", hashes[0].distance(&hashes[2]));
+        assert!(hashes[0].distance(&hashes[1]) > 32, "{}", hashes[0].distance(&hashes[1]));
+        assert_eq!(clusters(&hashes, 32), [vec![0, 2], vec![1], vec![3]]);
+        assert_eq!(clusters(&hashes, 0).len(), 4);
+    }
+}
@@ -0,0 +1,26 @@
+use crate::cli::Cli;
+use crate::errors::CrabError;
+use crate::ocr;
+use clap::builder::PossibleValuesParser;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::io;
+
+/// Print a completion script for `shell` to STDOUT.
+pub fn print_completions(shell: Shell) -> Result<(), CrabError> {
+    let mut cmd = Cli::command();
+    let name = cmd.get_name().to_string();
+    // Scripts are static, so offer the languages installed when they are generated
+    let langs = ocr::available_languages();
+    if !langs.is_empty() {
+        cmd = cmd.mut_arg("lang", |arg| arg.value_parser(PossibleValuesParser::new(langs)));
+    }
+    clap_complete::generate(shell, &mut cmd, name, &mut io::stdout());
+    Ok(())
+}
+
+/// Print the man page (roff) to STDOUT.
+pub fn print_man() -> Result<(), CrabError> {
+    clap_mangen::Man::new(Cli::command()).render(&mut io::stdout())?;
+    Ok(())
+}
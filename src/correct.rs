@@ -0,0 +1,110 @@
+use crate::errors::CrabError;
+use crate::renderer::{PageCorrection, Renderer};
+use crate::searchable::{self, GLYPHLESS_FONT};
+use serde_json::Value;
+use std::path::Path;
+
+/// A reviewed replacement of the text in a region of a page.
+#[derive(Debug, PartialEq)]
+pub struct Correction {
+    /// 1-based page.
+    pub page: usize,
+    /// Bounds in points (x0, y0, x1, y1), in page space like `--page-info`.
+    pub rect: [f32; 4],
+    pub text: String,
+}
+
+/// Write a copy of `input` whose text under each corrected region is replaced
+/// by the reviewed string: the old text is removed from the content stream and
+/// the new one is drawn invisibly over the region, stretched to its box.
+pub fn run(input: &Path, corrections: &Path, output: &Path) -> Result<(), CrabError> {
+    let corrections = load(corrections)?;
+    let renderer = Renderer::new()?;
+    let doc = renderer.open_pdf(input)?;
+    let page_count = renderer.page_count(&doc)? as usize;
+    if let Some(c) = corrections.iter().find(|c| c.page > page_count) {
+        return Err(CrabError::Cli(format!("Correction on page {}, but the document has {} page(s)", c.page, page_count)));
+    }
+
+    let mut pages: Vec<PageCorrection> = Vec::new();
+    for page in 1..=page_count {
+        let on_page: Vec<&Correction> = corrections.iter().filter(|c| c.page == page).collect();
+        if on_page.is_empty() {
+            continue;
+        }
+        let mut ops = String::from("BT\n3 Tr\n");
+        for c in &on_page {
+            let [x0, y0, x1, y1] = c.rect;
+            // Page space runs downwards, so the text matrix flips glyphs back up
+            searchable::show_text(&mut ops, "FCrabOCR", &c.text, [x1 - x0, y1 - y0], [1.0, 0.0, 0.0, -1.0, x0, y1]);
+        }
+        ops.push_str("ET\n");
+        pages.push(PageCorrection { page: page as i32 - 1, rects: on_page.iter().map(|c| c.rect).collect(), ops });
+    }
+    renderer.correct_text(&doc, &pages, GLYPHLESS_FONT, output)
+}
+
+/// Load a corrections file: a JSON array of
+/// `{"page": 3, "rect": [x0, y0, x1, y1], "text": "corrected"}`, with `rect` in
+/// points, or in pixels of a render at `"dpi"` when given (as word boxes in JSON output).
+pub fn load(path: &Path) -> Result<Vec<Correction>, CrabError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| CrabError::Cli(format!("Cannot read corrections file {:?}: {}", path, e)))?;
+    parse(&content).map_err(|e| CrabError::Cli(format!("Invalid corrections file {:?}: {}", path, e)))
+}
+
+fn parse(content: &str) -> Result<Vec<Correction>, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let entries = value.as_array().ok_or("expected an array of corrections")?;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let page = entry
+                .get("page")
+                .and_then(Value::as_u64)
+                .filter(|&p| p >= 1)
+                .ok_or(format!("correction {}: page must be >= 1", i))? as usize;
+            let rect: Vec<f32> = entry
+                .get("rect")
+                .and_then(Value::as_array)
+                .map(|a| a.iter().filter_map(Value::as_f64).map(|v| v as f32).collect())
+                .unwrap_or_default();
+            let mut rect: [f32; 4] = rect
+                .try_into()
+                .map_err(|_| format!("correction {}: rect must be 4 numbers", i))?;
+            if rect[2] <= rect[0] || rect[3] <= rect[1] {
+                return Err(format!("correction {}: rect is empty", i));
+            }
+            if let Some(dpi) = entry.get("dpi") {
+                let dpi = dpi.as_f64().filter(|&d| d > 0.0).ok_or(format!("correction {}: dpi must be positive", i))?;
+                rect = rect.map(|v| v * 72.0 / dpi as f32);
+            }
+            let text = entry
+                .get("text")
+                .and_then(Value::as_str)
+                .ok_or(format!("correction {}: text must be a string", i))?;
+            Ok(Correction { page, rect, text: text.to_string() })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let corrections = parse(
+            r#"[{"page": 2, "rect": [10, 20, 50, 32], "text": "Invoice"},
+                {"page": 1, "rect": [300, 150, 600, 200], "dpi": 300, "text": "Total"}]"#,
+        )
+        .unwrap();
+        assert_eq!(corrections[0], Correction { page: 2, rect: [10.0, 20.0, 50.0, 32.0], text: "Invoice".into() });
+        assert_eq!(corrections[1].rect, [72.0, 36.0, 144.0, 48.0]);
+
+        assert!(parse(r#"[{"rect": [0, 0, 1, 1], "text": "x"}]"#).unwrap_err().contains("page"));
+        assert!(parse(r#"[{"page": 1, "rect": [5, 0, 1, 1], "text": "x"}]"#).unwrap_err().contains("empty"));
+        assert!(parse(r#"[{"page": 1, "rect": [0, 0, 1, 1]}]"#).unwrap_err().contains("text"));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::cli::Denoise;
+use crate::errors::CrabError;
+use crate::preprocess::{self, GrayImage};
+use std::os::raw::c_int;
+
+// Leptonica is linked in for Tesseract; its filters are declared here as
+// the Tesseract bindings do not cover them.
+#[allow(non_snake_case)]
+mod sys {
+    use std::os::raw::{c_float, c_int};
+
+    #[repr(C)]
+    pub struct Pix {
+        _private: [u8; 0],
+    }
+
+    extern "C" {
+        pub fn pixCreate(width: c_int, height: c_int, depth: c_int) -> *mut Pix;
+        pub fn pixDestroy(ppix: *mut *mut Pix);
+        pub fn pixGetData(pix: *mut Pix) -> *mut u32;
+        pub fn pixGetWpl(pix: *const Pix) -> c_int;
+        pub fn pixMedianFilter(pixs: *mut Pix, wf: c_int, hf: c_int) -> *mut Pix;
+        pub fn pixOpenGray(pixs: *mut Pix, hsize: c_int, vsize: c_int) -> *mut Pix;
+        pub fn pixBackgroundNormSimple(pixs: *mut Pix, pixim: *mut Pix, pixg: *mut Pix) -> *mut Pix;
+        pub fn pixUnsharpMaskingGray(pixs: *mut Pix, halfwidth: c_int, fract: c_float) -> *mut Pix;
+        pub fn pixBilateralGray(pixs: *mut Pix, spatial_stdev: c_float, range_stdev: c_float, ncomps: c_int, reduction: c_int) -> *mut Pix;
+    }
+}
+use sys::*;
+
+/// An 8 bpp Leptonica image, destroyed when dropped.
+struct LeptImage(*mut Pix);
+
+impl LeptImage {
+    fn from_gray(image: &GrayImage) -> Result<Self, CrabError> {
+        let mut pix = Self::wrap(unsafe { pixCreate(image.width as c_int, image.height as c_int, 8) }, "pixCreate")?;
+        let wpl = pix.wpl();
+        let rows = image.data.chunks(image.width as usize);
+        for (row, line) in rows.zip(pix.words_mut(image.height).chunks_mut(wpl)) {
+            pack_row(row, line);
+        }
+        Ok(pix)
+    }
+
+    /// The image as a `GrayImage`; filters keep the size of the source image.
+    fn to_gray(&self, width: u32, height: u32) -> GrayImage {
+        let mut data = Vec::with_capacity(width as usize * height as usize);
+        for line in self.words(height).chunks(self.wpl()) {
+            data.extend(unpack_row(line, width as usize));
+        }
+        GrayImage { width, height, data }
+    }
+
+    fn wpl(&self) -> usize {
+        unsafe { pixGetWpl(self.0).max(1) as usize }
+    }
+
+    /// Pixel words of the `height` rows of the image, row padding included.
+    fn words(&self, height: u32) -> &[u32] {
+        unsafe { std::slice::from_raw_parts(pixGetData(self.0), self.wpl() * height as usize) }
+    }
+
+    fn words_mut(&mut self, height: u32) -> &mut [u32] {
+        unsafe { std::slice::from_raw_parts_mut(pixGetData(self.0), self.wpl() * height as usize) }
+    }
+
+    fn wrap(pix: *mut Pix, filter: &str) -> Result<Self, CrabError> {
+        if pix.is_null() {
+            return Err(CrabError::Internal(format!("Leptonica {} failed", filter)));
+        }
+        Ok(Self(pix))
+    }
+}
+
+/// Pixels of a row into the words of a Leptonica 8 bpp row, four per word,
+/// the first in the high byte.
+fn pack_row(row: &[u8], line: &mut [u32]) {
+    for (x, &p) in row.iter().enumerate() {
+        line[x / 4] |= (p as u32) << (24 - 8 * (x % 4));
+    }
+}
+
+/// The first `width` pixels of a Leptonica 8 bpp row.
+fn unpack_row(line: &[u32], width: usize) -> impl Iterator<Item = u8> + '_ {
+    (0..width).map(|x| (line[x / 4] >> (24 - 8 * (x % 4))) as u8)
+}
+
+impl Drop for LeptImage {
+    fn drop(&mut self) {
+        unsafe { pixDestroy(&mut self.0) };
+    }
+}
+
+/// Filter a page image for the degraded source it comes from (`--denoise`):
+/// - fax: a 3x3 median filter removes specks, then a vertical gray opening
+///   closes the scan lines dropped from strokes;
+/// - thermal: the background is flattened, edges sharpened by unsharp
+///   masking and the contrast stretched, for faded print;
+/// - photo: an edge-preserving bilateral filter smooths sensor noise, then
+///   the background is flattened against uneven lighting.
+pub fn denoise(image: &GrayImage, source: Denoise) -> Result<GrayImage, CrabError> {
+    if image.width == 0 || image.height == 0 {
+        return Ok(image.clone());
+    }
+    let pix = LeptImage::from_gray(image)?;
+    let filtered = unsafe {
+        match source {
+            Denoise::Fax => {
+                let median = LeptImage::wrap(pixMedianFilter(pix.0, 3, 3), "pixMedianFilter")?;
+                LeptImage::wrap(pixOpenGray(median.0, 1, 3), "pixOpenGray")?
+            }
+            Denoise::Thermal => {
+                let flat = LeptImage::wrap(pixBackgroundNormSimple(pix.0, std::ptr::null_mut(), std::ptr::null_mut()), "pixBackgroundNormSimple")?;
+                LeptImage::wrap(pixUnsharpMaskingGray(flat.0, 2, 0.5), "pixUnsharpMaskingGray")?
+            }
+            Denoise::Photo => {
+                let smooth = LeptImage::wrap(pixBilateralGray(pix.0, 2.0, 30.0, 6, 1), "pixBilateralGray")?;
+                LeptImage::wrap(pixBackgroundNormSimple(smooth.0, std::ptr::null_mut(), std::ptr::null_mut()), "pixBackgroundNormSimple")?
+            }
+        }
+    };
+    let image = filtered.to_gray(image.width, image.height);
+    Ok(match source {
+        Denoise::Thermal => preprocess::stretch_contrast(&image),
+        Denoise::Fax | Denoise::Photo => image,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_row() {
+        let row = [1, 2, 3, 4, 250];
+        let mut line = [0u32; 2];
+        pack_row(&row, &mut line);
+        assert_eq!(line, [0x01020304, 0xfa000000]);
+        assert_eq!(unpack_row(&line, row.len()).collect::<Vec<_>>(), row);
+    }
+}
@@ -0,0 +1,253 @@
+use crate::output::{OutputWriter, PageResult, XfaData};
+use crate::renderer::{ActiveContent, FontInfo, SignatureField};
+use crate::warnings::Warning;
+use std::io::{self, Write};
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/></Types>"#;
+
+const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/></Relationships>"#;
+
+const DOCUMENT_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>"#;
+
+/// Section break closing the previous page's section.
+const SECTION_BREAK: &str = r#"<w:p><w:pPr><w:sectPr><w:type w:val="nextPage"/></w:sectPr></w:pPr></w:p>"#;
+
+/// A Word document (OOXML) for back-office users: one section per page,
+/// holding the paragraphs of its merged blocks, its text layer or, when the
+/// page has no usable text layer, its OCR layer. Form data and warnings are
+/// not included. The archive is written by `finish`, its parts stored uncompressed.
+pub struct DocxWriter<W: Write> {
+    out: W,
+    /// Body of `word/document.xml`.
+    body: String,
+    pages: usize,
+}
+
+impl<W: Write> DocxWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, body: String::new(), pages: 0 }
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        self.body.push_str("<w:p><w:r>");
+        for (i, line) in text.lines().enumerate() {
+            if i > 0 {
+                self.body.push_str("<w:br/>");
+            }
+            for (j, part) in line.split('\t').enumerate() {
+                if j > 0 {
+                    self.body.push_str("<w:tab/>");
+                }
+                if !part.is_empty() {
+                    self.body.push_str("<w:t xml:space=\"preserve\">");
+                    self.body.push_str(&escape(part));
+                    self.body.push_str("</w:t>");
+                }
+            }
+        }
+        self.body.push_str("</w:r></w:p>");
+    }
+}
+
+impl<W: Write> OutputWriter for DocxWriter<W> {
+    fn xfa(&mut self, _xfa: &XfaData) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn signatures(&mut self, _fields: &[SignatureField]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn active_content(&mut self, _content: &ActiveContent) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn fonts(&mut self, _fonts: &[FontInfo]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        if self.pages > 0 {
+            self.body.push_str(SECTION_BREAK);
+        }
+        self.pages += 1;
+        let paragraphs = page.paragraphs();
+        for text in &paragraphs {
+            self.paragraph(text);
+        }
+        // Word needs a paragraph in every section
+        if paragraphs.is_empty() {
+            self.body.push_str("<w:p/>");
+        }
+        Ok(())
+    }
+
+    fn no_pages(&mut self, reason: &str) -> io::Result<()> {
+        self.paragraph(&format!("No pages processed: {}", reason));
+        Ok(())
+    }
+
+    fn warnings(&mut self, _warnings: &[Warning]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let mut document = String::with_capacity(DOCUMENT_HEADER.len() + self.body.len() + 64);
+        document.push_str(DOCUMENT_HEADER);
+        document.push_str(&self.body);
+        if self.body.is_empty() {
+            document.push_str("<w:p/>");
+        }
+        document.push_str("<w:sectPr/></w:body></w:document>");
+        let parts = [
+            ("[Content_Types].xml", CONTENT_TYPES.as_bytes()),
+            ("_rels/.rels", RELATIONSHIPS.as_bytes()),
+            ("word/document.xml", document.as_bytes()),
+        ];
+        write_zip(&mut self.out, &parts)?;
+        self.out.flush()
+    }
+}
+
+/// Escape text for XML, dropping characters XML 1.0 does not allow
+/// (control characters from broken text layers would make Word reject the file).
+pub fn escape(text: &str) -> String {
+    let allowed: String = text.chars().filter(|&c| !((c.is_control() && c != '\t') || c == '\u{FFFE}' || c == '\u{FFFF}')).collect();
+    crate::alto::escape(&allowed)
+}
+
+/// Write `parts` as a ZIP archive of stored (uncompressed) entries, as
+/// OOXML packages (DOCX, XLSX) are.
+pub fn write_zip(out: &mut impl Write, parts: &[(&str, &[u8])]) -> io::Result<()> {
+    // 1980-01-01 00:00, the earliest MS-DOS date
+    const DOS_TIME: u16 = 0;
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    let mut offset = 0u32;
+    let mut central = Vec::new();
+    for (name, data) in parts {
+        let crc = crc32(data);
+        let size = u32::try_from(data.len()).map_err(|_| io::Error::other("ZIP entry larger than 4 GiB"))?;
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        header.extend_from_slice(&0u16.to_le_bytes()); // flags
+        header.extend_from_slice(&0u16.to_le_bytes()); // stored
+        header.extend_from_slice(&DOS_TIME.to_le_bytes());
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra field
+        header.extend_from_slice(name.as_bytes());
+        out.write_all(&header)?;
+        out.write_all(data)?;
+
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&header[4..30]);
+        central.extend_from_slice(&0u16.to_le_bytes()); // comment
+        central.extend_from_slice(&0u16.to_le_bytes()); // disk
+        central.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        central.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+        offset = offset
+            .checked_add(header.len() as u32 + size)
+            .ok_or_else(|| io::Error::other("ZIP archive larger than 4 GiB"))?;
+    }
+    out.write_all(&central)?;
+    let mut end = Vec::with_capacity(22);
+    end.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    end.extend_from_slice(&0u16.to_le_bytes()); // disk
+    end.extend_from_slice(&0u16.to_le_bytes()); // disk of the central directory
+    end.extend_from_slice(&(parts.len() as u16).to_le_bytes());
+    end.extend_from_slice(&(parts.len() as u16).to_le_bytes());
+    end.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    end.extend_from_slice(&offset.to_le_bytes());
+    end.extend_from_slice(&0u16.to_le_bytes()); // comment
+    out.write_all(&end)
+}
+
+/// CRC-32 (IEEE) of `data`, as ZIP entries record it.
+fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+    !data.iter().fold(!0u32, |crc, &b| TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+/// Entries of a stored ZIP archive, read back through its central directory.
+#[cfg(test)]
+pub fn read_zip(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let u16_at = |at: usize| u16::from_le_bytes([zip[at], zip[at + 1]]) as usize;
+    let u32_at = |at: usize| u32::from_le_bytes(zip[at..at + 4].try_into().unwrap()) as usize;
+    let end = zip.len() - 22;
+    assert_eq!(u32_at(end), 0x06054b50);
+    let mut at = u32_at(end + 16);
+    let mut entries = Vec::new();
+    for _ in 0..u16_at(end + 10) {
+        assert_eq!(u32_at(at), 0x02014b50);
+        let (size, name_len, offset) = (u32_at(at + 24), u16_at(at + 28), u32_at(at + 42));
+        let name = String::from_utf8(zip[at + 46..at + 46 + name_len].to_vec()).unwrap();
+        let data_at = offset + 30 + u16_at(offset + 26);
+        let data = zip[data_at..data_at + size].to_vec();
+        assert_eq!(crc32(&data) as usize, u32_at(at + 16));
+        entries.push((name, data));
+        at += 46 + name_len;
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Layer;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_docx_writer() {
+        let layer = |text: &str| Some(Layer { text: text.into(), units: Vec::new() });
+        let mut buf = Vec::new();
+        {
+            let mut w = DocxWriter::new(&mut buf);
+            w.page(&PageResult { number: 1, text_layer: layer("Invoice 17\n\nTotal: 4 < 5 & more\nDue\t30 days\n"), ..Default::default() })
+                .unwrap();
+            w.page(&PageResult { number: 2, text_layer: layer(""), ocr_layer: layer("Scanned\x0c page\n"), ..Default::default() }).unwrap();
+            w.finish().unwrap();
+        }
+        let entries = read_zip(&buf);
+        let names: Vec<&str> = entries.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["[Content_Types].xml", "_rels/.rels", "word/document.xml"]);
+        let document = String::from_utf8(entries[2].1.clone()).unwrap();
+        let doc = roxmltree::Document::parse(&document).unwrap();
+        let paragraphs: Vec<String> = doc
+            .descendants()
+            .filter(|n| n.has_tag_name("p"))
+            .map(|p| p.descendants().filter_map(|n| if n.has_tag_name("t") { n.text() } else { None }).collect())
+            .collect();
+        assert_eq!(paragraphs, ["Invoice 17", "Total: 4 < 5 & moreDue30 days", "", "Scanned page"]);
+        assert_eq!(doc.descendants().filter(|n| n.has_tag_name("sectPr")).count(), 2);
+        assert!(document.contains("more</w:t><w:br/><w:t xml:space=\"preserve\">Due</w:t><w:tab/>"));
+    }
+}
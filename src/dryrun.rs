@@ -0,0 +1,160 @@
+use crate::cli::{Cli, Dpi, Mode, Models, TextLayerCheck};
+#[cfg(feature = "encrypt")]
+use crate::encrypt::Recipient;
+use crate::errors::CrabError;
+use crate::ocr::{self, Ocr};
+use crate::preflight;
+use crate::warnings::Warnings;
+
+/// Validate everything a run depends on and print the effective configuration.
+/// `pages` are the 0-based pages the range resolved to.
+/// `dump_bytes` is the estimated size of `--dump-ocr-input` images, if set.
+pub fn report(args: &Cli, page_count: usize, pages: &[usize], dump_bytes: Option<u64>) -> Result<(), CrabError> {
+    println!("Document pages: {}", page_count);
+    println!("Pages to process: {} ({})", pages.len(), format_pages(pages));
+    println!("Mode: {:?}", args.mode);
+    println!("Format: {:?}, granularity: {}", args.format, args.granularity.as_str());
+    println!("XFA: {:?}", args.xfa);
+
+    let ocr_needed = args.mode != Mode::Text
+        || args.annotations
+        || args.check_text_layer != TextLayerCheck::Off
+        || args.ocr_suspicious
+        || args.ocr_outlines;
+    if ocr_needed {
+        match args.dpi {
+            Dpi::Fixed(dpi) => println!("DPI: {}", dpi),
+            Dpi::Auto => println!("DPI: auto (longest edge {} px)", args.dpi_target),
+        }
+        if let Some(preset) = args.preset {
+            println!("Preset: {:?}", preset);
+        }
+        let tessdata = ocr::model_dir(args.models);
+        match &tessdata {
+            Some(dir) => println!("Models: {:?} ({:?})", args.models, dir),
+            None if args.models != Models::Installed => println!("Models: {:?} (not found, using installed)", args.models),
+            None => println!("Models: installed"),
+        }
+        let mut preprocessing = Vec::new();
+        let denoise = args.denoise.map(|source| format!("denoise {:?}", source).to_lowercase());
+        preprocessing.extend(denoise.as_deref());
+        if args.clean_borders {
+            preprocessing.push("clean borders");
+        }
+        if args.ensemble {
+            preprocessing.push("ensemble");
+        } else if args.retry_low_confidence {
+            preprocessing.push("ensemble retry of low-confidence pages");
+        }
+        if !preprocessing.is_empty() {
+            println!("Preprocessing: {}", preprocessing.join(", "));
+        }
+        if args.no_dict {
+            println!("Dictionary: off");
+        }
+        if args.preserve_spaces || args.layout {
+            println!("Interword spaces: preserved");
+        }
+        // Initializing the engine is the only reliable check that every language loads
+        let ocr = Ocr::with_tessdata(&args.lang, tessdata.as_deref(), !args.no_dict)?;
+        println!("Languages: {} (ok)", args.lang);
+        if !ocr.has_osd() {
+            println!("Orientation detection: unavailable (osd.traineddata not found)");
+        }
+    }
+
+    if let Some(dir) = &args.dump_ocr_input {
+        preflight::check_writable_dir(dir)?;
+        println!("OCR input dump: {:?} (writable)", dir);
+        if let Some(bytes) = dump_bytes {
+            preflight::check_free_space(dir, bytes, args.space_check, &mut Warnings::default())?;
+            println!("OCR input dump size: about {} MiB", bytes / (1 << 20));
+        }
+    }
+    if let Some(dir) = &args.export_images {
+        preflight::check_writable_dir(dir)?;
+        println!("Page images: {:?} (writable, {})", dir, args.image_format.extension());
+    }
+    check_outputs(args)?;
+    if args.timeout > 0 {
+        println!("Timeout: {}s", args.timeout);
+    }
+    if let Some(secs) = args.cpu_time_limit {
+        println!("CPU time limit: {}s (degraded past half)", secs);
+    }
+    if let Some(secs) = args.wall_limit {
+        println!("Wall time limit: {}s (degraded past half)", secs);
+    }
+
+    if pages.is_empty() {
+        return Err(CrabError::NoPages(format!(
+            "range '{}' matches none of the {} pages",
+            args.range, page_count
+        )));
+    }
+    println!("Dry run OK.");
+    Ok(())
+}
+
+/// Dry run of `--mode xfa-only`: the document is not opened, as only its
+/// form data will be read.
+pub fn report_forms(args: &Cli) -> Result<(), CrabError> {
+    println!("Mode: {:?} (pages are not loaded)", args.mode);
+    println!("Format: {:?}", args.format);
+    println!("XFA: {:?}", args.xfa);
+    check_outputs(args)?;
+    println!("Dry run OK.");
+    Ok(())
+}
+
+fn check_outputs(args: &Cli) -> Result<(), CrabError> {
+    for path in args.output.iter().chain(&args.output_pdf) {
+        if path.as_os_str() == "-" {
+            continue;
+        }
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+        preflight::check_writable_dir(dir)?;
+        println!("Output: {:?} (writable)", path);
+    }
+    if let Some(dir) = &args.output_dir {
+        preflight::check_writable_dir(dir)?;
+        println!("Output directory: {:?} (writable, one file per page and format)", dir);
+    }
+    #[cfg(feature = "encrypt")]
+    if let Some(id) = &args.encrypt_output {
+        let recipient = Recipient::new(id);
+        recipient.check().map_err(CrabError::Output)?;
+        println!("Output encryption: {} for {} (ok)", recipient.tool.name(), recipient.id);
+    }
+    Ok(())
+}
+
+/// 1-based page list with consecutive pages collapsed, e.g. "1-3,7".
+pub fn format_pages(pages: &[usize]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut iter = pages.iter().map(|p| p + 1).peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end + 1)) {
+            end = iter.next().unwrap_or(end);
+        }
+        parts.push(if start == end { start.to_string() } else { format!("{}-{}", start, end) });
+    }
+    if parts.is_empty() {
+        "none".to_string()
+    } else {
+        parts.join(",")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_pages() {
+        assert_eq!(format_pages(&[0, 1, 2, 6, 8, 9]), "1-3,7,9-10");
+        assert_eq!(format_pages(&[4]), "5");
+        assert_eq!(format_pages(&[]), "none");
+    }
+}
@@ -0,0 +1,136 @@
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::Mutex;
+
+/// The first encryption process that failed after its writer was dropped,
+/// where the error cannot be returned.
+static FAILED: Mutex<Option<String>> = Mutex::new(None);
+
+/// Tool encrypting output for a recipient.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tool {
+    Age,
+    Gpg,
+}
+
+impl Tool {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Age => "age",
+            Tool::Gpg => "gpg",
+        }
+    }
+}
+
+/// Recipient of encrypted output (`--encrypt-output`): an age public key
+/// (`age1...`) or SSH public key, encrypted with `age`, or else a GnuPG key
+/// ID, fingerprint or email, encrypted with `gpg`. Output is piped to the
+/// tool, so results never reach the disk in clear text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipient {
+    pub tool: Tool,
+    pub id: String,
+}
+
+impl Recipient {
+    pub fn new(id: &str) -> Self {
+        let tool = if id.starts_with("age1") || id.starts_with("ssh-") { Tool::Age } else { Tool::Gpg };
+        Self { tool, id: id.to_string() }
+    }
+
+    /// Check that the tool is installed, before anything is processed.
+    pub fn check(&self) -> io::Result<()> {
+        let status = Command::new(self.tool.name())
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| self.spawn_error(e))?;
+        if !status.success() {
+            return Err(io::Error::other(format!("`{} --version` failed ({})", self.tool.name(), status)));
+        }
+        Ok(())
+    }
+
+    /// A writer encrypting to `path`, or to STDOUT (ASCII-armored) without one.
+    pub fn create(&self, path: Option<&Path>) -> io::Result<Encrypted> {
+        let mut command = Command::new(self.tool.name());
+        match self.tool {
+            Tool::Age => command.args(["--encrypt", "--recipient", &self.id]),
+            Tool::Gpg => command.args(["--batch", "--yes", "--encrypt", "--recipient", &self.id]),
+        };
+        match path {
+            Some(path) => command.arg("--output").arg(path),
+            None => command.arg("--armor"),
+        };
+        let mut child = command.stdin(Stdio::piped()).spawn().map_err(|e| self.spawn_error(e))?;
+        let stdin = child.stdin.take().map(BufWriter::new);
+        let target = path.map_or_else(|| "STDOUT".to_string(), |p| format!("{:?}", p));
+        Ok(Encrypted { child, stdin, tool: self.tool, target })
+    }
+
+    fn spawn_error(&self, e: io::Error) -> io::Error {
+        io::Error::new(e.kind(), format!("Cannot run {} to encrypt output: {}", self.tool.name(), e))
+    }
+}
+
+/// Output piped to an encryption process. The process is waited for when the
+/// writer is dropped; a failure is printed then and reported by `failed`.
+pub struct Encrypted {
+    child: Child,
+    stdin: Option<BufWriter<ChildStdin>>,
+    tool: Tool,
+    target: String,
+}
+
+impl Write for Encrypted {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdin.as_mut().expect("open until dropped").write(buf).map_err(|e| self.write_error(e))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdin.as_mut().expect("open until dropped").flush().map_err(|e| self.write_error(e))
+    }
+}
+
+impl Encrypted {
+    /// A broken pipe means the tool exited, e.g. for an unknown recipient.
+    fn write_error(&self, e: io::Error) -> io::Error {
+        io::Error::new(e.kind(), format!("{} stopped encrypting {}: {}", self.tool.name(), self.target, e))
+    }
+}
+
+impl Drop for Encrypted {
+    fn drop(&mut self) {
+        // Closing STDIN ends the input
+        let flushed = self.stdin.take().map_or(Ok(()), |mut stdin| stdin.flush());
+        let status = self.child.wait();
+        if flushed.is_err() || !status.as_ref().is_ok_and(|s| s.success()) {
+            let reason = match status {
+                Ok(status) => status.to_string(),
+                Err(e) => e.to_string(),
+            };
+            let mut failed = FAILED.lock().unwrap_or_else(|e| e.into_inner());
+            failed.get_or_insert_with(|| format!("{} could not encrypt {} ({})", self.tool.name(), self.target, reason));
+        }
+    }
+}
+
+/// Why an encryption process failed once its writer was dropped, if one did.
+pub fn failed() -> Option<String> {
+    FAILED.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recipient_tool() {
+        assert_eq!(Recipient::new("age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p").tool, Tool::Age);
+        assert_eq!(Recipient::new("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHsKLqeplhpW+uObz5dvMgjz1OxfM/XXUB+VHtZ6isGN").tool, Tool::Age);
+        assert_eq!(Recipient::new("records@clinic.example").tool, Tool::Gpg);
+        assert_eq!(Recipient::new("0x3AA5C34371567BD2").tool, Tool::Gpg);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::errors::CrabError;
+use std::path::Path;
+
+/// Permission bits of the `P` entry (1-based, as in the PDF specification) with their names.
+const PERMISSIONS: [(u32, &str); 8] = [
+    (3, "print"),
+    (4, "modify"),
+    (5, "copy"),
+    (6, "annotate"),
+    (9, "fill forms"),
+    (10, "extract for accessibility"),
+    (11, "assemble"),
+    (12, "print high quality"),
+];
+
+/// The encryption of a PDF, as MuPDF reads it when it opens the document, or
+/// the security handler alone when MuPDF rejects the handler.
+#[derive(Debug, Default, PartialEq)]
+pub struct Encryption {
+    /// Security handler, e.g. "Standard" or "Adobe.APS".
+    pub filter: String,
+    /// Cipher ("RC4" or "AES") and key length in bits.
+    pub cipher: Option<(String, i32)>,
+    pub revision: Option<i32>,
+    /// User access permissions (`P`), a bit field.
+    pub permissions: Option<i32>,
+}
+
+impl Encryption {
+    /// Handler and cipher, e.g. "password security (AES 256-bit, revision 6)".
+    pub fn algorithm(&self) -> String {
+        let handler = match self.filter.as_str() {
+            "Standard" => "password security".to_string(),
+            "Adobe.APS" => "Adobe LiveCycle Rights Management DRM".to_string(),
+            "Adobe.PubSec" | "Adobe.PPKLite" => "certificate security".to_string(),
+            other => format!("the {} DRM handler", other),
+        };
+        let cipher = self.cipher.as_ref().map(|(method, length)| format!("{} {}-bit", method, length));
+        let details: Vec<String> = cipher.into_iter().chain(self.revision.map(|r| format!("revision {}", r))).collect();
+        if details.is_empty() {
+            handler
+        } else {
+            format!("{} ({})", handler, details.join(", "))
+        }
+    }
+
+    /// What the document's permissions allow, e.g. "allows print, copy; denies modify".
+    pub fn describe_permissions(&self) -> Option<String> {
+        let p = self.permissions?;
+        let (allowed, denied): (Vec<_>, Vec<_>) = PERMISSIONS.iter().partition(|(bit, _)| p & (1 << (bit - 1)) != 0);
+        let names = |list: Vec<&(u32, &str)>| list.iter().map(|(_, name)| *name).collect::<Vec<_>>().join(", ");
+        Some(match (allowed.is_empty(), denied.is_empty()) {
+            (_, true) => "allows everything".to_string(),
+            (true, _) => "denies everything".to_string(),
+            _ => format!("allows {}; denies {}", names(allowed), names(denied)),
+        })
+    }
+}
+
+/// The error for a document MuPDF could not open, or opened but cannot read
+/// without a password, when encryption is the cause. `message` is MuPDF's
+/// error, and `encryption` what MuPDF read of a document needing a password.
+pub fn open_error(path: &Path, encryption: Option<Encryption>, needs_password: bool, message: &str) -> Option<CrabError> {
+    let lower = message.to_lowercase();
+    let crypt_error = ["crypt", "aes", "key length"].iter().any(|k| lower.contains(k));
+    if !needs_password && !crypt_error {
+        return None;
+    }
+    let encryption = encryption.or_else(|| unknown_handler(message));
+    let algorithm = encryption.as_ref().map_or_else(|| "an unknown method".to_string(), Encryption::algorithm);
+    let permissions = encryption.as_ref().and_then(Encryption::describe_permissions);
+    let permissions = permissions.map(|p| format!(" Its permissions: {}.", p)).unwrap_or_default();
+    let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+    Some(CrabError::Encrypted(if needs_password {
+        format!(
+            "{} is encrypted with {} and needs a password to open.{} Remove the password first, e.g. `qpdf --decrypt --password=PASSWORD in.pdf out.pdf`.",
+            name, algorithm, permissions
+        )
+    } else {
+        format!(
+            "{} is protected by {}, which cannot be decrypted ({}).{} Ask the sender for an unprotected copy, or export one from the application that opens it.",
+            name, algorithm, message, permissions
+        )
+    }))
+}
+
+/// The handler MuPDF names when it does not support it:
+/// "unknown encryption handler: 'Adobe.APS'".
+fn unknown_handler(message: &str) -> Option<Encryption> {
+    let (_, rest) = message.split_once("unknown encryption handler: '")?;
+    let filter = rest.split('\'').next().filter(|f| !f.is_empty())?;
+    Some(Encryption { filter: filter.to_string(), ..Default::default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard_aes() {
+        let enc = Encryption { filter: "Standard".into(), cipher: Some(("AES".into(), 256)), revision: Some(6), permissions: Some(-1084) };
+        assert_eq!(enc.algorithm(), "password security (AES 256-bit, revision 6)");
+        // -1084: print, fill forms, accessibility and high quality print only
+        assert_eq!(
+            enc.describe_permissions().unwrap(),
+            "allows print, fill forms, extract for accessibility, print high quality; denies modify, copy, annotate, assemble"
+        );
+        let Some(CrabError::Encrypted(msg)) = open_error(Path::new("in/report.pdf"), Some(enc), true, "document needs a password") else {
+            panic!("not an encryption error");
+        };
+        assert!(msg.starts_with("report.pdf is encrypted with password security (AES 256-bit, revision 6) and needs a password"));
+    }
+
+    #[test]
+    fn test_drm_handler() {
+        let message = "unknown encryption handler: 'Adobe.APS'";
+        let Some(CrabError::Encrypted(msg)) = open_error(Path::new("form.pdf"), None, false, message) else {
+            panic!("not an encryption error");
+        };
+        assert!(msg.starts_with("form.pdf is protected by Adobe LiveCycle Rights Management DRM, which cannot be decrypted"));
+        assert!(open_error(Path::new("form.pdf"), None, false, "no objects found").is_none());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::cli::Granularity;
+use crate::errors::CrabError;
+use crate::ocr::{Ocr, OcrResult, Renderings, Word};
+use crate::output::{Layer, TextUnit};
+use crate::preprocess::GrayImage;
+
+/// Minimum overlap (intersection over union) for two words to be the same word.
+const MIN_IOU: f32 = 0.5;
+
+/// OCR a page and each of its preprocessing variants, then merge the results
+/// word by word, keeping the most confident reading of each word.
+/// hOCR and TSV, if requested, are those of the original rendition.
+pub fn recognize(
+    ocr: &Ocr,
+    image: &GrayImage,
+    variants: &[GrayImage],
+    dpi: i32,
+    granularity: Granularity,
+    renderings: Renderings,
+) -> Result<OcrResult, CrabError> {
+    let mut results = vec![ocr.recognize_page(image, dpi, Granularity::Word, renderings)?];
+    for variant in variants {
+        results.push(ocr.recognize(variant, dpi, Granularity::Word)?);
+    }
+
+    // Variants rejected for low confidence have no words and do not take part
+    let variants: Vec<Vec<Word>> = results.iter().map(|r| r.words.clone()).collect();
+    let words = merge_words(&variants);
+
+    // Page metadata comes from the original rendition
+    let original = results.swap_remove(0);
+    if words.is_empty() {
+        return Ok(OcrResult {
+            layer: Layer::empty(),
+            words,
+            ..original
+        });
+    }
+
+    let mean_conf = words.iter().map(|w| w.conf).sum::<f32>() / words.len() as f32;
+    let text = text_from_words(&words);
+    let units = units_from_words(&words, &text, mean_conf, granularity);
+
+    Ok(OcrResult {
+        layer: Layer { text, units },
+        mean_conf: mean_conf.round() as i32,
+        words,
+        ..original
+    })
+}
+
+/// Merge word lists from several OCR runs of the same page.
+///
+/// The run with the highest mean confidence provides the layout; each of its
+/// words is replaced by an overlapping word from another run when that one is
+/// more confident.
+pub fn merge_words(variants: &[Vec<Word>]) -> Vec<Word> {
+    let mean = |words: &Vec<Word>| {
+        if words.is_empty() {
+            0.0
+        } else {
+            words.iter().map(|w| w.conf).sum::<f32>() / words.len() as f32
+        }
+    };
+    let Some((base_idx, _)) = variants
+        .iter()
+        .enumerate()
+        .filter(|(_, words)| !words.is_empty())
+        .max_by(|(_, a), (_, b)| mean(a).total_cmp(&mean(b)))
+    else {
+        return Vec::new();
+    };
+
+    let mut merged = variants[base_idx].clone();
+    for (idx, other) in variants.iter().enumerate() {
+        if idx == base_idx {
+            continue;
+        }
+        for word in merged.iter_mut() {
+            let best = other
+                .iter()
+                .filter(|o| iou(o.bbox, word.bbox) >= MIN_IOU)
+                .max_by(|a, b| a.conf.total_cmp(&b.conf));
+            if let Some(best) = best {
+                if best.conf > word.conf {
+                    word.text = best.text.clone();
+                    word.conf = best.conf;
+                }
+            }
+        }
+    }
+    merged
+}
+
+/// Rebuild page text from words: spaces within a line, newlines between lines,
+/// and a blank line between paragraphs, like Tesseract's own text output.
+pub fn text_from_words(words: &[Word]) -> String {
+    let mut text = String::new();
+    let mut prev: Option<&Word> = None;
+    for word in words {
+        if let Some(p) = prev {
+            if word.para != p.para {
+                text.push_str("\n\n");
+            } else if word.line != p.line {
+                text.push('\n');
+            } else {
+                text.push(' ');
+            }
+        }
+        text.push_str(&word.text);
+        prev = Some(word);
+    }
+    text.push('\n');
+    text
+}
+
+/// Group words into output units of the requested granularity.
+pub fn units_from_words(words: &[Word], text: &str, mean_conf: f32, granularity: Granularity) -> Vec<TextUnit> {
+    let key = |w: &Word| match granularity {
+        Granularity::Block => w.block,
+        Granularity::Paragraph => w.para,
+        _ => w.line,
+    };
+
+    match granularity {
+        Granularity::Page => vec![TextUnit {
+            text: text.to_string(),
+            conf: Some(mean_conf),
+            bbox: None,
+            lang: None,
+        }],
+        Granularity::Word => words
+            .iter()
+            .map(|w| TextUnit {
+                text: w.text.clone(),
+                conf: Some(w.conf),
+                bbox: Some(w.bbox),
+                lang: None,
+            })
+            .collect(),
+        _ => words
+            .chunk_by(|a, b| key(a) == key(b))
+            .map(|group| {
+                let conf = group.iter().map(|w| w.conf).sum::<f32>() / group.len() as f32;
+                let bbox = group.iter().skip(1).fold(group[0].bbox, |acc, w| union(acc, w.bbox));
+                TextUnit {
+                    text: text_from_words(group).trim_end().to_string(),
+                    conf: Some(conf),
+                    bbox: Some(bbox),
+                    lang: None,
+                }
+            })
+            .collect(),
+    }
+}
+
+fn union(a: [i32; 4], b: [i32; 4]) -> [i32; 4] {
+    [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]
+}
+
+fn iou(a: [i32; 4], b: [i32; 4]) -> f32 {
+    let width = (a[2].min(b[2]) - a[0].max(b[0])).max(0) as f32;
+    let height = (a[3].min(b[3]) - a[1].max(b[1])).max(0) as f32;
+    let intersection = width * height;
+    let area = |r: [i32; 4]| ((r[2] - r[0]).max(0) * (r[3] - r[1]).max(0)) as f32;
+    let union = area(a) + area(b) - intersection;
+    if union <= 0.0 {
+        0.0
+    } else {
+        intersection / union
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, conf: f32, x: i32, line: usize) -> Word {
+        Word {
+            text: text.to_string(),
+            conf,
+            bbox: [x, 0, x + 50, 20],
+            block: 0,
+            para: 0,
+            line,
+            lang: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_prefers_confident_words() {
+        let original = vec![word("Inv0ice", 40.0, 0, 0), word("42", 95.0, 60, 0)];
+        let binarized = vec![word("Invoice", 90.0, 2, 0), word("4Z", 50.0, 60, 0)];
+        let merged = merge_words(&[original, binarized]);
+        assert_eq!(merged[0].text, "Invoice");
+        assert_eq!(merged[1].text, "42");
+    }
+
+    #[test]
+    fn test_merge_skips_empty_variants() {
+        let merged = merge_words(&[Vec::new(), vec![word("Total", 80.0, 0, 0)]]);
+        assert_eq!(merged.len(), 1);
+        assert!(merge_words(&[Vec::new()]).is_empty());
+    }
+
+    #[test]
+    fn test_text_and_line_units() {
+        let words = vec![word("Hello", 90.0, 0, 0), word("world", 80.0, 60, 0), word("Bye", 70.0, 0, 1)];
+        let text = text_from_words(&words);
+        assert_eq!(text, "Hello world\nBye\n");
+
+        let lines = units_from_words(&words, &text, 80.0, Granularity::Line);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "Hello world");
+        assert_eq!(lines[0].bbox, Some([0, 0, 110, 20]));
+    }
+}
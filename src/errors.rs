@@ -19,6 +19,21 @@ pub enum CrabError {
 
     #[error("Process timed out")]
     Timeout, // Exit 2
+
+    #[error("No pages processed: {0}")]
+    NoPages(String), // Exit 6
+
+    #[error("Output Write Error: {0}")]
+    Output(std::io::Error), // Exit 7
+
+    #[error("Warning treated as error: {0}")]
+    Warning(String), // Exit 8
+
+    #[error("Encrypted Document: {0}")]
+    Encrypted(String), // Exit 9
+
+    #[error("Resource Limit Exceeded: {0}")]
+    ResourceLimit(String), // Exit 10
 }
 
 impl CrabError {
@@ -30,6 +45,11 @@ impl CrabError {
             CrabError::Pdf(_) => 3,
             CrabError::Ocr(_) => 4,
             CrabError::Internal(_) => 5,
+            CrabError::NoPages(_) => 6,
+            CrabError::Output(_) => 7,
+            CrabError::Warning(_) => 8,
+            CrabError::Encrypted(_) => 9,
+            CrabError::ResourceLimit(_) => 10,
         }
     }
 }
@@ -0,0 +1,152 @@
+use crate::docx::escape;
+use crate::output::{OutputWriter, PageResult, XfaData};
+use crate::renderer::{ActiveContent, FontInfo, SignatureField};
+use crate::warnings::Warning;
+use std::io::{self, Write};
+
+const HTML_HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="generator" content="crabocr">
+<title>OCR output</title>
+<style>
+body { max-width: 45em; margin: 0 auto; padding: 1em; font-family: serif; line-height: 1.5; }
+section { page-break-after: always; break-after: page; margin-bottom: 3em; }
+section > h2 { font-size: 0.8em; font-family: sans-serif; color: #888; border-bottom: 1px solid #ccc; }
+img { max-width: 100%; height: auto; }
+</style>
+</head>
+<body>
+"#;
+
+/// An HTML document for reading scanned reports in browsers and e-readers:
+/// one `<section>` per page holding the paragraphs of its merged blocks, its
+/// text layer or its OCR layer, preceded by the page image when exported
+/// (`--export-images`), embedded as a data URI so the file stands alone.
+/// Form data and warnings are not included.
+pub struct HtmlWriter<W: Write> {
+    out: W,
+    started: bool,
+}
+
+impl<W: Write> HtmlWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, started: false }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            write!(self.out, "{}", HTML_HEADER)?;
+        }
+        Ok(())
+    }
+
+    fn paragraph(&mut self, text: &str) -> io::Result<()> {
+        let lines: Vec<String> = text.lines().map(escape).collect();
+        writeln!(self.out, "<p>{}</p>", lines.join("<br>\n"))
+    }
+}
+
+impl<W: Write> OutputWriter for HtmlWriter<W> {
+    fn xfa(&mut self, _xfa: &XfaData) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn signatures(&mut self, _fields: &[SignatureField]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn active_content(&mut self, _content: &ActiveContent) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn fonts(&mut self, _fonts: &[FontInfo]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        self.start()?;
+        let label = page.label();
+        writeln!(self.out, "<section id=\"page-{}\">", label)?;
+        writeln!(self.out, "<h2>Page {}</h2>", label)?;
+        if let Some(path) = &page.image_file {
+            let mime = match path.extension().and_then(|e| e.to_str()) {
+                Some("jpg" | "jpeg") => "image/jpeg",
+                _ => "image/png",
+            };
+            let data = std::fs::read(path)?;
+            writeln!(self.out, "<img src=\"data:{};base64,{}\" alt=\"Page {}\">", mime, base64(&data), label)?;
+        }
+        for text in page.paragraphs() {
+            self.paragraph(&text)?;
+        }
+        writeln!(self.out, "</section>")
+    }
+
+    fn no_pages(&mut self, reason: &str) -> io::Result<()> {
+        self.start()?;
+        self.paragraph(&format!("No pages processed: {}", reason))
+    }
+
+    fn warnings(&mut self, _warnings: &[Warning]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.start()?;
+        writeln!(self.out, "</body>\n</html>")?;
+        self.out.flush()
+    }
+}
+
+/// Standard Base64 with padding (RFC 4648).
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Layer;
+
+    #[test]
+    fn test_html_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("page_0002.jpg");
+        std::fs::write(&image, b"\xFF\xD8\xFF").unwrap();
+        let layer = |text: &str| Some(Layer { text: text.into(), units: Vec::new() });
+        let mut buf = Vec::new();
+        {
+            let mut w = HtmlWriter::new(&mut buf);
+            w.page(&PageResult { number: 1, text_layer: layer("Report <draft>\nline two\n\nR&D\n"), ..Default::default() }).unwrap();
+            w.page(&PageResult { number: 2, ocr_layer: layer("Scanned\n"), image_file: Some(image), ..Default::default() }).unwrap();
+            w.finish().unwrap();
+        }
+        let html = String::from_utf8(buf).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+        assert!(html.contains("<section id=\"page-1\">\n<h2>Page 1</h2>\n<p>Report &lt;draft&gt;<br>\nline two</p>\n<p>R&amp;D</p>\n</section>"));
+        assert!(html.contains("<section id=\"page-2\">\n<h2>Page 2</h2>\n<img src=\"data:image/jpeg;base64,/9j/\" alt=\"Page 2\">\n<p>Scanned</p>"));
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!([base64(b""), base64(b"f"), base64(b"fo"), base64(b"foo"), base64(b"foobar")], ["", "Zg==", "Zm8=", "Zm9v", "Zm9vYmFy"]);
+    }
+}
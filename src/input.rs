@@ -41,6 +41,7 @@ impl InputSource {
                 buffer.extend_from_slice(&chunk[..n]);
                 
                 if total_read > MAX_INMEM_PDF_BYTES {
+                    check_content(&buffer)?;
                     // Switch to temp file
                     let mut temp_file = NamedTempFile::new()?;
                     temp_file.write_all(&buffer)?;
@@ -50,7 +51,113 @@ impl InputSource {
                 }
             }
 
+            check_content(&buffer)?;
             Ok(InputSource::StdinBytes(buffer))
         }
     }
 }
+
+/// Reject input whose leading bytes identify a type we cannot process,
+/// instead of surfacing a cryptic MuPDF open failure later.
+fn check_content(head: &[u8]) -> Result<(), CrabError> {
+    match sniff_unsupported(head) {
+        Some(kind) => Err(CrabError::Input(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("input looks like {}, expected PDF/image", kind),
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Describe the input if its magic bytes identify an unsupported type.
+/// Returns None for PDFs, supported images, and anything unrecognized.
+fn sniff_unsupported(head: &[u8]) -> Option<&'static str> {
+    const SUPPORTED_MAGIC: &[&[u8]] = &[
+        b"\x89PNG",             // PNG
+        b"\xFF\xD8\xFF",         // JPEG
+        b"II*\0",               // TIFF (little-endian)
+        b"MM\0*",               // TIFF (big-endian)
+        b"GIF8",                // GIF
+        b"BM",                  // BMP
+        b"\0\0\0\x0CjP  ",      // JPEG 2000
+        b"\xFF\x4F\xFF\x51",     // JPEG 2000 codestream
+    ];
+
+    if head.is_empty() {
+        return Some("empty input");
+    }
+
+    // The PDF header may be preceded by junk, which readers tolerate
+    let window = &head[..head.len().min(1024)];
+    if window.windows(5).any(|w| w == b"%PDF-") {
+        return None;
+    }
+    if SUPPORTED_MAGIC.iter().any(|m| head.starts_with(m)) {
+        return None;
+    }
+    // PNM family: P1 to P7
+    if head.len() >= 2 && head[0] == b'P' && (b'1'..=b'7').contains(&head[1]) {
+        return None;
+    }
+
+    if head.starts_with(b"PK\x03\x04") {
+        return Some("a ZIP archive");
+    }
+    if head.starts_with(b"\x1F\x8B") {
+        return Some("gzip-compressed data");
+    }
+    if head.starts_with(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1") {
+        return Some("a legacy Microsoft Office document");
+    }
+    if head.starts_with(b"%!PS") {
+        return Some("a PostScript file");
+    }
+
+    // Text-based formats, ignoring a UTF-8 BOM and leading whitespace
+    let text = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    let start = text.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(text.len());
+    let text = &text[start..];
+    let lower: Vec<u8> = text.iter().take(64).map(u8::to_ascii_lowercase).collect();
+
+    if lower.starts_with(b"<!doctype html") || lower.starts_with(b"<html") {
+        return Some("an HTML page");
+    }
+    if lower.starts_with(b"<?xml") {
+        return Some("an XML document");
+    }
+    if text.starts_with(b"{") || text.starts_with(b"[") {
+        return Some("JSON");
+    }
+
+    let sample = &text[..text.len().min(512)];
+    if !sample.is_empty() && !sample.contains(&0) && std::str::from_utf8(sample).is_ok() {
+        return Some("plain text");
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_supported() {
+        assert_eq!(sniff_unsupported(b"%PDF-1.7\n%\xE2\xE3"), None);
+        assert_eq!(sniff_unsupported(b"\r\n%PDF-1.4\n"), None);
+        assert_eq!(sniff_unsupported(b"\x89PNG\r\n\x1A\n"), None);
+        assert_eq!(sniff_unsupported(b"\xFF\xD8\xFF\xE0"), None);
+        assert_eq!(sniff_unsupported(b"II*\0\x08\0"), None);
+        // Unrecognized binary is left to MuPDF
+        assert_eq!(sniff_unsupported(b"\x00\x01\x02\xFE"), None);
+    }
+
+    #[test]
+    fn test_sniff_unsupported() {
+        assert_eq!(sniff_unsupported(b""), Some("empty input"));
+        assert_eq!(sniff_unsupported(b"  <!DOCTYPE html><html>"), Some("an HTML page"));
+        assert_eq!(sniff_unsupported(b"PK\x03\x04\x14\0"), Some("a ZIP archive"));
+        assert_eq!(sniff_unsupported(b"{\"error\": 404}"), Some("JSON"));
+        assert_eq!(sniff_unsupported(b"Not Found\n"), Some("plain text"));
+    }
+}
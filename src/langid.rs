@@ -0,0 +1,141 @@
+use crate::stats;
+
+/// Fewer letters than this are too little to tell a language by.
+const MIN_LETTERS: usize = 20;
+/// Fewer function words than this leave Latin-script text untagged.
+const MIN_HITS: usize = 2;
+
+/// Frequent function words of Latin-script languages, by ISO 639-3 code.
+/// Words shared by several languages count for each of them; the words only
+/// one of them uses decide.
+const FUNCTION_WORDS: [(&str, &[&str]); 15] = [
+    ("eng", &["the", "and", "of", "to", "in", "is", "that", "for", "it", "with", "as", "was", "on", "are", "be", "this", "by", "not", "or", "from", "at", "which", "have", "an"]),
+    ("fra", &["le", "la", "les", "de", "des", "et", "est", "un", "une", "du", "en", "que", "qui", "pour", "dans", "sur", "par", "pas", "au", "avec", "ce", "il", "sont", "aux"]),
+    ("deu", &["der", "die", "das", "und", "ist", "nicht", "mit", "den", "von", "zu", "des", "ein", "eine", "auf", "für", "sich", "dem", "im", "auch", "es", "werden", "wird"]),
+    ("spa", &["el", "la", "los", "las", "de", "del", "y", "que", "en", "es", "un", "una", "por", "con", "para", "se", "no", "su", "al", "como"]),
+    ("ita", &["il", "la", "le", "di", "che", "e", "è", "un", "una", "per", "con", "non", "del", "della", "sono", "gli", "si", "nel", "al", "dei"]),
+    ("por", &["o", "a", "os", "as", "de", "do", "da", "e", "que", "em", "um", "uma", "para", "com", "não", "é", "dos", "das", "no", "na", "ao"]),
+    ("nld", &["de", "het", "een", "en", "van", "is", "dat", "niet", "op", "te", "met", "zijn", "voor", "die", "er", "aan", "ook", "bij"]),
+    ("swe", &["och", "att", "det", "är", "som", "en", "på", "för", "av", "med", "den", "till", "inte", "har", "om", "ett"]),
+    ("dan", &["og", "at", "det", "er", "som", "en", "på", "for", "af", "med", "den", "til", "ikke", "har", "et"]),
+    ("pol", &["i", "w", "na", "z", "że", "się", "nie", "do", "jest", "to", "o", "jak", "po", "przez", "dla", "od", "są"]),
+    ("ces", &["a", "v", "na", "se", "že", "je", "to", "s", "z", "do", "o", "jako", "pro", "by", "jsou", "není"]),
+    ("tur", &["ve", "bir", "bu", "da", "de", "için", "ile", "olarak", "çok", "daha", "gibi", "olan", "ne", "var"]),
+    ("ron", &["și", "de", "la", "în", "cu", "pe", "un", "o", "este", "că", "nu", "din", "pentru", "care", "sunt"]),
+    ("hun", &["a", "az", "és", "hogy", "nem", "is", "egy", "van", "meg", "ez", "de", "csak"]),
+    ("fin", &["ja", "on", "ei", "se", "että", "oli", "hän", "ovat", "kanssa", "mutta", "tai", "kun", "myös"]),
+];
+
+/// Language a text is written in, as an ISO 639-3 code, with how sure the
+/// guess is (0-1).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DetectedLanguage {
+    pub lang: &'static str,
+    pub confidence: f32,
+}
+
+/// Guess the language of recognized text (`--detect-language`), whatever
+/// the traineddata it was read with: by script for scripts mostly used by
+/// one language, else by the function words of Latin-script languages.
+/// `None` for short texts and texts matching no known language.
+pub fn detect(text: &str) -> Option<DetectedLanguage> {
+    let scripts = stats::script_histogram([text]);
+    let letters: usize = scripts.iter().map(|(_, n)| n).sum();
+    let &(script, count) = scripts.first()?;
+    if letters < MIN_LETTERS {
+        return None;
+    }
+    let share = count as f32 / letters as f32;
+    let by_script = |lang| Some(DetectedLanguage { lang, confidence: share });
+    match script {
+        "Latin" => by_function_words(text),
+        "Cyrillic" => by_script(cyrillic(text)),
+        "Arabic" => by_script(arabic(text)),
+        // Japanese mixes kana with Han characters
+        "Han" if scripts.iter().any(|(s, _)| *s == "Japanese") => by_script("jpn"),
+        "Han" => by_script("zho"),
+        "Japanese" => by_script("jpn"),
+        "Hangul" => by_script("kor"),
+        "Greek" => by_script("ell"),
+        "Hebrew" => by_script("heb"),
+        "Armenian" => by_script("hye"),
+        "Georgian" => by_script("kat"),
+        "Thai" => by_script("tha"),
+        "Devanagari" => by_script("hin"),
+        "Bengali" => by_script("ben"),
+        "Gurmukhi" => by_script("pan"),
+        "Gujarati" => by_script("guj"),
+        "Tamil" => by_script("tam"),
+        "Telugu" => by_script("tel"),
+        "Kannada" => by_script("kan"),
+        "Malayalam" => by_script("mal"),
+        _ => None,
+    }
+}
+
+/// The Latin-script language whose function words the text uses most, sure
+/// by as much as it leads the runner-up.
+fn by_function_words(text: &str) -> Option<DetectedLanguage> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let mut scores: Vec<(&'static str, usize)> = FUNCTION_WORDS
+        .iter()
+        .map(|(lang, list)| (*lang, words.iter().filter(|w| list.contains(&w.as_str())).count()))
+        .collect();
+    // Stable: ties go to the language listed first
+    scores.sort_by_key(|&(_, hits)| std::cmp::Reverse(hits));
+    let (lang, best) = scores[0];
+    if best < MIN_HITS {
+        return None;
+    }
+    Some(DetectedLanguage { lang, confidence: best as f32 / (best + scores[1].1) as f32 })
+}
+
+/// Cyrillic languages by the letters only they use, Russian otherwise.
+fn cyrillic(text: &str) -> &'static str {
+    if text.contains(['і', 'ї', 'є', 'ґ', 'І', 'Ї', 'Є', 'Ґ']) {
+        "ukr"
+    } else if text.contains(['ј', 'љ', 'њ', 'ћ', 'ђ', 'џ', 'Ј', 'Љ', 'Њ', 'Ћ', 'Ђ', 'Џ']) {
+        "srp"
+    } else if text.contains(['ў', 'Ў']) {
+        "bel"
+    } else {
+        "rus"
+    }
+}
+
+/// Arabic-script languages by the letters only they use, Arabic otherwise.
+fn arabic(text: &str) -> &'static str {
+    if text.contains(['ٹ', 'ڈ', 'ڑ', 'ں', 'ے']) {
+        "urd"
+    } else if text.contains(['پ', 'چ', 'ژ', 'گ']) {
+        "fas"
+    } else {
+        "ara"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let lang = |text: &str| detect(text).map(|d| d.lang);
+        assert_eq!(lang("The invoice is due at the end of the month and will be paid by transfer."), Some("eng"));
+        assert_eq!(lang("La facture est payable à la fin du mois par virement sur le compte."), Some("fra"));
+        assert_eq!(lang("Die Rechnung ist am Ende des Monats fällig und wird per Überweisung bezahlt."), Some("deu"));
+        assert_eq!(lang("La factura se paga al final del mes por transferencia a la cuenta."), Some("spa"));
+        assert_eq!(lang("Рахунок сплачується в кінці місяця переказом."), Some("ukr"));
+        assert_eq!(lang("Счёт оплачивается в конце месяца переводом."), Some("rus"));
+        assert_eq!(lang("請在月底前通過銀行轉帳支付這張發票謝謝您的合作與支持"), Some("zho"));
+        assert_eq!(lang("Total 1284.50 EUR"), None);
+        assert_eq!(lang("Invoice 2024-0193 Reference ABCDEFGH Total"), None);
+
+        let detected = detect("The invoice is due at the end of the month.").unwrap();
+        assert_eq!((detected.lang, detected.confidence), ("eng", 6.0 / 7.0));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::ocr::Word;
+use crate::renderer::TextLine;
+
+/// Most blank lines kept for a vertical gap between two rows.
+const MAX_BLANK_LINES: usize = 2;
+/// Gap between two OCR words of a line, in word heights, that starts a new fragment.
+const WORD_GAP: f32 = 1.0;
+
+/// A piece of text placed at its position on the page.
+struct Fragment {
+    rect: [f32; 4],
+    text: String,
+}
+
+/// The text-layer lines of a page laid out as plain text (`--layout`): each
+/// line starts at the column matching its position, as with `pdftotext -layout`.
+/// MuPDF starts a new line at wide gaps, so columns line up.
+pub fn from_lines(lines: &[TextLine]) -> String {
+    let fragments = lines
+        .iter()
+        .filter(|l| !l.text.trim().is_empty())
+        .map(|l| Fragment { rect: l.rect, text: l.text.trim().to_string() })
+        .collect();
+    layout(fragments)
+}
+
+/// OCR words laid out as plain text (`--layout`). Words of a line closer than
+/// they are tall are kept together, so rounding to columns does not widen spaces.
+pub fn from_words(words: &[Word]) -> String {
+    let mut fragments: Vec<Fragment> = Vec::new();
+    let mut prev: Option<&Word> = None;
+    for word in words.iter().filter(|w| !w.text.trim().is_empty()) {
+        let rect = word.bbox.map(|v| v as f32);
+        let joined = prev.is_some_and(|p| {
+            let height = (word.bbox[3] - word.bbox[1]).max(p.bbox[3] - p.bbox[1]);
+            p.line == word.line && (word.bbox[0] - p.bbox[2]) as f32 <= WORD_GAP * height as f32
+        });
+        match fragments.last_mut() {
+            Some(fragment) if joined => {
+                fragment.rect = [
+                    fragment.rect[0].min(rect[0]),
+                    fragment.rect[1].min(rect[1]),
+                    fragment.rect[2].max(rect[2]),
+                    fragment.rect[3].max(rect[3]),
+                ];
+                fragment.text.push(' ');
+                fragment.text.push_str(&word.text);
+            }
+            _ => fragments.push(Fragment { rect, text: word.text.clone() }),
+        }
+        prev = Some(word);
+    }
+    layout(fragments)
+}
+
+/// Group fragments into rows by vertical position and place each at the
+/// column of its left edge, in characters of the page's median width.
+/// Vertical gaps of a line or more become blank lines.
+fn layout(mut fragments: Vec<Fragment>) -> String {
+    if fragments.is_empty() {
+        return String::new();
+    }
+    let char_width = median(fragments.iter().map(|f| (f.rect[2] - f.rect[0]) / f.text.chars().count() as f32));
+    let line_height = median(fragments.iter().map(|f| f.rect[3] - f.rect[1]));
+    let left = fragments.iter().map(|f| f.rect[0]).fold(f32::MAX, f32::min);
+
+    let center_y = |f: &Fragment| (f.rect[1] + f.rect[3]) / 2.0;
+    fragments.sort_by(|a, b| center_y(a).total_cmp(&center_y(b)));
+    let mut rows: Vec<Vec<Fragment>> = Vec::new();
+    for fragment in fragments {
+        let cy = center_y(&fragment);
+        match rows.last_mut() {
+            Some(row) if row.iter().any(|f| f.rect[1] <= cy && cy <= f.rect[3]) => row.push(fragment),
+            _ => rows.push(vec![fragment]),
+        }
+    }
+
+    let mut text = String::new();
+    let mut prev_bottom: Option<f32> = None;
+    for mut row in rows {
+        row.sort_by(|a, b| a.rect[0].total_cmp(&b.rect[0]));
+        let top = row.iter().map(|f| f.rect[1]).fold(f32::MAX, f32::min);
+        if let Some(bottom) = prev_bottom {
+            let blank = ((top - bottom) / line_height).round().max(0.0) as usize;
+            text.push_str(&"\n".repeat(blank.min(MAX_BLANK_LINES)));
+        }
+        prev_bottom = Some(row.iter().map(|f| f.rect[3]).fold(f32::MIN, f32::max));
+
+        let mut line = String::new();
+        let mut width = 0;
+        for fragment in &row {
+            let column = ((fragment.rect[0] - left) / char_width).round().max(0.0) as usize;
+            let pad = if width == 0 { column } else { column.saturating_sub(width).max(1) };
+            line.push_str(&" ".repeat(pad));
+            line.push_str(&fragment.text);
+            width += pad + fragment.text.chars().count();
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+fn median(values: impl Iterator<Item = f32>) -> f32 {
+    let mut values: Vec<f32> = values.filter(|v| v.is_finite() && *v > 0.0).collect();
+    if values.is_empty() {
+        return 1.0;
+    }
+    values.sort_by(f32::total_cmp);
+    values[values.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, rect: [f32; 4]) -> TextLine {
+        TextLine { rect, text: text.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_columns_line_up() {
+        // 5 points per character
+        let lines = [
+            line("Invoice", [50.0, 40.0, 85.0, 50.0]),
+            line("Item", [50.0, 100.0, 70.0, 110.0]),
+            line("Price", [150.0, 100.0, 175.0, 110.0]),
+            line("Toner", [50.0, 112.0, 75.0, 122.0]),
+            line("39.90", [150.0, 112.0, 175.0, 122.0]),
+        ];
+        assert_eq!(from_lines(&lines), "Invoice\n\n\nItem                Price\nToner               39.90\n");
+    }
+
+    #[test]
+    fn test_ocr_words() {
+        let word = |text: &str, bbox: [i32; 4], line: usize| Word { text: text.into(), conf: 90.0, bbox, block: 0, para: 0, line, lang: None };
+        let words = [
+            word("Unit", [100, 100, 140, 120], 0),
+            word("price", [150, 100, 200, 120], 0),
+            word("Total", [400, 100, 450, 120], 0),
+            word("4.50", [100, 125, 140, 145], 1),
+            word("9.00", [400, 125, 440, 145], 1),
+        ];
+        assert_eq!(from_words(&words), "Unit price                    Total\n4.50                          9.00\n");
+        assert_eq!(from_words(&[]), "");
+    }
+}
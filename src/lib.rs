@@ -0,0 +1,60 @@
+//! The crabocr engine: PDF text and OCR extraction, and the types of its
+//! JSON output. `ocr_document` runs an extraction in a single call; the
+//! command line program is `run`.
+
+mod align;
+mod alto;
+mod analysis;
+mod api;
+mod app;
+mod audit;
+mod budget;
+mod cli;
+mod cluster;
+mod completions;
+mod correct;
+mod denoise;
+mod digest;
+mod docx;
+mod dryrun;
+#[cfg(feature = "encrypt")]
+mod encrypt;
+mod encryption;
+mod ensemble;
+mod errors;
+mod html;
+mod input;
+mod langid;
+mod layout;
+mod limits;
+mod logging;
+mod markdown;
+mod merge;
+mod ocr;
+mod output;
+mod pagexml;
+mod pipeline;
+mod postprocess;
+mod preflight;
+mod preprocess;
+mod redact;
+mod renderer;
+pub mod schema;
+mod searchable;
+mod selftest;
+mod sidecar;
+mod stats;
+mod synthtest;
+mod tables;
+mod textdiff;
+mod warnings;
+mod xfa;
+mod xfadiff;
+mod xfamap;
+mod xlsx;
+mod zones;
+
+pub use api::{ocr_document, DocumentResult, Options};
+pub use app::run;
+pub use cli::{Dpi, Mode, Models, XfaMode};
+pub use errors::CrabError;
@@ -0,0 +1,67 @@
+use crate::errors::CrabError;
+use crate::renderer::{Document, PageResources, Renderer};
+
+/// Bytes in a megabyte, as `--max-content-mb` counts them.
+pub const MB: u64 = 1024 * 1024;
+
+/// Limits on what a page may hold, checked before its text is read or it is
+/// rendered, for documents from untrusted sources (`--max-object-depth`,
+/// `--max-page-images`, `--max-content-mb`). A page past one fails the run.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// Nesting depth of form XObjects and tiling patterns.
+    pub max_depth: Option<u32>,
+    /// Image XObjects per page.
+    pub max_images: Option<u32>,
+    /// Decoded content stream bytes per page.
+    pub max_content: Option<u64>,
+}
+
+impl ResourceLimits {
+    pub fn is_set(&self) -> bool {
+        self.max_depth.is_some() || self.max_images.is_some() || self.max_content.is_some()
+    }
+
+    /// Walk the resources of a page (0-based) and fail if it holds more than allowed.
+    pub fn check(&self, renderer: &Renderer, doc: &Document, page_idx: i32) -> Result<(), CrabError> {
+        if !self.is_set() {
+            return Ok(());
+        }
+        let resources = renderer.page_resources(doc, page_idx, self.max_depth, self.max_content)?;
+        match self.violation(&resources) {
+            Some(reason) => Err(CrabError::ResourceLimit(format!("Page {} {}", page_idx + 1, reason))),
+            None => Ok(()),
+        }
+    }
+
+    /// The first limit a page goes past, described.
+    fn violation(&self, resources: &PageResources) -> Option<String> {
+        if let Some(max) = self.max_depth.filter(|&max| resources.depth > max) {
+            return Some(format!("nests forms or patterns more than {} levels deep (--max-object-depth)", max));
+        }
+        if let Some(max) = self.max_images.filter(|&max| resources.images > max) {
+            return Some(format!("holds {} images, more than {} (--max-page-images)", resources.images, max));
+        }
+        if let Some(max) = self.max_content.filter(|&max| resources.content_bytes > max) {
+            return Some(format!("has more than {} MB of content streams (--max-content-mb)", max / MB));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violation() {
+        let limits = ResourceLimits { max_depth: Some(8), max_images: Some(100), max_content: Some(2 * MB) };
+        let page = |depth, images, content_bytes| PageResources { depth, images, content_bytes };
+        assert_eq!(limits.violation(&page(8, 100, 2 * MB)), None);
+        assert_eq!(limits.violation(&page(9, 0, 0)).unwrap(), "nests forms or patterns more than 8 levels deep (--max-object-depth)");
+        assert_eq!(limits.violation(&page(1, 4000, 0)).unwrap(), "holds 4000 images, more than 100 (--max-page-images)");
+        assert_eq!(limits.violation(&page(1, 1, 2 * MB + 1)).unwrap(), "has more than 2 MB of content streams (--max-content-mb)");
+        assert!(!ResourceLimits::default().is_set());
+        assert_eq!(ResourceLimits::default().violation(&page(500, 500, u64::MAX)), None);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::ocr::Word;
+use crate::output::{OutputWriter, PageResult, XfaData};
+use crate::renderer::{ActiveContent, FontInfo, SignatureField, TextLine};
+use crate::warnings::Warning;
+use std::io::{self, Write};
+
+/// Paragraphs set this much larger than the body text are headings.
+const HEADING_RATIO: f32 = 1.2;
+/// Heading sizes within this ratio of each other share a level.
+const LEVEL_RATIO: f32 = 1.1;
+/// Paragraphs longer than this (characters) are never headings.
+const MAX_HEADING_CHARS: usize = 150;
+/// Characters opening an unordered list item, when followed by a space.
+const BULLETS: &[char] = &['•', '◦', '▪', '▫', '‣', '⁃', '●', '○', '■', '□', '-', '–', '*'];
+
+/// A Markdown document for LLM ingestion: headings, paragraphs and lists
+/// rebuilt from each page's text layer (font sizes and bold lines), or from
+/// Tesseract's paragraphs and line heights on pages without one. Pages are
+/// separated by an HTML comment; form data and warnings are not included.
+pub struct MarkdownWriter<W: Write> {
+    out: W,
+    pages: usize,
+}
+
+impl<W: Write> MarkdownWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, pages: 0 }
+    }
+}
+
+impl<W: Write> OutputWriter for MarkdownWriter<W> {
+    fn xfa(&mut self, _xfa: &XfaData) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn signatures(&mut self, _fields: &[SignatureField]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn active_content(&mut self, _content: &ActiveContent) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn fonts(&mut self, _fonts: &[FontInfo]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        if self.pages > 0 {
+            writeln!(self.out)?;
+        }
+        self.pages += 1;
+        writeln!(self.out, "<!-- page {} -->", page.label())?;
+        let paragraphs = if page.lines.is_empty() { ocr_paragraphs(&page.words) } else { text_paragraphs(&page.lines) };
+        let mut in_list = false;
+        for block in blocks(&paragraphs) {
+            let item = matches!(block, Block::Item(..));
+            // List items follow each other without a blank line
+            if !(item && in_list) {
+                writeln!(self.out)?;
+            }
+            in_list = item;
+            match block {
+                Block::Heading(level, text) => writeln!(self.out, "{} {}", "#".repeat(level), text)?,
+                Block::Paragraph(text) => writeln!(self.out, "{}", escape(&text))?,
+                Block::Item(Some(number), text) => writeln!(self.out, "{}. {}", number, escape(&text))?,
+                Block::Item(None, text) => writeln!(self.out, "- {}", escape(&text))?,
+            }
+        }
+        Ok(())
+    }
+
+    fn no_pages(&mut self, reason: &str) -> io::Result<()> {
+        writeln!(self.out, "<!-- no pages processed: {} -->", reason)
+    }
+
+    fn warnings(&mut self, _warnings: &[Warning]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// A line of a page, with the height its text is set at (points for the
+/// text layer, pixels for OCR: only compared within a page).
+struct Line {
+    text: String,
+    size: f32,
+    bold: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Block {
+    /// Level (1-6) and text.
+    Heading(usize, String),
+    Paragraph(String),
+    /// List item, with its number when ordered.
+    Item(Option<String>, String),
+}
+
+/// Text-layer lines grouped by MuPDF block.
+fn text_paragraphs(lines: &[TextLine]) -> Vec<Vec<Line>> {
+    lines
+        .chunk_by(|a, b| a.block == b.block)
+        .map(|block| {
+            block
+                .iter()
+                .filter(|l| !l.text.trim().is_empty())
+                .map(|l| Line { text: l.text.trim().to_string(), size: l.size, bold: l.bold })
+                .collect()
+        })
+        .collect()
+}
+
+/// OCR words grouped by Tesseract paragraph and line, sized by line height.
+fn ocr_paragraphs(words: &[Word]) -> Vec<Vec<Line>> {
+    words
+        .chunk_by(|a, b| a.para == b.para)
+        .map(|para| {
+            para.chunk_by(|a, b| a.line == b.line)
+                .map(|line| {
+                    let top = line.iter().map(|w| w.bbox[1]).min().unwrap_or(0);
+                    let bottom = line.iter().map(|w| w.bbox[3]).max().unwrap_or(0);
+                    let text: Vec<&str> = line.iter().map(|w| w.text.as_str()).collect();
+                    Line { text: text.join(" "), size: (bottom - top) as f32, bold: false }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Turn paragraphs into Markdown blocks. Short paragraphs set larger than the
+/// body text become headings, a level per size from the largest down; short
+/// bold lines at body size become headings below those. Lines opening with a
+/// bullet or a number start list items, which continue over the next lines.
+fn blocks(paragraphs: &[Vec<Line>]) -> Vec<Block> {
+    let paragraphs: Vec<&Vec<Line>> = paragraphs.iter().filter(|p| !p.is_empty()).collect();
+    let body = body_size(paragraphs.iter().flat_map(|p| p.iter()));
+    let size = |p: &[Line]| p.iter().map(|l| l.size).sum::<f32>() / p.len() as f32;
+    let short = |p: &[Line]| p.iter().map(|l| l.text.chars().count()).sum::<usize>() <= MAX_HEADING_CHARS && list_marker(&p[0].text).is_none();
+    let sized_heading = |p: &[Line]| short(p) && size(p) >= body * HEADING_RATIO;
+    let bold_heading = |p: &[Line]| {
+        short(p) && p.len() == 1 && p[0].bold && !p[0].text.ends_with(['.', ',', ';']) && size(p) * LEVEL_RATIO >= body
+    };
+
+    // Largest size of each heading level
+    let mut sizes: Vec<f32> = paragraphs.iter().filter(|p| sized_heading(p)).map(|p| size(p)).collect();
+    sizes.sort_by(|a, b| b.total_cmp(a));
+    let mut levels: Vec<f32> = Vec::new();
+    for s in sizes {
+        if levels.last().is_none_or(|last| s * LEVEL_RATIO < *last) {
+            levels.push(s);
+        }
+    }
+
+    let mut blocks = Vec::new();
+    for p in paragraphs {
+        if sized_heading(p) || bold_heading(p) {
+            let s = size(p);
+            let level = match levels.iter().position(|l| s * LEVEL_RATIO >= *l) {
+                Some(i) if sized_heading(p) => i + 1,
+                _ => levels.len() + 1,
+            };
+            let text = p.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join(" ");
+            blocks.push(Block::Heading(level.min(6), text));
+            continue;
+        }
+        let start = blocks.len();
+        for line in p {
+            let continued = blocks.len() > start;
+            match list_marker(&line.text) {
+                Some((number, text)) => blocks.push(Block::Item(number, text.to_string())),
+                None => match blocks.last_mut() {
+                    Some(Block::Paragraph(text) | Block::Item(_, text)) if continued => join(text, &line.text),
+                    _ => blocks.push(Block::Paragraph(line.text.clone())),
+                },
+            }
+        }
+    }
+    blocks
+}
+
+/// Size the most characters of the page are set at (a weighted median).
+fn body_size<'a>(lines: impl Iterator<Item = &'a Line>) -> f32 {
+    let mut sizes: Vec<(f32, usize)> = lines.map(|l| (l.size, l.text.chars().count())).collect();
+    sizes.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let total: usize = sizes.iter().map(|(_, n)| n).sum();
+    let mut seen = 0;
+    for (size, n) in sizes {
+        seen += n;
+        if seen * 2 >= total {
+            return size;
+        }
+    }
+    0.0
+}
+
+/// Split a list item's marker from its text: `None` for bullets, the number for ordered items.
+fn list_marker(text: &str) -> Option<(Option<String>, &str)> {
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    let (number, rest) = if (1..=3).contains(&digits) {
+        let rest = text[digits..].strip_prefix(['.', ')'])?;
+        (Some(text[..digits].to_string()), rest)
+    } else {
+        let rest = text.strip_prefix(BULLETS)?;
+        (None, rest)
+    };
+    let item = rest.trim_start();
+    (rest.starts_with(char::is_whitespace) && !item.is_empty()).then_some((number, item))
+}
+
+/// Append a wrapped line, rejoining words hyphenated across the break.
+fn join(text: &mut String, line: &str) {
+    let hyphenated = text.strip_suffix('-').is_some_and(|t| t.ends_with(char::is_alphabetic));
+    if hyphenated && line.starts_with(char::is_lowercase) {
+        text.pop();
+    } else {
+        text.push(' ');
+    }
+    text.push_str(line);
+}
+
+/// Keep text from reading as a heading or quote.
+fn escape(text: &str) -> String {
+    if text.starts_with(['#', '>']) {
+        format!("\\{}", text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, block: usize, size: f32, bold: bool) -> TextLine {
+        TextLine { text: text.to_string(), block, size, bold, ..Default::default() }
+    }
+
+    fn word(text: &str, bbox: [i32; 4], para: usize, line: usize) -> Word {
+        Word { text: text.to_string(), conf: 90.0, bbox, block: 0, para, line, lang: None }
+    }
+
+    #[test]
+    fn test_text_layer_blocks() {
+        let lines = [
+            line("Annual Report", 0, 24.0, true),
+            line("Overview", 1, 16.0, false),
+            line("Results", 2, 10.0, true),
+            line("Sales grew in every region, driven by strong de-", 3, 10.0, false),
+            line("mand for the new range.", 3, 10.0, false),
+            line("• First point", 4, 10.0, false),
+            line("continued here", 4, 10.0, false),
+            line("• Second point", 4, 10.0, false),
+            line("2) Numbered", 5, 10.0, false),
+            line("# not a heading", 6, 10.0, false),
+        ];
+        assert_eq!(
+            blocks(&text_paragraphs(&lines)),
+            [
+                Block::Heading(1, "Annual Report".into()),
+                Block::Heading(2, "Overview".into()),
+                Block::Heading(3, "Results".into()),
+                Block::Paragraph("Sales grew in every region, driven by strong demand for the new range.".into()),
+                Block::Item(None, "First point continued here".into()),
+                Block::Item(None, "Second point".into()),
+                Block::Item(Some("2".into()), "Numbered".into()),
+                Block::Paragraph("# not a heading".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ocr_blocks() {
+        let words = [
+            word("Chapter", [100, 100, 300, 160], 0, 0),
+            word("One", [320, 100, 420, 160], 0, 0),
+            word("It", [100, 200, 120, 230], 1, 1),
+            word("was", [130, 200, 180, 230], 1, 1),
+            word("a", [100, 240, 110, 270], 1, 2),
+            word("dark", [120, 240, 180, 270], 1, 2),
+            word("night.", [190, 240, 260, 270], 1, 2),
+        ];
+        assert_eq!(
+            blocks(&ocr_paragraphs(&words)),
+            [Block::Heading(1, "Chapter One".into()), Block::Paragraph("It was a dark night.".into())]
+        );
+    }
+
+    #[test]
+    fn test_markdown_writer() {
+        let page = PageResult {
+            number: 2,
+            lines: vec![line("Title", 0, 20.0, false), line("- a", 1, 10.0, false), line("- b", 1, 10.0, false), line("Body text.", 2, 10.0, false)],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        {
+            let mut w = MarkdownWriter::new(&mut buf);
+            w.page(&page).unwrap();
+            w.page(&PageResult { number: 3, ..Default::default() }).unwrap();
+            w.finish().unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "<!-- page 2 -->\n\n# Title\n\n- a\n- b\n\nBody text.\n\n<!-- page 3 -->\n"
+        );
+    }
+}
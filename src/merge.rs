@@ -0,0 +1,143 @@
+use crate::ocr::Word;
+use crate::output::ImagePlacement;
+use crate::renderer::TextLine;
+
+/// Distance (points) around text-layer lines within which OCR words are taken
+/// to read the same text.
+const LINE_MARGIN: f32 = 2.0;
+
+/// Where a merged block comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// The PDF text layer.
+    Text,
+    /// OCR of a region without text layer (scanned images, figures).
+    Ocr,
+}
+
+impl Source {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Text => "text",
+            Source::Ocr => "ocr",
+        }
+    }
+}
+
+/// A block of a page's merged reading order (`--merge`).
+#[derive(Clone, Debug)]
+pub struct MergedBlock {
+    pub source: Source,
+    /// Bounds on the page in points (x0, y0, x1, y1).
+    pub rect: [f32; 4],
+    pub text: String,
+}
+
+/// Interleave OCR of image regions with the text-layer blocks of a page.
+///
+/// Text-layer blocks keep MuPDF's reading order. OCR words over text-layer
+/// lines are dropped, as they read the same text; the remaining OCR blocks
+/// are inserted before the first block starting below them in the same
+/// column (any column when none overlaps), or appended at the end.
+/// `placement` maps word pixels to the page.
+pub fn merge(lines: &[TextLine], words: &[Word], placement: ImagePlacement) -> Vec<MergedBlock> {
+    let mut blocks: Vec<MergedBlock> = Vec::new();
+    let mut prev_block = None;
+    for line in lines {
+        match blocks.last_mut() {
+            Some(block) if prev_block == Some(line.block) => {
+                block.rect = union(block.rect, line.rect);
+                block.text.push('\n');
+                block.text.push_str(&line.text);
+            }
+            _ => blocks.push(MergedBlock { source: Source::Text, rect: line.rect, text: line.text.clone() }),
+        }
+        prev_block = Some(line.block);
+    }
+
+    for ocr_block in ocr_blocks(lines, words, placement) {
+        let [x0, y0, x1, _] = ocr_block.rect;
+        let below = |b: &MergedBlock| b.rect[1] >= y0;
+        let position = blocks
+            .iter()
+            .position(|b| below(b) && b.rect[0] < x1 && x0 < b.rect[2])
+            .or_else(|| blocks.iter().position(below))
+            .unwrap_or(blocks.len());
+        blocks.insert(position, ocr_block);
+    }
+    blocks
+}
+
+/// OCR blocks made of the words outside the text-layer lines, top to bottom.
+fn ocr_blocks(lines: &[TextLine], words: &[Word], placement: ImagePlacement) -> Vec<MergedBlock> {
+    let mut blocks: Vec<(usize, usize, MergedBlock)> = Vec::new();
+    for word in words {
+        let rect = placement.to_points(word.bbox);
+        let (cx, cy) = ((rect[0] + rect[2]) / 2.0, (rect[1] + rect[3]) / 2.0);
+        let on_text_layer = lines.iter().any(|line| {
+            let [x0, y0, x1, y1] = line.rect;
+            x0 - LINE_MARGIN <= cx && cx <= x1 + LINE_MARGIN && y0 - LINE_MARGIN <= cy && cy <= y1 + LINE_MARGIN
+        });
+        if on_text_layer {
+            continue;
+        }
+        match blocks.last_mut() {
+            Some((block, line, merged)) if *block == word.block => {
+                merged.rect = union(merged.rect, rect);
+                merged.text.push(if *line == word.line { ' ' } else { '\n' });
+                merged.text.push_str(&word.text);
+                *line = word.line;
+            }
+            _ => blocks.push((word.block, word.line, MergedBlock { source: Source::Ocr, rect, text: word.text.clone() })),
+        }
+    }
+    let mut blocks: Vec<MergedBlock> = blocks.into_iter().map(|(_, _, block)| block).collect();
+    blocks.sort_by(|a, b| a.rect[1].total_cmp(&b.rect[1]));
+    blocks
+}
+
+fn union(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, rect: [f32; 4], block: usize) -> TextLine {
+        TextLine { rect, text: text.to_string(), block, ..Default::default() }
+    }
+
+    fn word(text: &str, bbox: [i32; 4], block: usize, line: usize) -> Word {
+        Word { text: text.to_string(), conf: 90.0, bbox, block, para: block, line, lang: None }
+    }
+
+    #[test]
+    fn test_merge_inserts_image_text_in_reading_order() {
+        let lines = [
+            line("Title", [50.0, 50.0, 300.0, 70.0], 0),
+            line("Body text", [50.0, 400.0, 300.0, 412.0], 1),
+            line("continues", [50.0, 414.0, 300.0, 426.0], 1),
+        ];
+        // At 144 DPI: "Title" again over the text layer, then a scanned figure in between
+        let words = [
+            word("Title", [100, 100, 600, 140], 0, 0),
+            word("Figure", [100, 400, 250, 430], 1, 1),
+            word("1", [260, 400, 280, 430], 1, 1),
+            word("Scanned", [100, 440, 300, 470], 1, 2),
+        ];
+        let merged = merge(&lines, &words, ImagePlacement { scale: 0.5, offset: [0.0, 0.0] });
+        let order: Vec<(Source, &str)> = merged.iter().map(|b| (b.source, b.text.as_str())).collect();
+        assert_eq!(
+            order,
+            [(Source::Text, "Title"), (Source::Ocr, "Figure 1\nScanned"), (Source::Text, "Body text\ncontinues")]
+        );
+        assert_eq!(merged[1].rect, [50.0, 200.0, 150.0, 235.0]);
+
+        // With an offset (right half of a spread), OCR below everything goes last
+        let right = ImagePlacement { scale: 0.5, offset: [300.0, 0.0] };
+        let merged = merge(&lines[..1], &[word("Footer", [0, 1000, 100, 1020], 0, 0)], right);
+        assert_eq!(merged[1].rect[0], 300.0);
+        assert_eq!(merged[1].source, Source::Ocr);
+    }
+}
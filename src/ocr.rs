@@ -1,6 +1,9 @@
+use crate::cli::{Granularity, Models};
 use crate::errors::CrabError;
+use crate::output::{Layer, TextUnit};
+use crate::preprocess::GrayImage;
 use std::ffi::{CStr, CString};
-use crate::renderer::Renderer;
+use std::os::raw::{c_char, c_int};
 
 #[allow(non_upper_case_globals)]
 #[allow(non_camel_case_types)]
@@ -20,11 +23,73 @@ mod sys {
 }
 use sys::*;
 
+/// A `tessdata/` folder next to the executable or in the current directory,
+/// which takes precedence over `TESSDATA_PREFIX`.
+fn local_tessdata() -> Option<std::path::PathBuf> {
+    let possible_paths = vec![
+        std::env::current_exe().ok().and_then(|p| p.parent().map(|d| d.join("tessdata"))),
+        Some(std::path::PathBuf::from("tessdata")),
+    ];
+    possible_paths
+        .into_iter()
+        .flatten()
+        .filter(|p| p.is_dir())
+        .find_map(|p| std::fs::canonicalize(p).ok())
+}
+
+/// The `tessdata_fast` or `tessdata_best` folder for `models`: next to the
+/// executable, in the current directory, or beside the tessdata folder in use.
+/// `None` for the installed models, or when the folder is not found.
+pub fn model_dir(models: Models) -> Option<std::path::PathBuf> {
+    let name = match models {
+        Models::Installed => return None,
+        Models::Fast => "tessdata_fast",
+        Models::Best => "tessdata_best",
+    };
+    let tessdata = local_tessdata().or_else(|| std::env::var_os("TESSDATA_PREFIX").map(Into::into));
+    let possible_paths = vec![
+        std::env::current_exe().ok().and_then(|p| p.parent().map(|d| d.join(name))),
+        Some(std::path::PathBuf::from(name)),
+        tessdata.and_then(|p| p.parent().map(|d| d.join(name))),
+    ];
+    possible_paths
+        .into_iter()
+        .flatten()
+        .filter(|p| p.is_dir())
+        .find_map(|p| std::fs::canonicalize(p).ok())
+}
+
+/// Languages with a `.traineddata` file in the tessdata folder Tesseract will use.
+pub fn available_languages() -> Vec<String> {
+    let Some(dir) = local_tessdata().or_else(|| std::env::var_os("TESSDATA_PREFIX").map(Into::into)) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut langs: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().to_str()?.strip_suffix(".traineddata").map(str::to_string))
+        .filter(|lang| lang != "osd") // Orientation detection, not a language
+        .collect();
+    langs.sort();
+    langs
+}
+
+/// Whether `osd.traineddata`, for orientation detection, is in the tessdata
+/// folder Tesseract was initialized with.
+fn osd_available() -> bool {
+    std::env::var_os("TESSDATA_PREFIX").is_some_and(|prefix| std::path::Path::new(&prefix).join("osd.traineddata").exists())
+}
+
 // Helper for silencing stderr.
 //
 // Warning: This struct modifies the global file descriptor table (stderr).
 // It is NOT thread-safe. Using this in a multi-threaded environment where
-// other threads write to stderr may result in lost logs.
+// other threads write to stderr may result in lost logs. The render-ahead
+// thread (`pipeline::Prerender`) may run meanwhile: it never writes to
+// stderr, as MuPDF contexts report warnings and errors to no-op callbacks
+// (`my_new_context`).
 struct StderrSilencer {
     original_stderr: i32,
 }
@@ -62,14 +127,104 @@ impl Drop for StderrSilencer {
     }
 }
 
+/// Pages whose mean word confidence (0-100) is below this are rejected as
+/// noise, unless `--min-confidence` says otherwise.
+pub const MIN_MEAN_CONF: i32 = 60;
+
+/// A recognized word with its position in the page layout.
+#[derive(Clone, Debug)]
+pub struct Word {
+    pub text: String,
+    pub conf: f32,
+    /// `[left, top, right, bottom]` in image pixels.
+    pub bbox: [i32; 4],
+    /// Page-wide indices of the enclosing block, paragraph and line.
+    pub block: usize,
+    pub para: usize,
+    pub line: usize,
+    /// Language Tesseract recognized the word with (e.g. "fra" for `eng+fra`).
+    pub lang: Option<String>,
+}
+
+/// How many of a page's words were recognized with one of the loaded languages.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LanguageStat {
+    pub lang: String,
+    pub words: usize,
+    /// Share of the page's words, 0-1.
+    pub share: f32,
+    /// Mean confidence (0-100) of those words.
+    pub conf: f32,
+}
+
+/// Words per recognition language, most used first.
+pub fn language_stats(words: &[Word]) -> Vec<LanguageStat> {
+    let mut stats: Vec<LanguageStat> = Vec::new();
+    for word in words {
+        let Some(lang) = &word.lang else { continue };
+        match stats.iter_mut().find(|s| &s.lang == lang) {
+            Some(stat) => {
+                stat.words += 1;
+                stat.conf += word.conf;
+            }
+            None => stats.push(LanguageStat { lang: lang.clone(), words: 1, share: 0.0, conf: word.conf }),
+        }
+    }
+    let total: usize = stats.iter().map(|s| s.words).sum();
+    for stat in &mut stats {
+        stat.conf /= stat.words as f32;
+        stat.share = stat.words as f32 / total as f32;
+    }
+    stats.sort_by_key(|s| std::cmp::Reverse(s.words));
+    stats
+}
+
+/// Result of recognizing a single page.
+pub struct OcrResult {
+    pub layer: Layer,
+    /// Mean word confidence (0-100) reported by Tesseract.
+    pub mean_conf: i32,
+    /// Words of the page; empty when the page was rejected for low confidence.
+    pub words: Vec<Word>,
+    /// Direction the top of the text points, in degrees clockwise (0, 90, 180, 270).
+    pub orientation: Option<i32>,
+    /// Skew angle of the text, in degrees. Tesseract deskews by this amount.
+    pub deskew_angle: Option<f32>,
+    /// Tesseract's hOCR `ocr_page` element, when requested and not rejected.
+    pub hocr: Option<String>,
+    /// Tesseract's TSV rows (without header), when requested and not rejected.
+    pub tsv: Option<String>,
+}
+
+/// Tesseract renderings to return along with the text of a page.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Renderings {
+    /// 0-based page number the renderings are numbered with (hOCR ids, TSV `page_num` - 1).
+    pub page: i32,
+    pub hocr: bool,
+    pub tsv: bool,
+}
+
 pub struct Ocr {
     handle: *mut TessBaseAPI,
     // Keep file open to reuse FD
     _dev_null: std::fs::File,
+    min_conf: i32,
+    /// `osd.traineddata` was found, so pages are segmented with orientation detection.
+    osd: bool,
+    /// The page segmentation mode runs orientation detection.
+    detects_orientation: std::cell::Cell<bool>,
 }
 
 impl Ocr {
     pub fn new(lang: &str) -> Result<Self, CrabError> {
+        Self::with_tessdata(lang, None, true)
+    }
+
+    /// Load `lang` from the models in `tessdata` rather than the installed ones.
+    /// Without `dictionary`, words are read as the characters look rather than
+    /// matched to the word lists, for serial numbers and codes (`--no-dict`).
+    pub fn with_tessdata(lang: &str, tessdata: Option<&std::path::Path>, dictionary: bool) -> Result<Self, CrabError> {
         use std::os::fd::AsRawFd;
         
         let dev_null = std::fs::File::open("/dev/null")
@@ -93,24 +248,14 @@ impl Ocr {
             };
 
             // 1. Dictionary Support: "1" to enable
-            set_var("tessedit_enable_doc_dict", "1");
+            set_var("tessedit_enable_doc_dict", if dictionary { "1" } else { "0" });
 
             // 2. Layout Preservation: "0" to fix random paragraph splitting
             set_var("preserve_interword_spaces", "0");
             
             // Resolve datapath
-            let possible_paths = vec![
-                std::env::current_exe().ok().and_then(|p| p.parent().map(|d| d.join("tessdata"))), 
-                Some(std::path::PathBuf::from("tessdata")), 
-            ];
-            
-            for p in possible_paths.into_iter().flatten() {
-                 if p.exists() && p.is_dir() {
-                     if let Ok(abs_path) = std::fs::canonicalize(&p) {
-                         std::env::set_var("TESSDATA_PREFIX", abs_path);
-                         break;
-                     }
-                 }
+            if let Some(abs_path) = tessdata.map(std::path::Path::to_path_buf).or_else(local_tessdata) {
+                std::env::set_var("TESSDATA_PREFIX", abs_path);
             }
             
             // Set message severity
@@ -119,8 +264,26 @@ impl Ocr {
             let ptr_datapath = std::ptr::null(); // Use env var
             let c_lang = CString::new(lang).map_err(|_| CrabError::Input(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid lang string")))?;
 
+            // The word lists are loaded at initialization, so they can only be turned off there
+            let names = [CString::new("load_system_dawg").unwrap(), CString::new("load_freq_dawg").unwrap()];
+            let off = CString::new("0").unwrap();
+            let mut vars: Vec<*mut c_char> = names.iter().map(|n| n.as_ptr() as *mut c_char).collect();
+            let mut values: Vec<*mut c_char> = names.iter().map(|_| off.as_ptr() as *mut c_char).collect();
+            let var_count = if dictionary { 0 } else { vars.len() };
+
             // 3. Engine Mode: LSTM_ONLY (1)
-            let ret = TessBaseAPIInit2(handle, ptr_datapath, c_lang.as_ptr(), TessOcrEngineMode_OEM_LSTM_ONLY);
+            let ret = TessBaseAPIInit4(
+                handle,
+                ptr_datapath,
+                c_lang.as_ptr(),
+                TessOcrEngineMode_OEM_LSTM_ONLY,
+                std::ptr::null_mut(),
+                0,
+                vars.as_mut_ptr(),
+                values.as_mut_ptr(),
+                var_count,
+                0,
+            );
             
             if ret != 0 {
                 // If we return, silencer drops and restores stderr.
@@ -130,31 +293,76 @@ impl Ocr {
                 return Err(CrabError::Ocr(format!("Failed to initialize Tesseract with lang '{}' (OEM=LSTM_ONLY)", lang)));
             }
             
-            // Check if 'osd.traineddata' is available in TESSDATA_PREFIX.
-            let psm = if let Ok(prefix) = std::env::var("TESSDATA_PREFIX") {
-                let osd_path = std::path::Path::new(&prefix).join("osd.traineddata");
-                if osd_path.exists() {
-                     TessPageSegMode_PSM_AUTO_OSD
-                } else {
-                     // Print warning to stdout as stderr might be silenced.
-                     println!("Warning: 'osd.traineddata' not found in {:?}. Auto-rotation (OSD) disabled. Falling back to PSM_AUTO.", prefix);
-                     TessPageSegMode_PSM_AUTO
-                }
-            } else {
-                 TessPageSegMode_PSM_AUTO
-            };
+            // Without 'osd.traineddata' in TESSDATA_PREFIX, pages are segmented
+            // without orientation detection; callers report it (`has_osd`)
+            let osd = osd_available();
+            let psm = if osd { TessPageSegMode_PSM_AUTO_OSD } else { TessPageSegMode_PSM_AUTO };
             
             TessBaseAPISetPageSegMode(handle, psm);
             
             // StderrSilencer is dropped here, restoring stderr.
             Ok(Self { 
                 handle, 
-                _dev_null: dev_null 
+                _dev_null: dev_null,
+                min_conf: MIN_MEAN_CONF,
+                osd,
+                detects_orientation: std::cell::Cell::new(osd),
             })
         }
     }
     
-    pub fn recognize(&self, pix: &crate::renderer::Pixmap, renderer: &Renderer, dpi: i32) -> Result<String, CrabError> {
+    /// Whether `osd.traineddata` was found, so page orientation is detected.
+    pub fn has_osd(&self) -> bool {
+        self.osd
+    }
+
+    /// Keep the runs of spaces Tesseract measures between words instead of
+    /// single spaces, so fixed-width reports keep their columns (`--preserve-spaces`).
+    pub fn set_preserve_spaces(&self, preserve: bool) {
+        let name = CString::new("preserve_interword_spaces").unwrap();
+        let value = CString::new(if preserve { "1" } else { "0" }).unwrap();
+        unsafe {
+            TessBaseAPISetVariable(self.handle, name.as_ptr(), value.as_ptr());
+        }
+    }
+
+    /// Segment pages with Tesseract's page segmentation mode `psm` (1-13,
+    /// `--psm`) instead of the automatic one, e.g. 4 for a single column,
+    /// 6 for a single block or 11 for sparse text. Modes running orientation
+    /// detection (1 and 12) need `osd.traineddata`. Mode 0 reads no text.
+    pub fn set_page_seg_mode(&self, psm: u32) -> Result<(), CrabError> {
+        if psm == TessPageSegMode_PSM_OSD_ONLY || psm >= TessPageSegMode_PSM_COUNT {
+            return Err(CrabError::Cli(format!("Invalid page segmentation mode {} (1-13)", psm)));
+        }
+        let osd = [TessPageSegMode_PSM_AUTO_OSD, TessPageSegMode_PSM_SPARSE_TEXT_OSD];
+        if osd.contains(&psm) && !osd_available() {
+            return Err(CrabError::Cli(format!("--psm {} detects orientation and needs osd.traineddata, which was not found", psm)));
+        }
+        unsafe {
+            TessBaseAPISetPageSegMode(self.handle, psm);
+        }
+        self.detects_orientation.set(osd.contains(&psm));
+        Ok(())
+    }
+
+    /// Reject the text of pages whose mean word confidence (0-100) is below
+    /// `conf` (`--min-confidence`); 0 keeps every page.
+    pub fn set_min_confidence(&mut self, conf: i32) {
+        self.min_conf = conf;
+    }
+
+    pub fn recognize(&self, image: &GrayImage, dpi: i32, granularity: Granularity) -> Result<OcrResult, CrabError> {
+        self.recognize_page(image, dpi, granularity, Renderings::default())
+    }
+
+    /// Like `recognize`, also returning the hOCR and TSV renderings of the page when requested.
+    pub fn recognize_page(
+        &self,
+        image: &GrayImage,
+        dpi: i32,
+        granularity: Granularity,
+        renderings: Renderings,
+    ) -> Result<OcrResult, CrabError> {
         use std::os::fd::AsRawFd;
         // Silence entire recognition to catch OSD warnings
         let _silencer = StderrSilencer::new(self._dev_null.as_raw_fd());
@@ -162,14 +370,11 @@ impl Ocr {
         unsafe {
             // Silence everything in recognize to catch 'pixReadMemTiff' from SetImage or Recognize.
             
-            let width = pix.width(renderer);
-            let height = pix.height(renderer);
-            let stride = pix.stride(renderer);
-            let channels = pix.n(renderer); 
-            let samples = pix.samples(renderer);
+            let width = image.width as i32;
+            let height = image.height as i32;
 
-            // 2. Image Integrity
-            TessBaseAPISetImage(self.handle, samples.as_ptr(), width, height, channels, stride);
+            // 2. Image Integrity: 1 byte per pixel, rows are not padded
+            TessBaseAPISetImage(self.handle, image.data.as_ptr(), width, height, 1, width);
 
             // 1. Active DPI (Must be called AFTER SetImage)
             TessBaseAPISetSourceResolution(self.handle, dpi);
@@ -179,27 +384,266 @@ impl Ocr {
                  return Err(CrabError::Ocr("Error during recognition".into()));
             }
 
+            let (orientation, deskew_angle) = self.orientation();
+            let mean_conf = TessBaseAPIMeanTextConf(self.handle);
+            let result = |layer, words, (hocr, tsv)| OcrResult {
+                layer,
+                mean_conf,
+                words,
+                orientation,
+                deskew_angle,
+                hocr,
+                tsv,
+            };
+
             // Check confidence score.
-            // Reject output if the mean confidence is below 60 (out of 100).
+            // Reject output if the mean confidence is below the minimum.
             // This filters out noise from empty or garbled pages.
-            let mean_conf = TessBaseAPIMeanTextConf(self.handle);
-            if mean_conf < 60 {
+            if mean_conf < self.min_conf {
                 TessBaseAPIClear(self.handle);
-                return Ok(String::new());
+                return Ok(result(Layer::empty(), Vec::new(), (None, None)));
             }
 
+            let rendered = |requested: bool, render: unsafe extern "C" fn(*mut TessBaseAPI, c_int) -> *mut c_char| {
+                if !requested {
+                    return None;
+                }
+                let ptr = render(self.handle, renderings.page);
+                if ptr.is_null() {
+                    return None;
+                }
+                let text = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+                TessDeleteText(ptr);
+                Some(text)
+            };
+            let renders = (
+                rendered(renderings.hocr, TessBaseAPIGetHOCRText),
+                rendered(renderings.tsv, TessBaseAPIGetTsvText),
+            );
+
             let text_ptr = TessBaseAPIGetUTF8Text(self.handle);
             if text_ptr.is_null() {
-                return Ok(String::new()); 
+                return Ok(result(Layer::empty(), Vec::new(), renders)); 
             }
             
             let text = CStr::from_ptr(text_ptr).to_string_lossy().into_owned();
             TessDeleteText(text_ptr);
+
+            let units = match iterator_level(granularity) {
+                Some(level) => self.collect_units(level),
+                None if text.trim().is_empty() => Vec::new(),
+                None => vec![TextUnit {
+                    text: text.clone(),
+                    conf: Some(mean_conf as f32),
+                    bbox: None,
+                    lang: None,
+                }],
+            };
+            let words = self.collect_words();
             TessBaseAPIClear(self.handle);
             
-            Ok(text)
+            Ok(result(Layer { text, units }, words, renders))
+        }
+    }
+
+    /// Walk the words of the last recognition, tracking block, paragraph and line indices.
+    ///
+    /// Must be called before `TessBaseAPIClear`.
+    unsafe fn collect_words(&self) -> Vec<Word> {
+        let mut words = Vec::new();
+        let iter = TessBaseAPIGetIterator(self.handle);
+        if iter.is_null() {
+            return words;
+        }
+        let page_iter = TessResultIteratorGetPageIteratorConst(iter);
+        let level = TessPageIteratorLevel_RIL_WORD;
+
+        let (mut block, mut para, mut line) = (0, 0, 0);
+        let mut first = true;
+        loop {
+            if !first {
+                if TessPageIteratorIsAtBeginningOf(page_iter, TessPageIteratorLevel_RIL_BLOCK) != 0 {
+                    block += 1;
+                    para += 1;
+                    line += 1;
+                } else if TessPageIteratorIsAtBeginningOf(page_iter, TessPageIteratorLevel_RIL_PARA) != 0 {
+                    para += 1;
+                    line += 1;
+                } else if TessPageIteratorIsAtBeginningOf(page_iter, TessPageIteratorLevel_RIL_TEXTLINE) != 0 {
+                    line += 1;
+                }
+            }
+            first = false;
+
+            let text_ptr = TessResultIteratorGetUTF8Text(iter, level);
+            if !text_ptr.is_null() {
+                let text = CStr::from_ptr(text_ptr).to_string_lossy().trim().to_string();
+                TessDeleteText(text_ptr);
+
+                let (mut left, mut top, mut right, mut bottom) = (0, 0, 0, 0);
+                if !text.is_empty()
+                    && TessPageIteratorBoundingBox(page_iter, level, &mut left, &mut top, &mut right, &mut bottom) != 0
+                {
+                    words.push(Word {
+                        text,
+                        conf: TessResultIteratorConfidence(iter, level),
+                        bbox: [left, top, right, bottom],
+                        block,
+                        para,
+                        line,
+                        lang: recognition_language(iter),
+                    });
+                }
+            }
+
+            if TessResultIteratorNext(iter, level) == 0 {
+                break;
+            }
+        }
+
+        TessResultIteratorDelete(iter);
+        words
+    }
+
+    /// Orientation and deskew angle of the first block of the last recognition,
+    /// `None` without a block. The orientation is also `None` when the page
+    /// segmentation mode does not detect it (no `osd.traineddata`, or `--psm`).
+    ///
+    /// Must be called before `TessBaseAPIClear`.
+    unsafe fn orientation(&self) -> (Option<i32>, Option<f32>) {
+        let iter = TessBaseAPIGetIterator(self.handle);
+        if iter.is_null() {
+            return (None, None);
+        }
+        // Tesseract reports defaults when it found no block to orient
+        let (mut left, mut top, mut right, mut bottom) = (0, 0, 0, 0);
+        let page_iter = TessResultIteratorGetPageIterator(iter);
+        if TessPageIteratorBoundingBox(page_iter, TessPageIteratorLevel_RIL_BLOCK, &mut left, &mut top, &mut right, &mut bottom) == 0 {
+            TessResultIteratorDelete(iter);
+            return (None, None);
+        }
+
+        let mut orientation: TessOrientation = TessOrientation_ORIENTATION_PAGE_UP;
+        let mut direction: TessWritingDirection = 0;
+        let mut order: TessTextlineOrder = 0;
+        let mut deskew_angle = 0.0f32;
+        TessPageIteratorOrientation(
+            page_iter,
+            &mut orientation,
+            &mut direction,
+            &mut order,
+            &mut deskew_angle,
+        );
+        TessResultIteratorDelete(iter);
+
+        // Tesseract reports radians
+        (orientation_degrees(orientation, self.detects_orientation.get()), Some(deskew_angle.to_degrees()))
+    }
+
+    /// Walk the result iterator of the last recognition at the given level.
+    ///
+    /// Must be called before `TessBaseAPIClear`.
+    unsafe fn collect_units(&self, level: TessPageIteratorLevel) -> Vec<TextUnit> {
+        let mut units = Vec::new();
+        let iter = TessBaseAPIGetIterator(self.handle);
+        if iter.is_null() {
+            return units;
+        }
+
+        loop {
+            let text_ptr = TessResultIteratorGetUTF8Text(iter, level);
+            if !text_ptr.is_null() {
+                let text = CStr::from_ptr(text_ptr).to_string_lossy().trim().to_string();
+                TessDeleteText(text_ptr);
+
+                if !text.is_empty() {
+                    let conf = TessResultIteratorConfidence(iter, level);
+                    let page_iter = TessResultIteratorGetPageIteratorConst(iter);
+                    let (mut left, mut top, mut right, mut bottom) = (0, 0, 0, 0);
+                    let bbox = if TessPageIteratorBoundingBox(page_iter, level, &mut left, &mut top, &mut right, &mut bottom) != 0 {
+                        Some([left, top, right, bottom])
+                    } else {
+                        None
+                    };
+                    units.push(TextUnit { text, conf: Some(conf), bbox, lang: None });
+                }
+            }
+
+            if TessResultIteratorNext(iter, level) == 0 {
+                break;
+            }
+        }
+
+        TessResultIteratorDelete(iter);
+        units
+    }
+}
+
+/// Language of the word at the iterator, owned by Tesseract (not freed here).
+unsafe fn recognition_language(iter: *const TessResultIterator) -> Option<String> {
+    let ptr = TessResultIteratorWordRecognitionLanguage(iter);
+    if ptr.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(ptr).to_string_lossy().into_owned()).filter(|lang| !lang.is_empty())
+}
+
+/// Map an output granularity to a Tesseract iterator level.
+/// Returns None for page granularity, which uses the full-page text.
+fn iterator_level(granularity: Granularity) -> Option<TessPageIteratorLevel> {
+    match granularity {
+        Granularity::Page => None,
+        Granularity::Block => Some(TessPageIteratorLevel_RIL_BLOCK),
+        Granularity::Paragraph => Some(TessPageIteratorLevel_RIL_PARA),
+        Granularity::Line => Some(TessPageIteratorLevel_RIL_TEXTLINE),
+        Granularity::Word => Some(TessPageIteratorLevel_RIL_WORD),
+    }
+}
+
+/// Mark the low-confidence words of an OCR layer (`--mark-words-below`): in
+/// its text, and in each unit with the words whose center lies in its box.
+pub fn mark_layer(layer: &mut Layer, words: &[Word], below: f32, markers: (&str, &str)) {
+    layer.text = mark_words(&layer.text, words, below, markers);
+    for unit in &mut layer.units {
+        let inside: Vec<Word> = match unit.bbox {
+            Some([left, top, right, bottom]) => words
+                .iter()
+                .filter(|w| (left..=right).contains(&((w.bbox[0] + w.bbox[2]) / 2)) && (top..=bottom).contains(&((w.bbox[1] + w.bbox[3]) / 2)))
+                .cloned()
+                .collect(),
+            None => words.to_vec(),
+        };
+        unit.text = mark_words(&unit.text, &inside, below, markers);
+    }
+}
+
+/// `text` with each of its `words` recognized with a confidence below
+/// `below` wrapped in `open` and `close` (`--mark-words-below`). Words are
+/// found in reading order as whole words, so spacing and line breaks are
+/// kept, and words no longer in the text (dropped lines) are passed over.
+pub fn mark_words(text: &str, words: &[Word], below: f32, (open, close): (&str, &str)) -> String {
+    let mut marked = String::with_capacity(text.len() + words.len() * (open.len() + close.len()));
+    let mut rest = text;
+    for word in words {
+        let whole = |&(at, found): &(usize, &str)| {
+            rest[..at].chars().next_back().is_none_or(char::is_whitespace)
+                && rest[at + found.len()..].chars().next().is_none_or(char::is_whitespace)
+        };
+        let Some((at, _)) = rest.match_indices(word.text.as_str()).find(whole) else { continue };
+        let (before, after) = rest.split_at(at);
+        let (found, after) = after.split_at(word.text.len());
+        marked.push_str(before);
+        if word.conf < below {
+            marked.push_str(open);
+            marked.push_str(found);
+            marked.push_str(close);
+        } else {
+            marked.push_str(found);
         }
+        rest = after;
     }
+    marked.push_str(rest);
+    marked
 }
 
 impl Drop for Ocr {
@@ -210,3 +654,73 @@ impl Drop for Ocr {
         }
     }
 }
+
+/// Degrees clockwise of a Tesseract page orientation, which only means
+/// something when orientation detection ran (`osd`): the page is reported
+/// upright otherwise.
+#[allow(non_upper_case_globals)]
+fn orientation_degrees(orientation: TessOrientation, osd: bool) -> Option<i32> {
+    if !osd {
+        return None;
+    }
+    Some(match orientation {
+        TessOrientation_ORIENTATION_PAGE_RIGHT => 90,
+        TessOrientation_ORIENTATION_PAGE_DOWN => 180,
+        TessOrientation_ORIENTATION_PAGE_LEFT => 270,
+        _ => 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orientation_degrees() {
+        assert_eq!(orientation_degrees(TessOrientation_ORIENTATION_PAGE_RIGHT, true), Some(90));
+        assert_eq!(orientation_degrees(TessOrientation_ORIENTATION_PAGE_UP, true), Some(0));
+        // Without osd.traineddata, Tesseract's upright default is not a detection
+        assert_eq!(orientation_degrees(TessOrientation_ORIENTATION_PAGE_UP, false), None);
+        assert_eq!(orientation_degrees(TessOrientation_ORIENTATION_PAGE_DOWN, false), None);
+    }
+
+    #[test]
+    fn test_language_stats() {
+        let word = |lang: Option<&str>, conf: f32| Word {
+            text: "w".into(),
+            conf,
+            bbox: [0, 0, 1, 1],
+            block: 0,
+            para: 0,
+            line: 0,
+            lang: lang.map(String::from),
+        };
+        let words = [word(Some("eng"), 80.0), word(Some("fra"), 90.0), word(Some("fra"), 70.0), word(None, 10.0)];
+        let stats = language_stats(&words);
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].lang.as_str(), stats[0].words), ("fra", 2));
+        assert!((stats[0].share - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(stats[0].conf, 80.0);
+        assert_eq!(stats[1].lang, "eng");
+        assert!(language_stats(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_mark_words() {
+        let word = |text: &str, conf: f32| Word { text: text.into(), conf, bbox: [0, 0, 1, 1], block: 0, para: 0, line: 0, lang: None };
+        let words = [word("Total", 95.0), word("a", 20.0), word("due", 41.0), word("1284.50", 58.5), word("EUR", 90.0), word("missing", 10.0)];
+        let text = "Total  due\n1284.50 EUR\n\n";
+        assert_eq!(mark_words(text, &words, 60.0, ("⟦", "⟧")), "Total  ⟦due⟧\n⟦1284.50⟧ EUR\n\n");
+        assert_eq!(mark_words(text, &words, 0.0, ("⟦", "⟧")), text);
+        assert_eq!(mark_words(text, &words, 100.0, ("[?", "]")), "[?Total]  [?due]\n[?1284.50] [?EUR]\n\n");
+
+        let mut words = words.to_vec();
+        words[3].bbox = [0, 20, 40, 30];
+        words[4].bbox = [50, 20, 80, 30];
+        let unit = |text: &str, bbox| TextUnit { text: text.into(), conf: None, bbox, lang: None };
+        let mut layer = Layer { text: text.into(), units: vec![unit("due", Some([0, 0, 9, 9])), unit("1284.50 EUR", Some([0, 20, 80, 30]))] };
+        mark_layer(&mut layer, &words, 60.0, ("⟦", "⟧"));
+        assert_eq!(layer.text, "Total  ⟦due⟧\n⟦1284.50⟧ EUR\n\n");
+        assert_eq!([layer.units[0].text.as_str(), layer.units[1].text.as_str()], ["⟦due⟧", "⟦1284.50⟧ EUR"]);
+    }
+}
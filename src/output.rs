@@ -0,0 +1,1847 @@
+use crate::align::Alignment;
+use crate::alto::AltoWriter;
+use crate::docx::DocxWriter;
+use crate::html::HtmlWriter;
+#[cfg(feature = "encrypt")]
+use crate::encrypt::Recipient;
+use crate::langid::DetectedLanguage;
+use crate::markdown::MarkdownWriter;
+use crate::pagexml::PageXmlWriter;
+use crate::analysis::{ColorClass, InkMetrics};
+use crate::budget::Degradation;
+use crate::cli::{Format, Granularity, Newline};
+use crate::merge::MergedBlock;
+use crate::stats::{DocumentStats, PageStats};
+use crate::ocr::{LanguageStat, Word};
+use crate::tables::Table;
+use crate::zones::Zone;
+use crate::renderer::{ActiveContent, FontInfo, PageBoxes, SignatureField, TextBlock, TextLine};
+use crate::warnings::Warning;
+use crate::xlsx::XlsxWriter;
+use crate::schema;
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::os::fd::{FromRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A segment of extracted or recognized text.
+pub struct TextUnit {
+    pub text: String,
+    /// Recognition confidence (0-100). Only set for OCR output.
+    pub conf: Option<f32>,
+    /// Bounding box as `[left, top, right, bottom]` in rendered pixels.
+    pub bbox: Option<[i32; 4]>,
+    /// ISO 639-3 code of the language detected in the text, with `--detect-language`.
+    pub lang: Option<&'static str>,
+}
+
+impl TextUnit {
+    pub fn plain(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            conf: None,
+            bbox: None,
+            lang: None,
+        }
+    }
+}
+
+/// The output of one extraction layer (text or OCR) for a page.
+pub struct Layer {
+    /// Flat text, as printed in text output.
+    pub text: String,
+    /// Text segmented according to `--granularity`.
+    pub units: Vec<TextUnit>,
+}
+
+impl Layer {
+    pub fn empty() -> Self {
+        Self {
+            text: String::new(),
+            units: Vec::new(),
+        }
+    }
+}
+
+/// Rotation applied to a page, so viewers can mirror the transforms used.
+#[derive(Default)]
+pub struct Rotation {
+    /// `/Rotate` declared by the page, in degrees clockwise. MuPDF applies it when rendering.
+    pub declared: i32,
+    /// Text orientation detected by Tesseract, in degrees clockwise; `None`
+    /// when orientation detection did not run.
+    pub detected: Option<i32>,
+    /// Skew angle corrected by Tesseract, in degrees.
+    pub deskew: Option<f32>,
+}
+
+/// OCR output of an annotation appearance, which is not part of the page content.
+pub struct AnnotationResult {
+    /// PDF annotation subtype, e.g. "Stamp".
+    pub kind: String,
+    /// Bounds on the page in points (x0, y0, x1, y1).
+    pub rect: [f32; 4],
+    pub layer: Layer,
+}
+
+/// A text-layer line whose font has no usable Unicode mapping, read with OCR instead.
+pub struct EncodingFallback {
+    /// Bounds on the page in points (x0, y0, x1, y1).
+    pub rect: [f32; 4],
+    /// The line as extracted, with unmapped glyphs as U+FFFD.
+    pub original: String,
+    /// OCR of the region, substituted into the text layer.
+    pub text: String,
+}
+
+/// Physical page properties, reported with `--page-info`.
+pub struct PageInfo {
+    pub boxes: PageBoxes,
+    /// Whether the rendered page uses color, shades of gray, or only black and white.
+    pub color: Option<ColorClass>,
+    pub ink: Option<InkMetrics>,
+}
+
+/// Where the OCRed image lies on the page, to map word boxes to page space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImagePlacement {
+    /// Points per pixel (72 / DPI).
+    pub scale: f32,
+    /// Position of the image's top left corner on the page, in points
+    /// (non-zero for the halves of a split spread).
+    pub offset: [f32; 2],
+}
+
+impl ImagePlacement {
+    /// A pixel box `[left, top, right, bottom]` in points (x0, y0, x1, y1).
+    pub fn to_points(self, bbox: [i32; 4]) -> [f32; 4] {
+        let [l, t, r, b] = bbox.map(|v| v as f32 * self.scale);
+        let [x, y] = self.offset;
+        [l + x, t + y, r + x, b + y]
+    }
+}
+
+/// Everything produced for a single page.
+#[derive(Default)]
+pub struct PageResult {
+    /// 1-based page number.
+    pub number: usize,
+    /// Half of a split two-page spread (`a` left, `b` right), with `--split-spread`.
+    pub part: Option<char>,
+    /// 1-based document of a batch scan the page belongs to, with `--split-on`.
+    pub document: Option<usize>,
+    /// SHA-256 of the page render, with `--hash-pages`. Shared by the halves of a spread.
+    pub raster_hash: Option<String>,
+    /// Tesseract's hOCR `ocr_page` element, for `--format hocr`.
+    pub hocr: Option<String>,
+    /// Tesseract's TSV rows, for `--format tsv`.
+    pub tsv: Option<String>,
+    /// Recognized words with their boxes, for `--format alto`.
+    pub words: Vec<Word>,
+    /// Size in pixels of the OCRed image (`[width, height]`), which word boxes refer to.
+    pub ocr_size: Option<[u32; 2]>,
+    /// Placement of the OCRed image on the page.
+    pub ocr_placement: Option<ImagePlacement>,
+    /// Text-layer lines with their font size and style, for `--format markdown`.
+    pub lines: Vec<TextLine>,
+    /// Text-layer blocks, lines and spans (`--text-structure`).
+    pub text_blocks: Vec<TextBlock>,
+    /// Tables detected on the page (`--tables`).
+    pub tables: Vec<Table>,
+    /// Header, body, sidebar and footer text of the page (`--zones`).
+    pub zones: Vec<Zone>,
+    /// How the page was degraded near a resource limit.
+    pub degraded: Option<Degradation>,
+    /// Retries rendering the page needed (`--render-retries`).
+    pub render_retries: u32,
+    pub rotation: Rotation,
+    pub info: Option<PageInfo>,
+    pub text_layer: Option<Layer>,
+    pub ocr_layer: Option<Layer>,
+    pub annotations: Vec<AnnotationResult>,
+    pub encoding_fallback: Vec<EncodingFallback>,
+    /// Bounds of detected stamps and signatures in points (x0, y0, x1, y1), with `--stamps`.
+    pub stamps: Vec<[f32; 4]>,
+    /// Why the text layer looks broken, if it does.
+    pub suspicious_text_layer: Vec<String>,
+    /// The page has no text layer but draws its text as vector outlines.
+    pub outlined_text: bool,
+    /// OCR words per recognition language, when several languages are loaded.
+    pub languages: Vec<LanguageStat>,
+    /// Language detected in the text the page reads as, with `--detect-language`.
+    pub language: Option<DetectedLanguage>,
+    /// Text and OCR blocks in reading order, with `--merge`. Replaces both layers in output.
+    pub merged: Option<Vec<MergedBlock>>,
+    /// Text-layer words aligned with OCR words, with `--align`.
+    pub alignment: Option<Alignment>,
+    /// Mean word confidence (0-100) Tesseract reported for the page, kept when the page is rejected.
+    pub ocr_conf: Option<f32>,
+    /// Summary figures of the page, with `--stats`.
+    pub stats: Option<PageStats>,
+    /// The page image written with `--export-images`, embedded by `--format html`.
+    pub image_file: Option<PathBuf>,
+    pub timings: Timings,
+}
+
+/// Time spent on a page by stage. Stages that did not run stay at zero;
+/// halves of a split spread each report the rendering of the whole page.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timings {
+    pub render: Duration,
+    /// Border cleaning, redaction, stamp detection and cropping of the render.
+    pub preprocessing: Duration,
+    /// Recognition of the page, of lines without Unicode mapping and of annotations.
+    pub ocr: Duration,
+    /// Text layer extraction.
+    pub text: Duration,
+}
+
+impl PageResult {
+    /// Page number as shown in output, e.g. "12" or "12a".
+    pub fn label(&self) -> String {
+        match self.part {
+            Some(part) => format!("{}{}", self.number, part),
+            None => self.number.to_string(),
+        }
+    }
+
+    /// The text the page reads as: its text layer, or its OCR layer when the
+    /// text layer is empty or looks broken.
+    pub fn read_text(&self) -> &str {
+        let text = self.text_layer.as_ref().map(|l| l.text.as_str()).filter(|t| !t.trim().is_empty());
+        let ocr = self.ocr_layer.as_ref().map(|l| l.text.as_str()).filter(|t| !t.trim().is_empty());
+        match (text, ocr) {
+            (Some(_), Some(ocr)) if !self.suspicious_text_layer.is_empty() => ocr,
+            (Some(text), _) => text,
+            (None, ocr) => ocr.unwrap_or_default(),
+        }
+    }
+
+    /// Paragraphs of the page: its merged blocks, or the blocks of the text
+    /// it reads as (MuPDF and Tesseract both end blocks with a blank line).
+    pub fn paragraphs(&self) -> Vec<String> {
+        if let Some(blocks) = &self.merged {
+            return blocks.iter().map(|b| b.text.clone()).collect();
+        }
+        let mut paragraphs = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        for line in self.read_text().lines() {
+            if line.trim().is_empty() {
+                if !current.is_empty() {
+                    paragraphs.push(current.join("\n"));
+                    current.clear();
+                }
+            } else {
+                current.push(line.trim_end());
+            }
+        }
+        if !current.is_empty() {
+            paragraphs.push(current.join("\n"));
+        }
+        paragraphs
+    }
+}
+
+/// XFA form data, either as the original XML or converted to JSON.
+pub enum XfaData {
+    Raw(String),
+    Parsed(Value),
+}
+
+pub trait OutputWriter {
+    fn xfa(&mut self, xfa: &XfaData) -> io::Result<()>;
+    /// Signature fields of the form, with their status.
+    fn signatures(&mut self, fields: &[SignatureField]) -> io::Result<()>;
+    /// Scripts, launch actions, embedded files and external references, with `--page-info`.
+    fn active_content(&mut self, content: &ActiveContent) -> io::Result<()>;
+    /// Fonts of the document, with `--page-info`.
+    fn fonts(&mut self, fonts: &[FontInfo]) -> io::Result<()>;
+    fn page(&mut self, page: &PageResult) -> io::Result<()>;
+    /// Write a page whose text layer `text` writes as it is extracted
+    /// (`--stream-text`). Only text output supports it.
+    fn page_streamed(&mut self, _page: &PageResult, _text: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "only text output can stream the text layer"))
+    }
+    /// Record that nothing was processed (empty document or range).
+    fn no_pages(&mut self, reason: &str) -> io::Result<()>;
+    /// Figures of the whole document, with `--stats`, recorded after the pages.
+    /// Only text and JSON output include them.
+    fn document_stats(&mut self, _stats: &DocumentStats) -> io::Result<()> {
+        Ok(())
+    }
+    /// Warnings collected during the run, recorded before `finish`.
+    fn warnings(&mut self, warnings: &[Warning]) -> io::Result<()>;
+    /// Figures of the run, with `--summary`, recorded after the warnings.
+    /// Only text output includes them.
+    fn summary(&mut self, _summary: &RunSummary) -> io::Result<()> {
+        Ok(())
+    }
+    fn finish(&mut self) -> io::Result<()>;
+    /// The document kept in memory, once finished. Only `DocumentWriter` keeps it.
+    fn document(&mut self) -> Option<schema::Document> {
+        None
+    }
+}
+
+/// Figures of a run for the `--summary` footer of text output.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RunSummary {
+    /// Pages passed to the output, each half of a split spread counting as one.
+    pub pages_processed: usize,
+    /// Pages of the range not output: separator sheets, and pages left after a timeout or limit.
+    pub pages_skipped: usize,
+    pub warnings: usize,
+    /// Characters of the text the pages read as (text layer, or OCR layer in its place).
+    pub characters: usize,
+    pub duration: Duration,
+}
+
+/// Create the writer for the requested outputs, as (format, file) pairs with
+/// `None` for STDOUT. Files are created (truncated) right away. Without
+/// `pages` (form data only), JSON has no granularity or page list.
+pub fn writer_for(
+    sinks: &[(Format, Option<PathBuf>)],
+    granularity: Granularity,
+    meta: &[(String, String)],
+    words: bool,
+    pages: bool,
+    options: &OutputOptions,
+) -> io::Result<Box<dyn OutputWriter>> {
+    let mut writers: Vec<Box<dyn OutputWriter>> = Vec::new();
+    for (format, path) in sinks {
+        let out = create_output(path.as_deref(), options, !format.is_zip())?;
+        writers.push(match format {
+            Format::Text => Box::new(TextWriter::new(out)),
+            Format::Json => Box::new(JsonWriter::new(out, granularity).with_meta(meta).with_words(words).with_pages(pages)),
+            Format::Ndjson => Box::new(NdjsonWriter::new(out, granularity).with_meta(meta).with_words(words)),
+            Format::Hocr => Box::new(HocrWriter::new(out)),
+            Format::Alto => Box::new(AltoWriter::new(out)),
+            Format::Tsv => Box::new(TsvWriter::new(out)),
+            Format::Markdown => Box::new(MarkdownWriter::new(out)),
+            Format::PageXml => Box::new(PageXmlWriter::new(out)),
+            Format::Docx => Box::new(DocxWriter::new(out)),
+            Format::Xlsx => Box::new(XlsxWriter::new(out)),
+            Format::Html => Box::new(HtmlWriter::new(out)),
+        });
+    }
+    Ok(match writers.len() {
+        1 => writers.remove(0),
+        _ => Box::new(FanOut { writers }),
+    })
+}
+
+/// How output is written, whatever its format: encryption, and line endings
+/// and byte order mark of text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OutputOptions {
+    /// Recipient every output is encrypted for (`--encrypt-output`).
+    #[cfg(feature = "encrypt")]
+    pub encrypt: Option<Recipient>,
+    /// Line endings of text output (`--newline`); as written when not set.
+    pub newline: Option<Newline>,
+    /// Start text output with a UTF-8 byte order mark (`--bom`).
+    pub bom: bool,
+}
+
+/// An output file, or STDOUT without `path`, written as `options` say. Line
+/// endings and BOM only apply to `text` output, not to archives.
+pub fn create_output(path: Option<&Path>, options: &OutputOptions, text: bool) -> io::Result<Box<dyn Write>> {
+    let out: Box<dyn Write> = match (path, encrypted(path, options)?) {
+        (_, Some(out)) => out,
+        (Some(path), None) => {
+            let file = File::create(path).map_err(|e| io::Error::new(e.kind(), format!("{:?}: {}", path, e)))?;
+            Box::new(BufWriter::new(file))
+        }
+        (None, None) => Box::new(io::stdout()),
+    };
+    Ok(encode(out, options, text))
+}
+
+/// Output to a file descriptor the caller opened (`--xfa-out 3`), written as
+/// `create_output` writes files. The descriptor is closed when it is dropped.
+pub fn create_fd_output(fd: RawFd, options: &OutputOptions, text: bool) -> io::Result<Box<dyn Write>> {
+    // SAFETY: fcntl only reads the descriptor's flags
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("file descriptor {} is not open", fd)));
+    }
+    // The encryption tool inherits the descriptor
+    let out: Box<dyn Write> = match encrypted(Some(Path::new(&format!("/dev/fd/{}", fd))), options)? {
+        Some(out) => out,
+        // SAFETY: the descriptor is open, and nothing else in the process owns it
+        None => Box::new(BufWriter::new(unsafe { File::from_raw_fd(fd) })),
+    };
+    Ok(encode(out, options, text))
+}
+
+/// Output piped to the encryption tool, which writes `path` or STDOUT, when
+/// `options` encrypt output.
+#[cfg(feature = "encrypt")]
+fn encrypted(path: Option<&Path>, options: &OutputOptions) -> io::Result<Option<Box<dyn Write>>> {
+    match &options.encrypt {
+        Some(recipient) => Ok(Some(Box::new(recipient.create(path)?))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(not(feature = "encrypt"))]
+fn encrypted(_path: Option<&Path>, _options: &OutputOptions) -> io::Result<Option<Box<dyn Write>>> {
+    Ok(None)
+}
+
+/// Text output rewritten for `--newline` and `--bom`.
+fn encode(out: Box<dyn Write>, options: &OutputOptions, text: bool) -> Box<dyn Write> {
+    match options.newline {
+        _ if !text => out,
+        None if !options.bom => out,
+        newline => Box::new(TextEncoder { out, newline, bom: options.bom, pending_cr: false }),
+    }
+}
+
+/// Rewrites line endings of text output as it passes (LF and CRLF to
+/// `newline`) and starts it with a BOM.
+struct TextEncoder<W: Write> {
+    out: W,
+    newline: Option<Newline>,
+    /// The BOM is still to be written.
+    bom: bool,
+    /// A CR ended the last write: the start of a CRLF, or a lone CR.
+    pending_cr: bool,
+}
+
+impl<W: Write> Write for TextEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if std::mem::take(&mut self.bom) {
+            self.out.write_all("\u{FEFF}".as_bytes())?;
+        }
+        let Some(newline) = self.newline else {
+            self.out.write_all(buf)?;
+            return Ok(buf.len());
+        };
+        let eol: &[u8] = match newline {
+            Newline::Lf => b"\n",
+            Newline::Crlf => b"\r\n",
+        };
+        let mut encoded = Vec::with_capacity(buf.len() + buf.len() / 32);
+        for &b in buf {
+            if std::mem::take(&mut self.pending_cr) && b != b'\n' {
+                encoded.push(b'\r');
+            }
+            match b {
+                b'\r' => self.pending_cr = true,
+                b'\n' => encoded.extend_from_slice(eol),
+                _ => encoded.push(b),
+            }
+        }
+        self.out.write_all(&encoded)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Drop for TextEncoder<W> {
+    fn drop(&mut self) {
+        // A lone CR at the very end
+        if self.pending_cr {
+            let _ = self.out.write_all(b"\r").and_then(|_| self.out.flush());
+        }
+    }
+}
+
+/// Create the writer for `--split-pages`: each page goes to its own file in
+/// `dir`, one per format, and the rest to `index.json`. The directory is created if needed.
+pub fn page_files_writer(
+    dir: &Path,
+    formats: &[Format],
+    granularity: Granularity,
+    meta: &[(String, String)],
+    words: bool,
+    options: &OutputOptions,
+) -> io::Result<Box<dyn OutputWriter>> {
+    std::fs::create_dir_all(dir).map_err(|e| io::Error::new(e.kind(), format!("{:?}: {}", dir, e)))?;
+    let index = create_output(Some(&dir.join("index.json")), options, true)?;
+    Ok(Box::new(PageFiles {
+        dir: dir.to_path_buf(),
+        formats: formats.to_vec(),
+        granularity,
+        meta: meta.to_vec(),
+        words,
+        options: options.clone(),
+        index: JsonWriter::new(index, granularity).with_meta(meta),
+    }))
+}
+
+/// Writes every page as a document of its own per format (`page_0001.txt`,
+/// `page_0012a.json`), and a JSON document to `index.json` whose pages list
+/// their files and metadata instead of their content.
+struct PageFiles {
+    dir: PathBuf,
+    formats: Vec<Format>,
+    granularity: Granularity,
+    meta: Vec<(String, String)>,
+    words: bool,
+    options: OutputOptions,
+    index: JsonWriter<Box<dyn Write>>,
+}
+
+impl OutputWriter for PageFiles {
+    fn xfa(&mut self, xfa: &XfaData) -> io::Result<()> {
+        self.index.xfa(xfa)
+    }
+
+    fn signatures(&mut self, fields: &[SignatureField]) -> io::Result<()> {
+        self.index.signatures(fields)
+    }
+
+    fn active_content(&mut self, content: &ActiveContent) -> io::Result<()> {
+        self.index.active_content(content)
+    }
+
+    fn fonts(&mut self, fonts: &[FontInfo]) -> io::Result<()> {
+        self.index.fonts(fonts)
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        let name = format!("page_{:04}{}", page.number, page.part.map(String::from).unwrap_or_default());
+        let mut files = Vec::new();
+        for format in &self.formats {
+            let file = format!("{}.{}", name, format.extension());
+            let mut writer = writer_for(&[(format.clone(), Some(self.dir.join(&file)))], self.granularity, &self.meta, self.words, true, &self.options)?;
+            writer.page(page)?;
+            writer.finish()?;
+            files.push(file);
+        }
+        // The manifest keeps page metadata, the content is in the files
+        self.index.pages.push(schema::Page {
+            merged: None,
+            text_layer: None,
+            text_structure: Vec::new(),
+            alignment: None,
+            ocr_layer: None,
+            tables: Vec::new(),
+            zones: Vec::new(),
+            stamps: Vec::new(),
+            annotations: Vec::new(),
+            encoding_fallback: Vec::new(),
+            files,
+            ..page_to_json(page, false)
+        });
+        Ok(())
+    }
+
+    fn no_pages(&mut self, reason: &str) -> io::Result<()> {
+        self.index.no_pages(reason)
+    }
+
+    fn document_stats(&mut self, stats: &DocumentStats) -> io::Result<()> {
+        self.index.document_stats(stats)
+    }
+
+    fn warnings(&mut self, warnings: &[Warning]) -> io::Result<()> {
+        self.index.warnings(warnings)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.index.finish()
+    }
+}
+
+/// Passes every result to several writers, one per `--output`.
+struct FanOut {
+    writers: Vec<Box<dyn OutputWriter>>,
+}
+
+impl OutputWriter for FanOut {
+    fn xfa(&mut self, xfa: &XfaData) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.xfa(xfa))
+    }
+
+    fn signatures(&mut self, fields: &[SignatureField]) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.signatures(fields))
+    }
+
+    fn active_content(&mut self, content: &ActiveContent) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.active_content(content))
+    }
+
+    fn fonts(&mut self, fonts: &[FontInfo]) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.fonts(fonts))
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.page(page))
+    }
+
+    fn no_pages(&mut self, reason: &str) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.no_pages(reason))
+    }
+
+    fn document_stats(&mut self, stats: &DocumentStats) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.document_stats(stats))
+    }
+
+    fn warnings(&mut self, warnings: &[Warning]) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.warnings(warnings))
+    }
+
+    fn summary(&mut self, summary: &RunSummary) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.summary(summary))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writers.iter_mut().try_for_each(|w| w.finish())
+    }
+}
+
+/// Split flat text-layer output into units of the requested granularity.
+///
+/// MuPDF's plain text output separates blocks with a blank line, so blocks
+/// and paragraphs are treated alike.
+pub fn split_text(text: &str, granularity: Granularity) -> Vec<TextUnit> {
+    match granularity {
+        Granularity::Page => {
+            if text.trim().is_empty() {
+                Vec::new()
+            } else {
+                vec![TextUnit::plain(text)]
+            }
+        }
+        Granularity::Block | Granularity::Paragraph => text
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(TextUnit::plain)
+            .collect(),
+        Granularity::Line => text
+            .lines()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(TextUnit::plain)
+            .collect(),
+        Granularity::Word => text.split_whitespace().map(TextUnit::plain).collect(),
+    }
+}
+
+/// Delimited plain text output.
+pub struct TextWriter<W: Write> {
+    out: W,
+    /// Document whose section is open, with `--split-on`.
+    document: Option<usize>,
+}
+
+impl<W: Write> TextWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, document: None }
+    }
+
+    /// Close the open document section, if any.
+    fn end_document(&mut self) -> io::Result<()> {
+        if let Some(document) = self.document.take() {
+            writeln!(self.out, "--- DOCUMENT {} END ---", document)?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    /// Sections of a page before its text layer.
+    fn start_page(&mut self, page: &PageResult) -> io::Result<()> {
+        if page.document != self.document {
+            self.end_document()?;
+            if let Some(document) = page.document {
+                writeln!(self.out, "--- DOCUMENT {} START ---", document)?;
+                writeln!(self.out)?;
+            }
+            self.document = page.document;
+        }
+        writeln!(self.out, "--- PAGE {} START ---", page.label())?;
+        writeln!(self.out)?;
+
+        if let Some(degraded) = page.degraded {
+            writeln!(self.out, "--- DEGRADED: {} ---", degraded.as_str())?;
+            writeln!(self.out)?;
+        }
+
+        if page.render_retries > 0 {
+            writeln!(self.out, "--- RENDER RETRIES: {} ---", page.render_retries)?;
+            writeln!(self.out)?;
+        }
+
+        if let Some(info) = &page.info {
+            writeln!(self.out, "--- PAGE INFO START ---")?;
+            for (name, rect) in boxes(&info.boxes) {
+                let [x0, y0, x1, y1] = rect;
+                let [w_mm, h_mm] = size_in(rect, MM_PER_PT);
+                let [w_in, h_in] = size_in(rect, IN_PER_PT);
+                writeln!(
+                    self.out,
+                    "{}: [{:.1}, {:.1}, {:.1}, {:.1}] pt, {:.1} x {:.1} mm, {:.2} x {:.2} in",
+                    name, x0, y0, x1, y1, w_mm, h_mm, w_in, h_in
+                )?;
+            }
+            writeln!(self.out, "Rotation: {}", page.rotation.declared)?;
+            if let Some(color) = info.color {
+                writeln!(self.out, "Color: {}", color.as_str())?;
+            }
+            if let Some(ink) = &info.ink {
+                let bands: Vec<String> = ink.histogram.iter().map(|v| format!("{:.3}", v)).collect();
+                writeln!(self.out, "Ink coverage: {:.1}%", ink.coverage)?;
+                writeln!(self.out, "Density (dark to light): {}", bands.join(" "))?;
+            }
+            writeln!(self.out, "--- PAGE INFO END ---")?;
+            writeln!(self.out)?;
+        }
+
+        if let Some(blocks) = &page.merged {
+            writeln!(self.out, "--- MERGED START ---")?;
+            let texts: Vec<&str> = blocks.iter().map(|b| b.text.as_str()).collect();
+            writeln!(self.out, "{}", texts.join("\n\n"))?;
+            writeln!(self.out, "--- MERGED END ---")?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    /// Sections of a page after its text layer.
+    fn end_page(&mut self, page: &PageResult) -> io::Result<()> {
+        if let Some(layer) = page.ocr_layer.as_ref().filter(|_| page.merged.is_none()) {
+            writeln!(self.out, "--- OCR LAYER START ---")?;
+            write!(self.out, "{}", layer.text)?;
+            writeln!(self.out, "--- OCR LAYER END ---")?;
+            writeln!(self.out)?;
+        }
+
+        for (i, table) in page.tables.iter().enumerate() {
+            writeln!(self.out, "--- TABLE {} START ---", i + 1)?;
+            write!(self.out, "{}", table.to_csv())?;
+            writeln!(self.out, "--- TABLE {} END ---", i + 1)?;
+            writeln!(self.out)?;
+        }
+
+        for zone in &page.zones {
+            let kind = zone.kind.as_str().to_uppercase();
+            let [x0, y0, x1, y1] = zone.rect;
+            writeln!(self.out, "--- ZONE {} START ([{:.1}, {:.1}, {:.1}, {:.1}]) ---", kind, x0, y0, x1, y1)?;
+            writeln!(self.out, "{}", zone.text)?;
+            writeln!(self.out, "--- ZONE {} END ---", kind)?;
+            writeln!(self.out)?;
+        }
+
+        if !page.stamps.is_empty() {
+            writeln!(self.out, "--- STAMPS START ---")?;
+            for [x0, y0, x1, y1] in &page.stamps {
+                writeln!(self.out, "[{:.1}, {:.1}, {:.1}, {:.1}]", x0, y0, x1, y1)?;
+            }
+            writeln!(self.out, "--- STAMPS END ---")?;
+            writeln!(self.out)?;
+        }
+
+        for annot in &page.annotations {
+            let [x0, y0, x1, y1] = annot.rect;
+            writeln!(
+                self.out,
+                "--- ANNOTATION START ({} [{:.1}, {:.1}, {:.1}, {:.1}]) ---",
+                annot.kind, x0, y0, x1, y1
+            )?;
+            write!(self.out, "{}", annot.layer.text)?;
+            writeln!(self.out, "--- ANNOTATION END ---")?;
+            writeln!(self.out)?;
+        }
+
+        if let Some(language) = &page.language {
+            writeln!(self.out, "--- LANGUAGE START ---")?;
+            writeln!(self.out, "{} (confidence {:.2})", language.lang, language.confidence)?;
+            writeln!(self.out, "--- LANGUAGE END ---")?;
+            writeln!(self.out)?;
+        }
+
+        if let Some(stats) = &page.stats {
+            let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+            writeln!(self.out, "--- STATS START ---")?;
+            writeln!(self.out, "Characters: {} text layer, {} OCR", stats.text_chars, stats.ocr_chars)?;
+            if let Some(conf) = stats.ocr_conf {
+                writeln!(self.out, "OCR confidence: {:.0}", conf)?;
+            }
+            writeln!(self.out, "Time: render {:.1} ms, OCR {:.1} ms", ms(stats.render), ms(stats.ocr))?;
+            if let Some(script) = stats.script {
+                writeln!(self.out, "Script: {}", script)?;
+            }
+            if let Some(orientation) = stats.orientation {
+                writeln!(self.out, "Orientation: {}", orientation)?;
+            }
+            writeln!(self.out, "Text layer only: {}", if stats.text_layer_only { "yes" } else { "no" })?;
+            writeln!(self.out, "--- STATS END ---")?;
+            writeln!(self.out)?;
+        }
+
+        writeln!(self.out, "--- PAGE {} END ---", page.label())?;
+        writeln!(self.out) // Blank line between pages or after page
+    }
+}
+
+impl<W: Write> OutputWriter for TextWriter<W> {
+    fn xfa(&mut self, xfa: &XfaData) -> io::Result<()> {
+        writeln!(self.out, "--- XFA DATA START ---")?;
+        match xfa {
+            XfaData::Raw(xml) => write!(self.out, "{}", xml)?,
+            XfaData::Parsed(value) => write!(self.out, "{}", pretty(value)?)?,
+        }
+        writeln!(self.out, "\n--- XFA DATA END ---")?;
+        writeln!(self.out) // Blank line between sections
+    }
+
+    fn signatures(&mut self, fields: &[SignatureField]) -> io::Result<()> {
+        writeln!(self.out, "--- SIGNATURES START ---")?;
+        for field in fields {
+            if !field.signed {
+                writeln!(self.out, "{}: unsigned", field.name)?;
+                continue;
+            }
+            let mut details = Vec::new();
+            if let Some(signer) = &field.signer {
+                details.push(format!("by {}", signer));
+            }
+            if let Some(date) = &field.date {
+                details.push(format!("on {}", date));
+            }
+            if let Some(reason) = &field.reason {
+                details.push(format!("reason: {}", reason));
+            }
+            if let Some(location) = &field.location {
+                details.push(format!("location: {}", location));
+            }
+            if details.is_empty() {
+                writeln!(self.out, "{}: signed", field.name)?;
+            } else {
+                writeln!(self.out, "{}: signed {}", field.name, details.join(", "))?;
+            }
+        }
+        writeln!(self.out, "--- SIGNATURES END ---")?;
+        writeln!(self.out)
+    }
+
+    fn active_content(&mut self, content: &ActiveContent) -> io::Result<()> {
+        writeln!(self.out, "--- SECURITY START ---")?;
+        if *content == ActiveContent::default() {
+            writeln!(self.out, "No active content or external references.")?;
+        }
+        if !content.open_actions.is_empty() {
+            writeln!(self.out, "Open actions: {}", content.open_actions.join(", "))?;
+        }
+        if content.javascript > 0 {
+            writeln!(self.out, "JavaScript: {}", content.javascript)?;
+        }
+        for (label, targets) in [
+            ("Launch", &content.launch),
+            ("Embedded file", &content.embedded_files),
+            ("External reference", &content.external),
+        ] {
+            for target in targets {
+                writeln!(self.out, "{}: {}", label, target)?;
+            }
+        }
+        writeln!(self.out, "--- SECURITY END ---")?;
+        writeln!(self.out)
+    }
+
+    fn fonts(&mut self, fonts: &[FontInfo]) -> io::Result<()> {
+        writeln!(self.out, "--- FONTS START ---")?;
+        for font in fonts {
+            let name = if font.name.is_empty() { "(unnamed)" } else { &font.name };
+            let embedding = match &font.format {
+                Some(_) if font.subset() => "embedded subset",
+                Some(_) => "embedded",
+                None => "not embedded",
+            };
+            writeln!(self.out, "{}: {}, {}", name, font.kind, embedding)?;
+        }
+        writeln!(self.out, "--- FONTS END ---")?;
+        writeln!(self.out)
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        self.start_page(page)?;
+        if let Some(layer) = page.text_layer.as_ref().filter(|_| page.merged.is_none()) {
+            writeln!(self.out, "--- TEXT LAYER START ---")?;
+            // The text output may contain newlines if the PDF structure suggests them.
+            write!(self.out, "{}", layer.text)?;
+            writeln!(self.out, "--- TEXT LAYER END ---")?;
+            writeln!(self.out)?;
+        }
+        self.end_page(page)
+    }
+
+    fn page_streamed(&mut self, page: &PageResult, text: &mut dyn FnMut(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+        self.start_page(page)?;
+        writeln!(self.out, "--- TEXT LAYER START ---")?;
+        text(&mut self.out)?;
+        writeln!(self.out, "--- TEXT LAYER END ---")?;
+        writeln!(self.out)?;
+        self.end_page(page)
+    }
+
+    fn no_pages(&mut self, reason: &str) -> io::Result<()> {
+        writeln!(self.out, "--- NO PAGES PROCESSED ---")?;
+        writeln!(self.out, "{}", reason)?;
+        writeln!(self.out)
+    }
+
+    fn document_stats(&mut self, stats: &DocumentStats) -> io::Result<()> {
+        self.end_document()?;
+        writeln!(self.out, "--- DOCUMENT STATS START ---")?;
+        writeln!(self.out, "Pages: {} ({} text layer only)", stats.pages, stats.text_layer_only_pages)?;
+        let scripts: Vec<String> = stats.scripts().iter().map(|(script, _, share)| format!("{} {:.1}%", script, share * 100.0)).collect();
+        if !scripts.is_empty() {
+            writeln!(self.out, "Scripts: {}", scripts.join(", "))?;
+        }
+        let languages: Vec<String> =
+            stats.languages().iter().map(|l| format!("{} {:.1}% (conf {:.0})", l.lang, l.share * 100.0, l.conf)).collect();
+        if !languages.is_empty() {
+            writeln!(self.out, "Languages: {}", languages.join(", "))?;
+        }
+        let c = stats.chars;
+        writeln!(
+            self.out,
+            "Characters: {} letters, {} digits, {} punctuation, {} whitespace, {} other",
+            c.letters, c.digits, c.punctuation, c.whitespace, c.other
+        )?;
+        writeln!(self.out, "--- DOCUMENT STATS END ---")?;
+        writeln!(self.out)
+    }
+
+    fn warnings(&mut self, _warnings: &[Warning]) -> io::Result<()> {
+        // Text output keeps to page content; warnings stay on STDERR
+        Ok(())
+    }
+
+    fn summary(&mut self, summary: &RunSummary) -> io::Result<()> {
+        self.end_document()?;
+        // One key=value per line, as in the --quiet summary, for log scrapers
+        writeln!(self.out, "--- SUMMARY START ---")?;
+        writeln!(self.out, "pages_processed={}", summary.pages_processed)?;
+        writeln!(self.out, "pages_skipped={}", summary.pages_skipped)?;
+        writeln!(self.out, "warnings={}", summary.warnings)?;
+        writeln!(self.out, "characters={}", summary.characters)?;
+        writeln!(self.out, "duration_ms={}", summary.duration.as_millis())?;
+        writeln!(self.out, "--- SUMMARY END ---")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.end_document()?;
+        self.out.flush()
+    }
+}
+
+/// An hOCR document: Tesseract's `ocr_page` element of each page, in order.
+/// Word boxes are in pixels of the OCRed image. Form data and warnings are not included.
+pub struct HocrWriter<W: Write> {
+    out: W,
+    started: bool,
+    /// Pages written so far. Page ids count pages in output order, as Tesseract's do.
+    pages: usize,
+}
+
+impl<W: Write> HocrWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, started: false, pages: 0 }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            write!(self.out, "{}", HOCR_HEADER)?;
+        }
+        Ok(())
+    }
+}
+
+const HOCR_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="en" lang="en">
+ <head>
+  <title></title>
+  <meta http-equiv="Content-Type" content="text/html;charset=utf-8"/>
+  <meta name="ocr-system" content="crabocr (tesseract)"/>
+  <meta name="ocr-capabilities" content="ocr_page ocr_carea ocr_par ocr_line ocrx_word ocrp_wconf"/>
+ </head>
+ <body>
+"#;
+
+impl<W: Write> OutputWriter for HocrWriter<W> {
+    fn xfa(&mut self, _xfa: &XfaData) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn signatures(&mut self, _fields: &[SignatureField]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn active_content(&mut self, _content: &ActiveContent) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn fonts(&mut self, _fonts: &[FontInfo]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        self.start()?;
+        self.pages += 1;
+        match &page.hocr {
+            Some(hocr) => write!(self.out, "{}", hocr),
+            // Rejected for low confidence: the page is kept, without content
+            None => writeln!(
+                self.out,
+                "  <div class='ocr_page' id='page_{}' title='ppageno {}'></div>",
+                self.pages,
+                self.pages - 1
+            ),
+        }
+    }
+
+    fn no_pages(&mut self, _reason: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn warnings(&mut self, _warnings: &[Warning]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.start()?;
+        writeln!(self.out, " </body>\n</html>")?;
+        self.out.flush()
+    }
+}
+
+/// Tesseract's TSV of each page under a single header row. Boxes are in
+/// pixels of the OCRed image; `page_num` counts pages in output order.
+pub struct TsvWriter<W: Write> {
+    out: W,
+    started: bool,
+}
+
+impl<W: Write> TsvWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, started: false }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            writeln!(self.out, "{}", TSV_HEADER)?;
+        }
+        Ok(())
+    }
+}
+
+const TSV_HEADER: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext";
+
+impl<W: Write> OutputWriter for TsvWriter<W> {
+    fn xfa(&mut self, _xfa: &XfaData) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn signatures(&mut self, _fields: &[SignatureField]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn active_content(&mut self, _content: &ActiveContent) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn fonts(&mut self, _fonts: &[FontInfo]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        self.start()?;
+        // Pages rejected for low confidence have no rows
+        match &page.tsv {
+            Some(tsv) => write!(self.out, "{}", tsv),
+            None => Ok(()),
+        }
+    }
+
+    fn no_pages(&mut self, _reason: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn warnings(&mut self, _warnings: &[Warning]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.start()?;
+        self.out.flush()
+    }
+}
+
+/// A single JSON document, written once all pages are processed.
+pub struct JsonWriter<W: Write> {
+    out: W,
+    /// Include OCR words (`--words`).
+    words: bool,
+    /// Whether pages are processed; form-data-only documents have no page list.
+    with_pages: bool,
+    doc: schema::Document,
+    pages: Vec<schema::Page>,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(out: W, granularity: Granularity) -> Self {
+        Self {
+            out,
+            words: false,
+            with_pages: true,
+            doc: schema::Document {
+                format_version: schema::FORMAT_VERSION.to_string(),
+                granularity: Some(granularity.as_str().to_string()),
+                ..Default::default()
+            },
+            pages: Vec::new(),
+        }
+    }
+
+    /// Output `--meta` tags in the document header.
+    pub fn with_meta(mut self, meta: &[(String, String)]) -> Self {
+        self.doc.meta = meta_to_json(meta);
+        self
+    }
+
+    /// Output the OCR words of each page (`--words`).
+    pub fn with_words(mut self, words: bool) -> Self {
+        self.words = words;
+        self
+    }
+
+    /// Leave out the granularity and page list, for form data only (`--mode xfa-only`).
+    pub fn with_pages(mut self, pages: bool) -> Self {
+        self.with_pages = pages;
+        self
+    }
+}
+
+impl<W: Write> OutputWriter for JsonWriter<W> {
+    fn xfa(&mut self, xfa: &XfaData) -> io::Result<()> {
+        self.doc.xfa = Some(match xfa {
+            XfaData::Raw(xml) => Value::String(xml.clone()),
+            XfaData::Parsed(value) => value.clone(),
+        });
+        Ok(())
+    }
+
+    fn signatures(&mut self, fields: &[SignatureField]) -> io::Result<()> {
+        self.doc.signatures = Some(
+            fields
+                .iter()
+                .map(|f| schema::Signature {
+                    field: f.name.clone(),
+                    signed: f.signed,
+                    signer: f.signer.clone(),
+                    date: f.date.clone(),
+                    reason: f.reason.clone(),
+                    location: f.location.clone(),
+                })
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn active_content(&mut self, content: &ActiveContent) -> io::Result<()> {
+        self.doc.security = Some(schema::Security {
+            open_actions: content.open_actions.clone(),
+            javascript: content.javascript,
+            launch: content.launch.clone(),
+            embedded_files: content.embedded_files.clone(),
+            external: content.external.clone(),
+        });
+        Ok(())
+    }
+
+    fn fonts(&mut self, fonts: &[FontInfo]) -> io::Result<()> {
+        self.doc.fonts = Some(
+            fonts
+                .iter()
+                .map(|f| schema::Font {
+                    name: f.name.clone(),
+                    kind: f.kind.clone(),
+                    embedded: f.format.is_some(),
+                    subset: f.subset(),
+                    format: f.format.clone(),
+                })
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        self.pages.push(page_to_json(page, self.words));
+        Ok(())
+    }
+
+    fn no_pages(&mut self, reason: &str) -> io::Result<()> {
+        self.doc.status = schema::Status::NoPages;
+        self.doc.reason = Some(reason.to_string());
+        Ok(())
+    }
+
+    fn document_stats(&mut self, stats: &DocumentStats) -> io::Result<()> {
+        let c = stats.chars;
+        self.doc.stats = Some(schema::DocumentStats {
+            pages: stats.pages,
+            text_layer_only_pages: stats.text_layer_only_pages,
+            scripts: stats
+                .scripts()
+                .into_iter()
+                .map(|(script, letters, share)| schema::Script { script: script.to_string(), letters, share: round(share, 3) })
+                .collect(),
+            languages: stats
+                .languages()
+                .iter()
+                .map(|l| schema::Language { lang: l.lang.clone(), words: l.words, share: round(l.share, 3), conf: round(l.conf, 1) })
+                .collect(),
+            chars: schema::CharClasses { letters: c.letters, digits: c.digits, punctuation: c.punctuation, whitespace: c.whitespace, other: c.other },
+        });
+        Ok(())
+    }
+
+    fn warnings(&mut self, warnings: &[Warning]) -> io::Result<()> {
+        self.doc.warnings = warnings.iter().map(Warning::to_schema).collect();
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let doc = self.take_document();
+        writeln!(self.out, "{}", pretty(&doc)?)?;
+        self.out.flush()
+    }
+}
+
+impl<W: Write> JsonWriter<W> {
+    /// The document collected so far, with its pages.
+    fn take_document(&mut self) -> schema::Document {
+        let mut doc = std::mem::take(&mut self.doc);
+        if self.with_pages {
+            doc.pages = Some(std::mem::take(&mut self.pages));
+        } else {
+            doc.granularity = None;
+        }
+        doc
+    }
+}
+
+/// The JSON document kept in memory rather than written, for `ocr_document`.
+pub struct DocumentWriter {
+    /// The document is collected as for JSON.
+    json: JsonWriter<io::Sink>,
+    doc: Option<schema::Document>,
+}
+
+impl DocumentWriter {
+    pub fn new(granularity: Granularity) -> Self {
+        Self { json: JsonWriter::new(io::sink(), granularity), doc: None }
+    }
+
+    /// Leave out the granularity and page list, for form data only (`--mode xfa-only`).
+    pub fn with_pages(self, pages: bool) -> Self {
+        Self { json: self.json.with_pages(pages), ..self }
+    }
+
+}
+
+impl OutputWriter for DocumentWriter {
+    fn xfa(&mut self, xfa: &XfaData) -> io::Result<()> {
+        self.json.xfa(xfa)
+    }
+
+    fn signatures(&mut self, fields: &[SignatureField]) -> io::Result<()> {
+        self.json.signatures(fields)
+    }
+
+    fn active_content(&mut self, content: &ActiveContent) -> io::Result<()> {
+        self.json.active_content(content)
+    }
+
+    fn fonts(&mut self, fonts: &[FontInfo]) -> io::Result<()> {
+        self.json.fonts(fonts)
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        self.json.page(page)
+    }
+
+    fn no_pages(&mut self, reason: &str) -> io::Result<()> {
+        self.json.no_pages(reason)
+    }
+
+    fn document_stats(&mut self, stats: &DocumentStats) -> io::Result<()> {
+        self.json.document_stats(stats)
+    }
+
+    fn warnings(&mut self, warnings: &[Warning]) -> io::Result<()> {
+        self.json.warnings(warnings)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.doc = Some(self.json.take_document());
+        Ok(())
+    }
+
+    fn document(&mut self) -> Option<schema::Document> {
+        self.doc.take()
+    }
+}
+
+/// JSON lines for pipelines: each page is written and flushed as soon as it
+/// is done, as a `page` record, and the rest of the document follows as a
+/// `document` record once all pages are processed.
+pub struct NdjsonWriter<W: Write> {
+    /// Form data, fonts and warnings are collected as for JSON.
+    json: JsonWriter<W>,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(out: W, granularity: Granularity) -> Self {
+        Self { json: JsonWriter::new(out, granularity) }
+    }
+
+    /// Output `--meta` tags in the document record.
+    pub fn with_meta(self, meta: &[(String, String)]) -> Self {
+        Self { json: self.json.with_meta(meta) }
+    }
+
+    /// Output the OCR words of each page (`--words`).
+    pub fn with_words(self, words: bool) -> Self {
+        Self { json: self.json.with_words(words) }
+    }
+
+    fn record(&mut self, record: &schema::Record) -> io::Result<()> {
+        serde_json::to_writer(&mut self.json.out, record).map_err(io::Error::other)?;
+        writeln!(self.json.out)?;
+        self.json.out.flush()
+    }
+}
+
+impl<W: Write> OutputWriter for NdjsonWriter<W> {
+    fn xfa(&mut self, xfa: &XfaData) -> io::Result<()> {
+        self.json.xfa(xfa)
+    }
+
+    fn signatures(&mut self, fields: &[SignatureField]) -> io::Result<()> {
+        self.json.signatures(fields)
+    }
+
+    fn active_content(&mut self, content: &ActiveContent) -> io::Result<()> {
+        self.json.active_content(content)
+    }
+
+    fn fonts(&mut self, fonts: &[FontInfo]) -> io::Result<()> {
+        self.json.fonts(fonts)
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        let record = schema::Record::Page(Box::new(page_to_json(page, self.json.words)));
+        self.record(&record)
+    }
+
+    fn no_pages(&mut self, reason: &str) -> io::Result<()> {
+        self.json.no_pages(reason)
+    }
+
+    fn document_stats(&mut self, stats: &DocumentStats) -> io::Result<()> {
+        self.json.document_stats(stats)
+    }
+
+    fn warnings(&mut self, warnings: &[Warning]) -> io::Result<()> {
+        self.json.warnings(warnings)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let record = schema::Record::Document(Box::new(std::mem::take(&mut self.json.doc)));
+        self.record(&record)
+    }
+}
+
+/// `--meta` tags as a JSON object; a repeated key keeps its last value.
+pub fn meta_to_json(meta: &[(String, String)]) -> Map<String, Value> {
+    meta.iter().map(|(key, value)| (key.clone(), json!(value))).collect()
+}
+
+/// Rounded in f64 so JSON gets 11.69 rather than 11.6899995.
+fn round(value: f32, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    (value as f64 * factor).round() / factor
+}
+
+fn page_to_json(page: &PageResult, words: bool) -> schema::Page {
+    // Tenths of a millisecond: text extraction often takes less than one
+    let ms = |d: Duration| (d.as_secs_f64() * 10_000.0).round() / 10.0;
+    schema::Page {
+        page: page.number,
+        part: page.part,
+        document: page.document,
+        raster_hash: page.raster_hash.clone(),
+        degraded: page.degraded.map(|d| d.as_str().to_string()),
+        render_retries: page.render_retries,
+        rotation: schema::Rotation {
+            declared: page.rotation.declared,
+            detected: page.rotation.detected,
+            deskew: page.rotation.deskew,
+        },
+        info: page.info.as_ref().map(info_to_json),
+        merged: page.merged.as_ref().map(|blocks| {
+            blocks
+                .iter()
+                .map(|b| schema::MergedBlock { source: b.source.as_str().to_string(), rect: b.rect, text: b.text.clone() })
+                .collect()
+        }),
+        text_layer: page.text_layer.as_ref().filter(|_| page.merged.is_none()).map(|l| units_to_json(&l.units)),
+        text_structure: text_blocks_to_json(&page.text_blocks),
+        suspicious_text_layer: page.suspicious_text_layer.clone(),
+        outlined_text: page.outlined_text,
+        ocr_layer: page.ocr_layer.as_ref().filter(|_| page.merged.is_none()).map(|l| units_to_json(&l.units)),
+        words: words.then(|| words_to_json(&page.words, page.ocr_placement)),
+        alignment: page.alignment.as_ref().map(|a| schema::Alignment {
+            agreement: round(a.agreement(), 3),
+            words: a
+                .words
+                .iter()
+                .map(|w| schema::AlignedWord { text: w.text.clone(), rect: w.rect.map(|v| round(v, 2)), conf: round(w.conf, 1), ocr: w.ocr.clone() })
+                .collect(),
+        }),
+        languages: page
+            .languages
+            .iter()
+            .map(|l| schema::Language { lang: l.lang.clone(), words: l.words, share: round(l.share, 3), conf: round(l.conf, 1) })
+            .collect(),
+        language: page.language.map(|l| schema::DetectedLanguage { lang: l.lang.to_string(), confidence: round(l.confidence, 3) }),
+        tables: page
+            .tables
+            .iter()
+            .enumerate()
+            .map(|(i, t)| schema::Table { index: i + 1, rect: t.rect, rows: t.rows.clone(), csv: t.to_csv() })
+            .collect(),
+        zones: page.zones.iter().map(|z| schema::Zone { kind: z.kind.as_str().to_string(), rect: z.rect, text: z.text.clone() }).collect(),
+        stamps: page.stamps.clone(),
+        annotations: page
+            .annotations
+            .iter()
+            .map(|a| schema::Annotation { kind: a.kind.clone(), rect: a.rect, ocr_layer: units_to_json(&a.layer.units) })
+            .collect(),
+        encoding_fallback: page
+            .encoding_fallback
+            .iter()
+            .map(|f| schema::EncodingFallback { rect: f.rect, original: f.original.clone(), text: f.text.clone() })
+            .collect(),
+        stats: page.stats.as_ref().map(|s| schema::PageStats {
+            text_chars: s.text_chars,
+            ocr_chars: s.ocr_chars,
+            ocr_conf: s.ocr_conf.map(|c| round(c, 1)),
+            render_ms: ms(s.render),
+            ocr_ms: ms(s.ocr),
+            script: s.script.map(String::from),
+            orientation: s.orientation,
+            text_layer_only: s.text_layer_only,
+        }),
+        timings: schema::Timings {
+            render_ms: ms(page.timings.render),
+            preprocessing_ms: ms(page.timings.preprocessing),
+            ocr_ms: ms(page.timings.ocr),
+            text_ms: ms(page.timings.text),
+        },
+        files: Vec::new(),
+    }
+}
+
+fn info_to_json(info: &PageInfo) -> schema::PageInfo {
+    let convert = |rect: [f32; 4], factor: f32| rect.map(|v| (v as f64 * factor as f64 * 100.0).round() / 100.0);
+    let size = |rect: [f32; 4]| schema::BoxSize { pt: rect, mm: convert(rect, MM_PER_PT), inches: convert(rect, IN_PER_PT) };
+    schema::PageInfo {
+        media_box: size(info.boxes.media),
+        crop_box: size(info.boxes.crop),
+        trim_box: size(info.boxes.trim),
+        color: info.color.map(|c| c.as_str().to_string()),
+        ink: info.ink.as_ref().map(|ink| schema::Ink { coverage: ink.coverage, histogram: ink.histogram.to_vec() }),
+    }
+}
+
+/// OCR words with their box in pixels (`bbox`) and, once placed, on the page in points (`rect`).
+fn words_to_json(words: &[Word], placement: Option<ImagePlacement>) -> Vec<schema::Word> {
+    words
+        .iter()
+        .map(|w| schema::Word {
+            text: w.text.clone(),
+            conf: round(w.conf, 1),
+            bbox: w.bbox,
+            rect: placement.map(|p| p.to_points(w.bbox).map(|v| round(v, 2))),
+            block: w.block,
+            para: w.para,
+            line: w.line,
+            lang: w.lang.clone(),
+        })
+        .collect()
+}
+
+fn units_to_json(units: &[TextUnit]) -> Vec<schema::Unit> {
+    units.iter().map(|u| schema::Unit { text: u.text.clone(), conf: u.conf, bbox: u.bbox, lang: u.lang.map(String::from) }).collect()
+}
+
+const MM_PER_PT: f32 = 25.4 / 72.0;
+const IN_PER_PT: f32 = 1.0 / 72.0;
+
+/// Boxes with their PDF name.
+fn boxes(boxes: &PageBoxes) -> [(&'static str, [f32; 4]); 3] {
+    [("MediaBox", boxes.media), ("CropBox", boxes.crop), ("TrimBox", boxes.trim)]
+}
+
+/// Width and height of a rectangle in points, converted with `factor`.
+fn size_in(rect: [f32; 4], factor: f32) -> [f32; 2] {
+    [(rect[2] - rect[0]) * factor, (rect[3] - rect[1]) * factor]
+}
+
+fn pretty<T: serde::Serialize>(value: &T) -> io::Result<String> {
+    serde_json::to_string_pretty(value).map_err(io::Error::other)
+}
+
+fn text_blocks_to_json(blocks: &[TextBlock]) -> Vec<schema::Block> {
+    let rect = |r: [f32; 4]| r.map(|v| round(v, 2));
+    blocks
+        .iter()
+        .map(|block| schema::Block {
+            rect: rect(block.rect),
+            lines: block
+                .lines
+                .iter()
+                .map(|line| schema::Line {
+                    rect: rect(line.rect),
+                    spans: line
+                        .spans
+                        .iter()
+                        .map(|span| schema::Span { rect: rect(span.rect), font: span.font.clone(), size: round(span.size, 2), text: span.text.clone() })
+                        .collect(),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::warnings::WarningKind;
+
+    const SAMPLE: &str = "First line\nsecond line\n\nNext block here\n";
+
+    #[test]
+    fn test_split_page() {
+        let units = split_text(SAMPLE, Granularity::Page);
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].text, SAMPLE);
+        assert!(split_text("  \n", Granularity::Page).is_empty());
+    }
+
+    #[test]
+    fn test_split_block_line_word() {
+        let blocks = split_text(SAMPLE, Granularity::Block);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].text, "Next block here");
+
+        let lines = split_text(SAMPLE, Granularity::Line);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].text, "second line");
+
+        let words = split_text(SAMPLE, Granularity::Word);
+        assert_eq!(words.len(), 7);
+    }
+
+    #[test]
+    fn test_text_writer_framing() {
+        let mut page = PageResult {
+            number: 2,
+            part: None,
+            document: None,
+            raster_hash: None,
+            hocr: None,
+            tsv: None,
+            words: Vec::new(),
+            ocr_size: None,
+            ocr_placement: None,
+            lines: Vec::new(),
+            text_blocks: Vec::new(),
+            tables: Vec::new(),
+            zones: Vec::new(),
+            degraded: None,
+            render_retries: 0,
+            rotation: Rotation::default(),
+            info: None,
+            text_layer: Some(Layer {
+                text: "hello\n".into(),
+                units: Vec::new(),
+            }),
+            ocr_layer: None,
+            annotations: Vec::new(),
+            encoding_fallback: Vec::new(),
+            stamps: Vec::new(),
+            suspicious_text_layer: Vec::new(),
+            outlined_text: false,
+            languages: Vec::new(),
+            language: None,
+            merged: None,
+            alignment: None,
+            ocr_conf: None,
+            stats: None,
+            image_file: None,
+            timings: Timings {
+                render: Duration::from_micros(12_345),
+                ..Default::default()
+            },
+        };
+        let mut buf = Vec::new();
+        {
+            let mut w = TextWriter::new(&mut buf);
+            w.page(&page).unwrap();
+        }
+        let out = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            out,
+            "--- PAGE 2 START ---\n\n--- TEXT LAYER START ---\nhello\n--- TEXT LAYER END ---\n\n--- PAGE 2 END ---\n\n"
+        );
+
+        // Timings are JSON only, in tenths of a millisecond
+        let timings = page_to_json(&page, false).timings;
+        assert_eq!(timings.render_ms, 12.3);
+        assert_eq!(timings.ocr_ms, 0.0);
+
+        // Batch scans split with --split-on get document sections
+        let mut buf = Vec::new();
+        {
+            let mut w = TextWriter::new(&mut buf);
+            page.document = Some(1);
+            w.page(&page).unwrap();
+            page.document = Some(2);
+            w.page(&page).unwrap();
+            w.finish().unwrap();
+        }
+        let out = String::from_utf8(buf).unwrap();
+        assert!(out.starts_with("--- DOCUMENT 1 START ---\n\n--- PAGE 2 START ---"));
+        assert!(out.contains("--- PAGE 2 END ---\n\n--- DOCUMENT 1 END ---\n\n--- DOCUMENT 2 START ---\n\n"));
+        assert!(out.ends_with("--- DOCUMENT 2 END ---\n\n"));
+
+        let mut buf = Vec::new();
+        {
+            let mut w = HocrWriter::new(&mut buf);
+            w.page(&page).unwrap();
+            w.finish().unwrap();
+        }
+        let out = String::from_utf8(buf).unwrap();
+        assert!(out.starts_with("<?xml"));
+        assert!(out.ends_with("  <div class='ocr_page' id='page_1' title='ppageno 0'></div>\n </body>\n</html>\n"));
+
+        let mut buf = Vec::new();
+        {
+            let mut w = TsvWriter::new(&mut buf);
+            page.tsv = Some("1\t1\t0\t0\t0\t0\t0\t0\t2480\t3508\t-1\t\n".into());
+            w.page(&page).unwrap();
+            page.tsv = None;
+            w.page(&page).unwrap();
+            w.finish().unwrap();
+        }
+        let out = String::from_utf8(buf).unwrap();
+        assert_eq!(out.lines().count(), 2);
+        assert!(out.starts_with("level\tpage_num\t"));
+    }
+
+    #[test]
+    fn test_text_writer_streamed() {
+        let page = PageResult { number: 4, text_layer: Some(Layer { text: "one\ntwo\n".into(), units: Vec::new() }), ..Default::default() };
+        let mut written = Vec::new();
+        TextWriter::new(&mut written).page(&page).unwrap();
+        let mut streamed = Vec::new();
+        TextWriter::new(&mut streamed)
+            .page_streamed(&PageResult { number: 4, ..Default::default() }, &mut |out| {
+                out.write_all(b"one\n")?;
+                out.write_all(b"two\n")
+            })
+            .unwrap();
+        assert_eq!(streamed, written);
+        assert!(JsonWriter::new(Vec::new(), Granularity::Page).page_streamed(&page, &mut |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_text_writer_summary() {
+        let mut out = Vec::new();
+        {
+            let mut w = TextWriter::new(&mut out);
+            w.page(&PageResult { number: 1, document: Some(1), ..Default::default() }).unwrap();
+            let summary = RunSummary { pages_processed: 1, pages_skipped: 2, warnings: 3, characters: 1234, duration: Duration::from_millis(5678) };
+            w.summary(&summary).unwrap();
+            w.finish().unwrap();
+        }
+        let out = String::from_utf8(out).unwrap();
+        // The open document section is closed first, and the footer ends the output
+        assert!(out.ends_with(
+            "--- DOCUMENT 1 END ---\n\n--- SUMMARY START ---\npages_processed=1\npages_skipped=2\nwarnings=3\ncharacters=1234\nduration_ms=5678\n--- SUMMARY END ---\n"
+        ));
+    }
+
+    #[test]
+    fn test_page_info_units() {
+        let a4 = [0.0, 0.0, 595.28, 841.89];
+        let [w, h] = size_in(a4, MM_PER_PT);
+        assert!((w - 210.0).abs() < 0.1 && (h - 297.0).abs() < 0.1);
+        let info = info_to_json(&PageInfo {
+            boxes: PageBoxes { media: a4, crop: a4, trim: a4 },
+            color: None,
+            ink: None,
+        });
+        assert_eq!(info.trim_box.inches[3], 11.69);
+    }
+
+    #[test]
+    fn test_text_encoder() {
+        let encode = |newline: Option<Newline>, bom: bool, chunks: &[&str]| {
+            let mut buf = Vec::new();
+            {
+                let mut w = TextEncoder { out: &mut buf, newline, bom, pending_cr: false };
+                for chunk in chunks {
+                    w.write_all(chunk.as_bytes()).unwrap();
+                }
+            }
+            String::from_utf8(buf).unwrap()
+        };
+        assert_eq!(encode(Some(Newline::Crlf), false, &["a\nb\r", "\nc\r\r\n"]), "a\r\nb\r\nc\r\r\n");
+        assert_eq!(encode(Some(Newline::Lf), true, &["a\r\nb\n", "c\r"]), "\u{FEFF}a\nb\nc\r");
+        assert_eq!(encode(None, true, &["a\r\n", "b"]), "\u{FEFF}a\r\nb");
+    }
+
+    #[test]
+    fn test_page_files_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("pages");
+        {
+            let mut w = page_files_writer(&out, &[Format::Text, Format::Json], Granularity::Page, &[], false, &OutputOptions::default()).unwrap();
+            let text = Layer { text: "hello\n".into(), units: vec![TextUnit::plain("hello")] };
+            w.page(&PageResult { number: 1, text_layer: Some(text), ..Default::default() }).unwrap();
+            w.page(&PageResult { number: 12, part: Some('a'), ..Default::default() }).unwrap();
+            w.warnings(&[]).unwrap();
+            w.finish().unwrap();
+        }
+        let text = std::fs::read_to_string(out.join("page_0001.txt")).unwrap();
+        assert!(text.contains("--- TEXT LAYER START ---\nhello\n"));
+        let page: Value = serde_json::from_str(&std::fs::read_to_string(out.join("page_0012a.json")).unwrap()).unwrap();
+        assert_eq!(page["pages"][0]["part"], json!("a"));
+
+        let index: Value = serde_json::from_str(&std::fs::read_to_string(out.join("index.json")).unwrap()).unwrap();
+        assert_eq!(index["status"], json!("ok"));
+        let first = &index["pages"][0];
+        assert_eq!(first["files"], json!(["page_0001.txt", "page_0001.json"]));
+        assert!(first.get("rotation").is_some() && first.get("text_layer").is_none());
+        assert_eq!(index["pages"][1]["files"][0], json!("page_0012a.txt"));
+    }
+
+    #[test]
+    fn test_json_without_pages() {
+        let mut buf = Vec::new();
+        {
+            let mut w = JsonWriter::new(&mut buf, Granularity::Page).with_pages(false);
+            w.warnings(&[]).unwrap();
+            w.finish().unwrap();
+        }
+        let doc: Value = serde_json::from_slice(&buf).unwrap();
+        let keys: Vec<&String> = doc.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["format_version", "status", "warnings"]);
+    }
+
+    #[test]
+    fn test_document_writer() {
+        let mut w = DocumentWriter::new(Granularity::Page);
+        w.page(&PageResult { number: 3, ..Default::default() }).unwrap();
+        w.warnings(&[]).unwrap();
+        assert!(w.document().is_none());
+        w.finish().unwrap();
+        let doc = w.document().unwrap();
+        assert_eq!(doc.granularity.as_deref(), Some("page"));
+        assert_eq!(doc.pages.unwrap()[0].page, 3);
+
+        let mut w = DocumentWriter::new(Granularity::Page).with_pages(false);
+        w.finish().unwrap();
+        let doc = w.document().unwrap();
+        assert!(doc.granularity.is_none() && doc.pages.is_none());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let a4 = [0.0, 0.0, 595.28, 841.89];
+        let page = PageResult {
+            number: 7,
+            part: Some('b'),
+            raster_hash: Some("ab12".into()),
+            degraded: Some(Degradation::LowerDpi),
+            render_retries: 2,
+            rotation: Rotation { declared: 90, detected: Some(0), deskew: Some(0.4) },
+            info: Some(PageInfo { boxes: PageBoxes { media: a4, crop: a4, trim: a4 }, color: Some(ColorClass::Grayscale), ink: None }),
+            ocr_layer: Some(Layer { text: "Total 12\n".into(), units: vec![TextUnit { text: "Total 12".into(), conf: Some(88.5), bbox: Some([1, 2, 3, 4]), lang: None }] }),
+            words: vec![Word { text: "Total".into(), conf: 88.46, bbox: [100, 40, 180, 60], block: 0, para: 0, line: 0, lang: Some("eng".into()) }],
+            ocr_placement: Some(ImagePlacement { scale: 0.24, offset: [0.0, 0.0] }),
+            tables: vec![Table { rect: [10.0, 20.0, 30.5, 40.0], rows: vec![vec!["a".into(), "b,c".into()]] }],
+            stamps: vec![[1.5, 2.0, 3.0, 4.0]],
+            timings: Timings { ocr: Duration::from_micros(1_234_567), ..Default::default() },
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        {
+            let mut w = JsonWriter::new(&mut buf, Granularity::Line).with_meta(&[("case".into(), "C-1".into())]).with_words(true);
+            w.fonts(&[FontInfo { object: 4, name: "ABCDEF+Arial".into(), kind: "TrueType".into(), format: Some("truetype".into()) }]).unwrap();
+            w.page(&page).unwrap();
+            w.warnings(&[Warning { kind: WarningKind::LowConfidence, page: Some(7), part: Some('b'), message: "dropped".into() }]).unwrap();
+            w.finish().unwrap();
+        }
+        let json = String::from_utf8(buf).unwrap();
+        let doc: schema::Document = serde_json::from_str(&json).unwrap();
+        let pages = doc.pages.as_ref().unwrap();
+        assert_eq!(pages[0].part, Some('b'));
+        assert_eq!(pages[0].words.as_ref().unwrap()[0].rect, Some([24.0, 9.6, 43.2, 14.4]));
+        assert_eq!(pages[0].tables[0].csv, "a,\"b,c\"\n");
+        assert_eq!(doc.warnings[0].kind, "low_confidence");
+        assert_eq!(format!("{}\n", pretty(&doc).unwrap()), json);
+    }
+
+    #[test]
+    fn test_ndjson_writer() {
+        let mut buf = Vec::new();
+        {
+            let mut w = NdjsonWriter::new(&mut buf, Granularity::Page).with_meta(&[("batch".into(), "7".into())]);
+            w.page(&PageResult { number: 1, ..Default::default() }).unwrap();
+            w.page(&PageResult { number: 2, ..Default::default() }).unwrap();
+            w.warnings(&[]).unwrap();
+            w.finish().unwrap();
+        }
+        let out = String::from_utf8(buf).unwrap();
+        let records: Vec<schema::Record> = out.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(records.len(), 3);
+        assert!(out.starts_with("{\"type\":\"page\",\"page\":1,"));
+        match &records[2] {
+            schema::Record::Document(doc) => {
+                assert_eq!(doc.meta["batch"], json!("7"));
+                assert!(doc.pages.is_none());
+            }
+            _ => panic!("the document record comes last"),
+        }
+    }
+
+    #[test]
+    fn test_words_to_json() {
+        let word = Word { text: "Total".into(), conf: 91.26, bbox: [100, 40, 180, 60], block: 1, para: 2, line: 3, lang: None };
+        let placement = ImagePlacement { scale: 72.0 / 300.0, offset: [297.64, 0.0] };
+        let words = serde_json::to_value(words_to_json(std::slice::from_ref(&word), Some(placement))).unwrap();
+        assert_eq!(
+            words[0],
+            json!({ "text": "Total", "conf": 91.3, "bbox": [100, 40, 180, 60], "rect": [321.64, 9.6, 340.84, 14.4], "block": 1, "para": 2, "line": 3 })
+        );
+        assert!(words_to_json(&[word], None)[0].rect.is_none());
+    }
+}
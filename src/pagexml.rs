@@ -0,0 +1,171 @@
+use crate::alto::escape;
+use crate::audit::utc_timestamp;
+use crate::ocr::Word;
+use crate::output::{OutputWriter, PageResult, XfaData};
+use crate::renderer::{ActiveContent, FontInfo, SignatureField};
+use crate::warnings::Warning;
+use std::io::{self, Write};
+use std::time::SystemTime;
+
+/// A PRImA PAGE XML (2019) document for correction tools such as Transkribus
+/// and Aletheia: Tesseract's blocks, lines and words as `TextRegion`,
+/// `TextLine` and `Word`, with `Coords` polygons in pixels of the OCRed image
+/// and confidences from 0 to 1. A document holds a single page: write
+/// documents of several pages with `--split-pages`.
+pub struct PageXmlWriter<W: Write> {
+    out: W,
+    written: bool,
+}
+
+impl<W: Write> PageXmlWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, written: false }
+    }
+
+    fn region(&mut self, id: &str, words: &[Word]) -> io::Result<()> {
+        writeln!(self.out, "    <TextRegion id=\"{}\">", id)?;
+        writeln!(self.out, "      <Coords points=\"{}\"/>", points(words))?;
+        let mut lines = Vec::new();
+        for (i, line) in words.chunk_by(|a, b| a.line == b.line).enumerate() {
+            let line_id = format!("{}_l{}", id, i + 1);
+            writeln!(self.out, "      <TextLine id=\"{}\">", line_id)?;
+            writeln!(self.out, "        <Coords points=\"{}\"/>", points(line))?;
+            for (j, word) in line.iter().enumerate() {
+                writeln!(self.out, "        <Word id=\"{}_w{}\">", line_id, j + 1)?;
+                writeln!(self.out, "          <Coords points=\"{}\"/>", points(std::slice::from_ref(word)))?;
+                writeln!(self.out, "          {}", text_equiv(&word.text, Some(word.conf)))?;
+                writeln!(self.out, "        </Word>")?;
+            }
+            let text: Vec<&str> = line.iter().map(|w| w.text.as_str()).collect();
+            let text = text.join(" ");
+            writeln!(self.out, "        {}", text_equiv(&text, None))?;
+            writeln!(self.out, "      </TextLine>")?;
+            lines.push(text);
+        }
+        writeln!(self.out, "      {}", text_equiv(&lines.join("\n"), None))?;
+        writeln!(self.out, "    </TextRegion>")
+    }
+}
+
+const PAGE_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<PcGts xmlns="http://schema.primaresearch.org/PAGE/gts/pagecontent/2019-07-15" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://schema.primaresearch.org/PAGE/gts/pagecontent/2019-07-15 http://schema.primaresearch.org/PAGE/gts/pagecontent/2019-07-15/pagecontent.xsd">
+  <Metadata>
+    <Creator>crabocr {version} (tesseract)</Creator>
+    <Created>{time}</Created>
+    <LastChange>{time}</LastChange>
+  </Metadata>
+"#;
+
+impl<W: Write> OutputWriter for PageXmlWriter<W> {
+    fn xfa(&mut self, _xfa: &XfaData) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn signatures(&mut self, _fields: &[SignatureField]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn active_content(&mut self, _content: &ActiveContent) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn fonts(&mut self, _fonts: &[FontInfo]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        if self.written {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "PAGE XML holds one page per document; use --output-dir with --split-pages",
+            ));
+        }
+        self.written = true;
+        let time = utc_timestamp(SystemTime::now());
+        write!(self.out, "{}", PAGE_HEADER.replace("{version}", env!("CARGO_PKG_VERSION")).replace("{time}", &time))?;
+        let [width, height] = page.ocr_size.unwrap_or_default();
+        // Named after the image --dump-ocr-input writes for the page
+        let image = format!("page_{:04}{}.pgm", page.number, page.part.map(String::from).unwrap_or_default());
+        writeln!(self.out, "  <Page imageFilename=\"{}\" imageWidth=\"{}\" imageHeight=\"{}\">", image, width, height)?;
+        // Pages rejected for low confidence have no words, and no regions
+        for (i, block) in page.words.chunk_by(|a, b| a.block == b.block).enumerate() {
+            self.region(&format!("r{}", i + 1), block)?;
+        }
+        writeln!(self.out, "  </Page>\n</PcGts>")
+    }
+
+    fn no_pages(&mut self, _reason: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn warnings(&mut self, _warnings: &[Warning]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// `Coords` points of the box around `words`, clockwise from the top left.
+fn points(words: &[Word]) -> String {
+    let [l, t, r, b] = words.iter().fold([i32::MAX, i32::MAX, i32::MIN, i32::MIN], |acc, w| {
+        [acc[0].min(w.bbox[0]), acc[1].min(w.bbox[1]), acc[2].max(w.bbox[2]), acc[3].max(w.bbox[3])]
+    });
+    format!("{l},{t} {r},{t} {r},{b} {l},{b}")
+}
+
+fn text_equiv(text: &str, conf: Option<f32>) -> String {
+    let conf = conf.map(|c| format!(" conf=\"{:.2}\"", (c / 100.0).clamp(0.0, 1.0))).unwrap_or_default();
+    format!("<TextEquiv{}><Unicode>{}</Unicode></TextEquiv>", conf, escape(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, bbox: [i32; 4], block: usize, line: usize) -> Word {
+        Word { text: text.to_string(), conf: 91.0, bbox, block, para: block, line, lang: None }
+    }
+
+    #[test]
+    fn test_page_xml_writer() {
+        let page = PageResult {
+            number: 3,
+            part: Some('b'),
+            ocr_size: Some([1000, 1400]),
+            words: vec![
+                word("Fish", [100, 100, 200, 140], 0, 0),
+                word("&", [210, 100, 230, 140], 0, 0),
+                word("Chips", [240, 100, 350, 140], 0, 0),
+                word("Menu", [100, 160, 200, 200], 0, 1),
+                word("12", [800, 1300, 840, 1330], 1, 2),
+            ],
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        {
+            let mut w = PageXmlWriter::new(&mut buf);
+            w.page(&page).unwrap();
+            assert!(w.page(&PageResult { number: 4, ..Default::default() }).is_err());
+            w.finish().unwrap();
+        }
+        let xml = String::from_utf8(buf).unwrap();
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let page = doc.descendants().find(|n| n.has_tag_name("Page")).unwrap();
+        assert_eq!(page.attribute("imageFilename"), Some("page_0003b.pgm"));
+        assert_eq!(page.attribute("imageWidth"), Some("1000"));
+
+        let regions: Vec<_> = page.children().filter(|n| n.has_tag_name("TextRegion")).collect();
+        assert_eq!(regions.len(), 2);
+        let line = regions[0].children().find(|n| n.has_tag_name("TextLine")).unwrap();
+        let coords = line.children().find(|n| n.has_tag_name("Coords")).unwrap();
+        assert_eq!(coords.attribute("points"), Some("100,100 350,100 350,140 100,140"));
+        let words: Vec<_> = line.children().filter(|n| n.has_tag_name("Word")).collect();
+        let equiv = words[1].children().find(|n| n.has_tag_name("TextEquiv")).unwrap();
+        assert_eq!(equiv.attribute("conf"), Some("0.91"));
+        assert_eq!(equiv.first_element_child().and_then(|u| u.text()), Some("&"));
+        let region_text = regions[0].children().rfind(|n| n.has_tag_name("TextEquiv")).unwrap();
+        assert_eq!(region_text.first_element_child().and_then(|u| u.text()), Some("Fish & Chips\nMenu"));
+    }
+}
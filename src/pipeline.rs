@@ -0,0 +1,92 @@
+use crate::cli::Dpi;
+use crate::errors::CrabError;
+use crate::limits::ResourceLimits;
+use crate::preprocess::GrayImage;
+use crate::renderer::Renderer;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Rendered pages waiting for OCR. Each A4 page at 300 DPI holds about 9 MB.
+const QUEUE_DEPTH: usize = 2;
+
+/// A rendered page, the time rendering took and the retries it needed.
+type Rendered = (GrayImage, Duration, u32);
+
+/// Renders pages on a background thread, with its own MuPDF context and
+/// document, while the caller runs OCR on earlier pages. The thread must not
+/// write to stderr, which OCR redirects while it runs: render errors are sent
+/// to the caller, and MuPDF's own messages go to the context's callbacks.
+pub struct Prerender {
+    receiver: Receiver<(usize, Result<Rendered, CrabError>)>,
+    /// Most recent page received, kept for the second half of a split spread.
+    current: Option<(usize, Rendered)>,
+}
+
+impl Prerender {
+    /// Start rendering `pages` (0-based, in processing order) from the document at `path`.
+    /// `dpi` is resolved per page exactly as the main loop does, and pages are
+    /// checked against `limits` before they are rendered. Failed renders are
+    /// retried `render_retries` times.
+    pub fn spawn(path: PathBuf, pages: Vec<usize>, dpi: Dpi, dpi_target: u32, limits: ResourceLimits, render_retries: u32) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_DEPTH);
+        thread::spawn(move || {
+            let mut renderer = match Renderer::new() {
+                Ok(renderer) => renderer,
+                Err(e) => {
+                    let _ = sender.send((pages.first().copied().unwrap_or(0), Err(e)));
+                    return;
+                }
+            };
+            renderer.set_render_retries(render_retries);
+            let mut doc = match renderer.open(&path) {
+                Ok(doc) => doc,
+                Err(e) => {
+                    let _ = sender.send((pages.first().copied().unwrap_or(0), Err(e)));
+                    return;
+                }
+            };
+            for page_idx in pages {
+                let started = Instant::now();
+                let image = limits
+                    .check(&renderer, &doc, page_idx as i32)
+                    .and_then(|_| page_dpi(&renderer, &doc, page_idx as i32, dpi, dpi_target))
+                    .and_then(|dpi| crate::app::render_gray(&renderer, &doc, page_idx as i32, dpi))
+                    .map(|image| (image, started.elapsed(), renderer.take_render_retries()));
+                let failed = image.is_err();
+                // The receiver is gone when the run stopped early (error or timeout)
+                if sender.send((page_idx, image)).is_err() || failed {
+                    break;
+                }
+            }
+            doc.drop_with(&renderer);
+        });
+        Self { receiver, current: None }
+    }
+
+    /// The rendering of `page_idx`, the time it took on the render thread and
+    /// the retries it needed. Pages must be requested in the order given to `spawn`.
+    pub fn take(&mut self, page_idx: usize) -> Result<Rendered, CrabError> {
+        loop {
+            if let Some((idx, rendered)) = &self.current {
+                if *idx == page_idx {
+                    return Ok(rendered.clone());
+                }
+            }
+            let (idx, rendered) = self
+                .receiver
+                .recv()
+                .map_err(|_| CrabError::Internal(format!("Render thread stopped before page {}", page_idx + 1)))?;
+            self.current = Some((idx, rendered?));
+        }
+    }
+}
+
+/// Rasterization DPI of a page, with `--dpi auto` derived from its size.
+pub fn page_dpi(renderer: &Renderer, doc: &crate::renderer::Document, page_idx: i32, dpi: Dpi, dpi_target: u32) -> Result<i32, CrabError> {
+    Ok(match dpi {
+        Dpi::Fixed(dpi) => dpi as i32,
+        Dpi::Auto => crate::cli::auto_dpi(renderer.page_size(doc, page_idx)?, dpi_target) as i32,
+    })
+}
@@ -0,0 +1,332 @@
+use crate::output::Layer;
+
+/// Whether a line is mostly non-alphanumeric noise (speckle misreads such as `~;:^"`).
+/// Whitespace is ignored; blank lines are never garbage.
+pub fn is_garbage(line: &str, min_alnum_ratio: f32) -> bool {
+    let mut total = 0;
+    let mut alnum = 0;
+    for c in line.chars().filter(|c| !c.is_whitespace()) {
+        total += 1;
+        if c.is_alphanumeric() {
+            alnum += 1;
+        }
+    }
+    total > 0 && (alnum as f32 / total as f32) < min_alnum_ratio
+}
+
+/// Remove garbage lines from an OCR layer, including its units.
+/// Returns the dropped lines so callers can report them.
+pub fn drop_garbage_lines(layer: &mut Layer, min_alnum_ratio: f32) -> Vec<String> {
+    let mut dropped = Vec::new();
+    layer.text = filter_lines(&layer.text, min_alnum_ratio, &mut dropped);
+
+    // Units hold the same text at a different granularity, so their drops are not reported again
+    let mut ignored = Vec::new();
+    for unit in &mut layer.units {
+        unit.text = filter_lines(&unit.text, min_alnum_ratio, &mut ignored);
+    }
+    layer.units.retain(|u| !u.text.trim().is_empty());
+
+    dropped
+}
+
+/// Below this, a text layer is considered garbled.
+pub const MIN_TEXT_LAYER_SIMILARITY: f32 = 0.5;
+
+/// Minimum number of OCR characters for a text layer comparison to be meaningful.
+const MIN_COMPARE_CHARS: usize = 20;
+
+/// Similarity (0-1) of the character distributions of a text layer and OCR of
+/// the same page. Text layers with broken encodings map glyphs to the wrong
+/// characters and score far lower than a correct layer, even with OCR errors.
+/// Returns `None` when OCR found too little text to judge.
+pub fn text_layer_similarity(text_layer: &str, ocr: &str) -> Option<f32> {
+    let histogram = |text: &str| {
+        let mut counts = std::collections::HashMap::new();
+        for c in text.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase) {
+            *counts.entry(c).or_insert(0.0f32) += 1.0;
+        }
+        counts
+    };
+    let a = histogram(text_layer);
+    let b = histogram(ocr);
+    if b.values().sum::<f32>() < MIN_COMPARE_CHARS as f32 {
+        return None;
+    }
+
+    let dot: f32 = a.iter().map(|(c, n)| n * b.get(c).unwrap_or(&0.0)).sum();
+    let norm = |h: &std::collections::HashMap<char, f32>| h.values().map(|n| n * n).sum::<f32>().sqrt();
+    let denom = norm(&a) * norm(&b);
+    Some(if denom == 0.0 { 0.0 } else { dot / denom })
+}
+
+/// Share of replacement or invalid characters above which a text layer is suspicious.
+const MAX_INVALID_CHAR_RATIO: f32 = 0.05;
+/// Share of implausible words above which a text layer is suspicious.
+const MAX_IMPLAUSIBLE_WORD_RATIO: f32 = 0.3;
+/// Fewer words than this are not enough to judge plausibility.
+const MIN_WORDS_TO_JUDGE: usize = 10;
+
+/// Cheap checks for a broken text layer, without OCR. Returns the reasons a
+/// layer looks suspicious, or nothing if it looks fine.
+pub fn suspicious_text_reasons(text: &str) -> Vec<String> {
+    let mut reasons = Vec::new();
+    let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if chars.is_empty() {
+        return reasons;
+    }
+    let ratio = |count: usize, total: usize| count as f32 / total as f32;
+
+    let replacement = chars.iter().filter(|&&c| c == char::REPLACEMENT_CHARACTER).count();
+    if ratio(replacement, chars.len()) > MAX_INVALID_CHAR_RATIO {
+        reasons.push("replacement characters".to_string());
+    }
+
+    let invalid = chars.iter().filter(|&&c| is_invalid_char(c)).count();
+    if ratio(invalid, chars.len()) > MAX_INVALID_CHAR_RATIO {
+        reasons.push("control or private-use characters".to_string());
+    }
+
+    // Only plain Latin words are judged; other scripts have other rules
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| w.len() >= 4 && w.chars().all(|c| c.is_ascii_alphabetic()))
+        .collect();
+    if words.len() >= MIN_WORDS_TO_JUDGE {
+        let implausible = words.iter().filter(|w| is_implausible_word(w)).count();
+        if ratio(implausible, words.len()) > MAX_IMPLAUSIBLE_WORD_RATIO {
+            reasons.push("implausible words".to_string());
+        }
+    }
+    reasons
+}
+
+fn is_invalid_char(c: char) -> bool {
+    (c.is_control() && c != '\t')
+        || ('\u{e000}'..='\u{f8ff}').contains(&c)
+        || ('\u{fdd0}'..='\u{fdef}').contains(&c)
+        || (c as u32 & 0xfffe) == 0xfffe
+}
+
+/// A Latin word no language would write: no vowel, a long consonant run,
+/// or case flipping inside the word ("xQzRt").
+fn is_implausible_word(word: &str) -> bool {
+    let is_vowel = |c: char| "aeiouyAEIOUY".contains(c);
+    let mut consonant_run = 0;
+    let mut max_run = 0;
+    for c in word.chars() {
+        consonant_run = if is_vowel(c) { 0 } else { consonant_run + 1 };
+        max_run = max_run.max(consonant_run);
+    }
+    // Lowercase after the first letter, then uppercase again, e.g. "aBcD"
+    let case_flips = word
+        .chars()
+        .skip(1)
+        .collect::<Vec<_>>()
+        .windows(2)
+        .filter(|w| w[0].is_lowercase() && w[1].is_uppercase())
+        .count();
+    let all_caps = word.chars().all(|c| c.is_uppercase());
+    max_run == word.len() && !all_caps || max_run >= 6 || case_flips >= 2
+}
+
+/// Language-specific normalization applied to OCR output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LangRule {
+    /// Fold long s (ſ) to s and repair ß misread as B inside words ("StraBe").
+    German,
+    /// Use a single no-break space before `; : ! ? »` and after `«`.
+    French,
+    /// Fold Arabic presentation forms to base letters.
+    Arabic,
+}
+
+/// Rules for each language in a Tesseract language string such as `eng+deu`.
+pub fn rules_for(lang: &str) -> Vec<LangRule> {
+    let mut rules = Vec::new();
+    for code in lang.split('+') {
+        let rule = match code.split('_').next().unwrap_or(code) {
+            "deu" | "frk" => LangRule::German,
+            "fra" => LangRule::French,
+            "ara" | "fas" | "urd" => LangRule::Arabic,
+            _ => continue,
+        };
+        if !rules.contains(&rule) {
+            rules.push(rule);
+        }
+    }
+    rules
+}
+
+/// Apply language rules to an OCR layer, including its units.
+pub fn normalize_layer(layer: &mut Layer, rules: &[LangRule]) {
+    if rules.is_empty() {
+        return;
+    }
+    layer.text = normalize(&layer.text, rules);
+    for unit in &mut layer.units {
+        unit.text = normalize(&unit.text, rules);
+    }
+}
+
+pub fn normalize(text: &str, rules: &[LangRule]) -> String {
+    let mut text = text.to_string();
+    for rule in rules {
+        text = match rule {
+            LangRule::German => normalize_german(&text),
+            LangRule::French => normalize_french(&text),
+            LangRule::Arabic => text.chars().flat_map(fold_arabic_form).collect(),
+        };
+    }
+    text
+}
+
+fn normalize_german(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let between_lowercase = i > 0
+            && chars[i - 1].is_lowercase()
+            && chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+        match c {
+            'ſ' => out.push('s'),
+            'B' if between_lowercase => out.push('ß'),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn normalize_french(text: &str) -> String {
+    const NBSP: char = '\u{a0}';
+    let is_space = |c: char| c == ' ' || c == NBSP || c == '\u{202f}';
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' | ':' | '!' | '?' | '»' if out.ends_with(is_space) => {
+                out.truncate(out.trim_end_matches(is_space).len());
+                out.push(NBSP);
+                out.push(c);
+            }
+            '«' => {
+                out.push(c);
+                let mut spaced = false;
+                while chars.next_if(|&n| is_space(n)).is_some() {
+                    spaced = true;
+                }
+                if spaced {
+                    out.push(NBSP);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Base letters of Arabic Presentation Forms-B (U+FE80 to U+FEFC), with the
+/// number of contextual forms each one has, in code point order.
+const ARABIC_FORMS: &[(&str, u32)] = &[
+    ("\u{621}", 1), ("\u{622}", 2), ("\u{623}", 2), ("\u{624}", 2), ("\u{625}", 2),
+    ("\u{626}", 4), ("\u{627}", 2), ("\u{628}", 4), ("\u{629}", 2), ("\u{62a}", 4),
+    ("\u{62b}", 4), ("\u{62c}", 4), ("\u{62d}", 4), ("\u{62e}", 4), ("\u{62f}", 2),
+    ("\u{630}", 2), ("\u{631}", 2), ("\u{632}", 2), ("\u{633}", 4), ("\u{634}", 4),
+    ("\u{635}", 4), ("\u{636}", 4), ("\u{637}", 4), ("\u{638}", 4), ("\u{639}", 4),
+    ("\u{63a}", 4), ("\u{641}", 4), ("\u{642}", 4), ("\u{643}", 4), ("\u{644}", 4),
+    ("\u{645}", 4), ("\u{646}", 4), ("\u{647}", 4), ("\u{648}", 2), ("\u{649}", 2),
+    ("\u{64a}", 4), ("\u{644}\u{622}", 2), ("\u{644}\u{623}", 2), ("\u{644}\u{625}", 2),
+    ("\u{644}\u{627}", 2),
+];
+
+fn fold_arabic_form(c: char) -> Vec<char> {
+    let mut code = 0xFE80;
+    for &(base, forms) in ARABIC_FORMS {
+        if (code..code + forms).contains(&(c as u32)) {
+            return base.chars().collect();
+        }
+        code += forms;
+    }
+    vec![c]
+}
+
+fn filter_lines(text: &str, min_alnum_ratio: f32, dropped: &mut Vec<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        if is_garbage(line, min_alnum_ratio) {
+            dropped.push(line.trim_end().to_string());
+        } else {
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::TextUnit;
+
+    #[test]
+    fn test_is_garbage() {
+        assert!(is_garbage("~;:^\" ,.", 0.5));
+        assert!(!is_garbage("Total: 42.00 EUR", 0.5));
+        assert!(!is_garbage("   ", 0.5));
+    }
+
+    #[test]
+    fn test_drop_garbage_lines() {
+        let mut layer = Layer {
+            text: "Invoice 42\n~;:^\"'\n\nPaid in full\n".to_string(),
+            units: vec![TextUnit::plain("Invoice 42"), TextUnit::plain("~;:^\"'")],
+        };
+        let dropped = drop_garbage_lines(&mut layer, 0.5);
+        assert_eq!(dropped, vec!["~;:^\"'"]);
+        assert_eq!(layer.text, "Invoice 42\n\nPaid in full\n");
+        assert_eq!(layer.units.len(), 1);
+    }
+
+    #[test]
+    fn test_text_layer_similarity() {
+        let ocr = "Invoice number 42, payable within thirty days.";
+        assert!(text_layer_similarity("Invoice number 42, payab1e within thirty days.", ocr).unwrap() > 0.9);
+        assert!(text_layer_similarity("\u{1}\u{2}\u{3}\u{4} \u{5}\u{6}\u{7}\u{8} 42", ocr).unwrap() < 0.5);
+        assert!(text_layer_similarity("", ocr).unwrap() == 0.0);
+        assert_eq!(text_layer_similarity("anything", "short"), None);
+    }
+
+    #[test]
+    fn test_suspicious_text_reasons() {
+        let clean = "The quarterly report shows revenue growth across all regions this year, \
+                     with strong results in Europe and steady performance elsewhere.";
+        assert!(suspicious_text_reasons(clean).is_empty());
+
+        let garbled = "Tkqw xzvRtq bcdfg hjKlMn qwrtz pqRsT vbnmk lkjhg xzcvb mnbvc sdfgh";
+        assert_eq!(suspicious_text_reasons(garbled), vec!["implausible words"]);
+
+        let unmapped = "\u{fffd}\u{fffd}\u{fffd} \u{e001}\u{e002} ok";
+        assert_eq!(
+            suspicious_text_reasons(unmapped),
+            vec!["replacement characters", "control or private-use characters"]
+        );
+    }
+
+    #[test]
+    fn test_rules_for() {
+        assert_eq!(rules_for("eng"), vec![]);
+        assert_eq!(rules_for("eng+deu_latf+fra"), vec![LangRule::German, LangRule::French]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Die StraBe iſt lang. BMW", &[LangRule::German]), "Die Straße ist lang. BMW");
+        assert_eq!(
+            normalize("Quoi ? « Oui » 12:30 !", &[LangRule::French]),
+            "Quoi\u{a0}? «\u{a0}Oui\u{a0}» 12:30\u{a0}!"
+        );
+        // Isolated beh, final lam-alef
+        assert_eq!(normalize("\u{fe8f}\u{fefc}", &[LangRule::Arabic]), "\u{628}\u{644}\u{627}");
+    }
+}
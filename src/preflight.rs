@@ -0,0 +1,105 @@
+use crate::cli::{Cli, Dpi, SpaceCheck};
+use crate::errors::CrabError;
+use crate::renderer::{Document, Renderer};
+use crate::warnings::{WarningKind, Warnings};
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Extra room kept on top of the estimate, as a share of it.
+const SPACE_MARGIN: f64 = 0.1;
+
+/// The directory itself if it exists, otherwise its nearest existing ancestor
+/// (where it will be created).
+fn existing_ancestor(dir: &Path) -> &Path {
+    // Relative paths end with an empty ancestor, which does not exist
+    dir.ancestors().find(|p| p.exists()).unwrap_or(Path::new("."))
+}
+
+/// A directory is usable if it can be written to, or if it can be created
+/// under its nearest existing ancestor.
+pub fn check_writable_dir(dir: &Path) -> Result<(), CrabError> {
+    let target = existing_ancestor(dir);
+    if !target.is_dir() {
+        return Err(CrabError::Cli(format!("{:?} is not a directory", target)));
+    }
+    tempfile::tempfile_in(target)
+        .map(|_| ())
+        .map_err(|e| CrabError::Cli(format!("Cannot write to {:?}: {}", target, e)))
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `dir`.
+pub fn free_space(dir: &Path) -> std::io::Result<u64> {
+    let c_path = CString::new(existing_ancestor(dir).as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+}
+
+/// Estimated size of the images written by `--dump-ocr-input` for `pages`:
+/// one byte per rendered pixel, once per preprocessing variant.
+/// Annotation images are small and not counted.
+pub fn estimate_dump_bytes(args: &Cli, renderer: &Renderer, doc: &Document, pages: &[usize]) -> Result<u64, CrabError> {
+    let images_per_page = if args.ensemble { 3 } else { 1 };
+    let mut total = 0u64;
+    for &page in pages {
+        let (width, height) = renderer.page_size(doc, page as i32)?;
+        let dpi = match args.dpi {
+            Dpi::Fixed(dpi) => dpi,
+            Dpi::Auto => crate::cli::auto_dpi((width, height), args.dpi_target),
+        };
+        let scale = dpi as f64 / 72.0;
+        let pixels = (width as f64 * scale).ceil() * (height as f64 * scale).ceil();
+        total += pixels as u64 * images_per_page;
+    }
+    Ok(total)
+}
+
+/// Compare an estimate against the free space of `dir`, failing or warning
+/// as configured when it does not fit.
+pub fn check_free_space(dir: &Path, needed: u64, check: SpaceCheck, warnings: &mut Warnings) -> Result<(), CrabError> {
+    if check == SpaceCheck::Off {
+        return Ok(());
+    }
+    let available = free_space(dir).map_err(|e| CrabError::Cli(format!("Cannot read free space of {:?}: {}", dir, e)))?;
+    let required = needed + (needed as f64 * SPACE_MARGIN) as u64;
+    if available >= required {
+        return Ok(());
+    }
+
+    let message = format!(
+        "{:?} needs about {} MiB but only {} MiB is free",
+        dir,
+        required / (1 << 20),
+        available / (1 << 20)
+    );
+    match check {
+        SpaceCheck::Fail => Err(CrabError::Cli(format!("{} (use --space-check warn to continue)", message))),
+        _ => {
+            warnings.push(WarningKind::LowDiskSpace, format!("{}.", message));
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_free_space_check() {
+        let dir = std::env::temp_dir();
+        assert!(free_space(&dir.join("not/created/yet")).unwrap() > 0);
+        let mut warnings = Warnings::default();
+        assert!(check_free_space(&dir, 0, SpaceCheck::Fail, &mut warnings).is_ok());
+        assert!(check_free_space(&dir, u64::MAX / 2, SpaceCheck::Fail, &mut warnings).is_err());
+        assert!(warnings.list().is_empty());
+        assert!(check_free_space(&dir, u64::MAX / 2, SpaceCheck::Warn, &mut warnings).is_ok());
+        assert_eq!(warnings.list()[0].kind, WarningKind::LowDiskSpace);
+    }
+}
@@ -0,0 +1,392 @@
+use crate::renderer::{Pixmap, Renderer};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// An 8-bit grayscale image owned by Rust, handed to Tesseract after preprocessing.
+#[derive(Clone)]
+pub struct GrayImage {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major pixels, one byte each, no padding.
+    pub data: Vec<u8>,
+}
+
+impl GrayImage {
+    /// Copy a rendered pixmap, dropping stride padding and converting to gray if needed.
+    pub fn from_pixmap(pix: &Pixmap, renderer: &Renderer) -> Self {
+        let width = pix.width(renderer).max(0) as usize;
+        let height = pix.height(renderer).max(0) as usize;
+        let stride = pix.stride(renderer).max(0) as usize;
+        let n = pix.n(renderer).max(1) as usize;
+        let samples = pix.samples(renderer);
+
+        let mut data = Vec::with_capacity(width * height);
+        for row in samples.chunks(stride).take(height) {
+            if n == 1 {
+                data.extend_from_slice(&row[..width]);
+            } else {
+                // Renders are grayscale, but average the color channels just in case
+                let colors = n.min(3);
+                data.extend(row.chunks(n).take(width).map(|px| {
+                    (px[..colors].iter().map(|&c| c as u32).sum::<u32>() / colors as u32) as u8
+                }));
+            }
+        }
+
+        Self {
+            width: width as u32,
+            height: height as u32,
+            data,
+        }
+    }
+
+    /// Write the image as binary PGM, which any image viewer can open.
+    ///
+    /// The image is written to `<path>.partial` and renamed once complete, so
+    /// an interrupted write never leaves a truncated file under the final name.
+    pub fn write_pgm(&self, path: &Path) -> io::Result<()> {
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let mut out = io::BufWriter::new(std::fs::File::create(&partial)?);
+        write!(out, "P5\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.data)?;
+        out.flush()?;
+        std::fs::rename(&partial, path)
+    }
+
+    /// Copy the region `[x0, y0, x1, y1)` in pixels, clamped to the image.
+    /// Paint the pixels of a component white.
+    pub fn erase(&mut self, component: &Component) {
+        for &i in &component.pixels {
+            self.data[i] = 255;
+        }
+    }
+
+    pub fn crop(&self, rect: [i32; 4]) -> Self {
+        let clamp_x = |v: i32| v.clamp(0, self.width as i32) as usize;
+        let clamp_y = |v: i32| v.clamp(0, self.height as i32) as usize;
+        let (x0, x1) = (clamp_x(rect[0]), clamp_x(rect[2]).max(clamp_x(rect[0])));
+        let (y0, y1) = (clamp_y(rect[1]), clamp_y(rect[3]).max(clamp_y(rect[1])));
+
+        let stride = self.width as usize;
+        let mut data = Vec::with_capacity((x1 - x0) * (y1 - y0));
+        for y in y0..y1 {
+            data.extend_from_slice(&self.data[y * stride + x0..y * stride + x1]);
+        }
+        Self {
+            width: (x1 - x0) as u32,
+            height: (y1 - y0) as u32,
+            data,
+        }
+    }
+
+    fn map(&self, f: impl Fn(u8) -> u8) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            data: self.data.iter().map(|&p| f(p)).collect(),
+        }
+    }
+}
+
+/// Global threshold that best separates ink from background (Otsu's method).
+pub fn otsu_threshold(image: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for &p in &image.data {
+        histogram[p as usize] += 1;
+    }
+
+    let total = image.data.len() as f64;
+    let sum_all: f64 = histogram.iter().enumerate().map(|(i, &c)| i as f64 * c as f64).sum();
+
+    let (mut sum_bg, mut weight_bg) = (0.0, 0.0);
+    let (mut best, mut best_variance) = (128u8, 0.0);
+    for (t, &count) in histogram.iter().enumerate() {
+        weight_bg += count as f64;
+        if weight_bg == 0.0 {
+            continue;
+        }
+        let weight_fg = total - weight_bg;
+        if weight_fg == 0.0 {
+            break;
+        }
+        sum_bg += t as f64 * count as f64;
+        let mean_bg = sum_bg / weight_bg;
+        let mean_fg = (sum_all - sum_bg) / weight_fg;
+        let variance = weight_bg * weight_fg * (mean_bg - mean_fg).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = t as u8;
+        }
+    }
+    best
+}
+
+/// Black and white image, split at the Otsu threshold.
+pub fn binarize(image: &GrayImage) -> GrayImage {
+    let threshold = otsu_threshold(image);
+    image.map(|p| if p > threshold { 255 } else { 0 })
+}
+
+/// Stretch the darkest and lightest 1% of pixels to the full 0-255 range,
+/// which helps faded or low-contrast scans.
+pub fn stretch_contrast(image: &GrayImage) -> GrayImage {
+    let mut histogram = [0usize; 256];
+    for &p in &image.data {
+        histogram[p as usize] += 1;
+    }
+
+    let cutoff = image.data.len() / 100;
+    let percentile = |iter: &mut dyn Iterator<Item = usize>| {
+        let mut seen = 0;
+        for i in iter {
+            seen += histogram[i];
+            if seen > cutoff {
+                return i;
+            }
+        }
+        0
+    };
+    let low = percentile(&mut (0..256));
+    let high = percentile(&mut (0..256).rev());
+    if high <= low {
+        return image.clone();
+    }
+
+    let range = (high - low) as f32;
+    image.map(|p| (((p as f32 - low as f32) / range).clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// Punch hole diameter range, as a share of the shorter image side.
+const HOLE_SIZE: (f32, f32) = (0.015, 0.05);
+/// Punch holes sit within this share of the width or height from an edge.
+const HOLE_EDGE_DISTANCE: f32 = 0.12;
+
+/// A 4-connected group of dark pixels.
+pub struct Component {
+    /// Indices into the image data.
+    pub pixels: Vec<usize>,
+    /// Inclusive pixel bounds (x0, y0, x1, y1).
+    pub bbox: [usize; 4],
+}
+
+/// Group the dark pixels of an image (by Otsu threshold) into connected components.
+fn dark_components(image: &GrayImage) -> Vec<Component> {
+    let (width, height) = (image.width as usize, image.height as usize);
+    let threshold = otsu_threshold(image);
+    let mut visited = vec![false; image.data.len()];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+
+    for start in 0..image.data.len() {
+        if visited[start] || image.data[start] > threshold {
+            continue;
+        }
+        // Flood fill one dark component
+        visited[start] = true;
+        stack.push(start);
+        let mut pixels = Vec::new();
+        let (mut x0, mut y0, mut x1, mut y1) = (usize::MAX, usize::MAX, 0, 0);
+        while let Some(i) = stack.pop() {
+            pixels.push(i);
+            let (x, y) = (i % width, i / width);
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+            let neighbors = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+            for n in neighbors.into_iter().flatten() {
+                if !visited[n] && image.data[n] <= threshold {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+        components.push(Component {
+            pixels,
+            bbox: [x0, y0, x1, y1],
+        });
+    }
+    components
+}
+
+/// Remove scanner artifacts that OCR reads as garbage: dark regions touching
+/// the image edge (black borders, edge noise) and punch holes near an edge.
+///
+/// Dark pixels (by Otsu threshold) are grouped into 4-connected components;
+/// offending components are painted white.
+pub fn clean_borders(image: &GrayImage) -> GrayImage {
+    let (width, height) = (image.width as usize, image.height as usize);
+    let mut out = image.clone();
+    for component in dark_components(image) {
+        let [x0, y0, x1, y1] = component.bbox;
+        let touches_edge = x0 == 0 || y0 == 0 || x1 + 1 == width || y1 + 1 == height;
+        if touches_edge || is_punch_hole(component.bbox, component.pixels.len(), width, height) {
+            out.erase(&component);
+        }
+    }
+    out
+}
+
+/// Size range of a stamp or signature along its longer side, in inches.
+const STAMP_SIZE_IN: (f32, f32) = (0.6, 3.5);
+/// Minimum size along the shorter side, in inches, which rules out rules and underlines.
+const STAMP_MIN_THICKNESS_IN: f32 = 0.25;
+/// Stamps and signatures are drawn with strokes, so ink covers little of their bounds.
+const STAMP_MAX_DENSITY: f32 = 0.35;
+/// Share of ink along the bounds above which a component is a box or table frame.
+const FRAME_EDGE_SHARE: f32 = 0.6;
+
+/// Find large stroked graphics (round stamps, signatures) that Tesseract
+/// would otherwise read as characters.
+///
+/// Candidates are connected components far larger than glyphs at `dpi`,
+/// sparse enough to be strokes rather than solid fills, and not rectangular
+/// frames such as form fields or table borders.
+pub fn stamp_regions(image: &GrayImage, dpi: i32) -> Vec<Component> {
+    let dpi = dpi.max(1) as f32;
+    dark_components(image)
+        .into_iter()
+        .filter(|c| {
+            let [x0, y0, x1, y1] = c.bbox;
+            let (w, h) = ((x1 - x0 + 1) as f32, (y1 - y0 + 1) as f32);
+            let sized = (STAMP_SIZE_IN.0..=STAMP_SIZE_IN.1).contains(&(w.max(h) / dpi))
+                && w.min(h) / dpi >= STAMP_MIN_THICKNESS_IN;
+            if !sized || c.pixels.len() as f32 / (w * h) > STAMP_MAX_DENSITY {
+                return false;
+            }
+            // Ink within a stroke's width of the bounds
+            let band = (dpi / 50.0).ceil() as usize;
+            let width = image.width as usize;
+            let on_edge = c
+                .pixels
+                .iter()
+                .filter(|&&i| {
+                    let (x, y) = (i % width, i / width);
+                    x < x0 + band || x + band > x1 || y < y0 + band || y + band > y1
+                })
+                .count();
+            (on_edge as f32 / c.pixels.len() as f32) < FRAME_EDGE_SHARE
+        })
+        .collect()
+}
+
+/// A filled, roughly round blob of punch hole size close to an edge.
+fn is_punch_hole(bbox: [usize; 4], area: usize, width: usize, height: usize) -> bool {
+    let (w, h) = ((bbox[2] - bbox[0] + 1) as f32, (bbox[3] - bbox[1] + 1) as f32);
+    let short_side = width.min(height) as f32;
+    let round = (0.75..=1.33).contains(&(w / h)) && (0.6..=0.95).contains(&(area as f32 / (w * h)));
+    let sized = (HOLE_SIZE.0..=HOLE_SIZE.1).contains(&(w.max(h) / short_side));
+
+    let (cx, cy) = ((bbox[0] + bbox[2]) as f32 / 2.0, (bbox[1] + bbox[3]) as f32 / 2.0);
+    let (mx, my) = (width as f32 * HOLE_EDGE_DISTANCE, height as f32 * HOLE_EDGE_DISTANCE);
+    let near_edge = cx < mx || cx > width as f32 - mx || cy < my || cy > height as f32 - my;
+
+    round && sized && near_edge
+}
+
+/// Alternative renditions of a page for ensemble OCR (the original is not included).
+pub fn ensemble_variants(image: &GrayImage) -> Vec<GrayImage> {
+    vec![binarize(image), stretch_contrast(image)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(data: Vec<u8>) -> GrayImage {
+        GrayImage {
+            width: data.len() as u32,
+            height: 1,
+            data,
+        }
+    }
+
+    #[test]
+    fn test_binarize() {
+        let img = image(vec![10, 20, 30, 200, 210, 220]);
+        let t = otsu_threshold(&img);
+        assert!((30..200).contains(&t));
+        assert_eq!(binarize(&img).data, vec![0, 0, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_crop() {
+        let img = GrayImage {
+            width: 3,
+            height: 2,
+            data: vec![1, 2, 3, 4, 5, 6],
+        };
+        let out = img.crop([1, 0, 5, 1]);
+        assert_eq!((out.width, out.height), (2, 1));
+        assert_eq!(out.data, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_clean_borders() {
+        let (width, height) = (400usize, 400usize);
+        let mut data = vec![255u8; width * height];
+        let mut fill = |x0: usize, y0: usize, x1: usize, y1: usize, round: bool| {
+            let (cx, cy, r) = ((x0 + x1) as f32 / 2.0, (y0 + y1) as f32 / 2.0, (x1 - x0) as f32 / 2.0);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    if !round || ((x as f32 - cx).powi(2) + (y as f32 - cy).powi(2)).sqrt() <= r {
+                        data[y * width + x] = 0;
+                    }
+                }
+            }
+        };
+        fill(0, 0, 8, 400, false); // Black scanner edge
+        fill(20, 190, 32, 202, true); // Punch hole near the left edge
+        fill(150, 190, 250, 194, false); // A text stroke
+        let image = GrayImage {
+            width: width as u32,
+            height: height as u32,
+            data,
+        };
+
+        let out = clean_borders(&image);
+        assert_eq!(out.data[200 * width + 2], 255);
+        assert_eq!(out.data[196 * width + 26], 255);
+        assert_eq!(out.data[192 * width + 200], 0);
+    }
+
+    #[test]
+    fn test_stamp_regions() {
+        // 300 x 300 at 100 DPI: a 1.5 in ring stamp, a 1 in box frame and a glyph-sized dot
+        let (width, height) = (300usize, 300usize);
+        let mut data = vec![255u8; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let r = ((x as f32 - 100.0).powi(2) + (y as f32 - 100.0).powi(2)).sqrt();
+                let ring = (70.0..=74.0).contains(&r);
+                let frame = (180..=280).contains(&x)
+                    && (200..=280).contains(&y)
+                    && (x <= 182 || x >= 278 || y <= 202 || y >= 278);
+                let dot = (250..=255).contains(&x) && (20..=25).contains(&y);
+                if ring || frame || dot {
+                    data[y * width + x] = 0;
+                }
+            }
+        }
+        let image = GrayImage {
+            width: width as u32,
+            height: height as u32,
+            data,
+        };
+
+        let stamps = stamp_regions(&image, 100);
+        assert_eq!(stamps.len(), 1);
+        assert_eq!(stamps[0].bbox, [26, 26, 174, 174]);
+    }
+
+    #[test]
+    fn test_stretch_contrast() {
+        let img = image(vec![100, 120, 140, 160]);
+        let out = stretch_contrast(&img);
+        assert_eq!(out.data[0], 0);
+        assert_eq!(out.data[3], 255);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::errors::CrabError;
+use crate::preprocess::GrayImage;
+use crate::renderer::TextLine;
+use serde_json::Value;
+use std::path::Path;
+
+/// Replaces redacted text-layer lines.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A region to hide before extraction and OCR.
+pub struct Redaction {
+    /// 1-based page, or every page if unset.
+    pub page: Option<usize>,
+    /// Bounds in points (x0, y0, x1, y1), in page space like `--page-info`.
+    pub rect: [f32; 4],
+}
+
+/// Load a redaction list: a JSON array of `{"page": 3, "rect": [x0, y0, x1, y1]}`,
+/// where `page` may be omitted to redact the region on every page.
+pub fn load(path: &Path) -> Result<Vec<Redaction>, CrabError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| CrabError::Cli(format!("Cannot read redaction file {:?}: {}", path, e)))?;
+    parse(&content).map_err(|e| CrabError::Cli(format!("Invalid redaction file {:?}: {}", path, e)))
+}
+
+fn parse(content: &str) -> Result<Vec<Redaction>, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let entries = value.as_array().ok_or("expected an array of regions")?;
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let page = match entry.get("page") {
+                None => None,
+                Some(p) => Some(p.as_u64().filter(|&p| p >= 1).ok_or(format!("region {}: page must be >= 1", i))? as usize),
+            };
+            let rect: Vec<f32> = entry
+                .get("rect")
+                .and_then(Value::as_array)
+                .map(|a| a.iter().filter_map(Value::as_f64).map(|v| v as f32).collect())
+                .unwrap_or_default();
+            let rect: [f32; 4] = rect
+                .try_into()
+                .map_err(|_| format!("region {}: rect must be 4 numbers", i))?;
+            Ok(Redaction { page, rect })
+        })
+        .collect()
+}
+
+/// Regions applying to a 1-based page.
+pub fn for_page(redactions: &[Redaction], page: usize) -> Vec<[f32; 4]> {
+    redactions
+        .iter()
+        .filter(|r| r.page.is_none_or(|p| p == page))
+        .map(|r| r.rect)
+        .collect()
+}
+
+/// Paint regions black on a page rendered at `dpi`.
+pub fn apply_to_image(image: &mut GrayImage, rects: &[[f32; 4]], dpi: i32) {
+    let scale = dpi as f32 / 72.0;
+    let width = image.width as usize;
+    for rect in rects {
+        // Round outwards so partially covered pixels are painted too
+        let to_px = |v: f32, max: u32| (v.max(0.0) as usize).min(max as usize);
+        let (x0, x1) = (to_px((rect[0] * scale).floor(), image.width), to_px((rect[2] * scale).ceil(), image.width));
+        let (y0, y1) = (to_px((rect[1] * scale).floor(), image.height), to_px((rect[3] * scale).ceil(), image.height));
+        for y in y0..y1 {
+            image.data[y * width + x0..y * width + x1.max(x0)].fill(0);
+        }
+    }
+}
+
+/// Rebuild page text from its lines, replacing every line that touches a
+/// region. Whole lines are hidden, so nothing next to a region leaks.
+pub fn redact_lines(lines: &[TextLine], rects: &[[f32; 4]]) -> String {
+    let mut text = String::new();
+    let mut prev_block = None;
+    for line in lines {
+        if prev_block.is_some_and(|b| b != line.block) {
+            text.push('\n'); // Blank line between blocks
+        }
+        prev_block = Some(line.block);
+        if rects.iter().any(|r| intersects(*r, line.rect)) {
+            text.push_str(REDACTED);
+        } else {
+            text.push_str(&line.text);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Whether two rectangles overlap.
+pub fn intersects(a: [f32; 4], b: [f32; 4]) -> bool {
+    a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, y: f32, block: usize) -> TextLine {
+        TextLine {
+            rect: [72.0, y, 300.0, y + 12.0],
+            text: text.to_string(),
+            block,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_and_for_page() {
+        let regions = parse(r#"[{"page": 2, "rect": [0, 0, 10, 10]}, {"rect": [5, 5, 6, 6]}]"#).unwrap();
+        assert_eq!(for_page(&regions, 1).len(), 1);
+        assert_eq!(for_page(&regions, 2).len(), 2);
+        assert!(parse(r#"[{"page": 0, "rect": [0, 0, 1, 1]}]"#).is_err());
+        assert!(parse(r#"[{"rect": [0, 0, 1]}]"#).is_err());
+    }
+
+    #[test]
+    fn test_redact_lines() {
+        let lines = vec![line("Name: Jane Doe", 100.0, 0), line("SSN: 123-45-6789", 114.0, 0), line("Total", 200.0, 1)];
+        let text = redact_lines(&lines, &[[100.0, 115.0, 200.0, 120.0]]);
+        assert_eq!(text, "Name: Jane Doe\n[REDACTED]\n\nTotal\n");
+    }
+
+    #[test]
+    fn test_apply_to_image() {
+        let mut image = GrayImage {
+            width: 4,
+            height: 2,
+            data: vec![255; 8],
+        };
+        // At 144 DPI a point is two pixels
+        apply_to_image(&mut image, &[[0.5, 0.0, 1.5, 0.5]], 144);
+        assert_eq!(image.data, vec![255, 0, 0, 255, 255, 255, 255, 255]);
+    }
+}
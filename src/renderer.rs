@@ -1,7 +1,13 @@
+use crate::encryption;
 use crate::errors::CrabError;
+use crate::preprocess::GrayImage;
+use std::cell::Cell;
 use std::ffi::CString;
+use std::io::Write;
+use std::os::raw::{c_char, c_int, c_void};
 use std::path::Path;
 use std::ptr;
+use std::time::Duration;
 
 // Include generated bindings
 #[allow(non_upper_case_globals)]
@@ -13,8 +19,15 @@ mod sys {
 }
 use sys::*;
 
+/// Wait before the first retry of a failed render, doubled for each next one.
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
 pub struct Renderer {
     ctx: *mut fz_context,
+    /// Times a failed page render is tried again (`--render-retries`).
+    render_retries: u32,
+    /// Retries made since the last `take_render_retries`.
+    retried: Cell<u32>,
 }
 
 pub struct Document {
@@ -28,7 +41,7 @@ impl Renderer {
             if ctx.is_null() {
                 return Err(CrabError::Internal("Failed to create MuPDF context".into()));
             }
-            Ok(Self { ctx })
+            Ok(Self { ctx, render_retries: 0, retried: Cell::new(0) })
         }
     }
 
@@ -39,10 +52,14 @@ impl Renderer {
         unsafe {
             let mut doc: *mut fz_document = ptr::null_mut();
             let mut err_buf = [0i8; 256];
-            let ret = my_open_document(self.ctx, c_path.as_ptr(), &mut doc, err_buf.as_mut_ptr(), err_buf.len());
+            let mut enc: my_encryption = std::mem::zeroed();
+            let ret = my_open_document(self.ctx, c_path.as_ptr(), &mut doc, &mut enc, err_buf.as_mut_ptr(), err_buf.len());
             
             if ret != 0 {
                 let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                if let Some(e) = encryption::open_error(path, read_encryption(&enc), ret == 2, &err_msg) {
+                    return Err(e);
+                }
                 return Err(CrabError::Pdf(format!("Failed to open document: {}", err_msg)));
             }
             
@@ -50,6 +67,30 @@ impl Renderer {
         }
     }
     
+    /// Open a file as a bare PDF, for reading document-level objects (XFA,
+    /// form fields) only: no document handlers are registered and no pages are loaded.
+    pub fn open_pdf(&self, path: &Path) -> Result<Document, CrabError> {
+        let path_str = path.to_str().ok_or_else(|| CrabError::Input(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid path encoding")))?;
+        let c_path = CString::new(path_str).map_err(|_| CrabError::Input(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Null byte in path")))?;
+
+        unsafe {
+            let mut doc: *mut fz_document = ptr::null_mut();
+            let mut err_buf = [0i8; 256];
+            let mut enc: my_encryption = std::mem::zeroed();
+            let ret = my_open_pdf(self.ctx, c_path.as_ptr(), &mut doc, &mut enc, err_buf.as_mut_ptr(), err_buf.len());
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                if let Some(e) = encryption::open_error(path, read_encryption(&enc), ret == 2, &err_msg) {
+                    return Err(e);
+                }
+                return Err(CrabError::Pdf(format!("Failed to open PDF: {}", err_msg)));
+            }
+
+            Ok(Document { doc })
+        }
+    }
+
     pub fn page_count(&self, doc: &Document) -> Result<i32, CrabError> {
         unsafe {
             let mut count = 0;
@@ -64,21 +105,228 @@ impl Renderer {
         }
     }
 
+    /// Page size in points (width, height), as rendered.
+    pub fn page_size(&self, doc: &Document, page_number: i32) -> Result<(f32, f32), CrabError> {
+        unsafe {
+            let (mut width, mut height) = (0f32, 0f32);
+            let mut err_buf = [0i8; 256];
+            let ret = my_page_size(self.ctx, doc.doc, page_number, &mut width, &mut height, err_buf.as_mut_ptr(), err_buf.len());
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to measure page {}: {}", page_number, err_msg)));
+            }
+            Ok((width, height))
+        }
+    }
+
+    /// Declared `/Rotate` of a page in degrees (0, 90, 180 or 270).
+    pub fn page_rotation(&self, doc: &Document, page_number: i32) -> Result<i32, CrabError> {
+        unsafe {
+            let mut rotate = 0;
+            let mut err_buf = [0i8; 256];
+            let ret = my_page_rotation(self.ctx, doc.doc, page_number, &mut rotate, err_buf.as_mut_ptr(), err_buf.len());
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to read rotation of page {}: {}", page_number, err_msg)));
+            }
+            Ok(rotate)
+        }
+    }
+
+    /// MediaBox, CropBox and TrimBox of a page in points.
+    pub fn page_boxes(&self, doc: &Document, page_number: i32) -> Result<PageBoxes, CrabError> {
+        unsafe {
+            let mut boxes = [0f32; 12];
+            let mut err_buf = [0i8; 256];
+            let ret = my_page_boxes(self.ctx, doc.doc, page_number, boxes.as_mut_ptr(), err_buf.as_mut_ptr(), err_buf.len());
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to read boxes of page {}: {}", page_number, err_msg)));
+            }
+            let rect = |i: usize| [boxes[i * 4], boxes[i * 4 + 1], boxes[i * 4 + 2], boxes[i * 4 + 3]];
+            Ok(PageBoxes {
+                media: rect(0),
+                crop: rect(1),
+                trim: rect(2),
+            })
+        }
+    }
+
+    /// Try failed page renders `retries` more times (`--render-retries`),
+    /// waiting longer before each and emptying MuPDF's cache first.
+    pub fn set_render_retries(&mut self, retries: u32) {
+        self.render_retries = retries;
+    }
+
+    /// Retries page renders needed since the last call, and reset the count.
+    pub fn take_render_retries(&self) -> u32 {
+        self.retried.replace(0)
+    }
+
     pub fn render_page(&self, doc: &Document, page_number: i32, dpi: i32) -> Result<Pixmap, CrabError> {
+        self.with_retries(page_number, |pix, err_buf| unsafe {
+            my_render_page(self.ctx, doc.doc, page_number, dpi, pix, err_buf.as_mut_ptr(), err_buf.len())
+        })
+    }
+
+
+    /// Render a page in RGB, for color analysis.
+    pub fn render_page_rgb(&self, doc: &Document, page_number: i32, dpi: i32) -> Result<Pixmap, CrabError> {
+        self.with_retries(page_number, |pix, err_buf| unsafe {
+            my_render_page_rgb(self.ctx, doc.doc, page_number, dpi, pix, err_buf.as_mut_ptr(), err_buf.len())
+        })
+    }
+
+    /// Run a render until it succeeds or the retries run out, reporting the last failure.
+    fn with_retries(&self, page_number: i32, render: impl Fn(&mut *mut fz_pixmap, &mut [i8; 256]) -> c_int) -> Result<Pixmap, CrabError> {
+        let mut attempt = 0;
+        loop {
+            let mut pix: *mut fz_pixmap = ptr::null_mut();
+            let mut err_buf = [0i8; 256];
+            if render(&mut pix, &mut err_buf) == 0 {
+                return Ok(Pixmap { pix });
+            }
+            if attempt == self.render_retries {
+                let err_msg = unsafe { std::ffi::CStr::from_ptr(err_buf.as_ptr()) }.to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to render page {}: {}", page_number, err_msg)));
+            }
+            std::thread::sleep(RETRY_BACKOFF * 2u32.pow(attempt));
+            // Failures under memory pressure may pass once cached resources are freed
+            unsafe { my_empty_store(self.ctx) };
+            attempt += 1;
+            self.retried.set(self.retried.get() + 1);
+        }
+    }
+
+    /// Save a grayscale image to `path` as PNG, or as JPEG of `jpeg_quality`
+    /// (1-100) when given, recording `dpi` in the file.
+    pub fn save_gray(&self, image: &GrayImage, dpi: i32, path: &Path, jpeg_quality: Option<i32>) -> Result<(), CrabError> {
+        let path_str = path.to_str().ok_or_else(|| CrabError::Input(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid path encoding")))?;
+        let c_path = CString::new(path_str).map_err(|_| CrabError::Input(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Null byte in path")))?;
+
+        unsafe {
+            let mut err_buf = [0i8; 256];
+            let ret = my_save_gray(
+                self.ctx,
+                image.data.as_ptr(),
+                image.width as i32,
+                image.height as i32,
+                dpi,
+                c_path.as_ptr(),
+                jpeg_quality.unwrap_or(0),
+                err_buf.as_mut_ptr(),
+                err_buf.len(),
+            );
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Output(std::io::Error::other(format!("Failed to write {:?}: {}", path, err_msg))));
+            }
+            Ok(())
+        }
+    }
+
+    /// Number of annotations on a page (0 for non-PDF documents).
+    pub fn annotation_count(&self, doc: &Document, page_number: i32) -> Result<i32, CrabError> {
+        unsafe {
+            let mut count = 0;
+            let mut err_buf = [0i8; 256];
+            let ret = my_count_annots(self.ctx, doc.doc, page_number, &mut count, err_buf.as_mut_ptr(), err_buf.len());
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to list annotations on page {}: {}", page_number, err_msg)));
+            }
+            Ok(count)
+        }
+    }
+
+    /// Form nesting, images and content size of a page, read without running
+    /// it. Forms nested deeper than `max_depth` are not walked and content is
+    /// read no further than `max_content` bytes past what was counted.
+    pub fn page_resources(&self, doc: &Document, page_number: i32, max_depth: Option<u32>, max_content: Option<u64>) -> Result<PageResources, CrabError> {
+        unsafe {
+            let mut depth = 0;
+            let mut images = 0;
+            let mut content = 0usize;
+            let mut err_buf = [0i8; 256];
+            let ret = my_page_resources(
+                self.ctx,
+                doc.doc,
+                page_number,
+                max_depth.map_or(0, |d| d.min(i32::MAX as u32) as i32),
+                max_content.map_or(0, |c| c.min(usize::MAX as u64) as usize),
+                &mut depth,
+                &mut images,
+                &mut content,
+                err_buf.as_mut_ptr(),
+                err_buf.len(),
+            );
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to read the resources of page {}: {}", page_number, err_msg)));
+            }
+            Ok(PageResources { depth: depth as u32, images: images as u32, content_bytes: content as u64 })
+        }
+    }
+
+    /// Filled paths drawn by a page and their curve segments, to spot text converted to outlines.
+    pub fn vector_stats(&self, doc: &Document, page_number: i32) -> Result<VectorStats, CrabError> {
+        unsafe {
+            let mut stats = VectorStats::default();
+            let mut err_buf = [0i8; 256];
+            let ret = my_vector_stats(
+                self.ctx,
+                doc.doc,
+                page_number,
+                &mut stats.paths,
+                &mut stats.curves,
+                err_buf.as_mut_ptr(),
+                err_buf.len(),
+            );
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to read the drawing of page {}: {}", page_number, err_msg)));
+            }
+            Ok(stats)
+        }
+    }
+
+    /// Render the appearance stream of a single annotation.
+    pub fn render_annotation(&self, doc: &Document, page_number: i32, index: i32, dpi: i32) -> Result<Annotation, CrabError> {
         unsafe {
             let mut pix: *mut fz_pixmap = ptr::null_mut();
+            let mut type_buf = [0i8; 64];
+            let mut rect = [0f32; 4];
             let mut err_buf = [0i8; 256];
-            let ret = my_render_page(self.ctx, doc.doc, page_number, dpi, &mut pix, err_buf.as_mut_ptr(), err_buf.len());
+            let ret = my_render_annot(
+                self.ctx,
+                doc.doc,
+                page_number,
+                index,
+                dpi,
+                &mut pix,
+                type_buf.as_mut_ptr(),
+                type_buf.len(),
+                rect.as_mut_ptr(),
+                err_buf.as_mut_ptr(),
+                err_buf.len(),
+            );
 
             if ret != 0 {
                 let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
-                return Err(CrabError::Pdf(format!("Failed to render page {}: {}", page_number, err_msg)));
+                return Err(CrabError::Pdf(format!("Failed to render annotation {} on page {}: {}", index, page_number, err_msg)));
             }
 
-            Ok(Pixmap { pix })
+            let kind = std::ffi::CStr::from_ptr(type_buf.as_ptr()).to_string_lossy().into_owned();
+            Ok(Annotation { kind, rect, pixmap: Pixmap { pix } })
         }
     }
-    
+
     /// Extract XFA XML data from the document if present.
     /// Returns None if no XFA data exists.
     pub fn extract_xfa(&self, doc: &Document) -> Option<String> {
@@ -111,30 +359,398 @@ impl Renderer {
 
     /// Extract structured text from a page.
     pub fn extract_text(&self, doc: &Document, page_number: i32) -> Result<String, CrabError> {
+        let mut buf = Vec::new();
+        self.extract_text_to(doc, page_number, &mut buf)?;
+        Ok(match String::from_utf8(buf) {
+            Ok(text) => text,
+            Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        })
+    }
+
+    /// Write the text of a page to `out` in chunks as MuPDF prints it, so the
+    /// text of enormous pages is never held whole (`--stream-text`). Failed
+    /// writes stop extraction and are returned as `CrabError::Output`.
+    pub fn extract_text_to(&self, doc: &Document, page_number: i32, out: &mut dyn Write) -> Result<(), CrabError> {
+        struct Sink<'a> {
+            out: &'a mut dyn Write,
+            error: Option<std::io::Error>,
+        }
+
+        unsafe extern "C" fn write(opaque: *mut c_void, data: *const c_char, len: usize) -> c_int {
+            let sink = &mut *(opaque as *mut Sink);
+            match sink.out.write_all(std::slice::from_raw_parts(data as *const u8, len)) {
+                Ok(()) => 0,
+                Err(e) => {
+                    sink.error = Some(e);
+                    1
+                }
+            }
+        }
+
+        let mut sink = Sink { out, error: None };
+        let mut err_buf = [0i8; 256];
+        let status = unsafe {
+            my_extract_text_to(
+                self.ctx,
+                doc.doc,
+                page_number,
+                Some(write),
+                &mut sink as *mut Sink as *mut c_void,
+                err_buf.as_mut_ptr(),
+                err_buf.len(),
+            )
+        };
+        if let Some(e) = sink.error {
+            return Err(CrabError::Output(e));
+        }
+        if status != 0 {
+            let err_msg = unsafe { std::ffi::CStr::from_ptr(err_buf.as_ptr()) }.to_string_lossy().into_owned();
+            return Err(CrabError::Pdf(format!("Failed to extract text from page {}: {}", page_number, err_msg)));
+        }
+        Ok(())
+    }
+
+    /// Extract the text lines of a page with their bounds.
+    pub fn extract_lines(&self, doc: &Document, page_number: i32) -> Result<Vec<TextLine>, CrabError> {
+        let raw = unsafe {
+            let mut err_buf = [0i8; 256];
+            let text_ptr = my_extract_lines(self.ctx, doc.doc, page_number, err_buf.as_mut_ptr(), err_buf.len());
+
+            if text_ptr.is_null() {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to extract lines from page {}: {}", page_number, err_msg)));
+            }
+
+            let text = std::ffi::CStr::from_ptr(text_ptr).to_string_lossy().into_owned();
+            my_free_text(self.ctx, text_ptr);
+            text
+        };
+
+        // Blocks are separated by a blank line
+        let mut lines = Vec::new();
+        for (block, chunk) in raw.split("\n\n").enumerate() {
+            for line in chunk.lines() {
+                let mut fields = line.splitn(4, '\t');
+                let (Some(bounds), Some(size), Some(bold), Some(text)) = (fields.next(), fields.next(), fields.next(), fields.next()) else {
+                    continue;
+                };
+                let coords: Vec<f32> = bounds.split(' ').filter_map(|v| v.parse().ok()).collect();
+                if let Ok(rect) = coords.try_into() {
+                    lines.push(TextLine {
+                        rect,
+                        text: text.to_string(),
+                        block,
+                        size: size.parse().unwrap_or(0.0),
+                        bold: bold == "1",
+                    });
+                }
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Text blocks of a page with their lines and spans (runs of glyphs in the
+    /// same font and size), each with its bounds in points.
+    pub fn extract_text_structured(&self, doc: &Document, page_number: i32) -> Result<Vec<TextBlock>, CrabError> {
+        let raw = unsafe {
+            let mut err_buf = [0i8; 256];
+            let text_ptr = my_extract_structured(self.ctx, doc.doc, page_number, err_buf.as_mut_ptr(), err_buf.len());
+
+            if text_ptr.is_null() {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to extract text structure from page {}: {}", page_number, err_msg)));
+            }
+
+            let text = std::ffi::CStr::from_ptr(text_ptr).to_string_lossy().into_owned();
+            my_free_text(self.ctx, text_ptr);
+            text
+        };
+        Ok(parse_structured(&raw))
+    }
+
+    /// Signature fields of the AcroForm (XFA signatures are stored there too),
+    /// with their signed status and the signer details recorded in the signature.
+    pub fn signature_fields(&self, doc: &Document) -> Result<Vec<SignatureField>, CrabError> {
+        let raw = unsafe {
+            let mut err_buf = [0i8; 256];
+            let text_ptr = my_signature_fields(self.ctx, doc.doc, err_buf.as_mut_ptr(), err_buf.len());
+
+            if text_ptr.is_null() {
+                if err_buf[0] != 0 {
+                    let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                    return Err(CrabError::Pdf(format!("Failed to read signature fields: {}", err_msg)));
+                }
+                return Ok(Vec::new());
+            }
+
+            let text = std::ffi::CStr::from_ptr(text_ptr).to_string_lossy().into_owned();
+            my_free_text(self.ctx, text_ptr);
+            text
+        };
+        Ok(raw.lines().filter_map(parse_signature_line).collect())
+    }
+
+    /// Scripts, launch actions, embedded files and external references of a PDF.
+    /// Other documents have none.
+    pub fn active_content(&self, doc: &Document) -> Result<ActiveContent, CrabError> {
+        let raw = unsafe {
+            let mut err_buf = [0i8; 256];
+            let text_ptr = my_active_content(self.ctx, doc.doc, err_buf.as_mut_ptr(), err_buf.len());
+
+            if text_ptr.is_null() {
+                if err_buf[0] != 0 {
+                    let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                    return Err(CrabError::Pdf(format!("Failed to scan for active content: {}", err_msg)));
+                }
+                return Ok(ActiveContent::default());
+            }
+
+            let text = std::ffi::CStr::from_ptr(text_ptr).to_string_lossy().into_owned();
+            my_free_text(self.ctx, text_ptr);
+            text
+        };
+        Ok(parse_active_content(&raw))
+    }
+
+    /// Start a new PDF whose text layers use `font_program`, a glyphless TrueType font.
+    pub fn new_pdf(&self, font_program: &[u8]) -> Result<PdfWriter, CrabError> {
+        unsafe {
+            let mut err_buf = [0i8; 256];
+            let pdf = my_new_pdf(self.ctx, font_program.as_ptr(), font_program.len(), err_buf.as_mut_ptr(), err_buf.len());
+
+            if pdf.is_null() {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Internal(format!("Failed to create PDF: {}", err_msg)));
+            }
+            Ok(PdfWriter { pdf })
+        }
+    }
+
+    /// Append a page of `size` points (width, height) showing an 8-bit gray image,
+    /// then drawing the content stream operators in `text`.
+    pub fn pdf_add_page(&self, pdf: &PdfWriter, gray: &[u8], width: u32, height: u32, size: (f32, f32), text: &str) -> Result<(), CrabError> {
+        let c_text = CString::new(text).map_err(|_| CrabError::Internal("Null byte in PDF content".into()))?;
         unsafe {
             let mut err_buf = [0i8; 256];
-            let text_ptr = my_extract_text(
+            let ret = my_pdf_add_page(
+                self.ctx,
+                pdf.pdf,
+                gray.as_ptr(),
+                width as i32,
+                height as i32,
+                size.0,
+                size.1,
+                c_text.as_ptr(),
+                err_buf.as_mut_ptr(),
+                err_buf.len(),
+            );
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Internal(format!("Failed to add PDF page: {}", err_msg)));
+            }
+            Ok(())
+        }
+    }
+
+    /// Start a new PDF of text pages (`pdf_add_text_page`), without a glyphless font.
+    pub fn new_text_pdf(&self) -> Result<PdfWriter, CrabError> {
+        unsafe {
+            let mut err_buf = [0i8; 256];
+            let pdf = my_new_pdf(self.ctx, ptr::null(), 0, err_buf.as_mut_ptr(), err_buf.len());
+
+            if pdf.is_null() {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Internal(format!("Failed to create PDF: {}", err_msg)));
+            }
+            Ok(PdfWriter { pdf })
+        }
+    }
+
+    /// Append a page of `size` points (width, height) drawing the content
+    /// stream operators in `text`, with the standard 14 font `font` (e.g.
+    /// "Times-Roman") as `/F1`.
+    pub fn pdf_add_text_page(&self, pdf: &PdfWriter, size: (f32, f32), font: &str, text: &str) -> Result<(), CrabError> {
+        let c_font = CString::new(font).map_err(|_| CrabError::Internal("Null byte in font name".into()))?;
+        let c_text = CString::new(text).map_err(|_| CrabError::Internal("Null byte in PDF content".into()))?;
+        unsafe {
+            let mut err_buf = [0i8; 256];
+            let ret = my_pdf_add_text_page(self.ctx, pdf.pdf, size.0, size.1, c_font.as_ptr(), c_text.as_ptr(), err_buf.as_mut_ptr(), err_buf.len());
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Internal(format!("Failed to add PDF page in {}: {}", font, err_msg)));
+            }
+            Ok(())
+        }
+    }
+
+    pub fn save_pdf(&self, pdf: &PdfWriter, path: &Path) -> Result<(), CrabError> {
+        let path_str = path.to_str().ok_or_else(|| CrabError::Input(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid path encoding")))?;
+        let c_path = CString::new(path_str).map_err(|_| CrabError::Input(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Null byte in path")))?;
+
+        unsafe {
+            let mut err_buf = [0i8; 256];
+            let ret = my_pdf_save(self.ctx, pdf.pdf, c_path.as_ptr(), err_buf.as_mut_ptr(), err_buf.len());
+
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Output(std::io::Error::other(format!("Failed to write {:?}: {}", path, err_msg))));
+            }
+            Ok(())
+        }
+    }
+
+    /// Replace text of a PDF and save it to `path`. On each corrected page, text
+    /// under the rectangles is removed (images and drawings stay) and the content
+    /// stream operators are drawn in page space (points, y down), with
+    /// `font_program` as the glyphless font `/FCrabOCR`.
+    pub fn correct_text(&self, doc: &Document, pages: &[PageCorrection], font_program: &[u8], path: &Path) -> Result<(), CrabError> {
+        let path_str = path.to_str().ok_or_else(|| CrabError::Input(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid path encoding")))?;
+        let c_path = CString::new(path_str).map_err(|_| CrabError::Input(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Null byte in path")))?;
+        let numbers: Vec<c_int> = pages.iter().map(|p| p.page).collect();
+        let rect_counts: Vec<c_int> = pages.iter().map(|p| p.rects.len() as c_int).collect();
+        let rects: Vec<f32> = pages.iter().flat_map(|p| p.rects.iter().flatten().copied()).collect();
+        let ops = pages
+            .iter()
+            .map(|p| CString::new(p.ops.as_str()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| CrabError::Internal("Null byte in content stream".into()))?;
+        let op_ptrs: Vec<*const c_char> = ops.iter().map(|o| o.as_ptr()).collect();
+
+        unsafe {
+            let mut err_buf = [0i8; 256];
+            let ret = my_correct_text(
                 self.ctx,
                 doc.doc,
-                page_number,
+                pages.len() as c_int,
+                numbers.as_ptr(),
+                rect_counts.as_ptr(),
+                rects.as_ptr(),
+                op_ptrs.as_ptr(),
+                font_program.as_ptr(),
+                font_program.len(),
+                c_path.as_ptr(),
                 err_buf.as_mut_ptr(),
                 err_buf.len(),
             );
 
+            if ret != 0 {
+                let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                return Err(CrabError::Pdf(format!("Failed to write corrected PDF {:?}: {}", path, err_msg)));
+            }
+            Ok(())
+        }
+    }
+
+    /// Font objects of a PDF, in object order. Other documents have none.
+    pub fn fonts(&self, doc: &Document) -> Result<Vec<FontInfo>, CrabError> {
+        let raw = unsafe {
+            let mut err_buf = [0i8; 256];
+            let text_ptr = my_fonts(self.ctx, doc.doc, err_buf.as_mut_ptr(), err_buf.len());
+
             if text_ptr.is_null() {
-                 let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
-                 return Err(CrabError::Pdf(format!("Failed to extract text from page {}: {}", page_number, err_msg)));
+                if err_buf[0] != 0 {
+                    let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                    return Err(CrabError::Pdf(format!("Failed to read fonts: {}", err_msg)));
+                }
+                return Ok(Vec::new());
             }
 
-            let c_str = std::ffi::CStr::from_ptr(text_ptr);
-            let text = c_str.to_string_lossy().into_owned();
-            
+            let text = std::ffi::CStr::from_ptr(text_ptr).to_string_lossy().into_owned();
             my_free_text(self.ctx, text_ptr);
-            
-            Ok(text)
+            text
+        };
+        Ok(raw.lines().filter_map(parse_font_line).collect())
+    }
+
+    /// The embedded program of a font listed by `fonts`, as stored in the PDF (decompressed).
+    pub fn font_program(&self, doc: &Document, font: &FontInfo) -> Result<Vec<u8>, CrabError> {
+        unsafe {
+            let mut len: usize = 0;
+            let mut err_buf = [0i8; 256];
+            let ptr = my_font_program(self.ctx, doc.doc, font.object, &mut len, err_buf.as_mut_ptr(), err_buf.len());
+
+            if ptr.is_null() {
+                if err_buf[0] != 0 {
+                    let err_msg = std::ffi::CStr::from_ptr(err_buf.as_ptr()).to_string_lossy().into_owned();
+                    return Err(CrabError::Pdf(format!("Failed to read font {}: {}", font.name, err_msg)));
+                }
+                return Ok(Vec::new());
+            }
+
+            let program = std::slice::from_raw_parts(ptr as *const u8, len).to_vec();
+            my_free_text(self.ctx, ptr);
+            Ok(program)
         }
     }
+}
 
+/// The encryption `my_open_document` read of a document needing a password.
+fn read_encryption(enc: &my_encryption) -> Option<encryption::Encryption> {
+    let text = |chars: &[c_char]| unsafe { std::ffi::CStr::from_ptr(chars.as_ptr()) }.to_string_lossy().into_owned();
+    (enc.encrypted != 0).then(|| encryption::Encryption {
+        filter: text(&enc.handler),
+        cipher: Some((text(&enc.method), enc.length)),
+        revision: Some(enc.revision),
+        permissions: Some(enc.permissions),
+    })
+}
+
+/// Parse a "name\tsigned\tsigner\tdate\treason\tlocation" line from `my_signature_fields`.
+fn parse_signature_line(line: &str) -> Option<SignatureField> {
+    let cols: Vec<&str> = line.split('\t').collect();
+    let [name, signed, signer, date, reason, location] = cols.as_slice() else {
+        return None;
+    };
+    let text = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    Some(SignatureField {
+        name: name.to_string(),
+        signed: *signed == "1",
+        signer: text(signer),
+        date: text(date),
+        reason: text(reason),
+        location: text(location),
+    })
+}
+
+/// Collect the "kind\tdetail" lines of `my_active_content`. Repeated targets
+/// (e.g. the same link on every page) are listed once.
+fn parse_active_content(raw: &str) -> ActiveContent {
+    let mut content = ActiveContent::default();
+    for line in raw.lines() {
+        let (kind, detail) = line.split_once('\t').unwrap_or((line, ""));
+        let detail = detail.trim().to_string();
+        let list = match kind {
+            "javascript" => {
+                content.javascript += 1;
+                continue;
+            }
+            "open_action" => &mut content.open_actions,
+            "launch" => &mut content.launch,
+            "embedded_file" => &mut content.embedded_files,
+            "external" => &mut content.external,
+            _ => continue,
+        };
+        if !list.contains(&detail) {
+            list.push(detail);
+        }
+    }
+    content
+}
+
+/// Parse an "object\tname\ttype\tformat" line from `my_fonts`.
+fn parse_font_line(line: &str) -> Option<FontInfo> {
+    let cols: Vec<&str> = line.split('\t').collect();
+    let [object, name, kind, format] = cols.as_slice() else {
+        return None;
+    };
+    Some(FontInfo {
+        object: object.parse().ok()?,
+        name: name.to_string(),
+        kind: kind.to_string(),
+        format: Some(format.to_string()).filter(|f| !f.is_empty()),
+    })
 }
 
 impl Drop for Renderer {
@@ -155,6 +771,152 @@ pub struct Pixmap {
     pix: *mut fz_pixmap,
 }
 
+/// Page boundary boxes in points (x0, y0, x1, y1), in page space after rotation.
+pub struct PageBoxes {
+    pub media: [f32; 4],
+    pub crop: [f32; 4],
+    pub trim: [f32; 4],
+}
+
+/// Vector drawing of a page.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VectorStats {
+    /// Number of filled paths.
+    pub paths: i32,
+    /// Curve segments in those paths (glyph outlines are mostly curves).
+    pub curves: i32,
+}
+
+/// What a PDF page holds, from `Renderer::page_resources`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PageResources {
+    /// Nesting depth of form XObjects and tiling patterns; the page content is depth 0.
+    pub depth: u32,
+    /// Image XObjects named by the page and its forms.
+    pub images: u32,
+    /// Decoded size of the page's content streams and those of its forms and patterns.
+    pub content_bytes: u64,
+}
+
+/// Text replacements on one page of a PDF.
+pub struct PageCorrection {
+    /// 0-based page number.
+    pub page: i32,
+    /// Regions whose text is removed, in points (x0, y0, x1, y1).
+    pub rects: Vec<[f32; 4]>,
+    /// Content stream operators drawing the replacement text, in page space.
+    pub ops: String,
+}
+
+/// A line of the text layer.
+#[derive(Clone, Debug, Default)]
+pub struct TextLine {
+    /// Bounds on the page in points (x0, y0, x1, y1).
+    pub rect: [f32; 4],
+    pub text: String,
+    /// Index of the text block holding the line.
+    pub block: usize,
+    /// Mean font size of the line, in points.
+    pub size: f32,
+    /// Whether every glyph of the line is set in a bold font.
+    pub bold: bool,
+}
+
+/// A text block of a page's text layer.
+#[derive(Debug, PartialEq)]
+pub struct TextBlock {
+    /// Bounds on the page in points (x0, y0, x1, y1).
+    pub rect: [f32; 4],
+    pub lines: Vec<BlockLine>,
+}
+
+/// A line of a [`TextBlock`].
+#[derive(Debug, PartialEq)]
+pub struct BlockLine {
+    pub rect: [f32; 4],
+    pub spans: Vec<TextSpan>,
+}
+
+/// A run of glyphs in the same font and size.
+#[derive(Debug, PartialEq)]
+pub struct TextSpan {
+    pub rect: [f32; 4],
+    /// Font name as MuPDF reports it, without a subset prefix.
+    pub font: String,
+    /// Font size in points.
+    pub size: f32,
+    pub text: String,
+}
+
+/// A signature form field.
+pub struct SignatureField {
+    /// Fully qualified field name, e.g. "form1.Signature1".
+    pub name: String,
+    /// Whether the field holds a signature. The signature itself is not verified.
+    pub signed: bool,
+    /// Signer name recorded in the signature dictionary, if any.
+    pub signer: Option<String>,
+    /// Signing time as recorded, a PDF date such as "D:20240131120000Z".
+    pub date: Option<String>,
+    pub reason: Option<String>,
+    pub location: Option<String>,
+}
+
+/// Content of a PDF that runs or reaches outside the document, for security triage.
+/// Targets are empty strings when the PDF does not name them.
+#[derive(Debug, Default, PartialEq)]
+pub struct ActiveContent {
+    /// Type of the actions run when the document opens, e.g. "JavaScript", or "AA" for document actions.
+    pub open_actions: Vec<String>,
+    /// Number of JavaScript actions and scripts.
+    pub javascript: usize,
+    /// Programs or files opened by launch actions.
+    pub launch: Vec<String>,
+    /// Names of embedded files.
+    pub embedded_files: Vec<String>,
+    /// URIs and other documents referred to by links, remote go-to, form submission and import actions.
+    pub external: Vec<String>,
+}
+
+/// A font of a PDF.
+pub struct FontInfo {
+    /// PDF object number, which tells apart fonts of the same name.
+    pub object: i32,
+    /// Base font name, e.g. "ABCDEF+Helvetica-Bold"; empty for Type 3 fonts without one.
+    pub name: String,
+    /// Font type: Type1, MMType1, TrueType, Type0 or Type3.
+    pub kind: String,
+    /// Format of the embedded program (type1, truetype, cff or opentype), `None` if not embedded.
+    pub format: Option<String>,
+}
+
+impl FontInfo {
+    /// Whether only the glyphs used are embedded, marked by a six-letter tag ("ABCDEF+").
+    pub fn subset(&self) -> bool {
+        let bytes = self.name.as_bytes();
+        bytes.len() > 7 && bytes[6] == b'+' && bytes[..6].iter().all(u8::is_ascii_uppercase)
+    }
+
+    /// File extension of the embedded program, as mutool uses.
+    pub fn extension(&self) -> &'static str {
+        match self.format.as_deref() {
+            Some("type1") => "pfa",
+            Some("truetype") => "ttf",
+            Some("opentype") => "otf",
+            _ => "cff",
+        }
+    }
+}
+
+/// A rendered annotation appearance (stamp, signature, ink, ...).
+pub struct Annotation {
+    /// PDF annotation subtype, e.g. "Stamp".
+    pub kind: String,
+    /// Bounds on the page in points (x0, y0, x1, y1).
+    pub rect: [f32; 4],
+    pub pixmap: Pixmap,
+}
+
 /// A wrapper around a MuPDF pixmap.
 ///
 /// # Safety
@@ -216,3 +978,145 @@ impl Document {
     }
 }
 
+/// A document released with its renderer when it goes out of scope, so that
+/// every return path cleans it up.
+pub struct DocumentGuard<'a> {
+    renderer: &'a Renderer,
+    doc: Document,
+}
+
+impl<'a> DocumentGuard<'a> {
+    pub fn new(renderer: &'a Renderer, doc: Document) -> Self {
+        Self { renderer, doc }
+    }
+}
+
+impl std::ops::Deref for DocumentGuard<'_> {
+    type Target = Document;
+
+    fn deref(&self) -> &Document {
+        &self.doc
+    }
+}
+
+impl Drop for DocumentGuard<'_> {
+    fn drop(&mut self) {
+        self.doc.drop_with(self.renderer);
+    }
+}
+
+/// A PDF being written, from `Renderer::new_pdf`.
+pub struct PdfWriter {
+    pdf: *mut my_pdf_writer,
+}
+
+impl PdfWriter {
+    pub fn drop_with(&mut self, ctx: &Renderer) {
+        unsafe {
+            if !self.pdf.is_null() {
+                my_drop_pdf(ctx.ctx, self.pdf);
+                self.pdf = ptr::null_mut();
+            }
+        }
+    }
+}
+
+/// Parse the records of `my_extract_structured`. Records out of place (a
+/// span before any line) are skipped.
+fn parse_structured(raw: &str) -> Vec<TextBlock> {
+    let parse_rect = |bounds: &str| -> Option<[f32; 4]> {
+        let coords: Vec<f32> = bounds.split(' ').filter_map(|v| v.parse().ok()).collect();
+        coords.try_into().ok()
+    };
+    let mut blocks: Vec<TextBlock> = Vec::new();
+    for record in raw.lines() {
+        let mut fields = record.splitn(5, '\t');
+        let (Some(kind), Some(rect)) = (fields.next(), fields.next().and_then(parse_rect)) else {
+            continue;
+        };
+        match kind {
+            "B" => blocks.push(TextBlock { rect, lines: Vec::new() }),
+            "L" => {
+                if let Some(block) = blocks.last_mut() {
+                    block.lines.push(BlockLine { rect, spans: Vec::new() });
+                }
+            }
+            "S" => {
+                let (Some(size), Some(font), Some(text)) = (fields.next(), fields.next(), fields.next()) else {
+                    continue;
+                };
+                if let Some(line) = blocks.last_mut().and_then(|b| b.lines.last_mut()) {
+                    line.spans.push(TextSpan {
+                        rect,
+                        font: font.to_string(),
+                        size: size.parse().unwrap_or(0.0),
+                        text: text.to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signature_line() {
+        let field = parse_signature_line("form1.Sig1\t1\tJane Roe\tD:20240131120000Z\t\tTunis").unwrap();
+        assert!(field.signed);
+        assert_eq!(field.signer.as_deref(), Some("Jane Roe"));
+        assert_eq!(field.reason, None);
+        assert_eq!(field.location.as_deref(), Some("Tunis"));
+
+        let field = parse_signature_line("Sig2\t0\t\t\t\t").unwrap();
+        assert!(!field.signed && field.signer.is_none());
+        assert!(parse_signature_line("garbage").is_none());
+    }
+
+    #[test]
+    fn test_parse_structured() {
+        let blocks = parse_structured(
+            "S\t0 0 1 1\t9\tTimes\torphan\nB\t10 20 200 40\nL\t10 20 200 30\n\
+             S\t10 20 60 30\t10\tHelvetica-Bold\tTotal:\nS\t60 20 200 30\t10\tHelvetica\t 1,234\n",
+        );
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].rect, [10.0, 20.0, 200.0, 40.0]);
+        let spans = &blocks[0].lines[0].spans;
+        assert_eq!(spans[0], TextSpan { rect: [10.0, 20.0, 60.0, 30.0], font: "Helvetica-Bold".into(), size: 10.0, text: "Total:".into() });
+        assert_eq!(spans[1].text, " 1,234");
+    }
+
+    #[test]
+    fn test_parse_font_line() {
+        let font = parse_font_line("12\tABCDEF+Helvetica-Bold\tTrueType\ttruetype").unwrap();
+        assert_eq!((font.object, font.kind.as_str(), font.extension()), (12, "TrueType", "ttf"));
+        assert!(font.subset());
+
+        let font = parse_font_line("7\tHelvetica\tType1\t").unwrap();
+        assert!(font.format.is_none() && !font.subset());
+        assert!(parse_font_line("x\tHelvetica\tType1\t").is_none());
+    }
+
+    #[test]
+    fn test_parse_active_content() {
+        let content = parse_active_content(
+            "open_action\tJavaScript\njavascript\t\njavascript\t\nexternal\thttps://example.com\n\
+             external\thttps://example.com\nlaunch\tcmd.exe\nembedded_file\tinvoice.xml\n",
+        );
+        assert_eq!(
+            content,
+            ActiveContent {
+                open_actions: vec!["JavaScript".into()],
+                javascript: 2,
+                launch: vec!["cmd.exe".into()],
+                embedded_files: vec!["invoice.xml".into()],
+                external: vec!["https://example.com".into()],
+            }
+        );
+        assert_eq!(parse_active_content(""), ActiveContent::default());
+    }
+}
@@ -0,0 +1,557 @@
+//! The JSON output of crabocr as Rust types. `-f json` writes a [`Document`];
+//! its pages are also the files of `--split-pages`, and the entries of the
+//! `index.json` manifest list their `files`. Keys are left out of the JSON
+//! where a field is `None` or empty, and read back as such.
+//!
+//! ```no_run
+//! let json = std::fs::read_to_string("scan.json").unwrap();
+//! let doc: crabocr::schema::Document = serde_json::from_str(&json).unwrap();
+//! for page in doc.pages.unwrap_or_default() {
+//!     for word in page.words.unwrap_or_default().iter().filter(|w| w.conf < 60.0) {
+//!         println!("page {}: {:?} at {:?}", page.page, word.text, word.rect);
+//!     }
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Version of the JSON output, `MAJOR.MINOR`: the minor version grows when
+/// keys are added, the major version when keys are removed or change meaning.
+pub const FORMAT_VERSION: &str = "1.2";
+
+/// JSON Schema (draft 2020-12) of the JSON output at `FORMAT_VERSION`, as
+/// printed by `--print-schema`. Lines of NDJSON output are its `record`.
+pub const JSON_SCHEMA: &str = include_str!("../assets/schema.json");
+
+/// A run over one input.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    /// `FORMAT_VERSION` of the writer; empty for output written before versioning.
+    #[serde(default)]
+    pub format_version: String,
+    pub status: Status,
+    /// Why no page was processed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// `--meta` tags.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub meta: Map<String, Value>,
+    /// Unit of the layer records (`page`, `block`, `paragraph`, `line` or `word`).
+    /// Not set for form data only (`--mode xfa-only`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub granularity: Option<String>,
+    /// XFA form data, converted to JSON or as the original XML string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xfa: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signatures: Option<Vec<Signature>>,
+    /// With `--page-info`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security: Option<Security>,
+    /// With `--page-info`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fonts: Option<Vec<Font>>,
+    pub warnings: Vec<Warning>,
+    /// With `--stats`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<DocumentStats>,
+    /// Not set for form data only (`--mode xfa-only`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pages: Option<Vec<Page>>,
+}
+
+/// A line of `-f ndjson` output: each page as soon as it is processed, then
+/// the document without its pages.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    Page(Box<Page>),
+    Document(Box<Document>),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    #[default]
+    Ok,
+    /// The document or page range was empty.
+    NoPages,
+}
+
+/// A signature field of the form. Signatures are not verified.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Signature {
+    /// Fully qualified field name.
+    pub field: String,
+    pub signed: bool,
+    pub signer: Option<String>,
+    /// PDF date, e.g. "D:20240131120000Z".
+    pub date: Option<String>,
+    pub reason: Option<String>,
+    pub location: Option<String>,
+}
+
+/// Scripts, launch actions, embedded files and external references.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Security {
+    pub open_actions: Vec<String>,
+    /// Number of JavaScript actions and scripts.
+    pub javascript: usize,
+    pub launch: Vec<String>,
+    pub embedded_files: Vec<String>,
+    pub external: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Font {
+    pub name: String,
+    /// Type1, MMType1, TrueType, Type0 or Type3.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub embedded: bool,
+    pub subset: bool,
+    /// Format of the embedded program (type1, truetype, cff or opentype).
+    pub format: Option<String>,
+}
+
+/// A problem that did not stop the run.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    /// Snake case identifier, e.g. `low_confidence`.
+    pub kind: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<char>,
+}
+
+/// A page, or half of a split spread. Rects are in points (x0, y0, x1, y1)
+/// and boxes (`bbox`) in pixels of the OCRed image (left, top, right, bottom).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Page {
+    /// 1-based page number.
+    pub page: usize,
+    /// `a` (left) or `b` (right) half of a split spread.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<char>,
+    /// 1-based document of a batch scan, with `--split-on`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raster_hash: Option<String>,
+    /// `lower_dpi`, `ocr_skipped` or `stopped`, near a resource limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub degraded: Option<String>,
+    /// Retries rendering needed, with `--render-retries`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub render_retries: u32,
+    pub rotation: Rotation,
+    /// With `--page-info`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<PageInfo>,
+    /// With `--merge`, in place of `text_layer` and `ocr_layer`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merged: Option<Vec<MergedBlock>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_layer: Option<Vec<Unit>>,
+    /// With `--text-structure`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text_structure: Vec<Block>,
+    /// Why the text layer looks broken.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suspicious_text_layer: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub outlined_text: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr_layer: Option<Vec<Unit>>,
+    /// With `--words`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<Word>>,
+    /// With `--align`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alignment: Option<Alignment>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<Language>,
+    /// With `--detect-language`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<DetectedLanguage>,
+    /// With `--tables`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<Table>,
+    /// With `--zones`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub zones: Vec<Zone>,
+    /// With `--stamps`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stamps: Vec<[f32; 4]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub encoding_fallback: Vec<EncodingFallback>,
+    /// With `--stats`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<PageStats>,
+    pub timings: Timings,
+    /// Files of the page, in the `index.json` manifest of `--split-pages`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+/// Rotation applied to the page, in degrees clockwise.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rotation {
+    /// `/Rotate` of the page.
+    pub declared: i32,
+    /// Text orientation detected by Tesseract.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected: Option<i32>,
+    /// Skew angle corrected by Tesseract.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deskew: Option<f32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageInfo {
+    pub media_box: BoxSize,
+    pub crop_box: BoxSize,
+    pub trim_box: BoxSize,
+    /// `color`, `grayscale` or `bilevel`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ink: Option<Ink>,
+}
+
+/// A page box in points, millimeters and inches.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoxSize {
+    pub pt: [f32; 4],
+    pub mm: [f64; 4],
+    #[serde(rename = "in")]
+    pub inches: [f64; 4],
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ink {
+    /// Percentage of pixels dark enough to be ink.
+    pub coverage: f32,
+    /// Share of pixels (0-1) per luminance band, darkest first.
+    pub histogram: Vec<f32>,
+}
+
+/// A text-layer or OCR block of `--merge`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergedBlock {
+    /// `text` or `ocr`.
+    pub source: String,
+    pub rect: [f32; 4],
+    pub text: String,
+}
+
+/// A record of a layer, at the `--granularity` of the document.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Unit {
+    pub text: String,
+    /// Recognition confidence (0-100), for OCR.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conf: Option<f32>,
+    /// For OCR below page level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[i32; 4]>,
+    /// ISO 639-3 code detected in the text, with `--detect-language`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+}
+
+/// A text-layer block of `--text-structure`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Block {
+    pub rect: [f64; 4],
+    pub lines: Vec<Line>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Line {
+    pub rect: [f64; 4],
+    pub spans: Vec<Span>,
+}
+
+/// A run of glyphs in one font and size.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub rect: [f64; 4],
+    /// Font name without subset prefix.
+    pub font: String,
+    /// Font size in points.
+    pub size: f64,
+    pub text: String,
+}
+
+/// A word read by Tesseract.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    /// Confidence (0-100).
+    pub conf: f64,
+    pub bbox: [i32; 4],
+    /// Position on the page, once placed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rect: Option<[f64; 4]>,
+    /// Page-wide indices of the block, paragraph and line of the word.
+    pub block: usize,
+    pub para: usize,
+    pub line: usize,
+    /// Recognition language, when several are loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+}
+
+/// The text-layer words of a page aligned with OCR words.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Alignment {
+    /// Share of text-layer words OCR read the same (0-1).
+    pub agreement: f64,
+    pub words: Vec<AlignedWord>,
+}
+
+/// A text-layer word with a confidence comparable to OCR's.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlignedWord {
+    pub text: String,
+    /// Position on the page, estimated from the bounds of its line.
+    pub rect: [f64; 4],
+    /// 100 when OCR reads the same word, 100 minus OCR's confidence when it
+    /// reads another, 0 when it read nothing there.
+    pub conf: f64,
+    /// What OCR read instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr: Option<String>,
+}
+
+/// OCR words recognized with one language.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Language {
+    pub lang: String,
+    pub words: usize,
+    /// Share of the page's words (0-1).
+    pub share: f64,
+    /// Mean confidence (0-100).
+    pub conf: f64,
+}
+
+/// Language detected in the text a page reads as, with `--detect-language`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DetectedLanguage {
+    /// ISO 639-3 code.
+    pub lang: String,
+    /// How sure the guess is (0-1).
+    pub confidence: f64,
+}
+
+/// A table of `--tables`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Table {
+    /// 1-based index on the page.
+    pub index: usize,
+    pub rect: [f32; 4],
+    /// Cell texts row by row, one per column.
+    pub rows: Vec<Vec<String>>,
+    pub csv: String,
+}
+
+/// Text of a layout zone of `--zones`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    /// `header`, `body`, `sidebar` or `footer`.
+    pub kind: String,
+    pub rect: [f32; 4],
+    pub text: String,
+}
+
+/// OCR of an annotation appearance.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// Annotation subtype, e.g. "Stamp".
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub rect: [f32; 4],
+    pub ocr_layer: Vec<Unit>,
+}
+
+/// A text-layer line without Unicode mapping, read with OCR.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EncodingFallback {
+    pub rect: [f32; 4],
+    /// The line as extracted, with unmapped glyphs as U+FFFD.
+    pub original: String,
+    pub text: String,
+}
+
+/// Summary figures of a page, for auditing quality across batches.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageStats {
+    /// Characters of the text layer, whitespace excluded.
+    pub text_chars: usize,
+    /// Characters of the OCR layer, whitespace excluded.
+    pub ocr_chars: usize,
+    /// Mean OCR word confidence (0-100).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr_conf: Option<f64>,
+    pub render_ms: f64,
+    pub ocr_ms: f64,
+    /// Script most letters are written in, e.g. `Latin`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    /// Text orientation detected by Tesseract, in degrees clockwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<i32>,
+    /// Read from the text layer alone, without OCR.
+    pub text_layer_only: bool,
+}
+
+/// Summary figures of a document, for routing it to language-specific pipelines.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentStats {
+    pub pages: usize,
+    pub text_layer_only_pages: usize,
+    /// Scripts of the letters, most used first.
+    pub scripts: Vec<Script>,
+    /// OCR languages over the document, when several are loaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<Language>,
+    pub chars: CharClasses,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Script {
+    /// E.g. `Latin`, `Cyrillic`, `Han`.
+    pub script: String,
+    pub letters: usize,
+    /// Share of the document's letters (0-1).
+    pub share: f64,
+}
+
+/// Characters of a document by class.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CharClasses {
+    pub letters: usize,
+    pub digits: usize,
+    pub punctuation: usize,
+    pub whitespace: usize,
+    /// Symbols, marks and control characters.
+    pub other: usize,
+}
+
+/// Time spent on the page by stage, in milliseconds.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    pub render_ms: f64,
+    pub preprocessing_ms: f64,
+    pub ocr_ms: f64,
+    pub text_ms: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that every key of `value` is declared by `schema`, and that the
+    /// keys it requires are there.
+    fn check(root: &Value, schema: &Value, value: &Value, path: &str) {
+        if let Some(Value::String(target)) = schema.get("$ref") {
+            let name = target.trim_start_matches("#/$defs/");
+            return check(root, &root["$defs"][name], value, path);
+        }
+        match value {
+            Value::Object(map) if schema.get("properties").is_some() => {
+                for required in schema["required"].as_array().unwrap() {
+                    assert!(map.contains_key(required.as_str().unwrap()), "{} lacks {}", path, required);
+                }
+                for (key, item) in map {
+                    let declared = &schema["properties"][key];
+                    assert!(!declared.is_null(), "{}.{} is not in the schema", path, key);
+                    check(root, declared, item, &format!("{}.{}", path, key));
+                }
+            }
+            Value::Array(items) => {
+                if let Some(schema) = schema.get("items") {
+                    for item in items {
+                        check(root, schema, item, &format!("{}[]", path));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let root: Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+        assert!(root["$id"].as_str().unwrap().ends_with(FORMAT_VERSION));
+        let major = FORMAT_VERSION.split('.').next().unwrap();
+        assert!(root["$defs"]["document"]["properties"]["format_version"]["pattern"].as_str().unwrap().starts_with(&format!("^{}\\.", major)));
+
+        let unit = Unit { bbox: Some([0; 4]), conf: Some(0.0), lang: Some("eng".into()), ..Default::default() };
+        let language = Language::default();
+        let page = Page {
+            part: Some('a'),
+            document: Some(1),
+            raster_hash: Some(String::new()),
+            degraded: Some("stopped".into()),
+            render_retries: 1,
+            rotation: Rotation { declared: 0, detected: Some(90), deskew: Some(0.5) },
+            info: Some(PageInfo { color: Some("color".into()), ink: Some(Ink::default()), ..Default::default() }),
+            merged: Some(vec![MergedBlock::default()]),
+            text_layer: Some(vec![unit.clone()]),
+            text_structure: vec![Block { lines: vec![Line { spans: vec![Span::default()], ..Default::default() }], ..Default::default() }],
+            suspicious_text_layer: vec![String::new()],
+            outlined_text: true,
+            ocr_layer: Some(vec![unit.clone()]),
+            words: Some(vec![Word { rect: Some([0.0; 4]), lang: Some("eng".into()), ..Default::default() }]),
+            alignment: Some(Alignment { words: vec![AlignedWord { ocr: Some(String::new()), ..Default::default() }], ..Default::default() }),
+            languages: vec![language.clone()],
+            language: Some(DetectedLanguage::default()),
+            tables: vec![Table { rows: vec![vec![String::new()]], ..Default::default() }],
+            zones: vec![Zone::default()],
+            stamps: vec![[0.0; 4]],
+            annotations: vec![Annotation { ocr_layer: vec![unit], ..Default::default() }],
+            encoding_fallback: vec![EncodingFallback::default()],
+            stats: Some(PageStats { ocr_conf: Some(0.0), script: Some("Latin".into()), orientation: Some(0), ..Default::default() }),
+            files: vec![String::new()],
+            ..Default::default()
+        };
+        let mut meta = Map::new();
+        meta.insert("batch".into(), Value::String("7".into()));
+        let doc = Document {
+            format_version: FORMAT_VERSION.into(),
+            reason: Some(String::new()),
+            meta,
+            granularity: Some("page".into()),
+            xfa: Some(Value::Object(Map::new())),
+            signatures: Some(vec![Signature::default()]),
+            security: Some(Security { open_actions: vec![String::new()], ..Default::default() }),
+            fonts: Some(vec![Font::default()]),
+            warnings: vec![Warning { page: Some(1), part: Some('b'), ..Default::default() }],
+            stats: Some(DocumentStats { scripts: vec![Script::default()], languages: vec![language], ..Default::default() }),
+            pages: Some(vec![page.clone()]),
+            ..Default::default()
+        };
+        check(&root, &root, &serde_json::to_value(&doc).unwrap(), "document");
+        // NDJSON lines are a page or the document, tagged with their type
+        for (record, def) in [(Record::Page(Box::new(page)), "page"), (Record::Document(Box::new(doc)), "document")] {
+            let mut value = serde_json::to_value(record).unwrap();
+            assert_eq!(value.as_object_mut().unwrap().remove("type").unwrap(), def);
+            check(&root, &root["$defs"][def], &value, def);
+        }
+    }
+}
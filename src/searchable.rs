@@ -0,0 +1,182 @@
+use crate::errors::CrabError;
+use crate::ocr::Word;
+use crate::preprocess::GrayImage;
+use crate::renderer::{PdfWriter, Renderer};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Tesseract's glyphless font: every character maps to one blank glyph, so
+/// the text can be searched and selected but is never drawn.
+pub const GLYPHLESS_FONT: &[u8] = include_bytes!("../vendor/tesseract-5.3.4/tessdata/pdf.ttf");
+
+/// Advance of every glyph of the glyphless font, in em.
+const GLYPH_WIDTH: f32 = 0.5;
+
+/// A searchable PDF (`--output-pdf`): each OCRed image as a page, with the
+/// recognized words as invisible text over it.
+pub struct SearchablePdf {
+    pdf: PdfWriter,
+    pages: usize,
+    filter: WordFilter,
+}
+
+/// Which recognized words the text layer holds, by confidence (0-100).
+#[derive(Clone, Debug, Default)]
+pub struct WordFilter {
+    /// Words below this confidence are left out, the image alone shows them.
+    pub min_conf: i32,
+    /// Words below this confidence are wrapped in the markers, as `--mark-words-below` does.
+    pub mark_below: Option<(i32, (String, String))>,
+}
+
+impl SearchablePdf {
+    pub fn new(renderer: &Renderer, filter: WordFilter) -> Result<Self, CrabError> {
+        Ok(Self { pdf: renderer.new_pdf(GLYPHLESS_FONT)?, pages: 0, filter })
+    }
+
+    /// Add a page showing `image` at its size at `dpi`, with `words` (boxes in pixels of `image`).
+    pub fn add_page(&mut self, renderer: &Renderer, image: &GrayImage, dpi: i32, words: &[Word]) -> Result<(), CrabError> {
+        let scale = 72.0 / dpi as f32;
+        let size = (image.width as f32 * scale, image.height as f32 * scale);
+        let text = text_layer(words, &self.filter, scale, size.1);
+        renderer.pdf_add_page(&self.pdf, &image.data, image.width, image.height, size, &text)?;
+        self.pages += 1;
+        Ok(())
+    }
+
+    /// Save the pages added so far to `path`, if any, and start over for the
+    /// next document of a split batch (`--split-on`).
+    pub fn save_document(&mut self, renderer: &Renderer, path: &Path) -> Result<(), CrabError> {
+        let result = self.save_pages(renderer, path);
+        let mut done = std::mem::replace(&mut self.pdf, renderer.new_pdf(GLYPHLESS_FONT)?);
+        done.drop_with(renderer);
+        self.pages = 0;
+        result
+    }
+
+    /// Save the pages of the last document of a split batch to `path`, if any.
+    pub fn save_last_document(mut self, renderer: &Renderer, path: &Path) -> Result<(), CrabError> {
+        let result = self.save_pages(renderer, path);
+        self.pdf.drop_with(renderer);
+        result
+    }
+
+    /// Documents of a split batch without OCRed pages get no PDF.
+    fn save_pages(&self, renderer: &Renderer, path: &Path) -> Result<(), CrabError> {
+        match self.pages {
+            0 => Ok(()),
+            _ => renderer.save_pdf(&self.pdf, path),
+        }
+    }
+
+    pub fn save(mut self, renderer: &Renderer, path: &Path) -> Result<(), CrabError> {
+        let result = renderer.save_pdf(&self.pdf, path);
+        self.pdf.drop_with(renderer);
+        result
+    }
+}
+
+/// Path of the searchable PDF of the `document`th document of a split batch:
+/// `scan.pdf` becomes `scan-2.pdf`.
+pub fn document_path(path: &Path, document: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, document, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, document),
+    };
+    path.with_file_name(name)
+}
+
+/// Content stream operators drawing `words` in invisible text (render mode 3).
+/// Each word is set on the bottom of its box, at the box height, and stretched
+/// to the box width; a space ends every word followed by another on its line,
+/// so copied text keeps its word breaks. Words are left out or marked as
+/// `filter` says.
+fn text_layer(words: &[Word], filter: &WordFilter, scale: f32, page_height: f32) -> String {
+    let words: Vec<&Word> = words.iter().filter(|w| w.conf >= filter.min_conf as f32).collect();
+    if words.is_empty() {
+        return String::new();
+    }
+    let mut ops = String::from("BT\n3 Tr\n");
+    for (i, word) in words.iter().enumerate() {
+        let mut text = match &filter.mark_below {
+            Some((below, (open, close))) if word.conf < *below as f32 => format!("{}{}{}", open, word.text, close),
+            _ => word.text.clone(),
+        };
+        if words.get(i + 1).is_some_and(|next| next.line == word.line) {
+            text.push(' ');
+        }
+        let [l, t, r, b] = word.bbox.map(|v| v as f32 * scale);
+        show_text(&mut ops, "F0", &text, [r - l, b - t], [1.0, 0.0, 0.0, 1.0, l, page_height - b]);
+    }
+    ops.push_str("ET\n");
+    ops
+}
+
+/// Append the operators showing `text` in the glyphless font resource `font`,
+/// set at the box height and stretched to its width (`size`, points), from
+/// the text matrix `tm` placing the bottom left corner of the box.
+pub fn show_text(ops: &mut String, font: &str, text: &str, size: [f32; 2], tm: [f32; 6]) {
+    let codes: Vec<u16> = text.encode_utf16().collect();
+    let [width, height] = size.map(|v| v.max(1.0));
+    let natural_width = codes.len().max(1) as f32 * GLYPH_WIDTH * height;
+    let stretch = 100.0 * width / natural_width;
+    let hex: String = codes.iter().map(|c| format!("{:04X}", c)).collect();
+    let [a, b, c, d, e, f] = tm;
+    let _ = writeln!(
+        ops,
+        "/{} {:.2} Tf {:.2} Tz {} {} {} {} {:.2} {:.2} Tm <{}> Tj",
+        font, height, stretch, a, b, c, d, e, f, hex
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, bbox: [i32; 4], line: usize) -> Word {
+        Word { text: text.to_string(), conf: 90.0, bbox, block: 0, para: 0, line, lang: None }
+    }
+
+    #[test]
+    fn test_text_layer() {
+        // 144 DPI: half a point per pixel, on a 400pt high page
+        let words = [word("Hi", [100, 100, 140, 120], 0), word("é", [160, 100, 170, 120], 0), word("Next", [100, 200, 180, 220], 1)];
+        let ops = text_layer(&words, &WordFilter::default(), 0.5, 400.0);
+        let lines: Vec<&str> = ops.lines().collect();
+        assert_eq!(lines[..2], ["BT", "3 Tr"]);
+        // "Hi " is 3 glyphs of 5pt at 10pt, stretched to the 20pt box
+        assert_eq!(lines[2], "/F0 10.00 Tf 133.33 Tz 1 0 0 1 50.00 340.00 Tm <004800690020> Tj");
+        assert!(lines[3].ends_with("<00E9> Tj"));
+        assert!(lines[4].ends_with("<004E006500780074> Tj"));
+        assert_eq!(lines[5], "ET");
+        assert!(text_layer(&[], &WordFilter::default(), 0.5, 400.0).is_empty());
+    }
+
+    #[test]
+    fn test_document_path() {
+        assert_eq!(document_path(Path::new("out/scan.pdf"), 2), Path::new("out/scan-2.pdf"));
+        assert_eq!(document_path(Path::new("scan"), 1), Path::new("scan-1"));
+    }
+
+    #[test]
+    fn test_text_layer_filter() {
+        let mut words = [word("sure", [100, 100, 140, 120], 0), word("blur", [160, 100, 200, 120], 0), word("maybe", [220, 100, 260, 120], 0)];
+        words[1].conf = 20.0;
+        words[2].conf = 50.0;
+        let hex = |text: &str| text.encode_utf16().map(|c| format!("{:04X}", c)).collect::<String>();
+
+        // The low-confidence word is missing from the layer, its neighbours keep their space
+        let filter = WordFilter { min_conf: 40, mark_below: None };
+        let ops = text_layer(&words, &filter, 0.5, 400.0);
+        assert!(ops.contains(&hex("sure ")) && ops.contains(&hex("maybe")));
+        assert!(!ops.contains(&hex("blur")));
+
+        let filter = WordFilter { min_conf: 40, mark_below: Some((60, ("[".into(), "]".into()))) };
+        let ops = text_layer(&words, &filter, 0.5, 400.0);
+        assert!(ops.contains(&hex("sure ")) && ops.contains(&hex("[maybe]")));
+
+        let filter = WordFilter { min_conf: 100, mark_below: None };
+        assert!(text_layer(&words, &filter, 0.5, 400.0).is_empty());
+    }
+}
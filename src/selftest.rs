@@ -0,0 +1,69 @@
+use crate::cli::Granularity;
+use crate::errors::CrabError;
+use crate::ocr::Ocr;
+use crate::preprocess::GrayImage;
+use crate::renderer::Renderer;
+use std::io::Write;
+
+/// One-page PDF with "The quick brown fox / jumps over 12345" in 36pt Helvetica.
+const SAMPLE_PDF: &[u8] = include_bytes!("../assets/selftest.pdf");
+const EXPECTED: &str = "quick brown fox";
+const DPI: i32 = 300;
+
+/// Extract, render and OCR the embedded sample, checking each stage.
+/// Validates the MuPDF, Leptonica and Tesseract linkage and the `eng` tessdata.
+pub fn run() -> Result<(), CrabError> {
+    let mut failures = Vec::new();
+
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(SAMPLE_PDF)?;
+
+    let renderer = Renderer::new()?;
+    let mut doc = renderer.open(file.path())?;
+    let text = renderer.extract_text(&doc, 0).and_then(|text| expect(&text));
+    report("MuPDF text extraction", &text, &mut failures);
+
+    let image = renderer.render_page(&doc, 0, DPI).map(|mut pix| {
+        let image = GrayImage::from_pixmap(&pix, &renderer);
+        pix.drop_with(&renderer);
+        image
+    });
+    doc.drop_with(&renderer);
+    report("MuPDF rendering", &image, &mut failures);
+
+    let ocr = Ocr::new("eng");
+    report("Tesseract init (eng)", &ocr, &mut failures);
+
+    if let (Ok(image), Ok(ocr)) = (&image, &ocr) {
+        let result = ocr
+            .recognize(image, DPI, Granularity::Page)
+            .and_then(|result| expect(&result.layer.text));
+        report("Tesseract OCR", &result, &mut failures);
+    }
+
+    if failures.is_empty() {
+        println!("Self-test passed.");
+        Ok(())
+    } else {
+        Err(CrabError::Internal(format!("self-test failed: {}", failures.join(", "))))
+    }
+}
+
+fn expect(text: &str) -> Result<(), CrabError> {
+    let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    if normalized.contains(EXPECTED) {
+        Ok(())
+    } else {
+        Err(CrabError::Internal(format!("expected {:?}, got {:?}", EXPECTED, text.trim())))
+    }
+}
+
+fn report<T>(stage: &str, result: &Result<T, CrabError>, failures: &mut Vec<String>) {
+    match result {
+        Ok(_) => println!("{:<24} ok", stage),
+        Err(e) => {
+            println!("{:<24} FAILED: {}", stage, e);
+            failures.push(stage.to_string());
+        }
+    }
+}
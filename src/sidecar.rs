@@ -0,0 +1,109 @@
+use crate::cli::{Cli, SidecarExists};
+use crate::errors::CrabError;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the outputs of `--sidecar` go.
+#[derive(Debug, PartialEq)]
+pub enum Sidecars {
+    /// One path per `--format`, in order.
+    Write(Vec<PathBuf>),
+    /// A sidecar exists and `--sidecar-exists skip` leaves the input alone.
+    Skip(PathBuf),
+}
+
+/// Output paths derived from the input path (`--sidecar[=EXT]`): the input
+/// with the extension of each `--format`, or `ext` for a single format, with
+/// `--sidecar-exists` applied to the files already there.
+pub fn resolve(args: &Cli, ext: Option<&str>) -> Result<Sidecars, CrabError> {
+    let input = args
+        .input
+        .as_deref()
+        .ok_or_else(|| CrabError::Cli("--sidecar names its files after the input, which cannot be STDIN".into()))?;
+    let exts: Vec<&str> = match ext.map(|e| e.trim_start_matches('.')) {
+        Some(ext) if ext.is_empty() || ext.contains(std::path::is_separator) => {
+            return Err(CrabError::Cli(format!("--sidecar extension '{}' is not a file extension", ext)));
+        }
+        Some(_) if args.format.len() > 1 => {
+            return Err(CrabError::Cli("--sidecar=EXT names the file of a single --format; use --sidecar for several".into()));
+        }
+        Some(ext) => vec![ext],
+        None => args.format.iter().map(|format| format.extension()).collect(),
+    };
+
+    let mut paths: Vec<PathBuf> = Vec::with_capacity(exts.len());
+    for ext in &exts {
+        let path = input.with_extension(ext);
+        if path == input {
+            return Err(CrabError::Cli(format!("--sidecar would overwrite the input {:?}", input)));
+        }
+        if paths.contains(&path) {
+            return Err(CrabError::Cli(format!("--sidecar would write {:?} twice", path)));
+        }
+        paths.push(path);
+    }
+    let Some(existing) = paths.iter().find(|path| path.exists()) else {
+        return Ok(Sidecars::Write(paths));
+    };
+    match args.sidecar_exists {
+        SidecarExists::Fail => Err(CrabError::Output(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{:?} exists; choose what to do with --sidecar-exists", existing),
+        ))),
+        SidecarExists::Skip => Ok(Sidecars::Skip(existing.clone())),
+        SidecarExists::Overwrite => Ok(Sidecars::Write(paths)),
+        SidecarExists::Rename => Ok(Sidecars::Write(numbered(input, &exts))),
+    }
+}
+
+/// `report-N.ext` next to `input` for each of `exts`, with the first N free
+/// for all of them, so the sidecars of a run share their name.
+fn numbered(input: &Path, exts: &[&str]) -> Vec<PathBuf> {
+    let stem = input.file_stem().unwrap_or(input.as_os_str()).to_string_lossy();
+    let paths = |n: usize| exts.iter().map(|ext| input.with_file_name(format!("{}-{}.{}", stem, n, ext))).collect::<Vec<_>>();
+    (1..)
+        .map(paths)
+        .find(|paths| paths.iter().all(|path| !path.exists()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("report.pdf");
+        let path = |name: &str| dir.path().join(name);
+        let resolve_with = |extra: &[&str]| {
+            let args = Cli::try_parse_from(["crabocr", input.to_str().unwrap()].iter().chain(extra)).unwrap();
+            let ext = args.sidecar.clone().unwrap();
+            resolve(&args, ext.as_deref())
+        };
+
+        let sidecars = resolve_with(&["--sidecar", "-f", "text", "-f", "pagexml"]).unwrap();
+        assert_eq!(sidecars, Sidecars::Write(vec![path("report.txt"), path("report.page.xml")]));
+        assert_eq!(resolve_with(&["--sidecar=.ocr"]).unwrap(), Sidecars::Write(vec![path("report.ocr")]));
+        assert!(matches!(resolve_with(&["--sidecar=pdf"]), Err(CrabError::Cli(_))));
+        assert!(matches!(resolve_with(&["--sidecar=md", "-f", "text", "-f", "json"]), Err(CrabError::Cli(_))));
+
+        std::fs::write(path("report.txt"), "").unwrap();
+        std::fs::write(path("report-1.txt"), "").unwrap();
+        assert!(matches!(resolve_with(&["--sidecar"]), Err(CrabError::Output(_))));
+        assert_eq!(resolve_with(&["--sidecar", "--sidecar-exists", "skip"]).unwrap(), Sidecars::Skip(path("report.txt")));
+        assert_eq!(resolve_with(&["--sidecar", "--sidecar-exists", "overwrite"]).unwrap(), Sidecars::Write(vec![path("report.txt")]));
+        assert_eq!(resolve_with(&["--sidecar", "--sidecar-exists", "rename"]).unwrap(), Sidecars::Write(vec![path("report-2.txt")]));
+        // One suffix for all the sidecars of a run: the first free for every format
+        std::fs::write(path("report-2.json"), "").unwrap();
+        assert_eq!(
+            resolve_with(&["--sidecar", "-f", "text", "-f", "json", "--sidecar-exists", "rename"]).unwrap(),
+            Sidecars::Write(vec![path("report-3.txt"), path("report-3.json")])
+        );
+        assert_eq!(
+            resolve_with(&["--sidecar", "-f", "json", "-f", "hocr", "--sidecar-exists", "skip"]).unwrap(),
+            Sidecars::Write(vec![path("report.json"), path("report.hocr")])
+        );
+    }
+}
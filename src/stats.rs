@@ -0,0 +1,234 @@
+use crate::ocr::LanguageStat;
+use crate::output::PageResult;
+use std::time::Duration;
+
+/// Per-page figures for auditing quality across batches (`--stats`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PageStats {
+    /// Characters of the text layer, whitespace excluded.
+    pub text_chars: usize,
+    /// Characters of the OCR layer, whitespace excluded.
+    pub ocr_chars: usize,
+    /// Mean word confidence (0-100) Tesseract reported, also for a page rejected for low confidence.
+    pub ocr_conf: Option<f32>,
+    pub render: Duration,
+    pub ocr: Duration,
+    /// Script most letters of the page are written in, e.g. "Latin".
+    pub script: Option<&'static str>,
+    /// Text orientation detected by Tesseract, in degrees clockwise.
+    pub orientation: Option<i32>,
+    /// The page was read from its text layer alone, without OCR.
+    pub text_layer_only: bool,
+}
+
+impl PageStats {
+    pub fn of(page: &PageResult) -> Self {
+        let chars = |layer: &Option<crate::output::Layer>| layer.as_ref().map_or(0, |l| l.text.chars().filter(|c| !c.is_whitespace()).count());
+        let text_chars = chars(&page.text_layer);
+        Self {
+            text_chars,
+            ocr_chars: chars(&page.ocr_layer),
+            ocr_conf: page.ocr_conf,
+            render: page.timings.render,
+            ocr: page.timings.ocr,
+            script: script_histogram([page.read_text()]).first().map(|(script, _)| *script),
+            orientation: page.rotation.detected,
+            text_layer_only: text_chars > 0 && page.ocr_layer.is_none(),
+        }
+    }
+}
+
+/// Characters of a document by class.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CharClasses {
+    pub letters: usize,
+    pub digits: usize,
+    pub punctuation: usize,
+    pub whitespace: usize,
+    /// Symbols, marks and control characters.
+    pub other: usize,
+}
+
+impl CharClasses {
+    pub fn add(&mut self, text: &str) {
+        for c in text.chars() {
+            let class = if c.is_alphabetic() {
+                &mut self.letters
+            } else if c.is_numeric() {
+                &mut self.digits
+            } else if c.is_whitespace() {
+                &mut self.whitespace
+            } else if is_punctuation(c) {
+                &mut self.punctuation
+            } else {
+                &mut self.other
+            };
+            *class += 1;
+        }
+    }
+}
+
+/// ASCII and Latin-1 punctuation (not symbols such as `$` or `+`), general
+/// and CJK punctuation, and the marks of Arabic, Armenian, Hebrew and Devanagari.
+fn is_punctuation(c: char) -> bool {
+    (c.is_ascii_punctuation() && !"$+<=>^`|~".contains(c))
+        || matches!(c as u32, 0xA1 | 0xA7 | 0xAB | 0xB6 | 0xB7 | 0xBB | 0xBF | 0x2010..=0x2027 | 0x2030..=0x205E | 0x3000..=0x303F | 0xFF01..=0xFF0F)
+        || matches!(c, '\u{060C}' | '\u{061B}' | '\u{061F}' | '\u{0589}' | '\u{05BE}' | '\u{0964}' | '\u{0965}')
+}
+
+/// Figures of a whole document (`--stats`), for routing it to the right
+/// translation or NLP pipeline: scripts, OCR languages and character classes
+/// of the text its pages read as.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DocumentStats {
+    pub pages: usize,
+    /// Pages read from their text layer alone.
+    pub text_layer_only_pages: usize,
+    /// Letters per script, in order of first appearance.
+    scripts: Vec<(&'static str, usize)>,
+    /// OCR words per language, with the sum of their confidences.
+    languages: Vec<(String, usize, f32)>,
+    pub chars: CharClasses,
+}
+
+impl DocumentStats {
+    pub fn add(&mut self, page: &PageResult, stats: &PageStats) {
+        self.pages += 1;
+        if stats.text_layer_only {
+            self.text_layer_only_pages += 1;
+        }
+        let text = page.read_text();
+        self.chars.add(text);
+        for (script, letters) in script_histogram([text]) {
+            match self.scripts.iter_mut().find(|(s, _)| *s == script) {
+                Some((_, n)) => *n += letters,
+                None => self.scripts.push((script, letters)),
+            }
+        }
+        for lang in &page.languages {
+            let conf = lang.conf * lang.words as f32;
+            match self.languages.iter_mut().find(|(l, _, _)| *l == lang.lang) {
+                Some((_, words, sum)) => {
+                    *words += lang.words;
+                    *sum += conf;
+                }
+                None => self.languages.push((lang.lang.clone(), lang.words, conf)),
+            }
+        }
+    }
+
+    /// Letters per script with their share of all letters (0-1), most used first.
+    pub fn scripts(&self) -> Vec<(&'static str, usize, f32)> {
+        let total: usize = self.scripts.iter().map(|(_, n)| n).sum();
+        let mut scripts: Vec<_> = self.scripts.iter().map(|&(s, n)| (s, n, n as f32 / total as f32)).collect();
+        scripts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        scripts
+    }
+
+    /// OCR words per recognition language over the document, when several
+    /// languages are loaded, most used first.
+    pub fn languages(&self) -> Vec<LanguageStat> {
+        let total: usize = self.languages.iter().map(|(_, n, _)| n).sum();
+        let mut languages: Vec<LanguageStat> = self
+            .languages
+            .iter()
+            .filter(|(_, words, _)| *words > 0)
+            .map(|(lang, words, sum)| LanguageStat { lang: lang.clone(), words: *words, share: *words as f32 / total as f32, conf: sum / *words as f32 })
+            .collect();
+        languages.sort_by(|a, b| b.words.cmp(&a.words).then(a.lang.cmp(&b.lang)));
+        languages
+    }
+}
+
+/// Letters per script in `texts`, most used first (ties by name).
+pub fn script_histogram<'a>(texts: impl IntoIterator<Item = &'a str>) -> Vec<(&'static str, usize)> {
+    let mut counts: Vec<(&'static str, usize)> = Vec::new();
+    for c in texts.into_iter().flat_map(str::chars) {
+        if let Some(name) = script(c) {
+            match counts.iter_mut().find(|(s, _)| *s == name) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((name, 1)),
+            }
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    counts
+}
+
+/// Script of a letter, named as Tesseract's script models are, or `None`
+/// for digits, punctuation, symbols and scripts not listed.
+pub fn script(c: char) -> Option<&'static str> {
+    if !c.is_alphabetic() {
+        return None;
+    }
+    Some(match c as u32 {
+        0x0041..=0x024F | 0x1E00..=0x1EFF | 0xFB00..=0xFB06 => "Latin",
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => "Greek",
+        0x0400..=0x052F => "Cyrillic",
+        0x0530..=0x058F => "Armenian",
+        0x0590..=0x05FF | 0xFB1D..=0xFB4F => "Hebrew",
+        0x0600..=0x06FF | 0x0750..=0x077F | 0x08A0..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => "Arabic",
+        0x0900..=0x097F => "Devanagari",
+        0x0980..=0x09FF => "Bengali",
+        0x0A00..=0x0A7F => "Gurmukhi",
+        0x0A80..=0x0AFF => "Gujarati",
+        0x0B80..=0x0BFF => "Tamil",
+        0x0C00..=0x0C7F => "Telugu",
+        0x0C80..=0x0CFF => "Kannada",
+        0x0D00..=0x0D7F => "Malayalam",
+        0x0E00..=0x0E7F => "Thai",
+        0x10A0..=0x10FF => "Georgian",
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => "Hangul",
+        0x3040..=0x30FF => "Japanese",
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => "Han",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Layer;
+
+    #[test]
+    fn test_page_stats() {
+        let layer = |text: &str| Some(Layer { text: text.into(), units: Vec::new() });
+        let page = PageResult { number: 1, text_layer: layer("Résumé: Москва, Berlin 2024\n"), ..Default::default() };
+        let stats = PageStats::of(&page);
+        assert_eq!((stats.text_chars, stats.ocr_chars), (24, 0));
+        assert_eq!(stats.script, Some("Latin"));
+        assert!(stats.text_layer_only);
+
+        let page = PageResult { number: 2, text_layer: layer(""), ocr_layer: layer("Αθήνα 12\n"), ocr_conf: Some(81.0), ..Default::default() };
+        let stats = PageStats::of(&page);
+        assert_eq!((stats.text_chars, stats.ocr_chars, stats.ocr_conf), (0, 7, Some(81.0)));
+        assert_eq!(stats.script, Some("Greek"));
+        assert!(!stats.text_layer_only);
+        assert_eq!(script_histogram(["東京 tokyo とうきょう"]), [("Japanese", 5), ("Latin", 5), ("Han", 2)]);
+    }
+
+    #[test]
+    fn test_document_stats() {
+        let layer = |text: &str| Some(Layer { text: text.into(), units: Vec::new() });
+        let lang = |lang: &str, words: usize, conf: f32| LanguageStat { lang: lang.into(), words, share: 0.0, conf };
+        let pages = [
+            PageResult { number: 1, text_layer: layer("Preis: 12,50 €\n"), ..Default::default() },
+            PageResult {
+                number: 2,
+                ocr_layer: layer("Цена (руб.)\n"),
+                languages: vec![lang("rus", 2, 80.0), lang("deu", 1, 60.0)],
+                ..Default::default()
+            },
+            PageResult { number: 3, ocr_layer: layer("Summe\n"), languages: vec![lang("deu", 1, 90.0)], ..Default::default() },
+        ];
+        let mut stats = DocumentStats::default();
+        for page in &pages {
+            stats.add(page, &PageStats::of(page));
+        }
+        assert_eq!((stats.pages, stats.text_layer_only_pages), (3, 1));
+        assert_eq!(stats.scripts(), [("Latin", 10, 10.0 / 17.0), ("Cyrillic", 7, 7.0 / 17.0)]);
+        let languages: Vec<(String, usize, f32, f32)> = stats.languages().into_iter().map(|l| (l.lang, l.words, l.share, l.conf)).collect();
+        assert_eq!(languages, [("deu".into(), 2, 0.5, 75.0), ("rus".into(), 2, 0.5, 80.0)]);
+        assert_eq!(stats.chars, CharClasses { letters: 17, digits: 4, punctuation: 5, whitespace: 6, other: 1 });
+    }
+}
@@ -0,0 +1,187 @@
+use crate::cli::{Dpi, Mode};
+use crate::errors::CrabError;
+use crate::renderer::Renderer;
+use crate::schema;
+use crate::Options;
+use serde_json::json;
+use std::path::Path;
+
+/// Side of the square generated pages in points, so text fits whatever its rotation.
+const PAGE_SIZE: f32 = 842.0;
+/// Text of every generated page: words, codes, dates and amounts as found in reports.
+const LINES: [&str; 3] = [
+    "The quick brown fox jumps over the lazy dog",
+    "Invoice 2024-0193 dated 14 March 2024",
+    "Total due 1284.50 EUR within 30 days",
+];
+/// Font sizes (points) at which the longest line fits on the page in Courier, the widest face.
+pub const MIN_SIZE: f32 = 4.0;
+pub const MAX_SIZE: f32 = 32.0;
+
+/// A generated page: its standard 14 font, size in points and rotation in
+/// degrees counterclockwise.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Case {
+    pub font: String,
+    pub size: f32,
+    pub rotation: u32,
+}
+
+/// Generate a PDF with one page of known text per font, size and rotation,
+/// run it through extraction and OCR (`-m hybrid`, as the main command does),
+/// and print the share of words each layer recovered as JSON. Fails when the
+/// mean recovery of either layer is below `min_recovery`, so the run can gate
+/// a build or a deployment.
+pub fn run(fonts: &[String], sizes: &[f32], rotations: &[u32], lang: &str, dpi: i32, min_recovery: f32, keep: Option<&Path>) -> Result<(), CrabError> {
+    if let Some(size) = sizes.iter().find(|s| !(MIN_SIZE..=MAX_SIZE).contains(*s)) {
+        return Err(CrabError::Cli(format!("font size {} is outside {}-{} points", size, MIN_SIZE, MAX_SIZE)));
+    }
+    let cases: Vec<Case> = fonts
+        .iter()
+        .flat_map(|font| sizes.iter().flat_map(move |&size| rotations.iter().map(move |&rotation| Case { font: font.clone(), size, rotation })))
+        .collect();
+    if cases.is_empty() {
+        return Err(CrabError::Cli("synth-test needs at least one font, size and rotation".into()));
+    }
+
+    let dir = tempfile::tempdir()?;
+    let pdf_path = keep.map_or_else(|| dir.path().join("synth.pdf"), Path::to_path_buf);
+    write_pdf(&cases, &pdf_path)?;
+
+    // Negative DPIs are rejected by the extraction like any out-of-range DPI
+    let options = Options { lang: lang.into(), mode: Mode::Hybrid, dpi: Dpi::Fixed(u32::try_from(dpi).unwrap_or(0)), ..Default::default() };
+    let doc = crate::ocr_document(&pdf_path, &options)?;
+
+    let expected = LINES.join("\n");
+    let layer_text = |layer: &Option<Vec<schema::Unit>>| layer.iter().flatten().map(|u| u.text.as_str()).collect::<Vec<_>>().join("\n");
+    let mut results = Vec::new();
+    let (mut text_total, mut ocr_total) = (0.0, 0.0);
+    for (case, page) in cases.iter().zip(doc.pages.iter().flatten()) {
+        let text = recall(&expected, &layer_text(&page.text_layer));
+        let ocr = recall(&expected, &layer_text(&page.ocr_layer));
+        text_total += text;
+        ocr_total += ocr;
+        results.push(json!({ "font": case.font, "size": case.size, "rotation": case.rotation, "text_layer": round(text), "ocr": round(ocr) }));
+    }
+    let (text_layer, ocr) = (text_total / cases.len() as f64, ocr_total / cases.len() as f64);
+    let passed = text_layer >= min_recovery as f64 && ocr >= min_recovery as f64;
+
+    let out = json!({
+        "lang": lang,
+        "dpi": dpi,
+        "min_recovery": min_recovery,
+        "text_layer": round(text_layer),
+        "ocr": round(ocr),
+        "passed": passed,
+        "cases": results,
+    });
+    let out = serde_json::to_string_pretty(&out).map_err(|e| CrabError::Internal(e.to_string()))?;
+    println!("{}", out);
+    if !passed {
+        return Err(CrabError::Internal(format!(
+            "synth-test failed: recovered {:.1}% of words from the text layer and {:.1}% with OCR, below {:.1}%",
+            text_layer * 100.0,
+            ocr * 100.0,
+            min_recovery * 100.0
+        )));
+    }
+    Ok(())
+}
+
+fn write_pdf(cases: &[Case], path: &Path) -> Result<(), CrabError> {
+    let renderer = Renderer::new()?;
+    let mut pdf = renderer.new_text_pdf()?;
+    let result = cases
+        .iter()
+        .try_for_each(|case| renderer.pdf_add_text_page(&pdf, (PAGE_SIZE, PAGE_SIZE), &case.font, &content(case)))
+        .and_then(|_| renderer.save_pdf(&pdf, path));
+    pdf.drop_with(&renderer);
+    result
+}
+
+/// Content stream drawing `LINES` in the font of `case` (`/F1`), centered
+/// on the page and rotated about its center.
+fn content(case: &Case) -> String {
+    let (cos, sin) = match case.rotation {
+        90 => (0, 1),
+        180 => (-1, 0),
+        270 => (0, -1),
+        _ => (1, 0),
+    };
+    let leading = case.size * 1.5;
+    let longest = LINES.iter().map(|l| l.len()).max().unwrap_or(0) as f32;
+    let center = PAGE_SIZE / 2.0;
+    let mut ops = format!("BT\n/F1 {} Tf\n{} TL\n{} {} {} {} {} {} Tm\n", case.size, leading, cos, sin, -sin, cos, center, center);
+    // Courier glyphs are 0.6 em wide; other faces are narrower
+    ops.push_str(&format!("{} {} Td\n", -0.3 * case.size * longest, leading * (LINES.len() - 1) as f32 / 2.0));
+    for line in LINES {
+        ops.push_str(&format!("({}) Tj T*\n", escape_string(line)));
+    }
+    ops.push_str("ET\n");
+    ops
+}
+
+/// Escape text for a PDF literal string.
+fn escape_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '(' | ')' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Share of the words of `expected` found in `text`, each matched once,
+/// ignoring case and the punctuation around words.
+pub fn recall(expected: &str, text: &str) -> f64 {
+    let words = |s: &str| -> Vec<String> {
+        s.split_whitespace()
+            .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect()
+    };
+    let expected = words(expected);
+    if expected.is_empty() {
+        return 1.0;
+    }
+    let mut found = words(text);
+    let hits = expected
+        .iter()
+        .filter(|word| match found.iter().position(|f| f == *word) {
+            Some(i) => {
+                found.swap_remove(i);
+                true
+            }
+            None => false,
+        })
+        .count();
+    hits as f64 / expected.len() as f64
+}
+
+fn round(share: f64) -> f64 {
+    (share * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recall() {
+        assert_eq!(recall("Total due 1284.50 EUR", "TOTAL due: 1284.50 EUR"), 1.0);
+        assert_eq!(recall("the fox and the dog", "the fox and dog"), 0.8);
+        assert_eq!(recall("Invoice 2024-0193", "lnvoice 2024-0193"), 0.5);
+        assert_eq!(recall("", "anything"), 1.0);
+    }
+
+    #[test]
+    fn test_content() {
+        let case = Case { font: "Courier".into(), size: 10.0, rotation: 90 };
+        let ops = content(&case);
+        assert!(ops.starts_with("BT\n/F1 10 Tf\n15 TL\n0 1 -1 0 421 421 Tm\n-129 15 Td\n(The quick brown fox jumps over the lazy dog) Tj T*\n"));
+        assert!(ops.ends_with("ET\n"));
+        assert_eq!(escape_string(r"a (b) \c"), r"a \(b\) \\c");
+    }
+}
@@ -0,0 +1,230 @@
+use crate::ocr::Word;
+use crate::output::ImagePlacement;
+use crate::renderer::TextLine;
+
+/// Fewest rows of a table, header included.
+const MIN_ROWS: usize = 3;
+/// Fewest columns of a table.
+const MIN_COLUMNS: usize = 2;
+/// Gap between two OCR words of a line, in word heights, that separates cells.
+const CELL_GAP: f32 = 1.0;
+/// Gap between two rows, in row heights, that ends a table.
+const ROW_GAP: f32 = 2.0;
+/// Tables whose cells are longer than this on average (characters) are
+/// columns of prose, not tables.
+const MAX_MEAN_CELL_CHARS: usize = 30;
+
+/// A table detected on a page (`--tables`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    /// Bounds on the page in points (x0, y0, x1, y1).
+    pub rect: [f32; 4],
+    /// Cell texts row by row, one per column (empty where a row has no cell).
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// The table as CSV (RFC 4180), one line per row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for row in &self.rows {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| {
+                    if cell.contains([',', '"', '\n', '\r']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect();
+            csv.push_str(&cells.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// A piece of text that fills at most one cell.
+struct Fragment {
+    rect: [f32; 4],
+    text: String,
+}
+
+/// Tables among the text-layer lines of a page. MuPDF starts a new line at
+/// wide gaps, so each line is taken as a cell.
+pub fn from_lines(lines: &[TextLine]) -> Vec<Table> {
+    let fragments = lines
+        .iter()
+        .filter(|l| !l.text.trim().is_empty())
+        .map(|l| Fragment { rect: l.rect, text: l.text.trim().to_string() })
+        .collect();
+    detect(fragments)
+}
+
+/// Tables among the OCR words of a page: words of a line are split into cells
+/// at gaps wider than the words are tall. `placement` maps word pixels to the page.
+pub fn from_words(words: &[Word], placement: ImagePlacement) -> Vec<Table> {
+    let mut fragments: Vec<Fragment> = Vec::new();
+    let mut prev: Option<&Word> = None;
+    for word in words {
+        let rect = placement.to_points(word.bbox);
+        let same_cell = prev.is_some_and(|p| {
+            let height = (word.bbox[3] - word.bbox[1]).max(p.bbox[3] - p.bbox[1]);
+            p.line == word.line && (word.bbox[0] - p.bbox[2]) as f32 <= CELL_GAP * height as f32
+        });
+        match fragments.last_mut() {
+            Some(fragment) if same_cell => {
+                fragment.rect = union(fragment.rect, rect);
+                fragment.text.push(' ');
+                fragment.text.push_str(&word.text);
+            }
+            _ => fragments.push(Fragment { rect, text: word.text.clone() }),
+        }
+        prev = Some(word);
+    }
+    detect(fragments)
+}
+
+/// Group fragments into rows by vertical position, then take runs of close
+/// rows with several cells each as tables, with columns where their cells line up.
+fn detect(mut fragments: Vec<Fragment>) -> Vec<Table> {
+    let center_y = |f: &Fragment| (f.rect[1] + f.rect[3]) / 2.0;
+    fragments.sort_by(|a, b| center_y(a).total_cmp(&center_y(b)));
+    let mut rows: Vec<Vec<Fragment>> = Vec::new();
+    for fragment in fragments {
+        let cy = center_y(&fragment);
+        match rows.last_mut() {
+            Some(row) if row.iter().any(|f| f.rect[1] <= cy && cy <= f.rect[3]) => row.push(fragment),
+            _ => rows.push(vec![fragment]),
+        }
+    }
+    for row in &mut rows {
+        row.sort_by(|a, b| a.rect[0].total_cmp(&b.rect[0]));
+    }
+
+    let close = |above: &[Fragment], below: &[Fragment]| {
+        let bottom = above.iter().map(|f| f.rect[3]).fold(f32::MIN, f32::max);
+        let height = above.iter().map(|f| f.rect[3] - f.rect[1]).fold(0.0, f32::max);
+        let top = below.iter().map(|f| f.rect[1]).fold(f32::MAX, f32::min);
+        top - bottom <= ROW_GAP * height
+    };
+    let mut tables = Vec::new();
+    let mut start = 0;
+    while start < rows.len() {
+        let mut end = start;
+        while end < rows.len() && rows[end].len() >= MIN_COLUMNS && (end == start || close(&rows[end - 1], &rows[end])) {
+            end += 1;
+        }
+        if end - start >= MIN_ROWS {
+            tables.extend(table(&rows[start..end]));
+        }
+        start = end.max(start + 1);
+    }
+    tables
+}
+
+/// Lay rows out in columns: the horizontal extents of their cells, merged where they overlap.
+fn table(rows: &[Vec<Fragment>]) -> Option<Table> {
+    let mut extents: Vec<[f32; 2]> = rows.iter().flatten().map(|f| [f.rect[0], f.rect[2]]).collect();
+    extents.sort_by(|a, b| a[0].total_cmp(&b[0]));
+    let mut columns: Vec<[f32; 2]> = Vec::new();
+    for [x0, x1] in extents {
+        match columns.last_mut() {
+            Some(column) if x0 < column[1] => column[1] = column[1].max(x1),
+            _ => columns.push([x0, x1]),
+        }
+    }
+    if columns.len() < MIN_COLUMNS {
+        return None;
+    }
+
+    let mut cells = 0;
+    let mut chars = 0;
+    let mut rect = rows[0][0].rect;
+    let table_rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            let mut cells_of_row = vec![String::new(); columns.len()];
+            for fragment in row {
+                rect = union(rect, fragment.rect);
+                let column = columns.iter().position(|c| fragment.rect[0] < c[1]).unwrap_or(columns.len() - 1);
+                let cell = &mut cells_of_row[column];
+                if !cell.is_empty() {
+                    cell.push(' ');
+                }
+                cell.push_str(&fragment.text);
+                cells += 1;
+                chars += fragment.text.chars().count();
+            }
+            cells_of_row
+        })
+        .collect();
+    if chars > MAX_MEAN_CELL_CHARS * cells {
+        return None;
+    }
+    Some(Table { rect, rows: table_rows })
+}
+
+fn union(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, rect: [f32; 4]) -> TextLine {
+        TextLine { rect, text: text.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_invoice_lines() {
+        let lines = [
+            line("Invoice 2024-117", [50.0, 40.0, 200.0, 52.0]),
+            line("Item", [50.0, 100.0, 80.0, 110.0]),
+            line("Qty", [300.0, 100.0, 320.0, 110.0]),
+            line("Price", [400.0, 100.0, 430.0, 110.0]),
+            line("Paper, A4", [50.0, 115.0, 110.0, 125.0]),
+            line("2", [310.0, 115.0, 316.0, 125.0]),
+            line("4.50", [400.0, 115.0, 425.0, 125.0]),
+            line("Toner", [50.0, 130.0, 85.0, 140.0]),
+            line("39.90", [400.0, 130.0, 430.0, 140.0]),
+            line("Thank you for your business.", [50.0, 300.0, 250.0, 312.0]),
+        ];
+        let tables = from_lines(&lines);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].rect, [50.0, 100.0, 430.0, 140.0]);
+        assert_eq!(tables[0].to_csv(), "Item,Qty,Price\n\"Paper, A4\",2,4.50\nToner,,39.90\n");
+    }
+
+    #[test]
+    fn test_prose_columns_are_not_tables() {
+        let text = "a line of running text in a two-column layout";
+        let lines: Vec<TextLine> = (0..6)
+            .flat_map(|i| {
+                let y = 100.0 + i as f32 * 14.0;
+                [line(text, [50.0, y, 280.0, y + 10.0]), line(text, [310.0, y, 540.0, y + 10.0])]
+            })
+            .collect();
+        assert!(from_lines(&lines).is_empty());
+    }
+
+    #[test]
+    fn test_ocr_words() {
+        let word = |text: &str, bbox: [i32; 4], line: usize| Word { text: text.into(), conf: 90.0, bbox, block: 0, para: 0, line, lang: None };
+        // At 144 DPI: words far apart on a line are separate cells
+        let words = [
+            word("Unit", [100, 100, 160, 120], 0),
+            word("price", [170, 100, 230, 120], 0),
+            word("Total", [600, 100, 660, 120], 0),
+            word("10.00", [100, 130, 170, 150], 1),
+            word("20.00", [600, 130, 670, 150], 1),
+            word("5.00", [100, 160, 160, 180], 2),
+            word("5.00", [600, 160, 660, 180], 2),
+        ];
+        let tables = from_words(&words, ImagePlacement { scale: 0.5, offset: [0.0, 0.0] });
+        assert_eq!(tables[0].rows, [["Unit price", "Total"], ["10.00", "20.00"], ["5.00", "5.00"]]);
+        assert_eq!(tables[0].rect, [50.0, 50.0, 335.0, 90.0]);
+    }
+}
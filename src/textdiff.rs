@@ -0,0 +1,172 @@
+use crate::cli::{Granularity, Mode};
+use crate::errors::CrabError;
+use crate::ocr::Ocr;
+use crate::preprocess::GrayImage;
+use crate::renderer::{Document, Renderer};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// A line of page text with its bounds on the page in points (x0, y0, x1, y1).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub text: String,
+    pub rect: [f32; 4],
+}
+
+/// Read both PDFs and print the lines that differ, page by page, as JSON.
+/// In hybrid mode pages are read from the text layer, or with OCR when they
+/// have none, so a scan can be compared against its digital original.
+/// With `boxes`, each differing line carries its bounds on the page.
+pub fn run(a: &Path, b: &Path, mode: &Mode, lang: &str, dpi: i32, boxes: bool) -> Result<(), CrabError> {
+    if *mode == Mode::XfaOnly {
+        return Err(CrabError::Cli("diff compares page text; use xfa-diff to compare form data".into()));
+    }
+    let renderer = Renderer::new()?;
+    let ocr = match mode {
+        Mode::Text => None,
+        _ => Some(Ocr::new(lang)?),
+    };
+    let old = read_pages(&renderer, ocr.as_ref(), a, mode, dpi)?;
+    let new = read_pages(&renderer, ocr.as_ref(), b, mode, dpi)?;
+
+    let mut pages = Vec::new();
+    for i in 0..old.len().max(new.len()) {
+        let (old_lines, new_lines) = (old.get(i).map_or(&[][..], Vec::as_slice), new.get(i).map_or(&[][..], Vec::as_slice));
+        let changes = diff_lines(old_lines, new_lines);
+        if changes.is_empty() {
+            continue;
+        }
+        let changes: Vec<Value> = changes
+            .into_iter()
+            .map(|change| {
+                let (op, index, line) = match change {
+                    Change::Removed(i) => ("removed", i, &old_lines[i]),
+                    Change::Added(i) => ("added", i, &new_lines[i]),
+                };
+                let mut value = json!({ "op": op, "line": index + 1, "text": line.text });
+                if boxes {
+                    value["rect"] = json!(line.rect);
+                }
+                value
+            })
+            .collect();
+        pages.push(json!({ "page": i + 1, "changes": changes }));
+    }
+
+    let out = json!({
+        "page_count": { "a": old.len(), "b": new.len() },
+        "unchanged_pages": old.len().max(new.len()) - pages.len(),
+        "pages": pages,
+    });
+    let out = serde_json::to_string_pretty(&out).map_err(|e| CrabError::Internal(e.to_string()))?;
+    println!("{}", out);
+    Ok(())
+}
+
+fn read_pages(renderer: &Renderer, ocr: Option<&Ocr>, path: &Path, mode: &Mode, dpi: i32) -> Result<Vec<Vec<Line>>, CrabError> {
+    let mut doc = renderer.open(path)?;
+    let pages = (0..renderer.page_count(&doc)?)
+        .map(|page| read_page(renderer, &doc, ocr, page, mode, dpi))
+        .collect();
+    doc.drop_with(renderer);
+    pages
+}
+
+fn read_page(renderer: &Renderer, doc: &Document, ocr: Option<&Ocr>, page: i32, mode: &Mode, dpi: i32) -> Result<Vec<Line>, CrabError> {
+    if *mode != Mode::Ocr {
+        let lines: Vec<Line> = renderer
+            .extract_lines(doc, page)?
+            .into_iter()
+            .map(|line| Line { text: normalize(&line.text), rect: line.rect })
+            .filter(|line| !line.text.is_empty())
+            .collect();
+        if !lines.is_empty() || *mode == Mode::Text {
+            return Ok(lines);
+        }
+    }
+    let Some(ocr) = ocr else { return Ok(Vec::new()) };
+
+    let mut pix = renderer.render_page(doc, page, dpi)?;
+    let image = GrayImage::from_pixmap(&pix, renderer);
+    pix.drop_with(renderer);
+    let result = ocr.recognize(&image, dpi, Granularity::Line)?;
+
+    let scale = 72.0 / dpi as f32;
+    let mut lines: Vec<(usize, Line)> = Vec::new();
+    for word in &result.words {
+        let rect = word.bbox.map(|v| v as f32 * scale);
+        match lines.last_mut() {
+            Some((index, line)) if *index == word.line => {
+                line.text.push(' ');
+                line.text.push_str(&word.text);
+                line.rect = [line.rect[0].min(rect[0]), line.rect[1].min(rect[1]), line.rect[2].max(rect[2]), line.rect[3].max(rect[3])];
+            }
+            _ => lines.push((word.line, Line { text: word.text.clone(), rect })),
+        }
+    }
+    Ok(lines.into_iter().map(|(_, line)| line).filter(|line| !line.text.trim().is_empty()).collect())
+}
+
+/// Collapse runs of whitespace, which differ between text layers and OCR.
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A line only in the first page (by index there) or only in the second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Change {
+    Removed(usize),
+    Added(usize),
+}
+
+/// Lines to remove from `old` and add to get `new`, in page order, from
+/// the longest common subsequence of the line texts.
+fn diff_lines(old: &[Line], new: &[Line]) -> Vec<Change> {
+    let (n, m) = (old.len(), new.len());
+    // common[i][j]: length of the common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if old[i].text == new[j].text {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i].text == new[j].text {
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && common[i + 1][j] >= common[i][j + 1]) {
+            changes.push(Change::Removed(i));
+            i += 1;
+        } else {
+            changes.push(Change::Added(j));
+            j += 1;
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(texts: &[&str]) -> Vec<Line> {
+        texts.iter().map(|t| Line { text: t.to_string(), rect: [0.0; 4] }).collect()
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let old = lines(&["Agreement", "The price is 100 EUR.", "Signed:", "Date:"]);
+        let new = lines(&["Agreement", "The price is 120 EUR.", "Signed:", "J. Doe", "Date:"]);
+        assert_eq!(diff_lines(&old, &new), [Change::Removed(1), Change::Added(1), Change::Added(3)]);
+        assert!(diff_lines(&old, &old).is_empty());
+        assert_eq!(diff_lines(&old[..1], &[]), [Change::Removed(0)]);
+        assert_eq!(normalize("  The  price\tis "), "The price is");
+    }
+}
@@ -0,0 +1,213 @@
+use crate::errors::CrabError;
+use crate::schema;
+
+/// What a warning is about, as a stable identifier for tooling.
+/// Selected on the command line in kebab case (`low-confidence`).
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// The requested DPI was out of range and clamped.
+    DpiClamped,
+    /// The `--dump-ocr-input` filesystem looks too small.
+    LowDiskSpace,
+    /// XFA could not be parsed and was output as raw XML.
+    XfaFallback,
+    /// The text layer does not match what OCR reads (`--check-text-layer`).
+    TextLayerMismatch,
+    /// The text layer looks broken.
+    SuspiciousTextLayer,
+    /// MuPDF could not extract the text layer of a page.
+    TextExtractionFailed,
+    /// The models asked for by `--models` or `--preset` were not found.
+    ModelsUnavailable,
+    /// The page has no text layer but draws its text as vector outlines.
+    OutlinedText,
+    /// Form fields (signatures) could not be read.
+    FormReadFailed,
+    /// The document could not be scanned for scripts and external references (`--page-info`).
+    SecurityScanFailed,
+    /// Fonts could not be listed or extracted (`--page-info`, `--extract-fonts`).
+    FontReadFailed,
+    /// A page property, spread or annotation could not be read or rendered.
+    PageReadFailed,
+    /// Remaining pages were degraded or skipped near `--cpu-time-limit` or `--wall-limit`.
+    ResourceLimit,
+    /// OCR output was dropped because Tesseract's mean confidence was too low.
+    LowConfidence,
+    /// OCR lines were dropped by `--garbage-ratio`.
+    GarbageDropped,
+    /// A page rendered only after `--render-retries`.
+    RenderRetried,
+    /// `osd.traineddata` is missing, so page orientation is not detected.
+    OsdUnavailable,
+}
+
+impl WarningKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningKind::DpiClamped => "dpi_clamped",
+            WarningKind::LowDiskSpace => "low_disk_space",
+            WarningKind::XfaFallback => "xfa_fallback",
+            WarningKind::TextLayerMismatch => "text_layer_mismatch",
+            WarningKind::SuspiciousTextLayer => "suspicious_text_layer",
+            WarningKind::TextExtractionFailed => "text_extraction_failed",
+            WarningKind::ModelsUnavailable => "models_unavailable",
+            WarningKind::OutlinedText => "outlined_text",
+            WarningKind::FormReadFailed => "form_read_failed",
+            WarningKind::SecurityScanFailed => "security_scan_failed",
+            WarningKind::FontReadFailed => "font_read_failed",
+            WarningKind::PageReadFailed => "page_read_failed",
+            WarningKind::ResourceLimit => "resource_limit",
+            WarningKind::LowConfidence => "low_confidence",
+            WarningKind::GarbageDropped => "garbage_dropped",
+            WarningKind::RenderRetried => "render_retried",
+            WarningKind::OsdUnavailable => "osd_unavailable",
+        }
+    }
+}
+
+/// A problem that did not stop the run.
+#[derive(Clone, Debug)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// 1-based page number, for page-level warnings.
+    pub page: Option<usize>,
+    /// Half of a split spread the warning is about.
+    pub part: Option<char>,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn to_schema(&self) -> schema::Warning {
+        schema::Warning { kind: self.kind.as_str().to_string(), message: self.message.clone(), page: self.page, part: self.part }
+    }
+}
+
+/// Warnings collected during a run. Each is printed to STDERR as it happens
+/// (unless `--quiet`) and kept for the JSON output and the end-of-run summary.
+#[derive(Default)]
+pub struct Warnings {
+    list: Vec<Warning>,
+    /// Keep warnings off STDERR (`--quiet`).
+    quiet: bool,
+    /// Kinds dropped without a trace (`--ignore-warning`).
+    ignored: Vec<WarningKind>,
+    /// Kinds that fail the run (`--warn-as-error`).
+    as_error: Vec<WarningKind>,
+}
+
+impl Warnings {
+    pub fn new(quiet: bool) -> Self {
+        Self { quiet, ..Default::default() }
+    }
+
+    /// Drop the `ignored` kinds and fail the run on the `as_error` ones.
+    pub fn with_policy(mut self, ignored: &[WarningKind], as_error: &[WarningKind]) -> Self {
+        self.ignored = ignored.to_vec();
+        self.as_error = as_error.to_vec();
+        self
+    }
+
+    /// Record a document-level warning.
+    pub fn push(&mut self, kind: WarningKind, message: impl Into<String>) {
+        self.record(Warning { kind, page: None, part: None, message: message.into() });
+    }
+
+    /// Record a warning about a page (`part` for a half of a split spread).
+    pub fn push_page(&mut self, kind: WarningKind, page: usize, part: Option<char>, message: impl Into<String>) {
+        self.record(Warning { kind, page: Some(page), part, message: message.into() });
+    }
+
+    fn record(&mut self, warning: Warning) {
+        if self.ignored.contains(&warning.kind) {
+            return;
+        }
+        if !self.quiet {
+            let label = if self.as_error.contains(&warning.kind) { "Error" } else { "Warning" };
+            match warning.page {
+                Some(page) => eprintln!(
+                    "{}: Page {}{}: {}",
+                    label,
+                    page,
+                    warning.part.map(String::from).unwrap_or_default(),
+                    warning.message
+                ),
+                None => eprintln!("{}: {}", label, warning.message),
+            }
+        }
+        self.list.push(warning);
+    }
+
+    /// The first warning of a kind promoted with `--warn-as-error`, as an error.
+    pub fn promoted_error(&self) -> Option<CrabError> {
+        let warning = self.list.iter().find(|w| self.as_error.contains(&w.kind))?;
+        let page = warning.page.map(|p| format!("page {}{}: ", p, warning.part.map(String::from).unwrap_or_default()));
+        Some(CrabError::Warning(format!(
+            "{}{} ({})",
+            page.unwrap_or_default(),
+            warning.message,
+            warning.kind.as_str()
+        )))
+    }
+
+    pub fn list(&self) -> &[Warning] {
+        &self.list
+    }
+
+    /// One line counting warnings by kind, e.g. "3 warnings (2 low_confidence, 1 xfa_fallback)".
+    /// `None` when there were none.
+    pub fn summary(&self) -> Option<String> {
+        if self.list.is_empty() {
+            return None;
+        }
+        let mut counts: Vec<(WarningKind, usize)> = Vec::new();
+        for warning in &self.list {
+            match counts.iter_mut().find(|(kind, _)| *kind == warning.kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((warning.kind, 1)),
+            }
+        }
+        let parts: Vec<String> = counts.iter().map(|(kind, count)| format!("{} {}", count, kind.as_str())).collect();
+        let noun = if self.list.len() == 1 { "warning" } else { "warnings" };
+        Some(format!("{} {} ({})", self.list.len(), noun, parts.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_and_json() {
+        let mut warnings = Warnings::default();
+        assert_eq!(warnings.summary(), None);
+
+        warnings.push_page(WarningKind::LowConfidence, 2, None, "OCR output dropped");
+        warnings.push(WarningKind::XfaFallback, "raw XML");
+        warnings.push_page(WarningKind::LowConfidence, 5, Some('b'), "OCR output dropped");
+        assert_eq!(
+            warnings.summary().unwrap(),
+            "3 warnings (2 low_confidence, 1 xfa_fallback)"
+        );
+
+        assert!(warnings.promoted_error().is_none());
+
+        let json = serde_json::to_value(warnings.list()[2].to_schema()).unwrap();
+        assert_eq!(json["kind"], "low_confidence");
+        assert_eq!(json["page"], 5);
+        assert_eq!(json["part"], "b");
+        assert!(serde_json::to_value(warnings.list()[1].to_schema()).unwrap().get("page").is_none());
+    }
+
+    #[test]
+    fn test_policy() {
+        let mut warnings = Warnings::new(true).with_policy(&[WarningKind::XfaFallback], &[WarningKind::LowConfidence]);
+        warnings.push(WarningKind::XfaFallback, "raw XML");
+        assert!(warnings.list().is_empty());
+        warnings.push(WarningKind::DpiClamped, "clamped");
+        assert!(warnings.promoted_error().is_none());
+        warnings.push_page(WarningKind::LowConfidence, 4, Some('a'), "OCR output dropped");
+        let error = warnings.promoted_error().unwrap();
+        assert_eq!(error.exit_code(), 8);
+        assert_eq!(error.to_string(), "Warning treated as error: page 4a: OCR output dropped (low_confidence)");
+    }
+}
@@ -7,10 +7,19 @@ void my_warning_cb(void *user, const char *message) {
   (void)message;
 }
 
+// No-op error callback: errors reach Rust through the err_out buffers, and
+// MuPDF never writes to stderr, which the OCR thread may be redirecting
+// while a render thread runs.
+void my_error_cb(void *user, const char *message) {
+  (void)user;
+  (void)message;
+}
+
 fz_context *my_new_context() {
   fz_context *ctx = fz_new_context(NULL, NULL, FZ_STORE_DEFAULT);
   if (ctx) {
     fz_set_warning_callback(ctx, my_warning_cb, NULL);
+    fz_set_error_callback(ctx, my_error_cb, NULL);
   }
   return ctx;
 }
@@ -20,8 +29,58 @@ void my_drop_context(fz_context *ctx) {
     fz_drop_context(ctx);
 }
 
+void my_empty_store(fz_context *ctx) {
+  if (ctx)
+    fz_empty_store(ctx);
+}
+
+// The encryption of a PDF document, if it has any.
+static void read_encryption(fz_context *ctx, fz_document *doc,
+                            my_encryption *enc_out) {
+  pdf_document *pdf = pdf_specifics(ctx, doc);
+  memset(enc_out, 0, sizeof(*enc_out));
+  if (!pdf || !pdf->crypt)
+    return;
+  fz_try(ctx) {
+    pdf_obj *encrypt =
+        pdf_dict_get(ctx, pdf_trailer(ctx, pdf), PDF_NAME(Encrypt));
+    fz_strlcpy(enc_out->handler,
+               pdf_to_name(ctx, pdf_dict_get(ctx, encrypt, PDF_NAME(Filter))),
+               sizeof(enc_out->handler));
+    fz_strlcpy(enc_out->method, pdf_crypt_method(ctx, pdf->crypt),
+               sizeof(enc_out->method));
+    enc_out->version = pdf_crypt_version(ctx, pdf->crypt);
+    enc_out->revision = pdf_crypt_revision(ctx, pdf->crypt);
+    enc_out->length = pdf_crypt_length(ctx, pdf->crypt);
+    enc_out->permissions = pdf_crypt_permissions(ctx, pdf->crypt);
+    enc_out->encrypted = 1;
+  }
+  fz_catch(ctx) { enc_out->encrypted = 0; }
+}
+
+// An opened document that cannot be read without a password (MuPDF tries
+// the empty one) is dropped, after its encryption is read into enc_out:
+// returns 2 with *doc_out reset.
+static int needs_password(fz_context *ctx, fz_document **doc_out,
+                          my_encryption *enc_out, char *err_out,
+                          size_t err_len) {
+  int needs = 0;
+  fz_try(ctx) { needs = fz_needs_password(ctx, *doc_out); }
+  fz_catch(ctx) { needs = 1; }
+  if (!needs)
+    return 0;
+  if (enc_out)
+    read_encryption(ctx, *doc_out, enc_out);
+  fz_drop_document(ctx, *doc_out);
+  *doc_out = NULL;
+  if (err_out)
+    strncpy(err_out, "document needs a password", err_len - 1);
+  return 2;
+}
+
 int my_open_document(fz_context *ctx, const char *filename,
-                     fz_document **doc_out, char *err_out, size_t err_len) {
+                     fz_document **doc_out, my_encryption *enc_out,
+                     char *err_out, size_t err_len) {
   if (!ctx || !filename || !doc_out)
     return -1;
   *doc_out = NULL;
@@ -35,7 +94,22 @@ int my_open_document(fz_context *ctx, const char *filename,
       strncpy(err_out, fz_caught_message(ctx), err_len - 1);
     return 1;
   }
-  return 0;
+  return needs_password(ctx, doc_out, enc_out, err_out, err_len);
+}
+
+int my_open_pdf(fz_context *ctx, const char *filename, fz_document **doc_out,
+                my_encryption *enc_out, char *err_out, size_t err_len) {
+  if (!ctx || !filename || !doc_out)
+    return -1;
+  *doc_out = NULL;
+
+  fz_try(ctx) { *doc_out = &pdf_open_document(ctx, filename)->super; }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return needs_password(ctx, doc_out, enc_out, err_out, err_len);
 }
 
 void my_drop_document(fz_context *ctx, fz_document *doc) {
@@ -57,14 +131,91 @@ int my_count_pages(fz_context *ctx, fz_document *doc, int *count_out,
   return 0;
 }
 
-int my_render_page(fz_context *ctx, fz_document *doc, int page_number, int dpi,
-                   fz_pixmap **pix_out, char *err_out, size_t err_len) {
+int my_page_size(fz_context *ctx, fz_document *doc, int page_number,
+                 float *width_out, float *height_out, char *err_out,
+                 size_t err_len) {
+  if (!ctx || !doc || !width_out || !height_out)
+    return -1;
+
+  fz_page *page = NULL;
+  fz_var(page);
+  fz_try(ctx) {
+    page = fz_load_page(ctx, doc, page_number);
+    fz_rect bounds = fz_bound_page(ctx, page);
+    *width_out = bounds.x1 - bounds.x0;
+    *height_out = bounds.y1 - bounds.y0;
+  }
+  fz_always(ctx) { fz_drop_page(ctx, page); }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+int my_page_boxes(fz_context *ctx, fz_document *doc, int page_number,
+                  float *boxes_out, char *err_out, size_t err_len) {
+  if (!ctx || !doc || !boxes_out)
+    return -1;
+
+  fz_try(ctx) {
+    fz_page *page = fz_load_page(ctx, doc, page_number);
+    fz_box_type types[3] = {FZ_MEDIA_BOX, FZ_CROP_BOX, FZ_TRIM_BOX};
+    for (int i = 0; i < 3; i++) {
+      fz_rect r = fz_bound_page_box(ctx, page, types[i]);
+      boxes_out[i * 4 + 0] = r.x0;
+      boxes_out[i * 4 + 1] = r.y0;
+      boxes_out[i * 4 + 2] = r.x1;
+      boxes_out[i * 4 + 3] = r.y1;
+    }
+    fz_drop_page(ctx, page);
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+int my_page_rotation(fz_context *ctx, fz_document *doc, int page_number,
+                     int *rotate_out, char *err_out, size_t err_len) {
+  if (!ctx || !doc || !rotate_out)
+    return -1;
+  *rotate_out = 0;
+
+  fz_try(ctx) {
+    pdf_document *pdoc = pdf_specifics(ctx, doc);
+    if (pdoc) {
+      pdf_obj *page = pdf_lookup_page_obj(ctx, pdoc, page_number);
+      int rotate = pdf_to_int(
+          ctx, pdf_dict_get_inheritable(ctx, page, PDF_NAME(Rotate)));
+      // /Rotate must be a multiple of 90 but may be negative or > 360
+      *rotate_out = ((rotate % 360) + 360) % 360;
+    }
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+static int render_page(fz_context *ctx, fz_document *doc, int page_number,
+                       int dpi, fz_colorspace *cs, fz_pixmap **pix_out,
+                       char *err_out, size_t err_len) {
   if (!ctx || !doc || !pix_out)
     return -1;
 
+  // Dropped on failure too: MuPDF hands out a page that is still open again,
+  // and a retry must start from a fresh one
+  fz_page *page = NULL;
+  fz_var(page);
   fz_try(ctx) {
     // Load page
-    fz_page *page = fz_load_page(ctx, doc, page_number);
+    page = fz_load_page(ctx, doc, page_number);
 
     // Calculate matrix
     // Default dpi is 72. Scale = dpi / 72.
@@ -72,10 +223,107 @@ int my_render_page(fz_context *ctx, fz_document *doc, int page_number, int dpi,
     fz_matrix ctm = fz_scale(scale, scale);
 
     // Render
-    // Use Grayscale (1 byte per pixel) to save memory (75% less than RGBA) and
-    // speed up OCR. Tesseract converts to grayscale internally anyway.
-    *pix_out = fz_new_pixmap_from_page(ctx, page, ctm, fz_device_gray(ctx), 0);
+    *pix_out = fz_new_pixmap_from_page(ctx, page, ctm, cs, 0);
+  }
+  fz_always(ctx) { fz_drop_page(ctx, page); }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+int my_render_page(fz_context *ctx, fz_document *doc, int page_number, int dpi,
+                   fz_pixmap **pix_out, char *err_out, size_t err_len) {
+  // Use Grayscale (1 byte per pixel) to save memory (75% less than RGBA) and
+  // speed up OCR. Tesseract converts to grayscale internally anyway.
+  return render_page(ctx, doc, page_number, dpi, fz_device_gray(ctx), pix_out,
+                     err_out, err_len);
+}
+
+int my_render_page_rgb(fz_context *ctx, fz_document *doc, int page_number,
+                       int dpi, fz_pixmap **pix_out, char *err_out,
+                       size_t err_len) {
+  return render_page(ctx, doc, page_number, dpi, fz_device_rgb(ctx), pix_out,
+                     err_out, err_len);
+}
+
+int my_save_gray(fz_context *ctx, const unsigned char *samples, int w, int h,
+                 int dpi, const char *path, int jpeg_quality, char *err_out,
+                 size_t err_len) {
+  if (!ctx || !samples || !path)
+    return -1;
+
+  fz_pixmap *pix = NULL;
+  fz_var(pix);
+  fz_try(ctx) {
+    // The pixmap borrows the samples, it is only read
+    pix = fz_new_pixmap_with_data(ctx, fz_device_gray(ctx), w, h, NULL, 0, w,
+                                  (unsigned char *)samples);
+    fz_set_pixmap_resolution(ctx, pix, dpi, dpi);
+    if (jpeg_quality > 0)
+      fz_save_pixmap_as_jpeg(ctx, pix, path, jpeg_quality);
+    else
+      fz_save_pixmap_as_png(ctx, pix, path);
+  }
+  fz_always(ctx) { fz_drop_pixmap(ctx, pix); }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+// Device counting the filled paths of a page and their curve segments.
+// Text converted to outlines is drawn as many small filled curved paths.
+typedef struct {
+  fz_device super;
+  int paths;
+  int curves;
+} vector_device;
+
+static void walk_moveto(fz_context *ctx, void *arg, float x, float y) {}
+static void walk_lineto(fz_context *ctx, void *arg, float x, float y) {}
+static void walk_closepath(fz_context *ctx, void *arg) {}
+static void walk_curveto(fz_context *ctx, void *arg, float x1, float y1,
+                         float x2, float y2, float x3, float y3) {
+  (*(int *)arg)++;
+}
+
+static const fz_path_walker curve_counter = {walk_moveto, walk_lineto,
+                                             walk_curveto, walk_closepath};
+
+static void vector_fill_path(fz_context *ctx, fz_device *dev,
+                             const fz_path *path, int even_odd, fz_matrix ctm,
+                             fz_colorspace *cs, const float *color,
+                             float alpha, fz_color_params params) {
+  vector_device *vdev = (vector_device *)dev;
+  vdev->paths++;
+  fz_walk_path(ctx, path, &curve_counter, &vdev->curves);
+}
 
+int my_vector_stats(fz_context *ctx, fz_document *doc, int page_number,
+                    int *paths_out, int *curves_out, char *err_out,
+                    size_t err_len) {
+  if (!ctx || !doc || !paths_out || !curves_out)
+    return -1;
+
+  fz_page *volatile page = NULL;
+  fz_device *volatile dev = NULL;
+  fz_try(ctx) {
+    page = fz_load_page(ctx, doc, page_number);
+    vector_device *vdev = fz_new_derived_device(ctx, vector_device);
+    dev = &vdev->super;
+    vdev->super.fill_path = vector_fill_path;
+    fz_run_page(ctx, page, dev, fz_identity, NULL);
+    fz_close_device(ctx, dev);
+    *paths_out = vdev->paths;
+    *curves_out = vdev->curves;
+  }
+  fz_always(ctx) {
+    fz_drop_device(ctx, dev);
     fz_drop_page(ctx, page);
   }
   fz_catch(ctx) {
@@ -86,6 +334,211 @@ int my_render_page(fz_context *ctx, fz_document *doc, int page_number, int dpi,
   return 0;
 }
 
+// Recursion bound of the resource walk when no depth limit is given, well
+// within the C stack.
+#define MAX_WALK_DEPTH 1000
+
+typedef struct {
+  unsigned char *seen; // Indexed by object number
+  int seen_len;
+  int max_depth;
+  size_t max_content;
+  int depth;
+  int images;
+  size_t content;
+} resource_walk;
+
+// Whether an indirect object was visited already; marks it visited.
+static int walk_seen(fz_context *ctx, resource_walk *walk, pdf_obj *obj) {
+  int num = pdf_to_num(ctx, obj);
+  if (num <= 0 || num >= walk->seen_len)
+    return 0;
+  if (walk->seen[num])
+    return 1;
+  walk->seen[num] = 1;
+  return 0;
+}
+
+// Add the decoded size of a content stream, reading no further than the limit.
+static void walk_stream(fz_context *ctx, resource_walk *walk, pdf_obj *obj) {
+  if (!pdf_is_stream(ctx, obj))
+    return;
+  size_t remaining = SIZE_MAX;
+  if (walk->max_content) {
+    if (walk->content > walk->max_content)
+      return;
+    remaining = walk->max_content - walk->content + 1;
+  }
+  fz_stream *volatile stm = pdf_open_stream(ctx, obj);
+  fz_try(ctx) {
+    size_t n;
+    while (remaining > 0 &&
+           (n = fz_skip(ctx, stm, remaining < 65536 ? remaining : 65536)) > 0) {
+      walk->content += n;
+      remaining -= n;
+    }
+  }
+  fz_always(ctx) { fz_drop_stream(ctx, stm); }
+  fz_catch(ctx) { fz_rethrow(ctx); }
+}
+
+static void walk_resources(fz_context *ctx, resource_walk *walk, pdf_obj *res,
+                           int depth);
+
+// Forms and tiling patterns run content of their own, one level deeper.
+static void walk_content(fz_context *ctx, resource_walk *walk, pdf_obj *obj,
+                         int depth) {
+  if (depth > walk->depth)
+    walk->depth = depth;
+  if (depth > walk->max_depth)
+    return;
+  walk_stream(ctx, walk, obj);
+  walk_resources(ctx, walk, pdf_dict_get(ctx, obj, PDF_NAME(Resources)),
+                 depth);
+}
+
+static void walk_resources(fz_context *ctx, resource_walk *walk, pdf_obj *res,
+                           int depth) {
+  pdf_obj *xobjects = pdf_dict_get(ctx, res, PDF_NAME(XObject));
+  for (int i = 0; i < pdf_dict_len(ctx, xobjects); i++) {
+    pdf_obj *xobj = pdf_dict_get_val(ctx, xobjects, i);
+    if (!pdf_is_stream(ctx, xobj) || walk_seen(ctx, walk, xobj))
+      continue;
+    pdf_obj *subtype = pdf_dict_get(ctx, xobj, PDF_NAME(Subtype));
+    if (pdf_name_eq(ctx, subtype, PDF_NAME(Image)))
+      walk->images++;
+    else if (pdf_name_eq(ctx, subtype, PDF_NAME(Form)))
+      walk_content(ctx, walk, xobj, depth + 1);
+  }
+  pdf_obj *patterns = pdf_dict_get(ctx, res, PDF_NAME(Pattern));
+  for (int i = 0; i < pdf_dict_len(ctx, patterns); i++) {
+    pdf_obj *pattern = pdf_dict_get_val(ctx, patterns, i);
+    // Shading patterns are dictionaries without content
+    if (pdf_is_stream(ctx, pattern) && !walk_seen(ctx, walk, pattern))
+      walk_content(ctx, walk, pattern, depth + 1);
+  }
+}
+
+int my_page_resources(fz_context *ctx, fz_document *doc, int page_number,
+                      int max_depth, size_t max_content, int *depth_out,
+                      int *images_out, size_t *content_out, char *err_out,
+                      size_t err_len) {
+  if (!ctx || !doc || !depth_out || !images_out || !content_out)
+    return -1;
+  *depth_out = 0;
+  *images_out = 0;
+  *content_out = 0;
+
+  pdf_document *pdoc = pdf_specifics(ctx, doc);
+  if (!pdoc)
+    return 0;
+
+  resource_walk walk = {0};
+  walk.max_depth = max_depth > 0 && max_depth < MAX_WALK_DEPTH ? max_depth
+                                                                : MAX_WALK_DEPTH;
+  walk.max_content = max_content;
+  pdf_page *volatile page = NULL;
+  fz_try(ctx) {
+    walk.seen_len = pdf_xref_len(ctx, pdoc);
+    walk.seen = fz_calloc(ctx, walk.seen_len, 1);
+    page = pdf_load_page(ctx, pdoc, page_number);
+    pdf_obj *contents = pdf_page_contents(ctx, page);
+    if (pdf_is_array(ctx, contents)) {
+      for (int i = 0; i < pdf_array_len(ctx, contents); i++)
+        walk_stream(ctx, &walk, pdf_array_get(ctx, contents, i));
+    } else {
+      walk_stream(ctx, &walk, contents);
+    }
+    walk_resources(ctx, &walk, pdf_page_resources(ctx, page), 0);
+    *depth_out = walk.depth;
+    *images_out = walk.images;
+    *content_out = walk.content;
+  }
+  fz_always(ctx) {
+    fz_drop_page(ctx, (fz_page *)page);
+    fz_free(ctx, walk.seen);
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+int my_count_annots(fz_context *ctx, fz_document *doc, int page_number,
+                    int *count_out, char *err_out, size_t err_len) {
+  if (!ctx || !doc || !count_out)
+    return -1;
+  *count_out = 0;
+
+  pdf_document *pdoc = pdf_specifics(ctx, doc);
+  if (!pdoc)
+    return 0;
+
+  pdf_page *volatile page = NULL;
+  fz_try(ctx) {
+    page = pdf_load_page(ctx, pdoc, page_number);
+    for (pdf_annot *annot = pdf_first_annot(ctx, page); annot;
+         annot = pdf_next_annot(ctx, annot))
+      (*count_out)++;
+  }
+  fz_always(ctx) { fz_drop_page(ctx, (fz_page *)page); }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+int my_render_annot(fz_context *ctx, fz_document *doc, int page_number,
+                    int index, int dpi, fz_pixmap **pix_out, char *type_out,
+                    size_t type_len, float *rect_out, char *err_out,
+                    size_t err_len) {
+  if (!ctx || !doc || !pix_out || !type_out || !rect_out)
+    return -1;
+  *pix_out = NULL;
+
+  pdf_document *pdoc = pdf_specifics(ctx, doc);
+  if (!pdoc)
+    return -1;
+
+  pdf_page *volatile page = NULL;
+  fz_try(ctx) {
+    page = pdf_load_page(ctx, pdoc, page_number);
+
+    pdf_annot *annot = pdf_first_annot(ctx, page);
+    for (int i = 0; annot && i < index; i++)
+      annot = pdf_next_annot(ctx, annot);
+    if (!annot)
+      fz_throw(ctx, FZ_ERROR_GENERIC, "annotation %d not found", index);
+
+    const char *type =
+        pdf_string_from_annot_type(ctx, pdf_annot_type(ctx, annot));
+    strncpy(type_out, type, type_len - 1);
+    type_out[type_len - 1] = '\0';
+
+    fz_rect rect = pdf_bound_annot(ctx, annot);
+    rect_out[0] = rect.x0;
+    rect_out[1] = rect.y0;
+    rect_out[2] = rect.x1;
+    rect_out[3] = rect.y1;
+
+    // Same scale and colorspace as my_render_page
+    float scale = (float)dpi / 72.0f;
+    *pix_out = pdf_new_pixmap_from_annot(ctx, annot, fz_scale(scale, scale),
+                                         fz_device_gray(ctx), NULL, 0);
+  }
+  fz_always(ctx) { fz_drop_page(ctx, (fz_page *)page); }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
 void my_drop_pixmap(fz_context *ctx, fz_pixmap *pix) {
   if (ctx && pix)
     fz_drop_pixmap(ctx, pix);
@@ -192,61 +645,899 @@ void my_free_xfa(fz_context *ctx, char *xfa_data) {
     fz_free(ctx, xfa_data);
 }
 
-char *my_extract_text(fz_context *ctx, fz_document *doc, int page_number,
-                      char *err_out, size_t err_len) {
+// Append a value as one tab-separated column, flattening control characters
+static void append_column(fz_context *ctx, fz_buffer *buf, const char *s) {
+  fz_append_byte(ctx, buf, '\t');
+  for (; s && *s; s++)
+    fz_append_byte(ctx, buf, (unsigned char)*s < 0x20 ? ' ' : *s);
+}
+
+static void collect_signatures(fz_context *ctx, pdf_obj *field, fz_buffer *buf,
+                               int depth) {
+  if (depth > 32)
+    return;
+
+  // Fields with named kids are groups; unnamed kids are widgets
+  pdf_obj *kids = pdf_dict_get(ctx, field, PDF_NAME(Kids));
+  int has_field_kids = 0;
+  for (int i = 0; i < pdf_array_len(ctx, kids); i++) {
+    pdf_obj *kid = pdf_array_get(ctx, kids, i);
+    if (pdf_dict_get(ctx, kid, PDF_NAME(T))) {
+      has_field_kids = 1;
+      collect_signatures(ctx, kid, buf, depth + 1);
+    }
+  }
+  if (has_field_kids ||
+      !pdf_name_eq(ctx, pdf_dict_get_inheritable(ctx, field, PDF_NAME(FT)),
+                   PDF_NAME(Sig)))
+    return;
+
+  char *name = pdf_load_field_name(ctx, field);
+  pdf_obj *v = pdf_dict_get_inheritable(ctx, field, PDF_NAME(V));
+  int is_signed = pdf_is_dict(ctx, v);
+  for (char *c = name; *c; c++)
+    if ((unsigned char)*c < 0x20)
+      *c = ' ';
+  fz_append_string(ctx, buf, name);
+  fz_free(ctx, name);
+  fz_append_printf(ctx, buf, "\t%d", is_signed);
+  append_column(ctx, buf, is_signed ? pdf_dict_get_text_string(ctx, v, PDF_NAME(Name)) : "");
+  append_column(ctx, buf, is_signed ? pdf_dict_get_text_string(ctx, v, PDF_NAME(M)) : "");
+  append_column(ctx, buf, is_signed ? pdf_dict_get_text_string(ctx, v, PDF_NAME(Reason)) : "");
+  append_column(ctx, buf, is_signed ? pdf_dict_get_text_string(ctx, v, PDF_NAME(Location)) : "");
+  fz_append_byte(ctx, buf, '\n');
+}
+
+char *my_signature_fields(fz_context *ctx, fz_document *doc, char *err_out,
+                          size_t err_len) {
   if (!ctx || !doc)
     return NULL;
 
+  pdf_document *pdoc = pdf_specifics(ctx, doc);
+  if (!pdoc)
+    return NULL;
+
+  fz_buffer *volatile buf = NULL;
   char *volatile result = NULL;
+  fz_var(buf);
+  fz_var(result);
 
   fz_try(ctx) {
-    fz_page *page = fz_load_page(ctx, doc, page_number);
+    pdf_obj *fields = pdf_dict_getp(ctx, pdf_trailer(ctx, pdoc), "Root/AcroForm/Fields");
+    buf = fz_new_buffer(ctx, 256);
+    for (int i = 0; i < pdf_array_len(ctx, fields); i++)
+      collect_signatures(ctx, pdf_array_get(ctx, fields, i), buf, 0);
 
-    // Create a structured text page
-    fz_stext_page *text_page = fz_new_stext_page(ctx, fz_bound_page(ctx, page));
+    size_t len = fz_buffer_storage(ctx, buf, NULL);
+    if (len > 0) {
+      result = fz_malloc(ctx, len + 1);
+      memcpy(result, fz_string_from_buffer(ctx, buf), len);
+      result[len] = '\0';
+    }
+  }
+  fz_always(ctx) { fz_drop_buffer(ctx, buf); }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    fz_free(ctx, result);
+    return NULL;
+  }
+  return result;
+}
 
-    // Parse the page for text
-    fz_stext_options opts;
-    memset(&opts, 0, sizeof(opts));
-    // opts.flags = FZ_STEXT_PRESERVE_IMAGES;
+// Name of a file specification: a string, or the UF/F entry of a dictionary
+static const char *filespec_name(fz_context *ctx, pdf_obj *fs) {
+  if (pdf_is_string(ctx, fs))
+    return pdf_to_text_string(ctx, fs);
+  const char *name = pdf_dict_get_text_string(ctx, fs, PDF_NAME(UF));
+  return *name ? name : pdf_dict_get_text_string(ctx, fs, PDF_NAME(F));
+}
 
-    // Create a device to extract text
-    fz_device *dev = fz_new_stext_device(ctx, text_page, &opts);
+static void append_finding(fz_context *ctx, fz_buffer *buf, const char *kind,
+                           const char *detail) {
+  fz_append_string(ctx, buf, kind);
+  append_column(ctx, buf, detail);
+  fz_append_byte(ctx, buf, '\n');
+}
 
-    // Run the page through the device
-    fz_run_page(ctx, page, dev, fz_identity, NULL);
+// Classify one object: actions by their subtype, and file specifications
+// holding an embedded file
+static void scan_object(fz_context *ctx, pdf_obj *obj, fz_buffer *buf) {
+  if (!pdf_is_dict(ctx, obj))
+    return;
+
+  pdf_obj *s = pdf_dict_get(ctx, obj, PDF_NAME(S));
+  if (pdf_name_eq(ctx, s, PDF_NAME(JavaScript)) || pdf_dict_get(ctx, obj, PDF_NAME(JS)))
+    append_finding(ctx, buf, "javascript", "");
+  else if (pdf_name_eq(ctx, s, PDF_NAME(Launch))) {
+    pdf_obj *win = pdf_dict_gets(ctx, obj, "Win");
+    pdf_obj *target = pdf_dict_get(ctx, obj, PDF_NAME(F));
+    append_finding(ctx, buf, "launch",
+                   target ? filespec_name(ctx, target)
+                          : pdf_dict_get_text_string(ctx, win, PDF_NAME(F)));
+  } else if (pdf_name_eq(ctx, s, PDF_NAME(URI)))
+    append_finding(ctx, buf, "external", pdf_dict_get_text_string(ctx, obj, PDF_NAME(URI)));
+  else if (pdf_name_eq(ctx, s, PDF_NAME(GoToR)) || !strcmp(pdf_to_name(ctx, s), "GoToE") ||
+           !strcmp(pdf_to_name(ctx, s), "SubmitForm") ||
+           !strcmp(pdf_to_name(ctx, s), "ImportData"))
+    append_finding(ctx, buf, "external", filespec_name(ctx, pdf_dict_get(ctx, obj, PDF_NAME(F))));
+
+  if (pdf_dict_get(ctx, obj, PDF_NAME(EF)))
+    append_finding(ctx, buf, "embedded_file", filespec_name(ctx, obj));
+}
+
+char *my_active_content(fz_context *ctx, fz_document *doc, char *err_out,
+                        size_t err_len) {
+  if (!ctx || !doc)
+    return NULL;
+
+  pdf_document *pdoc = pdf_specifics(ctx, doc);
+  if (!pdoc)
+    return NULL;
+
+  fz_buffer *volatile buf = NULL;
+  char *volatile result = NULL;
+  fz_var(buf);
+  fz_var(result);
+
+  fz_try(ctx) {
+    buf = fz_new_buffer(ctx, 256);
+    pdf_obj *root = pdf_dict_get(ctx, pdf_trailer(ctx, pdoc), PDF_NAME(Root));
+    // A destination array only opens a page; an action dictionary runs
+    pdf_obj *open = pdf_dict_gets(ctx, root, "OpenAction");
+    if (pdf_is_dict(ctx, open))
+      append_finding(ctx, buf, "open_action", pdf_to_name(ctx, pdf_dict_get(ctx, open, PDF_NAME(S))));
+    if (pdf_dict_get(ctx, root, PDF_NAME(AA)))
+      append_finding(ctx, buf, "open_action", "AA");
+
+    int count = pdf_xref_len(ctx, pdoc);
+    for (int num = 1; num < count; num++) {
+      pdf_obj *volatile obj = NULL;
+      fz_var(obj);
+      // Broken objects are skipped, as viewers would
+      fz_try(ctx) {
+        obj = pdf_load_object(ctx, pdoc, num);
+        scan_object(ctx, obj, buf);
+      }
+      fz_always(ctx) { pdf_drop_obj(ctx, obj); }
+      fz_catch(ctx) {}
+    }
+
+    size_t len = fz_buffer_storage(ctx, buf, NULL);
+    if (len > 0) {
+      result = fz_malloc(ctx, len + 1);
+      memcpy(result, fz_string_from_buffer(ctx, buf), len);
+      result[len] = '\0';
+    }
+  }
+  fz_always(ctx) { fz_drop_buffer(ctx, buf); }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    fz_free(ctx, result);
+    return NULL;
+  }
+  return result;
+}
+
+// Font descriptor of a font, through the descendant font of composite (Type0) fonts
+static pdf_obj *font_descriptor(fz_context *ctx, pdf_obj *font) {
+  if (pdf_name_eq(ctx, pdf_dict_get(ctx, font, PDF_NAME(Subtype)), PDF_NAME(Type0)))
+    font = pdf_array_get(ctx, pdf_dict_get(ctx, font, PDF_NAME(DescendantFonts)), 0);
+  return pdf_dict_get(ctx, font, PDF_NAME(FontDescriptor));
+}
+
+// Embedded font program of a font, with its format: type1, truetype, cff or
+// opentype. NULL (and an empty format) if the font is not embedded.
+static pdf_obj *font_file(fz_context *ctx, pdf_obj *font, const char **format) {
+  pdf_obj *descriptor = font_descriptor(ctx, font);
+  pdf_obj *file;
+  if ((file = pdf_dict_get(ctx, descriptor, PDF_NAME(FontFile)))) {
+    *format = "type1";
+  } else if ((file = pdf_dict_get(ctx, descriptor, PDF_NAME(FontFile2)))) {
+    *format = "truetype";
+  } else if ((file = pdf_dict_get(ctx, descriptor, PDF_NAME(FontFile3)))) {
+    pdf_obj *subtype = pdf_dict_get(ctx, file, PDF_NAME(Subtype));
+    *format = pdf_name_eq(ctx, subtype, PDF_NAME(OpenType)) ? "opentype" : "cff";
+  } else {
+    *format = "";
+  }
+  return file;
+}
+
+char *my_fonts(fz_context *ctx, fz_document *doc, char *err_out, size_t err_len) {
+  if (!ctx || !doc)
+    return NULL;
+
+  pdf_document *pdoc = pdf_specifics(ctx, doc);
+  if (!pdoc)
+    return NULL;
+
+  fz_buffer *volatile buf = NULL;
+  char *volatile result = NULL;
+  fz_var(buf);
+  fz_var(result);
+
+  fz_try(ctx) {
+    buf = fz_new_buffer(ctx, 256);
+    int count = pdf_xref_len(ctx, pdoc);
+    for (int num = 1; num < count; num++) {
+      pdf_obj *volatile obj = NULL;
+      fz_var(obj);
+      fz_try(ctx) {
+        obj = pdf_load_object(ctx, pdoc, num);
+        pdf_obj *subtype = pdf_dict_get(ctx, obj, PDF_NAME(Subtype));
+        // Descendants of composite fonts are reported through their parent
+        if (pdf_name_eq(ctx, pdf_dict_get(ctx, obj, PDF_NAME(Type)), PDF_NAME(Font)) &&
+            !pdf_name_eq(ctx, subtype, PDF_NAME(CIDFontType0)) &&
+            !pdf_name_eq(ctx, subtype, PDF_NAME(CIDFontType2))) {
+          const char *format;
+          font_file(ctx, obj, &format);
+          fz_append_printf(ctx, buf, "%d", num);
+          append_column(ctx, buf, pdf_to_name(ctx, pdf_dict_get(ctx, obj, PDF_NAME(BaseFont))));
+          append_column(ctx, buf, pdf_to_name(ctx, subtype));
+          append_column(ctx, buf, format);
+          fz_append_byte(ctx, buf, '\n');
+        }
+      }
+      fz_always(ctx) { pdf_drop_obj(ctx, obj); }
+      fz_catch(ctx) {}
+    }
+
+    size_t len = fz_buffer_storage(ctx, buf, NULL);
+    if (len > 0) {
+      result = fz_malloc(ctx, len + 1);
+      memcpy(result, fz_string_from_buffer(ctx, buf), len);
+      result[len] = '\0';
+    }
+  }
+  fz_always(ctx) { fz_drop_buffer(ctx, buf); }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    fz_free(ctx, result);
+    return NULL;
+  }
+  return result;
+}
+
+char *my_font_program(fz_context *ctx, fz_document *doc, int num,
+                      size_t *len_out, char *err_out, size_t err_len) {
+  if (!ctx || !doc || !len_out)
+    return NULL;
+
+  *len_out = 0;
+  pdf_document *pdoc = pdf_specifics(ctx, doc);
+  if (!pdoc)
+    return NULL;
+
+  pdf_obj *volatile font = NULL;
+  fz_buffer *volatile buf = NULL;
+  unsigned char *data = NULL;
+  fz_var(font);
+  fz_var(buf);
+
+  fz_try(ctx) {
+    font = pdf_load_object(ctx, pdoc, num);
+    const char *format;
+    pdf_obj *file = font_file(ctx, font, &format);
+    if (file) {
+      buf = pdf_load_stream(ctx, file);
+      *len_out = fz_buffer_extract(ctx, buf, &data);
+    }
+  }
+  fz_always(ctx) {
+    fz_drop_buffer(ctx, buf);
+    pdf_drop_obj(ctx, font);
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return NULL;
+  }
+  return (char *)data;
+}
+
+struct my_pdf_writer {
+  pdf_document *doc;
+  // Type0 font for the invisible text layer, shared by all pages
+  pdf_obj *font;
+};
+
+// Maps every 2-byte code (UTF-16 of the text) back to itself, as Tesseract's
+// PDF renderer does
+static const char *IDENTITY_TO_UNICODE =
+    "/CIDInit /ProcSet findresource begin\n"
+    "12 dict begin\n"
+    "begincmap\n"
+    "/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n"
+    "/CMapName /Adobe-Identify-UCS def\n"
+    "/CMapType 2 def\n"
+    "1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n"
+    "1 beginbfrange\n<0000> <FFFF> <0000>\nendbfrange\n"
+    "endcmap\n"
+    "CMapName currentdict /CMap defineresource pop\n"
+    "end\n"
+    "end\n";
+
+// Add a stream holding a copy of data; returns its indirect reference
+static pdf_obj *add_data_stream(fz_context *ctx, pdf_document *doc,
+                                const unsigned char *data, size_t len,
+                                pdf_obj *dict) {
+  fz_buffer *buf = fz_new_buffer_from_copied_data(ctx, data, len);
+  pdf_obj *ref = NULL;
+  fz_try(ctx) { ref = pdf_add_stream(ctx, doc, buf, dict, 0); }
+  fz_always(ctx) { fz_drop_buffer(ctx, buf); }
+  fz_catch(ctx) { fz_rethrow(ctx); }
+  return ref;
+}
+
+// Type0 font over a TrueType program whose every character id maps to its
+// one (blank) glyph, with a width of 500/1000 em
+static pdf_obj *add_glyphless_font(fz_context *ctx, pdf_document *doc,
+                                   const unsigned char *program, size_t len) {
+  pdf_obj *volatile file = NULL, *volatile map = NULL, *volatile unicode = NULL;
+  pdf_obj *volatile descriptor = NULL, *volatile cid_font = NULL;
+  pdf_obj *volatile dict = NULL;
+  unsigned char *volatile gids = NULL;
+  pdf_obj *font = NULL;
+  fz_var(file);
+  fz_var(map);
+  fz_var(unicode);
+  fz_var(descriptor);
+  fz_var(cid_font);
+  fz_var(dict);
+  fz_var(gids);
+
+  fz_try(ctx) {
+    dict = pdf_new_dict(ctx, doc, 1);
+    pdf_dict_put_int(ctx, dict, PDF_NAME(Length1), len);
+    file = add_data_stream(ctx, doc, program, len, dict);
+    pdf_drop_obj(ctx, dict);
+    dict = NULL;
+
+    gids = fz_malloc(ctx, 2 * 65536);
+    for (int i = 0; i < 65536; i++) {
+      gids[2 * i] = 0;
+      gids[2 * i + 1] = 1;
+    }
+    map = add_data_stream(ctx, doc, gids, 2 * 65536, NULL);
+    unicode = add_data_stream(ctx, doc, (const unsigned char *)IDENTITY_TO_UNICODE,
+                              strlen(IDENTITY_TO_UNICODE), NULL);
+
+    dict = pdf_new_dict(ctx, doc, 11);
+    pdf_dict_put(ctx, dict, PDF_NAME(Type), PDF_NAME(FontDescriptor));
+    pdf_dict_put_name(ctx, dict, PDF_NAME(FontName), "GlyphLessFont");
+    pdf_dict_put_int(ctx, dict, PDF_NAME(Flags), 5);
+    pdf_obj *bbox = pdf_dict_put_array(ctx, dict, PDF_NAME(FontBBox), 4);
+    pdf_array_push_int(ctx, bbox, 0);
+    pdf_array_push_int(ctx, bbox, 0);
+    pdf_array_push_int(ctx, bbox, 500);
+    pdf_array_push_int(ctx, bbox, 1000);
+    pdf_dict_put_int(ctx, dict, PDF_NAME(ItalicAngle), 0);
+    pdf_dict_put_int(ctx, dict, PDF_NAME(Ascent), 1000);
+    pdf_dict_put_int(ctx, dict, PDF_NAME(Descent), -1);
+    pdf_dict_put_int(ctx, dict, PDF_NAME(CapHeight), 1000);
+    pdf_dict_put_int(ctx, dict, PDF_NAME(StemV), 80);
+    pdf_dict_put(ctx, dict, PDF_NAME(FontFile2), file);
+    descriptor = pdf_add_object(ctx, doc, dict);
+    pdf_drop_obj(ctx, dict);
+    dict = NULL;
+
+    dict = pdf_new_dict(ctx, doc, 7);
+    pdf_dict_put(ctx, dict, PDF_NAME(Type), PDF_NAME(Font));
+    pdf_dict_put(ctx, dict, PDF_NAME(Subtype), PDF_NAME(CIDFontType2));
+    pdf_dict_put_name(ctx, dict, PDF_NAME(BaseFont), "GlyphLessFont");
+    pdf_obj *info = pdf_dict_put_dict(ctx, dict, PDF_NAME(CIDSystemInfo), 3);
+    pdf_dict_put_text_string(ctx, info, PDF_NAME(Registry), "Adobe");
+    pdf_dict_put_text_string(ctx, info, PDF_NAME(Ordering), "Identity");
+    pdf_dict_put_int(ctx, info, PDF_NAME(Supplement), 0);
+    pdf_dict_put(ctx, dict, PDF_NAME(FontDescriptor), descriptor);
+    pdf_dict_put(ctx, dict, PDF_NAME(CIDToGIDMap), map);
+    pdf_dict_put_int(ctx, dict, PDF_NAME(DW), 500);
+    cid_font = pdf_add_object(ctx, doc, dict);
+    pdf_drop_obj(ctx, dict);
+    dict = NULL;
+
+    dict = pdf_new_dict(ctx, doc, 6);
+    pdf_dict_put(ctx, dict, PDF_NAME(Type), PDF_NAME(Font));
+    pdf_dict_put(ctx, dict, PDF_NAME(Subtype), PDF_NAME(Type0));
+    pdf_dict_put_name(ctx, dict, PDF_NAME(BaseFont), "GlyphLessFont");
+    pdf_dict_put(ctx, dict, PDF_NAME(Encoding), PDF_NAME(Identity_H));
+    pdf_array_push(ctx, pdf_dict_put_array(ctx, dict, PDF_NAME(DescendantFonts), 1), cid_font);
+    pdf_dict_put(ctx, dict, PDF_NAME(ToUnicode), unicode);
+    font = pdf_add_object(ctx, doc, dict);
+  }
+  fz_always(ctx) {
+    fz_free(ctx, gids);
+    pdf_drop_obj(ctx, dict);
+    pdf_drop_obj(ctx, cid_font);
+    pdf_drop_obj(ctx, descriptor);
+    pdf_drop_obj(ctx, unicode);
+    pdf_drop_obj(ctx, map);
+    pdf_drop_obj(ctx, file);
+  }
+  fz_catch(ctx) { fz_rethrow(ctx); }
+  return font;
+}
+
+my_pdf_writer *my_new_pdf(fz_context *ctx, const unsigned char *font_program,
+                          size_t font_len, char *err_out, size_t err_len) {
+  if (!ctx)
+    return NULL;
+
+  my_pdf_writer *volatile writer = NULL;
+  fz_var(writer);
+
+  fz_try(ctx) {
+    writer = fz_malloc_struct(ctx, my_pdf_writer);
+    writer->doc = pdf_create_document(ctx);
+    if (font_program)
+      writer->font =
+          add_glyphless_font(ctx, writer->doc, font_program, font_len);
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    my_drop_pdf(ctx, writer);
+    return NULL;
+  }
+  return writer;
+}
+
+int my_pdf_add_page(fz_context *ctx, my_pdf_writer *writer,
+                    const unsigned char *gray, int width, int height,
+                    float page_width, float page_height, const char *text,
+                    char *err_out, size_t err_len) {
+  if (!ctx || !writer || !writer->font || !gray || !text)
+    return -1;
+
+  fz_pixmap *volatile pix = NULL;
+  fz_image *volatile image = NULL;
+  pdf_obj *volatile image_ref = NULL;
+  pdf_obj *volatile resources = NULL;
+  pdf_obj *volatile page = NULL;
+  fz_buffer *volatile contents = NULL;
+  fz_var(pix);
+  fz_var(image);
+  fz_var(image_ref);
+  fz_var(resources);
+  fz_var(page);
+  fz_var(contents);
+
+  fz_try(ctx) {
+    // pdf_add_image copies the samples, so the caller's buffer is only borrowed
+    pix = fz_new_pixmap_with_data(ctx, fz_device_gray(ctx), width, height, NULL,
+                                  0, width, (unsigned char *)gray);
+    image = fz_new_image_from_pixmap(ctx, pix, NULL);
+    image_ref = pdf_add_image(ctx, writer->doc, image);
+
+    resources = pdf_new_dict(ctx, writer->doc, 2);
+    pdf_obj *xobjects = pdf_dict_put_dict(ctx, resources, PDF_NAME(XObject), 1);
+    pdf_dict_puts(ctx, xobjects, "Im0", image_ref);
+    pdf_obj *fonts = pdf_dict_put_dict(ctx, resources, PDF_NAME(Font), 1);
+    pdf_dict_puts(ctx, fonts, "F0", writer->font);
+
+    // The image fills the page; the text layer is drawn over it
+    contents = fz_new_buffer(ctx, 1024);
+    fz_append_printf(ctx, contents, "q %g 0 0 %g 0 0 cm /Im0 Do Q\n",
+                     page_width, page_height);
+    fz_append_string(ctx, contents, text);
+
+    page = pdf_add_page(ctx, writer->doc,
+                        fz_make_rect(0, 0, page_width, page_height), 0,
+                        resources, contents);
+    pdf_insert_page(ctx, writer->doc, -1, page);
+  }
+  fz_always(ctx) {
+    pdf_drop_obj(ctx, page);
+    fz_drop_buffer(ctx, contents);
+    pdf_drop_obj(ctx, resources);
+    pdf_drop_obj(ctx, image_ref);
+    fz_drop_image(ctx, image);
+    fz_drop_pixmap(ctx, pix);
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+int my_pdf_add_text_page(fz_context *ctx, my_pdf_writer *writer,
+                         float page_width, float page_height,
+                         const char *font_name, const char *text,
+                         char *err_out, size_t err_len) {
+  if (!ctx || !writer || !font_name || !text)
+    return -1;
+
+  fz_font *volatile font = NULL;
+  pdf_obj *volatile font_ref = NULL;
+  pdf_obj *volatile resources = NULL;
+  pdf_obj *volatile page = NULL;
+  fz_buffer *volatile contents = NULL;
+  fz_var(font);
+  fz_var(font_ref);
+  fz_var(resources);
+  fz_var(page);
+  fz_var(contents);
+
+  fz_try(ctx) {
+    font = fz_new_base14_font(ctx, font_name);
+    font_ref = pdf_add_simple_font(ctx, writer->doc, font,
+                                   PDF_SIMPLE_ENCODING_LATIN);
+
+    resources = pdf_new_dict(ctx, writer->doc, 1);
+    pdf_obj *fonts = pdf_dict_put_dict(ctx, resources, PDF_NAME(Font), 1);
+    pdf_dict_puts(ctx, fonts, "F1", font_ref);
+
+    contents = fz_new_buffer_from_copied_data(
+        ctx, (const unsigned char *)text, strlen(text));
+    page = pdf_add_page(ctx, writer->doc,
+                        fz_make_rect(0, 0, page_width, page_height), 0,
+                        resources, contents);
+    pdf_insert_page(ctx, writer->doc, -1, page);
+  }
+  fz_always(ctx) {
+    pdf_drop_obj(ctx, page);
+    fz_drop_buffer(ctx, contents);
+    pdf_drop_obj(ctx, resources);
+    pdf_drop_obj(ctx, font_ref);
+    fz_drop_font(ctx, font);
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+int my_pdf_save(fz_context *ctx, my_pdf_writer *writer, const char *filename,
+                char *err_out, size_t err_len) {
+  if (!ctx || !writer || !filename)
+    return -1;
+
+  pdf_write_options opts = pdf_default_write_options;
+  opts.do_compress = 1;
+  opts.do_compress_images = 1;
+  opts.do_compress_fonts = 1;
+  fz_try(ctx) { pdf_save_document(ctx, writer->doc, filename, &opts); }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+void my_drop_pdf(fz_context *ctx, my_pdf_writer *writer) {
+  if (!ctx || !writer)
+    return;
+  pdf_drop_obj(ctx, writer->font);
+  pdf_drop_document(ctx, writer->doc);
+  fz_free(ctx, writer);
+}
+
+// Add a content stream holding `text`; returns its indirect reference
+static pdf_obj *add_content_stream(fz_context *ctx, pdf_document *doc,
+                                   const char *text) {
+  return add_data_stream(ctx, doc, (const unsigned char *)text, strlen(text),
+                         NULL);
+}
+
+// Remove the text under the rectangles of a page, then draw `ops` (in page
+// space, y down) after the existing content, with the glyphless font as
+// /FCrabOCR
+static void correct_page(fz_context *ctx, pdf_document *doc, pdf_page *page,
+                         const float *rects, int count, const char *ops,
+                         pdf_obj *font) {
+  for (int i = 0; i < count; i++) {
+    const float *r = rects + 4 * i;
+    pdf_annot *annot = pdf_create_annot(ctx, page, PDF_ANNOT_REDACT);
+    fz_try(ctx) {
+      pdf_set_annot_rect(ctx, annot, fz_make_rect(r[0], r[1], r[2], r[3]));
+    }
+    fz_always(ctx) { pdf_drop_annot(ctx, annot); }
+    fz_catch(ctx) { fz_rethrow(ctx); }
+  }
+  // Text only: images and line art under the boxes stay
+  pdf_redact_options opts = {0, PDF_REDACT_IMAGE_NONE,
+                             PDF_REDACT_LINE_ART_NONE};
+  pdf_redact_page(ctx, doc, page, &opts);
+
+  pdf_obj *res =
+      pdf_dict_get_inheritable(ctx, page->obj, PDF_NAME(Resources));
+  if (!res)
+    res = pdf_dict_put_dict(ctx, page->obj, PDF_NAME(Resources), 1);
+  pdf_obj *fonts = pdf_dict_get(ctx, res, PDF_NAME(Font));
+  if (!fonts)
+    fonts = pdf_dict_put_dict(ctx, res, PDF_NAME(Font), 1);
+  pdf_dict_puts(ctx, fonts, "FCrabOCR", font);
+
+  fz_matrix ctm;
+  pdf_page_transform(ctx, page, NULL, &ctm);
+  fz_matrix m = fz_invert_matrix(ctm);
+
+  fz_buffer *volatile buf = NULL;
+  pdf_obj *volatile contents = NULL;
+  fz_var(buf);
+  fz_var(contents);
+  fz_try(ctx) {
+    // The existing content is wrapped in q/Q, so its graphics state does
+    // not carry over to the corrections
+    buf = fz_new_buffer(ctx, 1024);
+    fz_append_printf(ctx, buf, "Q\nq\n%g %g %g %g %g %g cm\n%sQ\n", m.a, m.b,
+                     m.c, m.d, m.e, m.f, ops);
+    fz_terminate_buffer(ctx, buf);
+
+    pdf_obj *old = pdf_dict_get(ctx, page->obj, PDF_NAME(Contents));
+    contents = pdf_new_array(ctx, doc, 3);
+    pdf_array_push_drop(ctx, contents, add_content_stream(ctx, doc, "q\n"));
+    if (pdf_is_array(ctx, old)) {
+      for (int i = 0; i < pdf_array_len(ctx, old); i++)
+        pdf_array_push(ctx, contents, pdf_array_get(ctx, old, i));
+    } else if (old) {
+      pdf_array_push(ctx, contents, old);
+    }
+    pdf_array_push_drop(
+        ctx, contents,
+        add_content_stream(ctx, doc, (const char *)fz_string_from_buffer(ctx, buf)));
+    pdf_dict_put(ctx, page->obj, PDF_NAME(Contents), contents);
+  }
+  fz_always(ctx) {
+    pdf_drop_obj(ctx, contents);
+    fz_drop_buffer(ctx, buf);
+  }
+  fz_catch(ctx) { fz_rethrow(ctx); }
+}
+
+int my_correct_text(fz_context *ctx, fz_document *doc, int count,
+                    const int *pages, const int *rect_counts,
+                    const float *rects, const char *const *ops,
+                    const unsigned char *font_program, size_t font_len,
+                    const char *filename, char *err_out, size_t err_len) {
+  if (!ctx || !doc || !pages || !rect_counts || !rects || !ops ||
+      !font_program || !filename)
+    return -1;
 
+  pdf_document *pdoc = pdf_specifics(ctx, doc);
+  if (!pdoc) {
+    if (err_out)
+      strncpy(err_out, "not a PDF document", err_len - 1);
+    return 1;
+  }
+
+  pdf_obj *volatile font = NULL;
+  pdf_page *volatile page = NULL;
+  fz_var(font);
+  fz_var(page);
+  fz_try(ctx) {
+    font = add_glyphless_font(ctx, pdoc, font_program, font_len);
+    const float *page_rects = rects;
+    for (int i = 0; i < count; i++) {
+      page = pdf_load_page(ctx, pdoc, pages[i]);
+      correct_page(ctx, pdoc, page, page_rects, rect_counts[i], ops[i], font);
+      fz_drop_page(ctx, (fz_page *)page);
+      page = NULL;
+      page_rects += 4 * rect_counts[i];
+    }
+    pdf_write_options opts = pdf_default_write_options;
+    opts.do_garbage = 1;
+    opts.do_compress = 1;
+    pdf_save_document(ctx, pdoc, filename, &opts);
+  }
+  fz_always(ctx) {
+    fz_drop_page(ctx, (fz_page *)page);
+    pdf_drop_obj(ctx, font);
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
+
+typedef struct {
+  my_text_sink sink;
+  void *opaque;
+} text_sink_state;
+
+static void text_sink_write(fz_context *ctx, void *state, const void *data,
+                            size_t n) {
+  text_sink_state *s = state;
+  if (s->sink(s->opaque, data, n) != 0)
+    fz_throw(ctx, FZ_ERROR_GENERIC, "text output stopped");
+}
+
+int my_extract_text_to(fz_context *ctx, fz_document *doc, int page_number,
+                       my_text_sink sink, void *opaque, char *err_out,
+                       size_t err_len) {
+  if (!ctx || !doc || !sink)
+    return 1;
+
+  text_sink_state state = {sink, opaque};
+  fz_page *volatile page = NULL;
+  fz_stext_page *volatile text_page = NULL;
+  fz_device *volatile dev = NULL;
+  fz_output *volatile out = NULL;
+
+  fz_var(page);
+  fz_var(text_page);
+  fz_var(dev);
+  fz_var(out);
+
+  fz_try(ctx) {
+    page = fz_load_page(ctx, doc, page_number);
+    text_page = fz_new_stext_page(ctx, fz_bound_page(ctx, page));
+    fz_stext_options opts;
+    memset(&opts, 0, sizeof(opts));
+    dev = fz_new_stext_device(ctx, text_page, &opts);
+    fz_run_page(ctx, page, dev, fz_identity, NULL);
     fz_close_device(ctx, dev);
+
+    // Flushed to the sink whenever the output buffer fills
+    out = fz_new_output(ctx, 8192, &state, text_sink_write, NULL, NULL);
+    fz_print_stext_page_as_text(ctx, out, text_page);
+    fz_close_output(ctx, out);
+  }
+  fz_always(ctx) {
+    fz_drop_output(ctx, out);
     fz_drop_device(ctx, dev);
+    fz_drop_stext_page(ctx, text_page);
+    fz_drop_page(ctx, page);
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return 1;
+  }
+  return 0;
+}
 
-    // Extract text from the text page to a buffer using an output stream.
+char *my_extract_lines(fz_context *ctx, fz_document *doc, int page_number,
+                       char *err_out, size_t err_len) {
+  if (!ctx || !doc)
+    return NULL;
 
-    fz_buffer *buf = fz_new_buffer(ctx, 1024);
-    fz_output *out = fz_new_output_with_buffer(ctx, buf);
+  char *volatile result = NULL;
+  fz_page *volatile page = NULL;
+  fz_stext_page *volatile text_page = NULL;
+  fz_buffer *volatile buf = NULL;
+  fz_output *volatile out = NULL;
 
-    fz_print_stext_page_as_text(ctx, out, text_page);
+  fz_try(ctx) {
+    page = fz_load_page(ctx, doc, page_number);
+    text_page = fz_new_stext_page(ctx, fz_bound_page(ctx, page));
+
+    // Same options as my_extract_text_to, so line text matches its output
+    fz_stext_options opts;
+    memset(&opts, 0, sizeof(opts));
+    fz_device *dev = fz_new_stext_device(ctx, text_page, &opts);
+    fz_run_page(ctx, page, dev, fz_identity, NULL);
+    fz_close_device(ctx, dev);
+    fz_drop_device(ctx, dev);
 
+    buf = fz_new_buffer(ctx, 1024);
+    out = fz_new_output_with_buffer(ctx, buf);
+    for (fz_stext_block *block = text_page->first_block; block;
+         block = block->next) {
+      if (block->type != FZ_STEXT_BLOCK_TEXT)
+        continue;
+      for (fz_stext_line *line = block->u.t.first_line; line;
+           line = line->next) {
+        // Mean font size, and whether every glyph is bold
+        float size = 0;
+        int glyphs = 0, bold = 1;
+        for (fz_stext_char *ch = line->first_char; ch; ch = ch->next) {
+          if (ch->c == ' ')
+            continue;
+          size += ch->size;
+          glyphs++;
+          if (!fz_font_is_bold(ctx, ch->font))
+            bold = 0;
+        }
+        fz_write_printf(ctx, out, "%g %g %g %g\t%g\t%d\t", line->bbox.x0,
+                        line->bbox.y0, line->bbox.x1, line->bbox.y1,
+                        glyphs ? size / glyphs : 0, glyphs && bold);
+        for (fz_stext_char *ch = line->first_char; ch; ch = ch->next)
+          fz_write_rune(ctx, out, ch->c);
+        fz_write_byte(ctx, out, '\n');
+      }
+      // Blank line between blocks
+      fz_write_byte(ctx, out, '\n');
+    }
     fz_close_output(ctx, out);
+
+    size_t len = fz_buffer_storage(ctx, buf, NULL);
+    result = fz_malloc(ctx, len + 1);
+    memcpy(result, fz_string_from_buffer(ctx, buf), len);
+    result[len] = '\0';
+  }
+  fz_always(ctx) {
     fz_drop_output(ctx, out);
+    fz_drop_buffer(ctx, buf);
     fz_drop_stext_page(ctx, text_page);
     fz_drop_page(ctx, page);
+  }
+  fz_catch(ctx) {
+    if (err_out)
+      strncpy(err_out, fz_caught_message(ctx), err_len - 1);
+    return NULL;
+  }
 
-    // Get string from buffer
-    unsigned char *data = NULL;
-    size_t len = fz_buffer_extract(ctx, buf, &data);
-    fz_drop_buffer(ctx, buf);
+  return result;
+}
 
-    if (len > 0 && data != NULL) {
-      // Ensure null termination
-      result = fz_malloc(ctx, len + 1);
-      memcpy(result, data, len);
-      result[len] = '\0';
-    } else {
-      // Empty string if no text found, to differentiate from error (NULL)
-      result = fz_malloc(ctx, 1);
-      result[0] = '\0';
+char *my_extract_structured(fz_context *ctx, fz_document *doc, int page_number,
+                            char *err_out, size_t err_len) {
+  if (!ctx || !doc)
+    return NULL;
+
+  char *volatile result = NULL;
+  fz_page *volatile page = NULL;
+  fz_stext_page *volatile text_page = NULL;
+  fz_buffer *volatile buf = NULL;
+  fz_output *volatile out = NULL;
+
+  fz_try(ctx) {
+    page = fz_load_page(ctx, doc, page_number);
+    text_page = fz_new_stext_page(ctx, fz_bound_page(ctx, page));
+
+    fz_stext_options opts;
+    memset(&opts, 0, sizeof(opts));
+    fz_device *dev = fz_new_stext_device(ctx, text_page, &opts);
+    fz_run_page(ctx, page, dev, fz_identity, NULL);
+    fz_close_device(ctx, dev);
+    fz_drop_device(ctx, dev);
+
+    // One record per line: "B\tbounds" for blocks, "L\tbounds" for lines and
+    // "S\tbounds\tsize\tfont\ttext" for runs of glyphs in the same font and size
+    buf = fz_new_buffer(ctx, 1024);
+    out = fz_new_output_with_buffer(ctx, buf);
+    for (fz_stext_block *block = text_page->first_block; block;
+         block = block->next) {
+      if (block->type != FZ_STEXT_BLOCK_TEXT)
+        continue;
+      fz_write_printf(ctx, out, "B\t%g %g %g %g\n", block->bbox.x0,
+                      block->bbox.y0, block->bbox.x1, block->bbox.y1);
+      for (fz_stext_line *line = block->u.t.first_line; line;
+           line = line->next) {
+        fz_write_printf(ctx, out, "L\t%g %g %g %g\n", line->bbox.x0,
+                        line->bbox.y0, line->bbox.x1, line->bbox.y1);
+        fz_stext_char *ch = line->first_char;
+        while (ch) {
+          fz_stext_char *end = ch;
+          fz_rect bbox = fz_rect_from_quad(ch->quad);
+          while (end->next && end->next->font == ch->font &&
+                 end->next->size == ch->size) {
+            end = end->next;
+            bbox = fz_union_rect(bbox, fz_rect_from_quad(end->quad));
+          }
+          fz_write_printf(ctx, out, "S\t%g %g %g %g\t%g\t%s\t", bbox.x0,
+                          bbox.y0, bbox.x1, bbox.y1, ch->size,
+                          fz_font_name(ctx, ch->font));
+          for (fz_stext_char *c = ch; c != end->next; c = c->next)
+            fz_write_rune(ctx, out, c->c == '\t' || c->c == '\n' ? ' ' : c->c);
+          fz_write_byte(ctx, out, '\n');
+          ch = end->next;
+        }
+      }
     }
-    fz_free(ctx, data);
+    fz_close_output(ctx, out);
+
+    size_t len = fz_buffer_storage(ctx, buf, NULL);
+    result = fz_malloc(ctx, len + 1);
+    memcpy(result, fz_string_from_buffer(ctx, buf), len);
+    result[len] = '\0';
+  }
+  fz_always(ctx) {
+    fz_drop_output(ctx, out);
+    fz_drop_buffer(ctx, buf);
+    fz_drop_stext_page(ctx, text_page);
+    fz_drop_page(ctx, page);
   }
   fz_catch(ctx) {
     if (err_out)
@@ -18,19 +18,95 @@ typedef struct {
 // Returns NULL on error. Error messages are printed to stderr by default.
 fz_context *my_new_context();
 void my_drop_context(fz_context *ctx);
+// Frees the resources (fonts, images, ...) MuPDF keeps cached between pages.
+void my_empty_store(fz_context *ctx);
 
-// Returns non-zero on error using error buffer
+// Encryption of a PDF that needs a password, read before it is dropped.
+// encrypted is 0 when nothing could be read.
+typedef struct {
+  int encrypted;
+  char handler[64]; // Security handler (/Filter), e.g. "Standard"
+  char method[16];  // Cipher of strings: "RC4" or "AES"
+  int version;
+  int revision;
+  int length; // Key length in bits
+  int permissions; // User access permissions (/P), a bit field
+} my_encryption;
+
+// Returns non-zero on error using error buffer, 2 for a document that needs
+// a password, with its encryption in enc_out (may be NULL).
+// Open a file as a bare PDF, without registering document handlers, for
+// reading document-level objects (XFA, form fields) only.
+int my_open_pdf(fz_context *ctx, const char *filename, fz_document **doc_out,
+                my_encryption *enc_out, char *err_out, size_t err_len);
 int my_open_document(fz_context *ctx, const char *filename,
-                     fz_document **doc_out, char *err_out, size_t err_len);
+                     fz_document **doc_out, my_encryption *enc_out,
+                     char *err_out, size_t err_len);
 void my_drop_document(fz_context *ctx, fz_document *doc);
 
 int my_count_pages(fz_context *ctx, fz_document *doc, int *count_out,
                    char *err_out, size_t err_len);
 
+// Page size in points, after rotation.
+int my_page_size(fz_context *ctx, fz_document *doc, int page_number,
+                 float *width_out, float *height_out, char *err_out,
+                 size_t err_len);
+
+// MediaBox, CropBox and TrimBox of a page in points, in page space (after
+// rotation, origin top-left). boxes_out receives 12 floats: x0, y0, x1, y1
+// for each box in that order. Missing boxes default as in the PDF spec.
+int my_page_boxes(fz_context *ctx, fz_document *doc, int page_number,
+                  float *boxes_out, char *err_out, size_t err_len);
+
+// Declared /Rotate of a page, normalized to 0, 90, 180 or 270.
+// Non-PDF documents report 0.
+int my_page_rotation(fz_context *ctx, fz_document *doc, int page_number,
+                     int *rotate_out, char *err_out, size_t err_len);
+
 // Returns pixmap or error
 int my_render_page(fz_context *ctx, fz_document *doc, int page_number, int dpi,
                    fz_pixmap **pix_out, char *err_out, size_t err_len);
 
+// Same in RGB, for color analysis.
+int my_render_page_rgb(fz_context *ctx, fz_document *doc, int page_number,
+                       int dpi, fz_pixmap **pix_out, char *err_out,
+                       size_t err_len);
+
+// Save a grayscale image (one byte per pixel, rows not padded) to path as
+// PNG (jpeg_quality 0) or JPEG of the given quality, tagged with dpi.
+int my_save_gray(fz_context *ctx, const unsigned char *samples, int w, int h,
+                 int dpi, const char *path, int jpeg_quality, char *err_out,
+                 size_t err_len);
+
+// Number of filled paths drawn by a page, and of curve segments in them.
+int my_vector_stats(fz_context *ctx, fz_document *doc, int page_number,
+                    int *paths_out, int *curves_out, char *err_out,
+                    size_t err_len);
+
+// What a PDF page holds, without running it: the nesting depth of its form
+// XObjects and tiling patterns (content drawn by the page is depth 0), the
+// image XObjects its resources name, and the decoded size of its content
+// streams and those of its forms and patterns. Each object is visited once;
+// forms nested deeper than max_depth are not descended into, and reading
+// stops once max_content bytes are exceeded (0: no limit). Non-PDF documents
+// report zeros.
+int my_page_resources(fz_context *ctx, fz_document *doc, int page_number,
+                      int max_depth, size_t max_content, int *depth_out,
+                      int *images_out, size_t *content_out, char *err_out,
+                      size_t err_len);
+
+// Annotations
+// Number of annotations on a page. Non-PDF documents report 0.
+int my_count_annots(fz_context *ctx, fz_document *doc, int page_number,
+                    int *count_out, char *err_out, size_t err_len);
+
+// Render the appearance of annotation `index` on a page. type_out receives
+// the annotation subtype name, rect_out its bounds in points (x0, y0, x1, y1).
+int my_render_annot(fz_context *ctx, fz_document *doc, int page_number,
+                    int index, int dpi, fz_pixmap **pix_out, char *type_out,
+                    size_t type_len, float *rect_out, char *err_out,
+                    size_t err_len);
+
 void my_drop_pixmap(fz_context *ctx, fz_pixmap *pix);
 
 // Accessors for pixmap
@@ -47,9 +123,84 @@ char *my_extract_xfa(fz_context *ctx, fz_document *doc, size_t *len_out,
                      char *err_out, size_t err_len);
 void my_free_xfa(fz_context *ctx, char *xfa_data);
 
+// Signature form fields, one per line:
+// "name\tsigned(0|1)\tsigner\tdate\treason\tlocation". Tabs and newlines in
+// values are replaced by spaces. Returns NULL if there are none; free with my_free_text().
+char *my_signature_fields(fz_context *ctx, fz_document *doc, char *err_out,
+                          size_t err_len);
+
+// Active and external content, one finding per line as "kind\tdetail":
+// javascript, launch (target), external (URI or file), embedded_file (name)
+// and open_action (action type, or AA for document actions). Returns NULL if
+// there is none; free with my_free_text().
+char *my_active_content(fz_context *ctx, fz_document *doc, char *err_out,
+                        size_t err_len);
+
+// Fonts of a PDF, one per line as "object\tname\ttype\tformat", where format
+// is that of the embedded program (type1, truetype, cff, opentype) or empty
+// if the font is not embedded. Descendants of Type0 fonts are not listed.
+// Returns NULL if there are none; free with my_free_text().
+char *my_fonts(fz_context *ctx, fz_document *doc, char *err_out, size_t err_len);
+// Embedded program of the font at object number num; NULL if not embedded.
+// len_out receives its length. Free with my_free_text().
+char *my_font_program(fz_context *ctx, fz_document *doc, int num,
+                      size_t *len_out, char *err_out, size_t err_len);
+
+// Searchable PDF writing: page images with an invisible text layer in a
+// glyphless font (font_program, e.g. Tesseract's pdf.ttf). Text drawn with
+// font resource /F0 is encoded as 2-byte UTF-16BE codes. Without a font
+// program, only my_pdf_add_text_page can add pages.
+typedef struct my_pdf_writer my_pdf_writer;
+my_pdf_writer *my_new_pdf(fz_context *ctx, const unsigned char *font_program,
+                          size_t font_len, char *err_out, size_t err_len);
+// Append a page of page_width x page_height points showing the 8-bit gray
+// image (rows not padded), followed by the content stream operators in text.
+int my_pdf_add_page(fz_context *ctx, my_pdf_writer *writer,
+                    const unsigned char *gray, int width, int height,
+                    float page_width, float page_height, const char *text,
+                    char *err_out, size_t err_len);
+// Append a page of page_width x page_height points drawing the content
+// stream operators in text, with the standard 14 font font_name (e.g.
+// "Helvetica") as font resource /F1 in Latin encoding.
+int my_pdf_add_text_page(fz_context *ctx, my_pdf_writer *writer,
+                         float page_width, float page_height,
+                         const char *font_name, const char *text,
+                         char *err_out, size_t err_len);
+int my_pdf_save(fz_context *ctx, my_pdf_writer *writer, const char *filename,
+                char *err_out, size_t err_len);
+void my_drop_pdf(fz_context *ctx, my_pdf_writer *writer);
+
+// Replace text of an existing PDF and save it. For each of `count` pages
+// (0-based `pages[i]`), the text under `rect_counts[i]` rectangles (4 floats
+// each, in page space, taken in order from `rects`) is removed and `ops[i]`
+// is drawn in page space with the glyphless font as /FCrabOCR.
+int my_correct_text(fz_context *ctx, fz_document *doc, int count,
+                    const int *pages, const int *rect_counts,
+                    const float *rects, const char *const *ops,
+                    const unsigned char *font_program, size_t font_len,
+                    const char *filename, char *err_out, size_t err_len);
+
 // Text extraction
-// Returns dynamically allocated UTF-8 string, or NULL if no text.
+// Receives text as it is produced; returns non-zero to stop extraction.
+typedef int (*my_text_sink)(void *opaque, const char *data, size_t len);
+
+// Text of a page as MuPDF prints it, passed to sink in chunks of a few
+// kilobytes as it is printed, without holding the page's text.
+// Returns non-zero on error, including a sink that stopped.
+int my_extract_text_to(fz_context *ctx, fz_document *doc, int page_number,
+                       my_text_sink sink, void *opaque, char *err_out,
+                       size_t err_len);
+// Text lines of a page, one per output line as
+// "x0 y0 x1 y1\tsize\tbold\ttext" (mean font size, 1 if all glyphs are bold), with
+// bounds in points and a blank line after each block. Unmapped glyphs appear
+// as U+FFFD.
+// Caller must free with my_free_text().
+char *my_extract_lines(fz_context *ctx, fz_document *doc, int page_number,
+                       char *err_out, size_t err_len);
+// Text blocks, lines and spans of a page, one record per output line:
+// "B\tx0 y0 x1 y1", "L\tx0 y0 x1 y1", then "S\tx0 y0 x1 y1\tsize\tfont\ttext"
+// for each run of glyphs in the same font and size. Bounds in points.
 // Caller must free with my_free_text().
-char *my_extract_text(fz_context *ctx, fz_document *doc, int page_number,
-                      char *err_out, size_t err_len);
+char *my_extract_structured(fz_context *ctx, fz_document *doc, int page_number,
+                            char *err_out, size_t err_len);
 void my_free_text(fz_context *ctx, char *text);
@@ -1,12 +1,22 @@
 use roxmltree::{Document, Node};
 use serde_json::{Map, Value};
+use std::collections::HashSet;
 
 
-/// Convert XFA XML string to structured JSON string.
+/// Convert XFA XML string to a structured JSON value.
 /// 
+/// Fields keep the order of the form (serde_json's `preserve_order`), and
+/// repeated elements become arrays in place of their first occurrence.
+/// Subforms the template declares as repeatable, and elements listed in
+/// `always_array` (by name or path such as `Form.Items`), are arrays even with
+/// a single instance, so documents of the same form share one shape.
 /// If `data_only` is true, metadata fields and large lookup lists are excluded.
-pub fn xfa_xml_to_json(xml: &str, data_only: bool) -> Result<String, String> {
+pub fn xfa_xml_to_value(xml: &str, data_only: bool, always_array: &[String]) -> Result<Value, String> {
     let doc = Document::parse(xml).map_err(|e| format!("XML parse error: {}", e))?;
+    let repeating = Repeating {
+        subforms: repeating_subforms(&doc),
+        paths: always_array,
+    };
     
     let data_node = find_data_section(&doc)
         .ok_or_else(|| "Could not locate form data section in XFA XML".to_string())?;
@@ -25,7 +35,8 @@ pub fn xfa_xml_to_json(xml: &str, data_only: bool) -> Result<String, String> {
             continue;
         }
         
-        if let Some(json_val) = element_to_json(child, data_only) {
+        let force_array = repeating.matches(tag_name, tag_name);
+        if let Some(json_val) = element_to_json(child, tag_name, &repeating) {
             // Check for top-level lookup lists if requested
             if data_only && is_lookup_list(tag_name, &json_val) {
                 continue;
@@ -41,13 +52,13 @@ pub fn xfa_xml_to_json(xml: &str, data_only: bool) -> Result<String, String> {
                         }
                     }
                     if !filtered_map.is_empty() {
-                         merge_into_map(&mut form_data, tag_name, Value::Object(filtered_map));
+                         merge_into_map(&mut form_data, tag_name, Value::Object(filtered_map), force_array);
                     }
                 } else {
-                     merge_into_map(&mut form_data, tag_name, json_val);
+                     merge_into_map(&mut form_data, tag_name, json_val, force_array);
                 }
             } else {
-                merge_into_map(&mut form_data, tag_name, json_val);
+                merge_into_map(&mut form_data, tag_name, json_val, force_array);
             }
         }
     }
@@ -56,12 +67,52 @@ pub fn xfa_xml_to_json(xml: &str, data_only: bool) -> Result<String, String> {
         return Err("No valid data found after extraction".to_string());
     }
     
-    serde_json::to_string_pretty(&Value::Object(form_data))
-        .map_err(|e| format!("JSON serialization error: {}", e))
+    Ok(Value::Object(form_data))
+}
+
+/// Elements that become arrays even with a single instance.
+struct Repeating<'a> {
+    /// Paths of subforms the template declares with `<occur max>` above 1.
+    subforms: HashSet<String>,
+    /// Names or data paths given by the user.
+    paths: &'a [String],
+}
+
+impl Repeating<'_> {
+    fn matches(&self, name: &str, path: &str) -> bool {
+        self.subforms.contains(path) || self.paths.iter().any(|p| p == name || p == path)
+    }
+}
+
+/// Paths of template subforms that may occur more than once (`max="-1"` is
+/// unbounded), as data paths: the names of the subform and its named
+/// ancestor subforms (`Form.Items.Item`). Unnamed subforms add no data
+/// element, so they are not part of the path.
+fn repeating_subforms(doc: &Document) -> HashSet<String> {
+    doc.descendants()
+        .filter(|n| n.is_element() && n.tag_name().name() == "subform")
+        .filter(|n| {
+            n.children()
+                .find(|c| c.is_element() && c.tag_name().name() == "occur")
+                .and_then(|occur| occur.attribute("max"))
+                .and_then(|max| max.trim().parse::<i64>().ok())
+                .is_some_and(|max| max == -1 || max > 1)
+        })
+        .filter(|n| n.has_attribute("name"))
+        .map(|n| {
+            let names: Vec<&str> = n
+                .ancestors()
+                .filter(|a| a.is_element() && a.tag_name().name() == "subform")
+                .filter_map(|a| a.attribute("name"))
+                .collect();
+            names.into_iter().rev().collect::<Vec<_>>().join(".")
+        })
+        .collect()
 }
 
 /// Helper to merge a key-value into a JSON map, handling duplicate keys by creating arrays.
-fn merge_into_map(map: &mut Map<String, Value>, key: &str, value: Value) {
+/// With `force_array`, the first instance is already wrapped in an array.
+fn merge_into_map(map: &mut Map<String, Value>, key: &str, value: Value, force_array: bool) {
     if let Some(existing) = map.get_mut(key) {
         if let Value::Array(arr) = existing {
             arr.push(value);
@@ -69,6 +120,8 @@ fn merge_into_map(map: &mut Map<String, Value>, key: &str, value: Value) {
             let old_val = existing.take();
             *existing = Value::Array(vec![old_val, value]);
         }
+    } else if force_array {
+        map.insert(key.to_string(), Value::Array(vec![value]));
     } else {
         map.insert(key.to_string(), value);
     }
@@ -106,7 +159,8 @@ fn find_data_section<'a>(doc: &'a Document) -> Option<Node<'a, 'a>> {
     doc.descendants().find(|n| n.is_element() && n.tag_name().name() == "data")
 }
 
-fn element_to_json(node: Node, data_only: bool) -> Option<Value> {
+/// `path` is the dotted path of `node` from the data section, e.g. `Form.Items`.
+fn element_to_json(node: Node, path: &str, repeating: &Repeating) -> Option<Value> {
     let tag_name = node.tag_name().name();
     
     // Skip system elements
@@ -150,8 +204,9 @@ fn element_to_json(node: Node, data_only: bool) -> Option<Value> {
         let child_name = child.tag_name().name();
         
         // recursive call
-        if let Some(child_val) = element_to_json(child, data_only) {
-             merge_into_map(&mut map, child_name, child_val);
+        let child_path = format!("{}.{}", path, child_name);
+        if let Some(child_val) = element_to_json(child, &child_path, repeating) {
+             merge_into_map(&mut map, child_name, child_val, repeating.matches(child_name, &child_path));
         }
     }
     
@@ -170,6 +225,33 @@ fn element_to_json(node: Node, data_only: bool) -> Option<Value> {
     Some(Value::Object(map))
 }
 
+/// Leaf values of converted XFA data by path (`Form.Applicant.name`, `Form.item[1]`),
+/// in document order. Attributes and element values keep their `_attributes`
+/// and `_value` keys as path segments.
+pub fn flatten(value: &Value) -> Map<String, Value> {
+    fn walk(value: &Value, path: String, out: &mut Map<String, Value>) {
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map {
+                    let child_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    walk(child, child_path, out);
+                }
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    walk(item, format!("{}[{}]", path, i), out);
+                }
+            }
+            leaf => {
+                out.insert(path, leaf.clone());
+            }
+        }
+    }
+    let mut out = Map::new();
+    walk(value, String::new(), &mut out);
+    out
+}
+
 fn is_metadata_field(name: &str) -> bool {
     let prefixes = [
         "FS", "fs", "_", "TEMPLATE", "QUERY", "TRANSFORMATION", 
@@ -212,8 +294,7 @@ mod tests {
     #[test]
     fn test_simple_structure() {
         let xml = r#"<data><name>John</name><age>30</age></data>"#;
-        let json_str = xfa_xml_to_json(xml, false).unwrap();
-        let v: Value = serde_json::from_str(&json_str).unwrap();
+        let v = xfa_xml_to_value(xml, false, &[]).unwrap();
         assert_eq!(v["name"], "John");
         assert_eq!(v["age"], "30");
     }
@@ -221,18 +302,61 @@ mod tests {
     #[test]
     fn test_attributes_and_value() {
         let xml = r#"<data><field id="1">Value</field></data>"#;
-        let json_str = xfa_xml_to_json(xml, false).unwrap();
-        let v: Value = serde_json::from_str(&json_str).unwrap();
+        let v = xfa_xml_to_value(xml, false, &[]).unwrap();
         // Since it has attributes, it should be an object with _value and _attributes
         assert_eq!(v["field"]["_value"], "Value");
         assert_eq!(v["field"]["_attributes"]["id"], "1");
     }
 
+    #[test]
+    fn test_document_order_and_repeated_groups() {
+        let xml = r#"<data><zip>1000</zip><name>Ann</name><item>A</item><city>Tunis</city><item>B</item></data>"#;
+        let mut v = xfa_xml_to_value(xml, false, &[]).unwrap();
+        let keys: Vec<&String> = v.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["zip", "name", "item", "city"]);
+        assert_eq!(v["item"], serde_json::json!(["A", "B"]));
+
+        v.sort_all_objects();
+        let keys: Vec<&String> = v.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["city", "item", "name", "zip"]);
+    }
+
+    #[test]
+    fn test_repeating_groups_are_arrays() {
+        let xml = r#"<xdp>
+            <template><subform name="Form"><subform name="Item"><occur min="0" max="-1"/></subform></subform></template>
+            <datasets><data><Form><Item><qty>2</qty></Item><Note><text>x</text></Note></Form></data></datasets>
+        </xdp>"#;
+        let v = xfa_xml_to_value(xml, false, &[]).unwrap();
+        assert_eq!(v["Form"]["Item"], serde_json::json!([{ "qty": "2" }]));
+        assert_eq!(v["Form"]["Note"], serde_json::json!({ "text": "x" }));
+
+        let v = xfa_xml_to_value(xml, false, &["Form.Note".to_string()]).unwrap();
+        assert_eq!(v["Form"]["Note"], serde_json::json!([{ "text": "x" }]));
+    }
+
+    #[test]
+    fn test_repeating_subform_name_reused() {
+        // "Line" repeats under Invoice, but is a single subform under Summary
+        let xml = r#"<xdp>
+            <template><subform name="Form">
+                <subform name="Invoice"><subform><subform name="Line"><occur max="-1"/></subform></subform></subform>
+                <subform name="Summary"><subform name="Line"><occur max="1"/></subform></subform>
+            </subform></template>
+            <datasets><data><Form>
+                <Invoice><Line><qty>2</qty></Line></Invoice>
+                <Summary><Line><total>9</total></Line></Summary>
+            </Form></data></datasets>
+        </xdp>"#;
+        let v = xfa_xml_to_value(xml, false, &[]).unwrap();
+        assert_eq!(v["Form"]["Invoice"]["Line"], serde_json::json!([{ "qty": "2" }]));
+        assert_eq!(v["Form"]["Summary"]["Line"], serde_json::json!({ "total": "9" }));
+    }
+
     #[test]
     fn test_metadata_filtering() {
         let xml = r#"<data><_sys>Hidden</_sys><visible>Shown</visible></data>"#;
-        let json_str = xfa_xml_to_json(xml, true).unwrap();
-        let v: Value = serde_json::from_str(&json_str).unwrap();
+        let v = xfa_xml_to_value(xml, true, &[]).unwrap();
         assert!(v.get("_sys").is_none());
         assert_eq!(v["visible"], "Shown");
     }
@@ -246,21 +370,19 @@ mod tests {
         }
         let xml = format!(r#"<data><MyDropdown><options>{}</options></MyDropdown></data>"#, list_items);
         
-        let json_str = xfa_xml_to_json(&xml, true).unwrap();
-        let v: Value = serde_json::from_str(&json_str).unwrap();
+        xfa_xml_to_value(&xml, true, &[]).unwrap();
         
         // Test a simpler structure where the list is direct children.
         let xml2 = format!(r#"<data><MyList>{}</MyList></data>"#, list_items);
          
         // With data_only=true, it should be skipped and result in empty data error.
-        let result = xfa_xml_to_json(&xml2, true);
+        let result = xfa_xml_to_value(&xml2, true, &[]);
         assert!(result.is_err());
         assert_eq!(result.err().unwrap(), "No valid data found after extraction");
         
         // Let's add a valid field
         let xml3 = format!(r#"<data><MyList>{}</MyList><real>Data</real></data>"#, list_items);
-        let json_str3 = xfa_xml_to_json(&xml3, true).unwrap();
-        let v3: Value = serde_json::from_str(&json_str3).unwrap();
+        let v3 = xfa_xml_to_value(&xml3, true, &[]).unwrap();
         
         assert!(v3.get("MyList").is_none());
         assert_eq!(v3["real"], "Data");
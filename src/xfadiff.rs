@@ -0,0 +1,77 @@
+use crate::errors::CrabError;
+use crate::renderer::Renderer;
+use crate::xfa;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Compare the XFA form data of two PDFs and print the changed, added and
+/// removed field values as JSON. With `full`, metadata and lookup lists are compared too.
+pub fn run(a: &Path, b: &Path, full: bool) -> Result<(), CrabError> {
+    let renderer = Renderer::new()?;
+    let old = form_data(&renderer, a, full)?;
+    let new = form_data(&renderer, b, full)?;
+    let out = serde_json::to_string_pretty(&diff(&old, &new)).map_err(|e| CrabError::Internal(e.to_string()))?;
+    println!("{}", out);
+    Ok(())
+}
+
+fn form_data(renderer: &Renderer, path: &Path, full: bool) -> Result<Value, CrabError> {
+    let mut doc = renderer.open(path)?;
+    let xml = renderer.extract_xfa(&doc);
+    doc.drop_with(renderer);
+    let xml = xml.ok_or_else(|| CrabError::Pdf(format!("{:?} has no XFA form", path)))?;
+    xfa::xfa_xml_to_value(&xml, !full, &[]).map_err(|e| CrabError::Pdf(format!("Failed to parse XFA content of {:?}: {}", path, e)))
+}
+
+/// Field-level differences between two converted XFA datasets, keyed by
+/// path (`Form.Applicant.name`, `Form.item[1]`), in document order.
+pub fn diff(old: &Value, new: &Value) -> Value {
+    let old_fields = xfa::flatten(old);
+    let new_fields = xfa::flatten(new);
+    let (mut changed, mut removed, mut added) = (Vec::new(), Vec::new(), Vec::new());
+    let mut unchanged = 0;
+
+    for (path, value) in &old_fields {
+        match new_fields.get(path) {
+            Some(new_value) if new_value == value => unchanged += 1,
+            Some(new_value) => changed.push(json!({ "path": path, "old": value, "new": new_value })),
+            None => removed.push(json!({ "path": path, "value": value })),
+        }
+    }
+    for (path, value) in &new_fields {
+        if !old_fields.contains_key(path) {
+            added.push(json!({ "path": path, "value": value }));
+        }
+    }
+
+    json!({ "changed": changed, "added": added, "removed": removed, "unchanged": unchanged })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old = xfa::xfa_xml_to_value(
+            "<data><Form><name>Ann</name><zip>1000</zip><item>A</item><fax>1</fax></Form></data>",
+            false,
+            &[],
+        )
+        .unwrap();
+        let new = xfa::xfa_xml_to_value(
+            "<data><Form><name>Anna</name><zip>1000</zip><item>A</item><item>B</item></Form></data>",
+            false,
+            &[],
+        )
+        .unwrap();
+
+        let d = diff(&old, &new);
+        assert_eq!(d["changed"], json!([{ "path": "Form.name", "old": "Ann", "new": "Anna" }]));
+        // A second instance turns a single value into an array
+        assert_eq!(d["removed"][0]["path"], "Form.item");
+        assert_eq!(d["added"], json!([{ "path": "Form.item[0]", "value": "A" }, { "path": "Form.item[1]", "value": "B" }]));
+        assert_eq!(d["removed"][1], json!({ "path": "Form.fax", "value": "1" }));
+        assert_eq!(d["unchanged"], 1);
+    }
+}
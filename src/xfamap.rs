@@ -0,0 +1,84 @@
+use crate::errors::CrabError;
+use crate::xfa;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// Renames of XFA field paths to downstream keys, from `--xfa-map`.
+pub struct KeyMap {
+    /// (field path, key) in file order.
+    entries: Vec<(String, String)>,
+}
+
+/// Read a mapping file: a JSON object from field path to key,
+/// e.g. `{"Form.Applicant.name": "applicant_name", "Form.Items": "items"}`.
+pub fn load(path: &Path) -> Result<KeyMap, CrabError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| CrabError::Cli(format!("Cannot read XFA map {:?}: {}", path, e)))?;
+    parse(&content).map_err(|e| CrabError::Cli(format!("Invalid XFA map {:?}: {}", path, e)))
+}
+
+fn parse(content: &str) -> Result<KeyMap, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let object = value.as_object().ok_or("expected an object of field path to key")?;
+    let entries = object
+        .iter()
+        .map(|(path, key)| match key.as_str() {
+            Some(key) => Ok((path.clone(), key.to_string())),
+            None => Err(format!("{}: key must be a string", path)),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(KeyMap { entries })
+}
+
+impl KeyMap {
+    /// Flatten converted XFA data to one key per field. A mapped path also
+    /// renames everything below it (`Form.Items` → `items` turns
+    /// `Form.Items[0].qty` into `items[0].qty`). Unmapped fields keep their
+    /// path, or are dropped when `strict`.
+    pub fn apply(&self, value: &Value, strict: bool) -> Value {
+        let mut out = Map::new();
+        for (path, leaf) in xfa::flatten(value) {
+            match self.rename(&path) {
+                Some(key) => {
+                    out.insert(key, leaf);
+                }
+                None if !strict => {
+                    out.insert(path, leaf);
+                }
+                None => {}
+            }
+        }
+        Value::Object(out)
+    }
+
+    /// The key for a field path, from the longest mapped path that covers it.
+    fn rename(&self, path: &str) -> Option<String> {
+        self.entries
+            .iter()
+            .filter(|(from, _)| {
+                path.strip_prefix(from.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.') || rest.starts_with('['))
+            })
+            .max_by_key(|(from, _)| from.len())
+            .map(|(from, to)| format!("{}{}", to, &path[from.len()..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply_map() {
+        let map = parse(r#"{"Form.name": "applicant_name", "Form.Items": "items", "Form.Items.qty": "q"}"#).unwrap();
+        let data = json!({ "Form": { "name": "Ann", "Items": [{ "qty": "2" }], "namesake": "x", "fax": "1" } });
+
+        assert_eq!(
+            map.apply(&data, false),
+            json!({ "applicant_name": "Ann", "items[0].qty": "2", "Form.namesake": "x", "Form.fax": "1" })
+        );
+        assert_eq!(map.apply(&data, true), json!({ "applicant_name": "Ann", "items[0].qty": "2" }));
+        assert!(parse(r#"{"Form.name": 1}"#).is_err());
+    }
+}
@@ -0,0 +1,267 @@
+use crate::docx::{escape, write_zip};
+use crate::output::{OutputWriter, PageResult, XfaData};
+use crate::renderer::{ActiveContent, FontInfo, SignatureField};
+use crate::warnings::Warning;
+use crate::xfa;
+use serde_json::Value;
+use std::io::{self, Write};
+
+const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const XML_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+"#;
+
+/// Excel's limit on sheet name length.
+const MAX_SHEET_NAME: usize = 31;
+
+/// A sheet of the workbook: its name and cell texts row by row.
+struct Sheet {
+    name: String,
+    rows: Vec<Vec<String>>,
+}
+
+/// An Excel workbook (OOXML) for case workers: the form fields of the XFA
+/// data on a "Form" sheet, by path, then each table detected on the pages
+/// (`--tables`) on a sheet of its own ("Page 3 Table 1"). Cells are text, so
+/// codes keep their leading zeros. Page text, annotations and warnings are not
+/// included. The archive is written by `finish`, its parts stored uncompressed.
+pub struct XlsxWriter<W: Write> {
+    out: W,
+    sheets: Vec<Sheet>,
+}
+
+impl<W: Write> XlsxWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, sheets: Vec::new() }
+    }
+}
+
+impl<W: Write> OutputWriter for XlsxWriter<W> {
+    fn xfa(&mut self, xfa: &XfaData) -> io::Result<()> {
+        // Raw XML (`--xfa raw`) is converted for its fields
+        let converted;
+        let value = match xfa {
+            XfaData::Parsed(value) => value,
+            XfaData::Raw(xml) => match xfa::xfa_xml_to_value(xml, true, &[]) {
+                Ok(value) => {
+                    converted = value;
+                    &converted
+                }
+                Err(_) => return Ok(()),
+            },
+        };
+        let mut rows = vec![vec!["Field".to_string(), "Value".to_string()]];
+        for (path, leaf) in xfa::flatten(value) {
+            let text = match leaf {
+                Value::String(s) => s,
+                Value::Null => String::new(),
+                other => other.to_string(),
+            };
+            rows.push(vec![path, text]);
+        }
+        // Form data comes before the pages
+        self.sheets.insert(0, Sheet { name: "Form".to_string(), rows });
+        Ok(())
+    }
+
+    fn signatures(&mut self, _fields: &[SignatureField]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn active_content(&mut self, _content: &ActiveContent) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn fonts(&mut self, _fonts: &[FontInfo]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn page(&mut self, page: &PageResult) -> io::Result<()> {
+        let page_name = format!("{}{}", page.number, page.part.map(String::from).unwrap_or_default());
+        for (i, table) in page.tables.iter().enumerate() {
+            let name = format!("Page {} Table {}", page_name, i + 1);
+            self.sheets.push(Sheet { name, rows: table.rows.clone() });
+        }
+        Ok(())
+    }
+
+    fn no_pages(&mut self, _reason: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn warnings(&mut self, _warnings: &[Warning]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        // A workbook needs a sheet
+        if self.sheets.is_empty() {
+            self.sheets.push(Sheet { name: "Empty".to_string(), rows: Vec::new() });
+        }
+        let names = sheet_names(&self.sheets);
+        let worksheets: Vec<String> = self.sheets.iter().map(|s| worksheet(&s.rows)).collect();
+        let paths: Vec<String> = (1..=self.sheets.len()).map(|i| format!("xl/worksheets/sheet{}.xml", i)).collect();
+
+        let mut content_types = String::from(XML_HEADER);
+        content_types.push_str(r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#);
+        let mut workbook = String::from(XML_HEADER);
+        workbook.push_str(r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#);
+        let mut workbook_rels = String::from(XML_HEADER);
+        workbook_rels.push_str(r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#);
+        for (i, (name, path)) in names.iter().zip(&paths).enumerate() {
+            content_types.push_str(&format!(
+                r#"<Override PartName="/{}" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+                path
+            ));
+            workbook.push_str(&format!(r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#, escape(name), i + 1, i + 1));
+            workbook_rels.push_str(&format!(
+                r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#,
+                i + 1,
+                i + 1
+            ));
+        }
+        content_types.push_str("</Types>");
+        workbook.push_str("</sheets></workbook>");
+        workbook_rels.push_str("</Relationships>");
+
+        let mut parts: Vec<(&str, &[u8])> = vec![
+            ("[Content_Types].xml", content_types.as_bytes()),
+            ("_rels/.rels", RELATIONSHIPS.as_bytes()),
+            ("xl/workbook.xml", workbook.as_bytes()),
+            ("xl/_rels/workbook.xml.rels", workbook_rels.as_bytes()),
+        ];
+        parts.extend(paths.iter().map(String::as_str).zip(worksheets.iter().map(String::as_bytes)));
+        write_zip(&mut self.out, &parts)?;
+        self.out.flush()
+    }
+}
+
+/// Sheet names as Excel accepts them: without `[]:*?/\`, at most 31
+/// characters, and unique (a repeated name gets a " (2)" suffix).
+fn sheet_names(sheets: &[Sheet]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(sheets.len());
+    for sheet in sheets {
+        let clean: String = sheet.name.chars().map(|c| if "[]:*?/\\".contains(c) { '_' } else { c }).take(MAX_SHEET_NAME).collect();
+        let mut name = clean.clone();
+        let mut n = 2;
+        while names.iter().any(|other| other.eq_ignore_ascii_case(&name)) {
+            let suffix = format!(" ({})", n);
+            name = clean.chars().take(MAX_SHEET_NAME - suffix.len()).chain(suffix.chars()).collect();
+            n += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// A worksheet of inline-string cells.
+fn worksheet(rows: &[Vec<String>]) -> String {
+    let mut xml = String::from(XML_HEADER);
+    xml.push_str(r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#);
+    for (r, row) in rows.iter().enumerate() {
+        xml.push_str(&format!(r#"<row r="{}">"#, r + 1));
+        for (c, cell) in row.iter().enumerate() {
+            if cell.is_empty() {
+                continue;
+            }
+            xml.push_str(&format!(
+                r#"<c r="{}{}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                column(c),
+                r + 1,
+                escape(cell)
+            ));
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// Column letters of a 0-based column index: A to Z, then AA, AB, ...
+fn column(mut index: usize) -> String {
+    let mut letters = Vec::new();
+    loop {
+        letters.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    letters.reverse();
+    String::from_utf8(letters).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docx::read_zip;
+    use crate::tables::Table;
+    use serde_json::json;
+
+    #[test]
+    fn test_xlsx_writer() {
+        let mut buf = Vec::new();
+        {
+            let mut w = XlsxWriter::new(&mut buf);
+            w.xfa(&XfaData::Parsed(json!({ "Form": { "name": "Doe & Sons", "zip": "01234", "item": [{ "qty": 2 }] } }))).unwrap();
+            let table = Table { rect: [0.0; 4], rows: vec![vec!["Item".into(), "Price".into()], vec!["Pen".into(), String::new()]] };
+            w.page(&PageResult { number: 3, tables: vec![table.clone(), table], ..Default::default() }).unwrap();
+            w.finish().unwrap();
+        }
+        let entries = read_zip(&buf);
+        let names: Vec<&str> = entries.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "xl/workbook.xml",
+                "xl/_rels/workbook.xml.rels",
+                "xl/worksheets/sheet1.xml",
+                "xl/worksheets/sheet2.xml",
+                "xl/worksheets/sheet3.xml"
+            ]
+        );
+        let text = |i: usize| String::from_utf8(entries[i].1.clone()).unwrap();
+        let workbook = text(2);
+        let doc = roxmltree::Document::parse(&workbook).unwrap();
+        let sheets: Vec<&str> = doc.descendants().filter(|n| n.has_tag_name("sheet")).filter_map(|n| n.attribute("name")).collect();
+        assert_eq!(sheets, ["Form", "Page 3 Table 1", "Page 3 Table 2"]);
+
+        let cells = |i: usize| -> Vec<(String, String)> {
+            let sheet = text(i);
+            let doc = roxmltree::Document::parse(&sheet).unwrap();
+            doc.descendants()
+                .filter(|n| n.has_tag_name("c"))
+                .map(|c| (c.attribute("r").unwrap().to_string(), c.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()).collect()))
+                .collect()
+        };
+        let cell = |r: &str, t: &str| (r.to_string(), t.to_string());
+        assert_eq!(
+            cells(4),
+            [
+                cell("A1", "Field"),
+                cell("B1", "Value"),
+                cell("A2", "Form.name"),
+                cell("B2", "Doe & Sons"),
+                cell("A3", "Form.zip"),
+                cell("B3", "01234"),
+                cell("A4", "Form.item[0].qty"),
+                cell("B4", "2")
+            ]
+        );
+        assert_eq!(cells(5), [cell("A1", "Item"), cell("B1", "Price"), cell("A2", "Pen")]);
+    }
+
+    #[test]
+    fn test_sheet_names_and_columns() {
+        let sheet = |name: &str| Sheet { name: name.into(), rows: Vec::new() };
+        let long = "Page 12a Table 3 of the annex: totals";
+        assert_eq!(
+            sheet_names(&[sheet("Form"), sheet("form"), sheet(long), sheet(long)]),
+            ["Form", "form (2)", "Page 12a Table 3 of the annex_ ", "Page 12a Table 3 of the ann (2)"]
+        );
+        assert_eq!([column(0), column(25), column(26), column(701), column(702)], ["A", "Z", "AA", "ZZ", "AAA"]);
+    }
+}
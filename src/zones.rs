@@ -0,0 +1,171 @@
+use crate::ocr::Word;
+use crate::output::ImagePlacement;
+use crate::renderer::TextLine;
+
+/// Share of the page height at the top (bottom) holding headers (footers).
+const EDGE_BAND: f32 = 0.1;
+/// Widest sidebar, as a share of the page width.
+const MAX_SIDEBAR_WIDTH: f32 = 0.3;
+
+/// Where a zone lies on the page.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ZoneKind {
+    Header,
+    Body,
+    Sidebar,
+    Footer,
+}
+
+impl ZoneKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ZoneKind::Header => "header",
+            ZoneKind::Body => "body",
+            ZoneKind::Sidebar => "sidebar",
+            ZoneKind::Footer => "footer",
+        }
+    }
+}
+
+/// Text blocks of a page that share a layout role (`--zones`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Zone {
+    pub kind: ZoneKind,
+    /// Bounds of its blocks on the page in points (x0, y0, x1, y1).
+    pub rect: [f32; 4],
+    /// Text of its blocks in reading order, separated by blank lines.
+    pub text: String,
+}
+
+/// A block of text with its bounds on the page.
+struct Block {
+    rect: [f32; 4],
+    text: String,
+}
+
+/// Zones of the text-layer lines of a page, by the blocks MuPDF groups them in.
+/// `page` is the region of the page in points.
+pub fn from_lines(lines: &[TextLine], page: [f32; 4]) -> Vec<Zone> {
+    let mut blocks: Vec<Block> = Vec::new();
+    for group in lines.chunk_by(|a, b| a.block == b.block) {
+        let texts: Vec<&str> = group.iter().map(|l| l.text.trim()).filter(|t| !t.is_empty()).collect();
+        if !texts.is_empty() {
+            let rect = group.iter().skip(1).fold(group[0].rect, |acc, l| union(acc, l.rect));
+            blocks.push(Block { rect, text: texts.join("\n") });
+        }
+    }
+    zones(blocks, page)
+}
+
+/// Zones of the OCR words of a page, by the blocks Tesseract found.
+pub fn from_words(words: &[Word], placement: ImagePlacement, page: [f32; 4]) -> Vec<Zone> {
+    let blocks = words
+        .chunk_by(|a, b| a.block == b.block)
+        .map(|group| {
+            let lines: Vec<String> = group
+                .chunk_by(|a, b| a.line == b.line)
+                .map(|line| line.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" "))
+                .collect();
+            let bbox = group.iter().skip(1).fold(group[0].bbox, |[l, t, r, b], w| {
+                [l.min(w.bbox[0]), t.min(w.bbox[1]), r.max(w.bbox[2]), b.max(w.bbox[3])]
+            });
+            Block { rect: placement.to_points(bbox), text: lines.join("\n") }
+        })
+        .collect();
+    zones(blocks, page)
+}
+
+/// Blocks in the top and bottom bands of the page are headers and footers.
+/// Narrow blocks in the outer part of either side are sidebars when body text
+/// runs beside them; everything else is body. Zones come in page order, left
+/// sidebar before right.
+fn zones(blocks: Vec<Block>, page: [f32; 4]) -> Vec<Zone> {
+    let [x0, y0, x1, y1] = page;
+    let (width, height) = (x1 - x0, y1 - y0);
+    let edge = |block: &Block| {
+        if block.rect[3] <= y0 + height * EDGE_BAND {
+            Some(ZoneKind::Header)
+        } else if block.rect[1] >= y1 - height * EDGE_BAND {
+            Some(ZoneKind::Footer)
+        } else {
+            None
+        }
+    };
+    let side_width = width * MAX_SIDEBAR_WIDTH;
+    // -1 for a narrow block on the left, 1 on the right
+    let side = |block: &Block| {
+        let [bx0, _, bx1, _] = block.rect;
+        if bx1 - bx0 > side_width {
+            0
+        } else if bx1 <= x0 + side_width {
+            -1
+        } else if bx0 >= x1 - side_width {
+            1
+        } else {
+            0
+        }
+    };
+    let beside = |block: &Block, other: &Block, side: i32| {
+        let overlaps = other.rect[1] < block.rect[3] && block.rect[1] < other.rect[3];
+        let across = if side < 0 { other.rect[0] >= block.rect[2] } else { other.rect[2] <= block.rect[0] };
+        overlaps && across
+    };
+
+    let kinds: Vec<(ZoneKind, i32)> = blocks
+        .iter()
+        .map(|block| match (edge(block), side(block)) {
+            (Some(kind), _) => (kind, 0),
+            (None, 0) => (ZoneKind::Body, 0),
+            (None, s) if blocks.iter().any(|other| edge(other).is_none() && side(other) == 0 && beside(block, other, s)) => (ZoneKind::Sidebar, s),
+            _ => (ZoneKind::Body, 0),
+        })
+        .collect();
+
+    let order = [(ZoneKind::Header, 0), (ZoneKind::Sidebar, -1), (ZoneKind::Body, 0), (ZoneKind::Sidebar, 1), (ZoneKind::Footer, 0)];
+    order
+        .iter()
+        .filter_map(|wanted| {
+            let members: Vec<&Block> = blocks.iter().zip(&kinds).filter(|(_, k)| *k == wanted).map(|(b, _)| b).collect();
+            let first = members.first()?;
+            Some(Zone {
+                kind: wanted.0,
+                rect: members.iter().skip(1).fold(first.rect, |acc, b| union(acc, b.rect)),
+                text: members.iter().map(|b| b.text.as_str()).collect::<Vec<_>>().join("\n\n"),
+            })
+        })
+        .collect()
+}
+
+fn union(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    [a[0].min(b[0]), a[1].min(b[1]), a[2].max(b[2]), a[3].max(b[3])]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zones() {
+        let a4 = [0.0, 0.0, 595.0, 842.0];
+        let line = |block, rect, text: &str| TextLine { rect, text: text.into(), block, size: 10.0, bold: false };
+        let lines = [
+            line(0, [50.0, 30.0, 545.0, 42.0], "ACME Corp - Annual Report"),
+            line(1, [40.0, 100.0, 150.0, 112.0], "In this issue"),
+            line(1, [40.0, 114.0, 150.0, 126.0], "Results, p. 3"),
+            line(2, [180.0, 100.0, 555.0, 112.0], "Revenue grew by 12% over the year,"),
+            line(2, [180.0, 114.0, 555.0, 126.0], "driven by exports."),
+            line(3, [60.0, 400.0, 140.0, 412.0], "Dear Sir,"),
+            line(4, [280.0, 800.0, 315.0, 812.0], "Page 2"),
+        ];
+        let zones = from_lines(&lines, a4);
+        let kinds: Vec<&str> = zones.iter().map(|z| z.kind.as_str()).collect();
+        assert_eq!(kinds, ["header", "sidebar", "body", "footer"]);
+        assert_eq!(zones[1].text, "In this issue\nResults, p. 3");
+        assert_eq!(zones[1].rect, [40.0, 100.0, 150.0, 126.0]);
+        // A short line alone at the margin is body text
+        assert_eq!(zones[2].text, "Revenue grew by 12% over the year,\ndriven by exports.\n\nDear Sir,");
+        assert_eq!(zones[2].rect, [60.0, 100.0, 555.0, 412.0]);
+        assert_eq!(zones[3].text, "Page 2");
+        assert!(from_lines(&[], a4).is_empty());
+    }
+}